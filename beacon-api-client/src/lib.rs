@@ -3,6 +3,7 @@ mod api_error;
 mod cli;
 mod serde;
 mod types;
+mod web3signer_client;
 
 pub use api_client::*;
 pub use api_error::*;
@@ -10,6 +11,7 @@ pub use cli::*;
 pub use error::*;
 pub use presets::*;
 pub use types::*;
+pub use web3signer_client::*;
 
 mod error {
     use crate::ApiError;
@@ -28,6 +30,8 @@ mod error {
         MissingExpectedData(String),
         #[error("json error: {0}")]
         Json(#[from] serde_json::Error),
+        #[error("error from remote signer ({status}): {message}")]
+        RemoteSigner { status: reqwest::StatusCode, message: String },
     }
 }
 
@@ -0,0 +1,309 @@
+use crate::Error;
+use ethereum_consensus::{
+    primitives::{BlsPublicKey, BlsSignature},
+    web3signer::{SigningRequest, SigningResponse},
+};
+use url::Url;
+
+async fn remote_signer_error_or_value<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, Error> {
+    match response.status() {
+        reqwest::StatusCode::OK => {
+            let value = response.json().await?;
+            Ok(value)
+        }
+        status => {
+            let message = response.text().await?;
+            Err(Error::RemoteSigner { status, message })
+        }
+    }
+}
+
+// Client for the Ethereum consensus API of a Web3Signer remote signer
+#[derive(Clone)]
+pub struct Web3SignerClient {
+    pub http: reqwest::Client,
+    pub endpoint: Url,
+}
+
+impl Web3SignerClient {
+    pub fn new_with_client<U: Into<Url>>(client: reqwest::Client, endpoint: U) -> Self {
+        Self { http: client, endpoint: endpoint.into() }
+    }
+
+    pub fn new<U: Into<Url>>(endpoint: U) -> Self {
+        let client = reqwest::Client::new();
+        Self::new_with_client(client, endpoint)
+    }
+
+    pub async fn upcheck(&self) -> Result<(), Error> {
+        let target = self.endpoint.join("upcheck")?;
+        let response = self.http.get(target).send().await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            status => {
+                let message = response.text().await?;
+                Err(Error::RemoteSigner { status, message })
+            }
+        }
+    }
+
+    // Public keys of the validators the remote signer holds keys for
+    pub async fn get_public_keys(&self) -> Result<Vec<BlsPublicKey>, Error> {
+        let target = self.endpoint.join("api/v1/eth2/publicKeys")?;
+        let response = self.http.get(target).send().await?;
+        remote_signer_error_or_value(response).await
+    }
+
+    pub async fn sign<B, A, C>(
+        &self,
+        public_key: &BlsPublicKey,
+        request: &SigningRequest<'_, B, A, C>,
+    ) -> Result<BlsSignature, Error>
+    where
+        B: serde::Serialize,
+        A: serde::Serialize,
+        C: serde::Serialize,
+    {
+        let path = format!("api/v1/eth2/sign/{public_key}");
+        let target = self.endpoint.join(&path)?;
+        let response = self
+            .http
+            .post(target)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .json(request)
+            .send()
+            .await?;
+        let response: SigningResponse = remote_signer_error_or_value(response).await?;
+        Ok(response.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethereum_consensus::{
+        altair::{mainnet::ContributionAndProof, SyncAggregatorSelectionData},
+        builder::ValidatorRegistration,
+        phase0::{mainnet::AggregateAndProof, AttestationData, BeaconBlockHeader, VoluntaryExit},
+        web3signer::{
+            mainnet::{SignableMessage, SigningRequest},
+            AggregationSlot, BlockRequest, DepositData, RandaoReveal, SyncCommitteeMessage,
+        },
+        Fork,
+    };
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    // The parts of a request checked by the tests
+    #[derive(Debug)]
+    struct ReceivedRequest {
+        method: String,
+        path: String,
+        body: String,
+    }
+
+    // Answer the first request to the returned endpoint with `status` and `body`, and return that
+    // request from the handle.
+    async fn serve_once(status: u16, body: String) -> (Url, JoinHandle<ReceivedRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buffer = [0u8; 4096];
+            let mut body_start = None;
+            let mut content_length = 0;
+            loop {
+                if let Some(start) = body_start {
+                    if request.len() >= start + content_length {
+                        break
+                    }
+                }
+                let n = stream.read(&mut buffer).await.unwrap();
+                assert!(n > 0, "connection closed before the end of the request");
+                request.extend_from_slice(&buffer[..n]);
+                if body_start.is_none() {
+                    let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n")
+                    else {
+                        continue
+                    };
+                    body_start = Some(end + 4);
+                    content_length = String::from_utf8_lossy(&request[..end])
+                        .lines()
+                        .filter_map(|line| line.split_once(':'))
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                        .map_or(0, |(_, value)| value.trim().parse().unwrap());
+                }
+            }
+
+            let headers = format!(
+                "content-type: application/json\r\ncontent-length: {}\r\nconnection: close",
+                body.len()
+            );
+            let response = format!("HTTP/1.1 {status} Status\r\n{headers}\r\n\r\n{body}");
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            let body_start = body_start.unwrap();
+            let head = String::from_utf8(request[..body_start].to_vec()).unwrap();
+            let mut request_line = head.lines().next().unwrap().split(' ');
+            ReceivedRequest {
+                method: request_line.next().unwrap().to_string(),
+                path: request_line.next().unwrap().to_string(),
+                body: String::from_utf8(request[body_start..].to_vec()).unwrap(),
+            }
+        });
+        (endpoint, handle)
+    }
+
+    fn public_key() -> BlsPublicKey {
+        BlsPublicKey::try_from([0xab; 48].as_ref()).unwrap()
+    }
+
+    fn signature() -> BlsSignature {
+        BlsSignature::try_from([0xcd; 96].as_ref()).unwrap()
+    }
+
+    fn remote_signer_error(result: Result<impl std::fmt::Debug, Error>) -> (u16, String) {
+        match result {
+            Err(Error::RemoteSigner { status, message }) => (status.as_u16(), message),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upcheck() {
+        let (endpoint, server) = serve_once(200, "OK".to_string()).await;
+        Web3SignerClient::new(endpoint).upcheck().await.unwrap();
+        let request = server.await.unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/upcheck"));
+
+        let (endpoint, server) = serve_once(503, "not ready".to_string()).await;
+        let result = Web3SignerClient::new(endpoint).upcheck().await;
+        assert_eq!(remote_signer_error(result), (503, "not ready".to_string()));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_public_keys() {
+        let body = json!([public_key()]).to_string();
+        let (endpoint, server) = serve_once(200, body).await;
+        // paths are relative to the endpoint, which may be behind a prefix
+        let client = Web3SignerClient::new(endpoint.join("signer/").unwrap());
+        assert_eq!(client.get_public_keys().await.unwrap(), vec![public_key()]);
+        let request = server.await.unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/signer/api/v1/eth2/publicKeys");
+
+        let (endpoint, server) = serve_once(500, "internal error".to_string()).await;
+        let result = Web3SignerClient::new(endpoint).get_public_keys().await;
+        assert_eq!(remote_signer_error(result), (500, "internal error".to_string()));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sign_each_message_type() {
+        let aggregate_and_proof = AggregateAndProof::default();
+        let attestation = AttestationData { slot: 5, ..Default::default() };
+        let voluntary_exit = VoluntaryExit { epoch: 4, validator_index: 2 };
+        let sync_aggregator_selection_data = SyncAggregatorSelectionData::default();
+        let contribution_and_proof = ContributionAndProof::default();
+        let validator_registration = ValidatorRegistration::default();
+        let messages = vec![
+            (
+                "AGGREGATION_SLOT",
+                SignableMessage::AggregationSlot { aggregation_slot: AggregationSlot { slot: 3 } },
+            ),
+            (
+                "AGGREGATE_AND_PROOF",
+                SignableMessage::AggregateAndProof { aggregate_and_proof: &aggregate_and_proof },
+            ),
+            ("ATTESTATION", SignableMessage::Attestation { attestation: &attestation }),
+            (
+                "BLOCK_V2",
+                SignableMessage::BlockV2 {
+                    beacon_block: BlockRequest::from_header(
+                        Fork::Deneb,
+                        BeaconBlockHeader { slot: 7, ..Default::default() },
+                    ),
+                },
+            ),
+            (
+                "DEPOSIT",
+                SignableMessage::Deposit {
+                    deposit: DepositData {
+                        public_key: public_key(),
+                        withdrawal_credentials: Default::default(),
+                        amount: 32_000_000_000,
+                        genesis_fork_version: [0, 0, 0, 1],
+                    },
+                },
+            ),
+            (
+                "RANDAO_REVEAL",
+                SignableMessage::RandaoReveal { randao_reveal: RandaoReveal { epoch: 9 } },
+            ),
+            ("VOLUNTARY_EXIT", SignableMessage::VoluntaryExit { voluntary_exit: &voluntary_exit }),
+            (
+                "SYNC_COMMITTEE_MESSAGE",
+                SignableMessage::SyncCommitteeMessage {
+                    sync_committee_message: SyncCommitteeMessage {
+                        beacon_block_root: Default::default(),
+                        slot: 8,
+                    },
+                },
+            ),
+            (
+                "SYNC_COMMITTEE_SELECTION_PROOF",
+                SignableMessage::SyncCommitteeSelectionProof {
+                    sync_aggregator_selection_data: &sync_aggregator_selection_data,
+                },
+            ),
+            (
+                "SYNC_COMMITTEE_CONTRIBUTION_AND_PROOF",
+                SignableMessage::SyncCommitteeContributionAndProof {
+                    contribution_and_proof: &contribution_and_proof,
+                },
+            ),
+            (
+                "VALIDATOR_REGISTRATION",
+                SignableMessage::ValidatorRegistration {
+                    validator_registration: &validator_registration,
+                },
+            ),
+        ];
+
+        for (message_type, message) in messages {
+            let signing_request = SigningRequest::new(message, None);
+            let body = json!({ "signature": signature() }).to_string();
+            let (endpoint, server) = serve_once(200, body).await;
+            let client = Web3SignerClient::new(endpoint);
+            assert_eq!(client.sign(&public_key(), &signing_request).await.unwrap(), signature());
+
+            let request = server.await.unwrap();
+            assert_eq!(request.method, "POST");
+            assert_eq!(request.path, format!("/api/v1/eth2/sign/0x{}", "ab".repeat(48)));
+            let body = serde_json::from_str::<serde_json::Value>(&request.body).unwrap();
+            assert_eq!(body, serde_json::to_value(&signing_request).unwrap());
+            assert_eq!(body["type"], message_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_maps_error_status() {
+        let message = SignableMessage::RandaoReveal { randao_reveal: RandaoReveal { epoch: 9 } };
+        let signing_request = SigningRequest::new(message, None);
+        for (status, message) in [(404, "public key not found"), (412, "slashing protection")] {
+            let (endpoint, server) = serve_once(status, message.to_string()).await;
+            let result =
+                Web3SignerClient::new(endpoint).sign(&public_key(), &signing_request).await;
+            assert_eq!(remote_signer_error(result), (status, message.to_string()));
+            server.await.unwrap();
+        }
+    }
+}
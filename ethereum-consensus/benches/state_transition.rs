@@ -0,0 +1,73 @@
+//! Benchmarks of the state transition over synthetic mainnet-scale states.
+//!
+//! Run with `cargo bench --features bench`. Set `BENCH_VALIDATOR_COUNT` to change the registry
+//! size from the default of one million. Add the `parallel` feature to compare the steps that
+//! are spread across threads with it.
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use ethereum_consensus::{
+    altair::mainnet::{
+        compute_shuffled_indices, get_active_validator_indices, get_current_epoch, get_seed,
+        process_block, process_epoch, process_rewards_and_penalties,
+    },
+    bench::StateGenerator,
+    domains::DomainType,
+    state_transition::Context,
+};
+
+fn generator() -> StateGenerator {
+    let validator_count = std::env::var("BENCH_VALIDATOR_COUNT")
+        .ok()
+        .map(|count| count.parse().expect("validator count is an integer"))
+        .unwrap_or(1_000_000);
+    StateGenerator::new(validator_count)
+}
+
+fn bench_state_transition(c: &mut Criterion) {
+    let context = Context::for_mainnet();
+    let generator = generator();
+    let state = generator.altair_state(&context).expect("can generate state");
+
+    let epoch = get_current_epoch(&state, &context);
+    let indices = get_active_validator_indices(&state, epoch);
+    let seed = get_seed(&state, epoch, DomainType::BeaconAttester, &context);
+    c.bench_function("compute_shuffled_indices", |b| {
+        b.iter(|| compute_shuffled_indices(&indices, &seed, &context))
+    });
+
+    let mut group = c.benchmark_group("state_transition");
+    group.sample_size(10);
+    group.bench_function("process_epoch", |b| {
+        b.iter_batched(
+            || state.clone(),
+            |mut state| process_epoch(&mut state, &context).expect("can process epoch"),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("process_rewards_and_penalties", |b| {
+        b.iter_batched(
+            || state.clone(),
+            |mut state| {
+                process_rewards_and_penalties(&mut state, &context)
+                    .expect("can process rewards and penalties")
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let mut block_state = state.clone();
+    let block = generator.next_block(&mut block_state, &context).expect("can build block");
+    group.bench_function("process_block", |b| {
+        b.iter_batched(
+            || (block_state.clone(), block.clone()),
+            |(mut state, mut block)| {
+                process_block(&mut state, &mut block, &context).expect("can process block")
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_state_transition);
+criterion_main!(benches);
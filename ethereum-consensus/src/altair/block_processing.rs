@@ -25,15 +25,12 @@ use crate::{
         invalid_operation_error, InvalidAttestation, InvalidDeposit, InvalidOperation,
         InvalidSyncAggregate,
     },
-    primitives::{BlsPublicKey, ParticipationFlags, ValidatorIndex},
+    primitives::{ParticipationFlags, ValidatorIndex},
     signing::compute_signing_root,
     ssz::prelude::*,
     state_transition::{Context, Result},
 };
-use std::{
-    collections::{HashMap, HashSet},
-    iter::zip,
-};
+use std::iter::zip;
 
 pub fn process_attestation<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -201,9 +198,9 @@ pub fn process_deposit<
 
     let public_key = &deposit.data.public_key;
     let amount = deposit.data.amount;
-    let validator_public_keys: HashSet<&BlsPublicKey> =
-        HashSet::from_iter(state.validators.iter().map(|v| &v.public_key));
-    if !validator_public_keys.contains(public_key) {
+    if let Some(index) = context.pubkey_cache.get_validator_index(&state.validators, public_key) {
+        increase_balance(state, index, amount);
+    } else {
         let mut deposit_message = DepositMessage {
             public_key: public_key.clone(),
             withdrawal_credentials: deposit.data.withdrawal_credentials.clone(),
@@ -216,15 +213,12 @@ pub fn process_deposit<
             // NOTE: explicitly return with no error and also no further mutations to `state`
             return Ok(())
         }
+        context.pubkey_cache.insert(public_key.clone(), state.validators.len());
         state.validators.push(get_validator_from_deposit(deposit, context));
         state.balances.push(amount);
         state.previous_epoch_participation.push(ParticipationFlags::default());
         state.current_epoch_participation.push(ParticipationFlags::default());
         state.inactivity_scores.push(0)
-    } else {
-        let index = state.validators.iter().position(|v| &v.public_key == public_key).unwrap();
-
-        increase_balance(state, index, amount);
     }
 
     Ok(())
@@ -268,12 +262,15 @@ pub fn process_sync_aggregate<
     )?;
     let mut root_at_slot = *get_block_root_at_slot(state, previous_slot)?;
     let signing_root = compute_signing_root(&mut root_at_slot, domain)?;
-    if eth_fast_aggregate_verify(
-        participant_public_keys.as_slice(),
-        signing_root.as_ref(),
-        &sync_aggregate.sync_committee_signature,
-    )
-    .is_err()
+    let signature = &sync_aggregate.sync_committee_signature;
+    let is_verified = context.verified_signature_cache.contains(
+        &participant_public_keys,
+        &signing_root,
+        signature,
+    );
+    if !is_verified &&
+        eth_fast_aggregate_verify(&participant_public_keys, signing_root.as_ref(), signature)
+            .is_err()
     {
         return Err(invalid_operation_error(InvalidOperation::SyncAggregate(
             InvalidSyncAggregate::InvalidSignature {
@@ -295,16 +292,14 @@ pub fn process_sync_aggregate<
         participant_reward * PROPOSER_WEIGHT / (WEIGHT_DENOMINATOR - PROPOSER_WEIGHT);
 
     // Apply participant and proposer rewards
-    let all_public_keys = state
-        .validators
-        .iter()
-        .enumerate()
-        .map(|(i, v)| (&v.public_key, i))
-        .collect::<HashMap<&BlsPublicKey, usize>>();
     let mut committee_indices: Vec<ValidatorIndex> = Vec::default();
     for public_key in state.current_sync_committee.public_keys.iter() {
-        committee_indices
-            .push(*all_public_keys.get(public_key).expect("validator public_key should exist"));
+        committee_indices.push(
+            context
+                .pubkey_cache
+                .get_validator_index(&state.validators, public_key)
+                .expect("validator public_key should exist"),
+        );
     }
     for (participant_index, participation_bit) in
         zip(committee_indices.iter(), sync_aggregate.sync_committee_bits.iter())
@@ -1,20 +1,19 @@
 use crate::{
     altair::{
         constants::{PARTICIPATION_FLAG_WEIGHTS, TIMELY_TARGET_FLAG_INDEX},
-        decrease_balance, get_current_epoch, get_eligible_validator_indices,
-        get_inactivity_penalty_deltas, get_previous_epoch, get_total_active_balance,
-        get_total_balance,
+        get_current_epoch, get_eligible_validator_indices, get_previous_epoch,
         helpers::{
-            get_base_reward_per_increment, get_flag_index_deltas, get_next_sync_committee,
-            get_unslashed_participating_indices,
+            get_base_reward_per_increment, get_epoch_processing_context, get_next_sync_committee,
+            get_participation_cache, get_unslashed_participating_indices, has_flag,
+            process_rewards_and_penalties_with_context, process_slashings_with_context,
         },
-        increase_balance, is_in_inactivity_leak, process_effective_balance_updates,
-        process_eth1_data_reset, process_historical_roots_update, process_randao_mixes_reset,
-        process_registry_updates, process_slashings_reset, weigh_justification_and_finalization,
-        BeaconState,
+        is_in_inactivity_leak, process_effective_balance_updates, process_eth1_data_reset,
+        process_historical_roots_update, process_randao_mixes_reset, process_registry_updates,
+        process_slashings_reset, weigh_justification_and_finalization, BeaconState,
     },
-    primitives::{Gwei, ParticipationFlags, ValidatorIndex, GENESIS_EPOCH},
+    primitives::{Epoch, Gwei, ParticipationFlags, ValidatorIndex, GENESIS_EPOCH},
     state_transition::{Context, Result},
+    Error,
 };
 use std::mem;
 
@@ -47,6 +46,84 @@ pub fn get_base_reward<
     Ok(increments * get_base_reward_per_increment(state, context)?)
 }
 
+// Participation of active, unslashed validators in the previous and current epochs.
+// Each entry holds the validator's participation flags, or no flags if it is slashed or inactive
+// in that epoch, so membership for any flag index is a single bit test.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParticipationCache {
+    pub previous_epoch: Epoch,
+    pub current_epoch: Epoch,
+    pub total_active_balance: Gwei,
+    pub previous_epoch_flags: Vec<ParticipationFlags>,
+    pub current_epoch_flags: Vec<ParticipationFlags>,
+    // Total unslashed participating balance, per flag index
+    pub previous_epoch_flag_balances: [Gwei; PARTICIPATION_FLAG_WEIGHTS.len()],
+    pub current_epoch_flag_balances: [Gwei; PARTICIPATION_FLAG_WEIGHTS.len()],
+}
+
+impl ParticipationCache {
+    fn epoch_data(&self, epoch: Epoch) -> Result<(&[ParticipationFlags], &[Gwei])> {
+        if epoch == self.current_epoch {
+            Ok((&self.current_epoch_flags, &self.current_epoch_flag_balances))
+        } else if epoch == self.previous_epoch {
+            Ok((&self.previous_epoch_flags, &self.previous_epoch_flag_balances))
+        } else {
+            Err(Error::InvalidEpoch {
+                requested: epoch,
+                previous: self.previous_epoch,
+                current: self.current_epoch,
+            })
+        }
+    }
+
+    pub fn is_unslashed_participating(
+        &self,
+        index: ValidatorIndex,
+        flag_index: usize,
+        epoch: Epoch,
+    ) -> Result<bool> {
+        let (flags, _) = self.epoch_data(epoch)?;
+        Ok(flags.get(index).is_some_and(|&flags| has_flag(flags, flag_index)))
+    }
+
+    // Equivalent to `get_total_balance` over `get_unslashed_participating_indices`
+    pub fn unslashed_participating_balance(&self, flag_index: usize, epoch: Epoch) -> Result<Gwei> {
+        let (_, balances) = self.epoch_data(epoch)?;
+        Ok(balances[flag_index])
+    }
+}
+
+// Scratch space reused by the reward and penalty computations of an epoch transition, so the
+// per-validator buffers are allocated once rather than for every set of deltas.
+// `effective_balance_increments` holds each validator's effective balance in units of
+// `EFFECTIVE_BALANCE_INCREMENT`, which does not change until `process_effective_balance_updates`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EpochProcessingContext {
+    pub eligible_indices: Vec<ValidatorIndex>,
+    pub effective_balance_increments: Vec<u64>,
+    pub rewards: Vec<Gwei>,
+    pub penalties: Vec<Gwei>,
+}
+
+impl EpochProcessingContext {
+    pub fn new(eligible_indices: Vec<ValidatorIndex>) -> Self {
+        Self { eligible_indices, ..Default::default() }
+    }
+
+    // `get_base_reward` for the validator at `index`.
+    pub fn base_reward(&self, index: ValidatorIndex, base_reward_per_increment: Gwei) -> Gwei {
+        self.effective_balance_increments[index] * base_reward_per_increment
+    }
+
+    // Zero the reward and penalty buffers for a registry of `validator_count` validators.
+    pub fn reset_deltas(&mut self, validator_count: usize) {
+        self.rewards.clear();
+        self.rewards.resize(validator_count, 0);
+        self.penalties.clear();
+        self.penalties.resize(validator_count, 0);
+    }
+}
+
 pub fn process_justification_and_finalization<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -77,21 +154,8 @@ pub fn process_justification_and_finalization<
         return Ok(())
     }
 
-    let previous_indices = get_unslashed_participating_indices(
-        state,
-        TIMELY_TARGET_FLAG_INDEX,
-        get_previous_epoch(state, context),
-        context,
-    )?;
-    let current_indices = get_unslashed_participating_indices(
-        state,
-        TIMELY_TARGET_FLAG_INDEX,
-        current_epoch,
-        context,
-    )?;
-    let total_active_balance = get_total_active_balance(state, context)?;
-    let previous_target_balance = get_total_balance(state, &previous_indices, context)?;
-    let current_target_balance = get_total_balance(state, &current_indices, context)?;
+    let (total_active_balance, previous_target_balance, current_target_balance) =
+        get_justification_balances(state, context)?;
     weigh_justification_and_finalization(
         state,
         total_active_balance,
@@ -101,6 +165,38 @@ pub fn process_justification_and_finalization<
     )
 }
 
+// Return the `(total_active_balance, previous_target_balance, current_target_balance)` weighed by
+// `process_justification_and_finalization`.
+pub fn get_justification_balances<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Result<(Gwei, Gwei, Gwei)> {
+    let participation = get_participation_cache(state, context)?;
+    let previous_target_balance = participation
+        .unslashed_participating_balance(TIMELY_TARGET_FLAG_INDEX, participation.previous_epoch)?;
+    let current_target_balance = participation
+        .unslashed_participating_balance(TIMELY_TARGET_FLAG_INDEX, participation.current_epoch)?;
+    Ok((participation.total_active_balance, previous_target_balance, current_target_balance))
+}
+
 pub fn process_inactivity_updates<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -176,26 +272,8 @@ pub fn process_rewards_and_penalties<
     >,
     context: &Context,
 ) -> Result<()> {
-    // No rewards are applied at the end of `GENESIS_EPOCH` because rewards are for work done in the
-    // previous epoch
-    let current_epoch = get_current_epoch(state, context);
-    if current_epoch == GENESIS_EPOCH {
-        return Ok(())
-    }
-
-    let mut deltas = Vec::new();
-    for flag_index in 0..PARTICIPATION_FLAG_WEIGHTS.len() {
-        let flag_index_delta = get_flag_index_deltas(state, flag_index, context)?;
-        deltas.push(flag_index_delta);
-    }
-    deltas.push(get_inactivity_penalty_deltas(state, context)?);
-    for (rewards, penalties) in deltas {
-        for index in 0..state.validators.len() {
-            increase_balance(state, index, rewards[index]);
-            decrease_balance(state, index, penalties[index]);
-        }
-    }
-    Ok(())
+    let mut epoch_context = get_epoch_processing_context(state, context);
+    process_rewards_and_penalties_with_context(state, &mut epoch_context, context)
 }
 
 pub fn process_participation_flag_updates<
@@ -249,25 +327,8 @@ pub fn process_slashings<
     >,
     context: &Context,
 ) -> Result<()> {
-    let epoch = get_current_epoch(state, context);
-    let total_balance = get_total_active_balance(state, context)?;
-    let adjusted_total_slashing_balance = Gwei::min(
-        state.slashings.iter().sum::<Gwei>() * context.proportional_slashing_multiplier_altair,
-        total_balance,
-    );
-    for i in 0..state.validators.len() {
-        let validator = &state.validators[i];
-        if validator.slashed &&
-            (epoch + context.epochs_per_slashings_vector / 2) == validator.withdrawable_epoch
-        {
-            let increment = context.effective_balance_increment;
-            let penalty_numerator =
-                validator.effective_balance / increment * adjusted_total_slashing_balance;
-            let penalty = penalty_numerator / total_balance * increment;
-            decrease_balance(state, i, penalty);
-        }
-    }
-    Ok(())
+    let epoch_context = get_epoch_processing_context(state, context);
+    process_slashings_with_context(state, &epoch_context, context)
 }
 
 pub fn process_sync_committee_updates<
@@ -326,9 +387,10 @@ pub fn process_epoch<
 ) -> Result<()> {
     process_justification_and_finalization(state, context)?;
     process_inactivity_updates(state, context)?;
-    process_rewards_and_penalties(state, context)?;
+    let mut epoch_context = get_epoch_processing_context(state, context);
+    process_rewards_and_penalties_with_context(state, &mut epoch_context, context)?;
     process_registry_updates(state, context);
-    process_slashings(state, context)?;
+    process_slashings_with_context(state, &epoch_context, context)?;
     process_eth1_data_reset(state, context);
     process_effective_balance_updates(state, context);
     process_slashings_reset(state, context);
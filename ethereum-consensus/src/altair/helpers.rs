@@ -1,22 +1,29 @@
 use crate::{
     altair::{
+        beacon_block::SignedBeaconBlock,
         beacon_state::BeaconState,
-        compute_shuffled_index,
+        collect_operation_signature_sets, collect_voluntary_exit_signature_sets,
+        compute_epoch_at_slot, compute_shuffled_index,
         constants::{
             PARTICIPATION_FLAG_WEIGHTS, PROPOSER_WEIGHT, TIMELY_HEAD_FLAG_INDEX,
             TIMELY_SOURCE_FLAG_INDEX, TIMELY_TARGET_FLAG_INDEX, WEIGHT_DENOMINATOR,
         },
-        decrease_balance, get_active_validator_indices, get_base_reward, get_beacon_proposer_index,
-        get_block_root, get_block_root_at_slot, get_current_epoch, get_eligible_validator_indices,
-        get_previous_epoch, get_seed, get_total_active_balance, get_total_balance,
-        increase_balance, initiate_validator_exit, is_in_inactivity_leak,
-        sync::SyncCommittee,
-        AttestationData,
+        decrease_balance, get_active_validator_indices, get_beacon_proposer_index, get_block_root,
+        get_block_root_at_slot, get_current_epoch, get_domain, get_eligible_validator_indices,
+        get_previous_epoch, get_seed, get_total_active_balance, increase_balance,
+        initiate_validator_exit, is_active_validator, is_in_inactivity_leak,
+        sync::{SyncAggregate, SyncCommittee},
+        AttestationData, EpochProcessingContext, ParticipationCache,
     },
-    crypto::{eth_aggregate_public_keys, hash},
+    crypto::{eth_aggregate_decompressed_public_keys, hash},
     domains::DomainType,
-    error::{invalid_operation_error, Error, InvalidAttestation, InvalidOperation},
-    primitives::{BlsPublicKey, Epoch, Gwei, ParticipationFlags, ValidatorIndex},
+    error::{
+        invalid_operation_error, Error, InvalidAttestation, InvalidOperation, InvalidSyncAggregate,
+    },
+    primitives::{
+        BlsPublicKey, Epoch, Gwei, ParticipationFlags, Slot, ValidatorIndex, GENESIS_EPOCH,
+    },
+    signing::compute_signing_root,
     ssz::prelude::Vector,
     state_transition::{Context, Result},
 };
@@ -35,6 +42,22 @@ pub fn has_flag(flags: ParticipationFlags, flag_index: usize) -> bool {
     flags & flag == flag
 }
 
+// Return the sync committee period containing ``epoch``
+pub fn compute_sync_committee_period(epoch: Epoch, context: &Context) -> u64 {
+    epoch / context.epochs_per_sync_committee_period
+}
+
+// Return the sync committee period containing ``slot``
+pub fn compute_sync_committee_period_at_slot(slot: Slot, context: &Context) -> u64 {
+    compute_sync_committee_period(compute_epoch_at_slot(slot, context), context)
+}
+
+// Return the (half-open) range of epochs covered by the sync committee ``period``
+pub fn sync_committee_period_boundaries(period: u64, context: &Context) -> std::ops::Range<Epoch> {
+    let start_epoch = period * context.epochs_per_sync_committee_period;
+    start_epoch..start_epoch + context.epochs_per_sync_committee_period
+}
+
 // Return the sync committee indices, with possible duplicates, for the next sync committee.
 pub fn get_next_sync_committee_indices<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -113,11 +136,20 @@ pub fn get_next_sync_committee<
     context: &Context,
 ) -> Result<SyncCommittee<SYNC_COMMITTEE_SIZE>> {
     let indices = get_next_sync_committee_indices(state, context)?;
+    // aggregate the cached decompressed keys rather than decompressing each selected key again
+    let mut decompressed_public_keys = Vec::with_capacity(indices.len());
+    for &index in &indices {
+        let public_key = context
+            .decompressed_pubkey_cache
+            .get(&state.validators, index)
+            .ok_or(Error::OutOfBounds { requested: index, bound: state.validators.len() })??;
+        decompressed_public_keys.push(public_key);
+    }
+    let aggregate_public_key = eth_aggregate_decompressed_public_keys(&decompressed_public_keys)?;
     let public_keys =
         indices.into_iter().map(|i| state.validators[i].public_key.clone()).collect::<Vec<_>>();
     let public_keys = Vector::<BlsPublicKey, SYNC_COMMITTEE_SIZE>::try_from(public_keys)
         .map_err(|(_, err)| err)?;
-    let aggregate_public_key = eth_aggregate_public_keys(&public_keys)?;
 
     Ok(SyncCommittee::<SYNC_COMMITTEE_SIZE> { public_keys, aggregate_public_key })
 }
@@ -285,32 +317,210 @@ pub fn get_flag_index_deltas<
     flag_index: usize,
     context: &Context,
 ) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
-    let validator_count = state.validators.len();
-    let mut rewards = vec![0; validator_count];
-    let mut penalties = vec![0; validator_count];
+    let participation = get_participation_cache(state, context)?;
+    get_flag_index_deltas_from_cache(state, flag_index, &participation, context)
+}
+
+// Compute the participation of every validator in the previous and current epochs in one pass
+pub fn get_participation_cache<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Result<ParticipationCache> {
     let previous_epoch = get_previous_epoch(state, context);
-    let unslashed_participating_indices =
-        get_unslashed_participating_indices(state, flag_index, previous_epoch, context)?;
+    let current_epoch = get_current_epoch(state, context);
+    let validator_count = state.validators.len();
+    let mut participation = ParticipationCache {
+        previous_epoch,
+        current_epoch,
+        previous_epoch_flags: vec![0; validator_count],
+        current_epoch_flags: vec![0; validator_count],
+        ..Default::default()
+    };
+    let mut total_active_balance = 0;
+    for (index, validator) in state.validators.iter().enumerate() {
+        let effective_balance = validator.effective_balance;
+        if is_active_validator(validator, current_epoch) {
+            total_active_balance += effective_balance;
+        }
+        if validator.slashed {
+            continue
+        }
+        if is_active_validator(validator, previous_epoch) {
+            let flags = state.previous_epoch_participation[index];
+            participation.previous_epoch_flags[index] = flags;
+            for (flag_index, balance) in
+                participation.previous_epoch_flag_balances.iter_mut().enumerate()
+            {
+                if has_flag(flags, flag_index) {
+                    *balance += effective_balance;
+                }
+            }
+        }
+        if is_active_validator(validator, current_epoch) {
+            let flags = state.current_epoch_participation[index];
+            participation.current_epoch_flags[index] = flags;
+            for (flag_index, balance) in
+                participation.current_epoch_flag_balances.iter_mut().enumerate()
+            {
+                if has_flag(flags, flag_index) {
+                    *balance += effective_balance;
+                }
+            }
+        }
+    }
+    // Match `get_total_balance`, which returns at least one increment
+    participation.total_active_balance =
+        u64::max(context.effective_balance_increment, total_active_balance);
+    for balance in participation
+        .previous_epoch_flag_balances
+        .iter_mut()
+        .chain(participation.current_epoch_flag_balances.iter_mut())
+    {
+        *balance = u64::max(context.effective_balance_increment, *balance);
+    }
+    Ok(participation)
+}
+
+// Return the scratch space for the epoch transition of `state`.
+pub fn get_epoch_processing_context<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> EpochProcessingContext {
+    let mut epoch_context =
+        EpochProcessingContext::new(get_eligible_validator_indices(state, context).collect());
+    epoch_context.effective_balance_increments = state
+        .validators
+        .iter()
+        .map(|validator| validator.effective_balance / context.effective_balance_increment)
+        .collect();
+    epoch_context
+}
+
+// `get_flag_index_deltas` using participation computed once for the epoch transition
+pub fn get_flag_index_deltas_from_cache<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    flag_index: usize,
+    participation: &ParticipationCache,
+    context: &Context,
+) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
+    let mut scratch = get_epoch_processing_context(state, context);
+    compute_flag_index_deltas(state, flag_index, participation, &mut scratch, context)?;
+    Ok((scratch.rewards, scratch.penalties))
+}
+
+// Write the deltas for `flag_index` into the buffers of `scratch`, which must hold the eligible
+// validator indices of `state`.
+pub fn compute_flag_index_deltas<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    flag_index: usize,
+    participation: &ParticipationCache,
+    scratch: &mut EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    scratch.reset_deltas(state.validators.len());
+    let previous_epoch = participation.previous_epoch;
     let weight = PARTICIPATION_FLAG_WEIGHTS[flag_index];
     let unslashed_participating_balance =
-        get_total_balance(state, &unslashed_participating_indices, context)?;
+        participation.unslashed_participating_balance(flag_index, previous_epoch)?;
     let unslashed_participating_increments =
         unslashed_participating_balance / context.effective_balance_increment;
     let active_increments =
-        get_total_active_balance(state, context)? / context.effective_balance_increment;
+        participation.total_active_balance / context.effective_balance_increment;
+    let base_reward_per_increment = context.effective_balance_increment *
+        context.base_reward_factor /
+        participation.total_active_balance.integer_sqrt();
     let not_leaking = !is_in_inactivity_leak(state, context);
-    for index in get_eligible_validator_indices(state, context) {
-        let base_reward = get_base_reward(state, index, context)?;
-        if unslashed_participating_indices.contains(&index) {
-            if not_leaking {
-                let reward_numerator = base_reward * weight * unslashed_participating_increments;
-                rewards[index] += reward_numerator / (active_increments * WEIGHT_DENOMINATOR);
+    let deltas =
+        crate::state_transition::map_validator_indices(&scratch.eligible_indices, |index| {
+            let base_reward = scratch.base_reward(index, base_reward_per_increment);
+            if participation.is_unslashed_participating(index, flag_index, previous_epoch)? {
+                if not_leaking {
+                    let reward_numerator =
+                        base_reward * weight * unslashed_participating_increments;
+                    return Ok((reward_numerator / (active_increments * WEIGHT_DENOMINATOR), 0))
+                }
+            } else if flag_index != TIMELY_HEAD_FLAG_INDEX {
+                return Ok((0, base_reward * weight / WEIGHT_DENOMINATOR))
             }
-        } else if flag_index != TIMELY_HEAD_FLAG_INDEX {
-            penalties[index] += base_reward * weight / WEIGHT_DENOMINATOR;
-        }
+            Ok((0, 0))
+        })?;
+    for (&index, (reward, penalty)) in scratch.eligible_indices.iter().zip(deltas) {
+        scratch.rewards[index] += reward;
+        scratch.penalties[index] += penalty;
     }
-    Ok((rewards, penalties))
+    Ok(())
 }
 
 pub fn get_inactivity_penalty_deltas<
@@ -335,26 +545,346 @@ pub fn get_inactivity_penalty_deltas<
     >,
     context: &Context,
 ) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
-    let validator_count = state.validators.len();
-    let rewards = vec![0; validator_count];
-    let mut penalties = vec![0; validator_count];
-    let previous_epoch = get_previous_epoch(state, context);
-    let matching_target_indices = get_unslashed_participating_indices(
-        state,
-        TIMELY_TARGET_FLAG_INDEX,
-        previous_epoch,
-        context,
-    )?;
-    for i in get_eligible_validator_indices(state, context) {
-        if !matching_target_indices.contains(&i) {
+    let participation = get_participation_cache(state, context)?;
+    let mut scratch = get_epoch_processing_context(state, context);
+    compute_inactivity_penalty_deltas(state, &participation, &mut scratch, context)?;
+    Ok((scratch.rewards, scratch.penalties))
+}
+
+// Write the inactivity penalty deltas into the buffers of `scratch`, which must hold the
+// eligible validator indices of `state`.
+pub fn compute_inactivity_penalty_deltas<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    participation: &ParticipationCache,
+    scratch: &mut EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    scratch.reset_deltas(state.validators.len());
+    let previous_epoch = participation.previous_epoch;
+    let penalty_deltas =
+        crate::state_transition::map_validator_indices(&scratch.eligible_indices, |i| {
+            if participation.is_unslashed_participating(
+                i,
+                TIMELY_TARGET_FLAG_INDEX,
+                previous_epoch,
+            )? {
+                return Ok(0)
+            }
             let penalty_numerator =
                 state.validators[i].effective_balance * state.inactivity_scores[i];
             let penalty_denominator =
                 context.inactivity_score_bias * context.inactivity_penalty_quotient_altair;
-            penalties[i] += penalty_numerator / penalty_denominator;
+            Ok(penalty_numerator / penalty_denominator)
+        })?;
+    for (&i, penalty) in scratch.eligible_indices.iter().zip(penalty_deltas) {
+        scratch.penalties[i] += penalty;
+    }
+    Ok(())
+}
+
+// `process_rewards_and_penalties` using the scratch space of `epoch_context`
+pub fn process_rewards_and_penalties_with_context<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    epoch_context: &mut EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    // No rewards are applied at the end of `GENESIS_EPOCH` because rewards are for work done in the
+    // previous epoch
+    let current_epoch = get_current_epoch(state, context);
+    if current_epoch == GENESIS_EPOCH {
+        return Ok(())
+    }
+
+    let participation = get_participation_cache(state, context)?;
+    // the spec computes every set of deltas before applying any of them, while here each set
+    // is applied before computing the next so the buffers can be reused; the deltas only depend
+    // on effective balances, participation and inactivity scores, so the result is the same
+    for flag_index in 0..=PARTICIPATION_FLAG_WEIGHTS.len() {
+        if flag_index < PARTICIPATION_FLAG_WEIGHTS.len() {
+            compute_flag_index_deltas(state, flag_index, &participation, epoch_context, context)?;
+        } else {
+            compute_inactivity_penalty_deltas(state, &participation, epoch_context, context)?;
         }
+        let (rewards, penalties) = (&epoch_context.rewards, &epoch_context.penalties);
+        crate::state_transition::update_balances(&mut state.balances, |index, balance| {
+            (balance + rewards[index]).saturating_sub(penalties[index])
+        });
+    }
+    Ok(())
+}
+
+// `process_slashings` using the effective balance increments of `epoch_context`
+pub fn process_slashings_with_context<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    epoch_context: &EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    let epoch = get_current_epoch(state, context);
+    let total_balance = get_total_active_balance(state, context)?;
+    let adjusted_total_slashing_balance = Gwei::min(
+        state.slashings.iter().sum::<Gwei>() * context.proportional_slashing_multiplier_altair,
+        total_balance,
+    );
+    for i in 0..state.validators.len() {
+        let validator = &state.validators[i];
+        if validator.slashed &&
+            (epoch + context.epochs_per_slashings_vector / 2) == validator.withdrawable_epoch
+        {
+            let increment = context.effective_balance_increment;
+            let penalty_numerator =
+                epoch_context.effective_balance_increments[i] * adjusted_total_slashing_balance;
+            let penalty = penalty_numerator / total_balance * increment;
+            decrease_balance(state, i, penalty);
+        }
+    }
+    Ok(())
+}
+
+// Per-validator breakdown of the rewards and penalties earned for the duties of `epoch`.
+// Penalties are reported as negative values.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidatorRewards {
+    pub validator_index: ValidatorIndex,
+    pub head: i64,
+    pub target: i64,
+    pub source: i64,
+    pub inactivity: i64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RewardsReport {
+    pub epoch: Epoch,
+    pub rewards: Vec<ValidatorRewards>,
+}
+
+// Return the components of the deltas applied by `process_rewards_and_penalties` for each
+// validator, without mutating the `state`.
+pub fn epoch_rewards<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Result<RewardsReport> {
+    let mut rewards = (0..state.validators.len())
+        .map(|validator_index| ValidatorRewards { validator_index, ..Default::default() })
+        .collect::<Vec<_>>();
+    let report_epoch = get_previous_epoch(state, context);
+    if get_current_epoch(state, context) == GENESIS_EPOCH {
+        return Ok(RewardsReport { epoch: report_epoch, rewards })
+    }
+
+    let net = |reward: Gwei, penalty: Gwei| reward as i64 - penalty as i64;
+    let (source_rewards, source_penalties) =
+        get_flag_index_deltas(state, TIMELY_SOURCE_FLAG_INDEX, context)?;
+    let (target_rewards, target_penalties) =
+        get_flag_index_deltas(state, TIMELY_TARGET_FLAG_INDEX, context)?;
+    let (head_rewards, head_penalties) =
+        get_flag_index_deltas(state, TIMELY_HEAD_FLAG_INDEX, context)?;
+    let (inactivity_rewards, inactivity_penalties) = get_inactivity_penalty_deltas(state, context)?;
+    for (index, entry) in rewards.iter_mut().enumerate() {
+        entry.source = net(source_rewards[index], source_penalties[index]);
+        entry.target = net(target_rewards[index], target_penalties[index]);
+        entry.head = net(head_rewards[index], head_penalties[index]);
+        entry.inactivity = net(inactivity_rewards[index], inactivity_penalties[index]);
     }
-    Ok((rewards, penalties))
+    Ok(RewardsReport { epoch: report_epoch, rewards })
+}
+
+// Add the signature of `sync_aggregate` to `batch`, unless it is the valid empty aggregate.
+pub fn collect_sync_aggregate_signature_sets<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &'a BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    sync_aggregate: &'a SyncAggregate<SYNC_COMMITTEE_SIZE>,
+    batch: &mut crate::signing::SignatureBatch<'a>,
+    context: &Context,
+) -> Result<()> {
+    let participant_public_keys = state
+        .current_sync_committee
+        .public_keys
+        .iter()
+        .zip(sync_aggregate.sync_committee_bits.iter())
+        .filter_map(|(public_key, bit)| if *bit { Some(public_key) } else { None })
+        .collect::<Vec<_>>();
+    let signature = &sync_aggregate.sync_committee_signature;
+    if participant_public_keys.is_empty() && signature.is_infinity() {
+        return Ok(())
+    }
+
+    let previous_slot = u64::max(state.slot, 1) - 1;
+    let domain = get_domain(
+        state,
+        DomainType::SyncCommittee,
+        Some(compute_epoch_at_slot(previous_slot, context)),
+        context,
+    )?;
+    let mut root_at_slot = *get_block_root_at_slot(state, previous_slot)?;
+    let signing_root = compute_signing_root(&mut root_at_slot, domain)?;
+    let error = invalid_operation_error(InvalidOperation::SyncAggregate(
+        InvalidSyncAggregate::InvalidSignature { signature: signature.clone(), root: signing_root },
+    ));
+    batch.push(participant_public_keys, signing_root, signature, error);
+    Ok(())
+}
+
+// Verify every signature in `signed_block` that `process_block` would check, using a single
+// batched verification. See the phase0 version for details.
+pub fn verify_block_signatures<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Result<()> {
+    let proposer_index = signed_block.message.proposer_index;
+    let proposer = state
+        .validators
+        .get(proposer_index)
+        .ok_or(Error::OutOfBounds { requested: proposer_index, bound: state.validators.len() })?;
+    let domain = get_domain(state, DomainType::BeaconProposer, None, context)?;
+    let signing_root = compute_signing_root(&mut signed_block.message, domain)?;
+
+    let signed_block = &*signed_block;
+    let body = &signed_block.message.body;
+    let mut batch = crate::signing::SignatureBatch::default();
+    let error = crate::crypto::Error::InvalidSignature.into();
+    batch.push(vec![&proposer.public_key], signing_root, &signed_block.signature, error);
+
+    let mut epoch = get_current_epoch(state, context);
+    let randao_proposer = &state.validators[get_beacon_proposer_index(state, context)?];
+    let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
+    let signing_root = compute_signing_root(&mut epoch, domain)?;
+    let error = invalid_operation_error(InvalidOperation::Randao(body.randao_reveal.clone()));
+    batch.push(vec![&randao_proposer.public_key], signing_root, &body.randao_reveal, error);
+
+    collect_operation_signature_sets(
+        state,
+        &body.proposer_slashings,
+        &body.attester_slashings,
+        &body.attestations,
+        &mut batch,
+        context,
+    )?;
+    collect_voluntary_exit_signature_sets(state, &body.voluntary_exits, &mut batch, context)?;
+    collect_sync_aggregate_signature_sets(state, &body.sync_aggregate, &mut batch, context)?;
+
+    batch.verify(&context.verified_signature_cache)
 }
 
 pub fn slash_validator<
@@ -409,3 +939,88 @@ pub fn slash_validator<
     increase_balance(state, whistleblower_index, whistleblower_reward - proposer_reward);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        altair::{epoch_processing::process_rewards_and_penalties, minimal},
+        primitives::FAR_FUTURE_EPOCH,
+    };
+
+    #[test]
+    fn test_epoch_rewards() {
+        let context = Context::for_minimal();
+        let mut state = minimal::BeaconState { slot: 19, ..Default::default() };
+        for index in 0..64 {
+            state.validators.push(minimal::Validator {
+                effective_balance: context.max_effective_balance,
+                exit_epoch: FAR_FUTURE_EPOCH,
+                withdrawable_epoch: FAR_FUTURE_EPOCH,
+                ..Default::default()
+            });
+            state.balances.push(context.max_effective_balance);
+            // the first half attested to everything in the previous epoch
+            state.previous_epoch_participation.push(if index < 32 { 0b111 } else { 0 });
+            state.current_epoch_participation.push(0);
+            state.inactivity_scores.push(0);
+        }
+        state.inactivity_scores[40] = 100;
+
+        let report = epoch_rewards(&state, &context).unwrap();
+        assert_eq!(report.epoch, 1);
+        assert_eq!(report.rewards.len(), 64);
+        let participant = &report.rewards[0];
+        assert!(participant.source > 0 && participant.target > 0 && participant.head > 0);
+        assert_eq!(participant.inactivity, 0);
+        let absent = &report.rewards[33];
+        assert!(absent.source < 0 && absent.target < 0);
+        // no penalty for missing the head
+        assert_eq!(absent.head, 0);
+        assert_eq!(absent.inactivity, 0);
+        assert!(report.rewards[40].inactivity < 0);
+
+        let mut post_state = state.clone();
+        process_rewards_and_penalties(&mut post_state, &context).unwrap();
+        for entry in &report.rewards {
+            let index = entry.validator_index;
+            let delta = post_state.balances[index] as i64 - state.balances[index] as i64;
+            assert_eq!(delta, entry.source + entry.target + entry.head + entry.inactivity);
+        }
+
+        // nothing is earned for the epoch before genesis
+        state.slot = 3;
+        let report = epoch_rewards(&state, &context).unwrap();
+        assert_eq!(report.epoch, GENESIS_EPOCH);
+        assert!(report.rewards.iter().all(|entry| {
+            *entry ==
+                ValidatorRewards { validator_index: entry.validator_index, ..Default::default() }
+        }));
+    }
+
+    #[test]
+    fn test_sync_committee_period_boundaries() {
+        let context = Context::for_minimal();
+        let epochs_per_period = context.epochs_per_sync_committee_period;
+        let slots_per_period = epochs_per_period * context.slots_per_epoch;
+        for period in [0, 1, 5] {
+            let first_slot = period * slots_per_period;
+            let last_slot = first_slot + slots_per_period - 1;
+            assert_eq!(compute_sync_committee_period_at_slot(first_slot, &context), period);
+            assert_eq!(compute_sync_committee_period_at_slot(last_slot, &context), period);
+            assert_eq!(compute_sync_committee_period_at_slot(last_slot + 1, &context), period + 1);
+
+            let epochs = sync_committee_period_boundaries(period, &context);
+            assert_eq!(epochs.start, compute_epoch_at_slot(first_slot, &context));
+            assert_eq!(epochs.end - 1, compute_epoch_at_slot(last_slot, &context));
+            assert!(epochs
+                .clone()
+                .all(|epoch| compute_sync_committee_period(epoch, &context) == period));
+        }
+        for k in 1..4 {
+            let epoch = k * epochs_per_period;
+            assert_eq!(compute_sync_committee_period(epoch - 1, &context), k - 1);
+            assert_eq!(compute_sync_committee_period(epoch, &context), k);
+        }
+    }
+}
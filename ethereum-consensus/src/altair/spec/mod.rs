@@ -13,17 +13,25 @@ pub use crate::{
             TIMELY_TARGET_WEIGHT, WEIGHT_DENOMINATOR,
         },
         epoch_processing::{
-            get_base_reward, process_epoch, process_inactivity_updates,
+            get_base_reward, get_justification_balances, process_epoch, process_inactivity_updates,
             process_justification_and_finalization, process_participation_flag_updates,
             process_rewards_and_penalties, process_slashings, process_sync_committee_updates,
+            EpochProcessingContext, ParticipationCache,
         },
         fork::upgrade_to_altair,
         genesis::initialize_beacon_state_from_eth1,
         helpers::{
-            add_flag, get_attestation_participation_flag_indices, get_base_reward_per_increment,
-            get_flag_index_deltas, get_inactivity_penalty_deltas, get_next_sync_committee,
-            get_next_sync_committee_indices, get_unslashed_participating_indices, has_flag,
-            slash_validator,
+            add_flag, collect_sync_aggregate_signature_sets, compute_flag_index_deltas,
+            compute_inactivity_penalty_deltas, compute_sync_committee_period,
+            compute_sync_committee_period_at_slot, epoch_rewards,
+            get_attestation_participation_flag_indices, get_base_reward_per_increment,
+            get_epoch_processing_context, get_flag_index_deltas, get_flag_index_deltas_from_cache,
+            get_inactivity_penalty_deltas, get_next_sync_committee,
+            get_next_sync_committee_indices, get_participation_cache,
+            get_unslashed_participating_indices, has_flag,
+            process_rewards_and_penalties_with_context, process_slashings_with_context,
+            slash_validator, sync_committee_period_boundaries, verify_block_signatures,
+            RewardsReport, ValidatorRewards,
         },
         light_client::{
             LightClientBootstrap, LightClientFinalityUpdate, LightClientHeader,
@@ -46,10 +54,12 @@ pub use crate::{
             BASE_REWARDS_PER_EPOCH, DEPOSIT_CONTRACT_TREE_DEPTH, DEPOSIT_DATA_LIST_BOUND,
             JUSTIFICATION_BITS_LENGTH,
         },
+        epoch_processing::{EpochTransitionSummary, JustificationAndFinalization},
+        helpers::JustificationBits,
         operations::{
             Attestation, AttestationData, AttesterSlashing, Checkpoint, Deposit, DepositData,
             DepositMessage, Eth1Data, IndexedAttestation, PendingAttestation, ProposerSlashing,
-            SignedVoluntaryExit, VoluntaryExit,
+            SignedVoluntaryExit, ValidAttestation, VoluntaryExit,
         },
         validator::{AggregateAndProof, Eth1Block, SignedAggregateAndProof, Validator},
     },
@@ -61,7 +71,10 @@ use crate::{
     crypto::{fast_aggregate_verify, hash},
     ssz::prelude::*,
 };
-use std::{cmp, collections::HashSet};
+use std::{
+    cmp,
+    collections::{hash_map::Entry, HashMap, HashSet},
+};
 pub fn process_proposer_slashing<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -123,7 +136,11 @@ pub fn process_proposer_slashing<
     {
         let signing_root = compute_signing_root(&mut signed_header.message, domain)?;
         let public_key = &proposer.public_key;
-        if verify_signature(public_key, signing_root.as_ref(), &signed_header.signature).is_err() {
+        if context
+            .verified_signature_cache
+            .verify_signature(public_key, &signing_root, &signed_header.signature)
+            .is_err()
+        {
             return Err(invalid_operation_error(InvalidOperation::ProposerSlashing(
                 InvalidProposerSlashing::InvalidSignature(signed_header.signature.clone()),
             )))
@@ -166,11 +183,7 @@ pub fn process_attester_slashing<
     }
     is_valid_indexed_attestation(state, attestation_1, context)?;
     is_valid_indexed_attestation(state, attestation_2, context)?;
-    let indices_1: HashSet<ValidatorIndex> =
-        HashSet::from_iter(attestation_1.attesting_indices.iter().cloned());
-    let indices_2 = HashSet::from_iter(attestation_2.attesting_indices.iter().cloned());
-    let mut indices = indices_1.intersection(&indices_2).cloned().collect::<Vec<_>>();
-    indices.sort_unstable();
+    let indices = attester_slashing.slashable_indices();
     let mut slashed_any = false;
     let current_epoch = get_current_epoch(state, context);
     for &index in &indices {
@@ -187,6 +200,199 @@ pub fn process_attester_slashing<
         Ok(())
     }
 }
+pub fn validate_attestation_without_signature<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    attestation: &Attestation<MAX_VALIDATORS_PER_COMMITTEE>,
+    committees: &mut HashMap<(Slot, CommitteeIndex), Vec<ValidatorIndex>>,
+    target_roots: &mut HashMap<Epoch, Option<Root>>,
+    context: &Context,
+) -> Result<(IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>, Root)> {
+    let previous_epoch = get_previous_epoch(state, context);
+    let current_epoch = get_current_epoch(state, context);
+    let data = &attestation.data;
+    let is_current = data.target.epoch == current_epoch;
+    if !is_current && data.target.epoch != previous_epoch {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidTargetEpoch {
+                target: data.target.epoch,
+                current: current_epoch,
+            },
+        )))
+    }
+    let attestation_epoch = compute_epoch_at_slot(data.slot, context);
+    if data.target.epoch != attestation_epoch {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidSlot {
+                slot: data.slot,
+                epoch: attestation_epoch,
+                target: data.target.epoch,
+            },
+        )))
+    }
+    let expected_source = if is_current {
+        &state.current_justified_checkpoint
+    } else {
+        &state.previous_justified_checkpoint
+    };
+    if data.source != *expected_source {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidSource {
+                expected: expected_source.clone(),
+                source_checkpoint: data.source.clone(),
+                current: current_epoch,
+            },
+        )))
+    }
+    let target_root = match target_roots.entry(data.target.epoch) {
+        Entry::Occupied(entry) => *entry.get(),
+        Entry::Vacant(entry) => {
+            let start_slot = compute_start_slot_at_epoch(data.target.epoch, context);
+            let root = if start_slot < state.slot {
+                Some(*get_block_root(state, data.target.epoch, context)?)
+            } else {
+                None
+            };
+            *entry.insert(root)
+        }
+    };
+    if let Some(expected) = target_root {
+        if data.target.root != expected {
+            return Err(invalid_operation_error(InvalidOperation::Attestation(
+                InvalidAttestation::InvalidTargetRoot { expected, target_root: data.target.root },
+            )))
+        }
+    }
+    let committee_count = get_committee_count_per_slot(state, data.target.epoch, context);
+    if data.index >= committee_count {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidIndex { index: data.index, upper_bound: committee_count },
+        )))
+    }
+    let committee = match committees.entry((data.slot, data.index)) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            entry.insert(get_beacon_committee(state, data.slot, data.index, context)?)
+        }
+    };
+    if attestation.aggregation_bits.len() != committee.len() {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::Bitfield {
+                expected_length: committee.len(),
+                length: attestation.aggregation_bits.len(),
+            },
+        )))
+    }
+    let mut attesting_indices = committee
+        .iter()
+        .enumerate()
+        .filter_map(|(i, index)| attestation.aggregation_bits[i].then_some(*index))
+        .collect::<Vec<_>>();
+    if attesting_indices.is_empty() {
+        return Err(invalid_operation_error(InvalidOperation::IndexedAttestation(
+            InvalidIndexedAttestation::AttestingIndicesEmpty,
+        )))
+    }
+    attesting_indices.sort_unstable();
+    let indexed_attestation = IndexedAttestation {
+        attesting_indices: attesting_indices.try_into().expect("at most committee size"),
+        data: data.clone(),
+        signature: attestation.signature.clone(),
+    };
+    let domain = get_domain(state, DomainType::BeaconAttester, Some(data.target.epoch), context)?;
+    let signing_root = compute_signing_root(&mut data.clone(), domain)?;
+    Ok((indexed_attestation, signing_root))
+}
+pub fn validate_attestations<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    attestations: &[Attestation<MAX_VALIDATORS_PER_COMMITTEE>],
+    context: &Context,
+) -> Vec<Result<ValidAttestation<MAX_VALIDATORS_PER_COMMITTEE>>> {
+    let mut committees = HashMap::new();
+    let mut target_roots = HashMap::new();
+    let results = attestations
+        .iter()
+        .map(|attestation| {
+            validate_attestation_without_signature(
+                state,
+                attestation,
+                &mut committees,
+                &mut target_roots,
+                context,
+            )
+        })
+        .collect::<Vec<_>>();
+    let sets = results
+        .iter()
+        .filter_map(|result| result.as_ref().ok())
+        .map(|(indexed_attestation, signing_root)| crate::crypto::SignatureSet {
+            public_keys: indexed_attestation
+                .attesting_indices
+                .iter()
+                .map(|&index| &state.validators[index].public_key)
+                .collect(),
+            message: signing_root.as_ref(),
+            signature: &indexed_attestation.signature,
+        })
+        .collect::<Vec<_>>();
+    let all_valid = crate::crypto::verify_signature_sets(&sets).is_ok();
+    drop(sets);
+    results
+        .into_iter()
+        .zip(attestations)
+        .map(|(result, attestation)| {
+            let (indexed_attestation, signing_root) = result?;
+            if !all_valid {
+                let public_keys = indexed_attestation
+                    .attesting_indices
+                    .iter()
+                    .map(|&index| &state.validators[index].public_key)
+                    .collect::<Vec<_>>();
+                fast_aggregate_verify(
+                    &public_keys,
+                    signing_root.as_ref(),
+                    &indexed_attestation.signature,
+                )?;
+            }
+            Ok(ValidAttestation { attestation: attestation.clone(), indexed_attestation })
+        })
+        .collect()
+}
 pub fn get_validator_from_deposit(deposit: &Deposit, context: &Context) -> Validator {
     let amount = deposit.data.amount;
     let effective_balance = Gwei::min(
@@ -263,8 +469,11 @@ pub fn process_voluntary_exit<
         )))
     }
     let domain = get_domain(state, DomainType::VoluntaryExit, Some(voluntary_exit.epoch), context)?;
+    let signing_root = compute_signing_root(voluntary_exit, domain)?;
     let public_key = &validator.public_key;
-    verify_signed_data(voluntary_exit, &signed_voluntary_exit.signature, public_key, domain)
+    context
+        .verified_signature_cache
+        .verify_signature(public_key, &signing_root, &signed_voluntary_exit.signature)
         .map_err(|_| {
             invalid_operation_error(InvalidOperation::VoluntaryExit(
                 InvalidVoluntaryExit::InvalidSignature(signed_voluntary_exit.signature.clone()),
@@ -393,7 +602,11 @@ pub fn process_randao<
     let proposer = &state.validators[proposer_index];
     let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
     let signing_root = compute_signing_root(&mut epoch, domain)?;
-    if verify_signature(&proposer.public_key, signing_root.as_ref(), &body.randao_reveal).is_err() {
+    if context
+        .verified_signature_cache
+        .verify_signature(&proposer.public_key, &signing_root, &body.randao_reveal)
+        .is_err()
+    {
         return Err(invalid_operation_error(InvalidOperation::Randao(body.randao_reveal.clone())))
     }
     let mix = xor(get_randao_mix(state, epoch), &hash(body.randao_reveal.as_ref()));
@@ -444,6 +657,37 @@ pub fn process_eth1_data<
         state.eth1_data = body.eth1_data.clone();
     }
 }
+pub fn get_validator_indices_touched_by_operations<
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    body: &BeaconBlockBody<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+    >,
+) -> Vec<ValidatorIndex> {
+    let mut indices = vec![];
+    for proposer_slashing in body.proposer_slashings.iter() {
+        indices.push(proposer_slashing.signed_header_1.message.proposer_index);
+    }
+    for attester_slashing in body.attester_slashings.iter() {
+        indices.extend(attester_slashing.attestation_1.attesting_indices.iter().copied());
+    }
+    for voluntary_exit in body.voluntary_exits.iter() {
+        indices.push(voluntary_exit.message.validator_index);
+    }
+    indices
+}
 pub fn process_operations<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -505,6 +749,42 @@ pub fn process_operations<
         .try_for_each(|op| process_voluntary_exit(state, op, context))?;
     Ok(())
 }
+pub fn get_unrealized_checkpoints<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Result<(Checkpoint, Checkpoint)> {
+    if get_current_epoch(state, context) <= GENESIS_EPOCH + 1 {
+        return Ok((state.current_justified_checkpoint.clone(), state.finalized_checkpoint.clone()))
+    }
+    let (total_active_balance, previous_target_balance, current_target_balance) =
+        get_justification_balances(state, context)?;
+    let ffg = compute_justification_and_finalization(
+        state,
+        total_active_balance,
+        previous_target_balance,
+        current_target_balance,
+        context,
+    )?;
+    Ok((ffg.current_justified_checkpoint, ffg.finalized_checkpoint))
+}
 pub fn process_registry_updates<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -627,6 +907,9 @@ pub fn process_effective_balance_updates<
             );
         }
     }
+    if let Some((epoch, root)) = get_total_active_balance_cache_key(state, context) {
+        context.total_active_balance_cache.invalidate(epoch, root);
+    }
 }
 pub fn process_slashings_reset<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -739,41 +1022,92 @@ pub fn weigh_justification_and_finalization<
     current_epoch_target_balance: Gwei,
     context: &Context,
 ) -> Result<()> {
+    let ffg = compute_justification_and_finalization(
+        state,
+        total_active_balance,
+        previous_epoch_target_balance,
+        current_epoch_target_balance,
+        context,
+    )?;
+    state.justification_bits = ffg.justification_bits;
+    state.previous_justified_checkpoint = ffg.previous_justified_checkpoint;
+    state.current_justified_checkpoint = ffg.current_justified_checkpoint;
+    state.finalized_checkpoint = ffg.finalized_checkpoint;
+    Ok(())
+}
+pub fn compute_justification_and_finalization<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    total_active_balance: Gwei,
+    previous_epoch_target_balance: Gwei,
+    current_epoch_target_balance: Gwei,
+    context: &Context,
+) -> Result<JustificationAndFinalization> {
     let previous_epoch = get_previous_epoch(state, context);
     let current_epoch = get_current_epoch(state, context);
-    let old_previous_justified_checkpoint = state.previous_justified_checkpoint.clone();
-    let old_current_justified_checkpoint = state.current_justified_checkpoint.clone();
-    state.previous_justified_checkpoint = state.current_justified_checkpoint.clone();
-    state.justification_bits.copy_within(..JUSTIFICATION_BITS_LENGTH - 1, 1);
-    state.justification_bits.set(0, false);
-    if previous_epoch_target_balance * 3 >= total_active_balance * 2 {
-        state.current_justified_checkpoint = Checkpoint {
+    let old_previous_justified_checkpoint = &state.previous_justified_checkpoint;
+    let old_current_justified_checkpoint = &state.current_justified_checkpoint;
+    let mut justification_bits = JustificationBits::from(state.justification_bits.clone());
+    let mut ffg = JustificationAndFinalization {
+        justification_bits: Default::default(),
+        previous_justified_checkpoint: state.current_justified_checkpoint.clone(),
+        current_justified_checkpoint: state.current_justified_checkpoint.clone(),
+        finalized_checkpoint: state.finalized_checkpoint.clone(),
+    };
+    let previous_epoch_justified = previous_epoch_target_balance * 3 >= total_active_balance * 2;
+    let current_epoch_justified = current_epoch_target_balance * 3 >= total_active_balance * 2;
+    justification_bits.shift_and_set(previous_epoch_justified, current_epoch_justified);
+    if previous_epoch_justified {
+        ffg.current_justified_checkpoint = Checkpoint {
             epoch: previous_epoch,
             root: *get_block_root(state, previous_epoch, context)?,
         };
-        state.justification_bits.set(1, true);
     }
-    if current_epoch_target_balance * 3 >= total_active_balance * 2 {
-        state.current_justified_checkpoint = Checkpoint {
+    if current_epoch_justified {
+        ffg.current_justified_checkpoint = Checkpoint {
             epoch: current_epoch,
             root: *get_block_root(state, current_epoch, context)?,
         };
-        state.justification_bits.set(0, true);
     }
-    let bits = &state.justification_bits;
-    if bits[1..4].all() && old_previous_justified_checkpoint.epoch + 3 == current_epoch {
-        state.finalized_checkpoint = old_previous_justified_checkpoint.clone();
+    if (1..4).all(|n| justification_bits.is_justified(n)) &&
+        old_previous_justified_checkpoint.epoch + 3 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_previous_justified_checkpoint.clone();
     }
-    if bits[1..3].all() && old_previous_justified_checkpoint.epoch + 2 == current_epoch {
-        state.finalized_checkpoint = old_previous_justified_checkpoint;
+    if (1..3).all(|n| justification_bits.is_justified(n)) &&
+        old_previous_justified_checkpoint.epoch + 2 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_previous_justified_checkpoint.clone();
     }
-    if bits[0..3].all() && old_current_justified_checkpoint.epoch + 2 == current_epoch {
-        state.finalized_checkpoint = old_current_justified_checkpoint.clone();
+    if (0..3).all(|n| justification_bits.is_justified(n)) &&
+        old_current_justified_checkpoint.epoch + 2 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_current_justified_checkpoint.clone();
     }
-    if bits[0..2].all() && old_current_justified_checkpoint.epoch + 1 == current_epoch {
-        state.finalized_checkpoint = old_current_justified_checkpoint;
+    if (0..2).all(|n| justification_bits.is_justified(n)) &&
+        old_current_justified_checkpoint.epoch + 1 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_current_justified_checkpoint.clone();
     }
-    Ok(())
+    ffg.justification_bits = justification_bits.into();
+    Ok(ffg)
 }
 pub fn get_proposer_reward<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -848,6 +1182,60 @@ pub fn is_in_inactivity_leak<
 ) -> bool {
     get_finality_delay(state, context) > context.min_epochs_to_inactivity_penalty
 }
+pub fn process_epoch_with_summary<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Result<EpochTransitionSummary> {
+    let epoch = get_current_epoch(state, context);
+    let total_slashings = state.slashings.iter().sum();
+    let balances = state.balances.to_vec();
+    let registry_epochs = state
+        .validators
+        .iter()
+        .map(|validator| (validator.activation_epoch, validator.exit_epoch))
+        .collect::<Vec<_>>();
+    process_epoch(state, context)?;
+    let mut summary = EpochTransitionSummary {
+        epoch,
+        total_slashings,
+        finalized_checkpoint: state.finalized_checkpoint.clone(),
+        ..Default::default()
+    };
+    for (index, (&previous, &current)) in balances.iter().zip(state.balances.iter()).enumerate() {
+        if current > previous {
+            summary.total_rewards += current - previous;
+        } else {
+            summary.total_penalties += previous - current;
+        }
+        let (activation_epoch, exit_epoch) = registry_epochs[index];
+        let validator = &state.validators[index];
+        if activation_epoch == FAR_FUTURE_EPOCH && validator.activation_epoch != FAR_FUTURE_EPOCH {
+            summary.activations.push(index);
+        }
+        if exit_epoch == FAR_FUTURE_EPOCH && validator.exit_epoch != FAR_FUTURE_EPOCH {
+            summary.exits.push(index);
+        }
+    }
+    Ok(summary)
+}
 pub fn is_valid_genesis_state<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -1004,14 +1392,14 @@ pub fn is_valid_indexed_attestation<
             InvalidIndexedAttestation::DuplicateIndices(Vec::from_iter(duplicates)),
         )))
     }
-    let mut public_keys = vec![];
-    for &index in &attesting_indices[..] {
-        let public_key = state.validators.get(index).map(|v| &v.public_key).ok_or_else(|| {
+    let mut signers = Vec::with_capacity(attesting_indices.len());
+    for &index in attesting_indices.iter() {
+        let validator = state.validators.get(index).ok_or_else(|| {
             invalid_operation_error(InvalidOperation::IndexedAttestation(
                 InvalidIndexedAttestation::InvalidIndex(index),
             ))
         })?;
-        public_keys.push(public_key);
+        signers.push(&validator.public_key);
     }
     let domain = get_domain(
         state,
@@ -1020,8 +1408,26 @@ pub fn is_valid_indexed_attestation<
         context,
     )?;
     let signing_root = compute_signing_root(&mut indexed_attestation.data, domain)?;
-    fast_aggregate_verify(&public_keys, signing_root.as_ref(), &indexed_attestation.signature)
-        .map_err(Into::into)
+    if context.verified_signature_cache.contains(
+        &signers,
+        &signing_root,
+        &indexed_attestation.signature,
+    ) {
+        return Ok(())
+    }
+    let public_keys = attesting_indices
+        .iter()
+        .map(|&index| {
+            context.decompressed_pubkey_cache.get(&state.validators, index).expect("index is valid")
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let public_keys = public_keys.iter().collect::<Vec<_>>();
+    crate::crypto::fast_aggregate_verify_decompressed(
+        &public_keys,
+        signing_root.as_ref(),
+        &indexed_attestation.signature,
+    )
+    .map_err(Into::into)
 }
 pub fn verify_block_signature<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -1069,7 +1475,8 @@ pub fn verify_block_signature<
     let public_key = &proposer.public_key;
     verify_signature(public_key, signing_root.as_ref(), &signed_block.signature).map_err(Into::into)
 }
-pub fn get_domain<
+pub fn collect_operation_signature_sets<
+    'a,
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1079,7 +1486,7 @@ pub fn get_domain<
     const MAX_VALIDATORS_PER_COMMITTEE: usize,
     const SYNC_COMMITTEE_SIZE: usize,
 >(
-    state: &BeaconState<
+    state: &'a BeaconState<
         SLOTS_PER_HISTORICAL_ROOT,
         HISTORICAL_ROOTS_LIMIT,
         ETH1_DATA_VOTES_BOUND,
@@ -1089,19 +1496,76 @@ pub fn get_domain<
         MAX_VALIDATORS_PER_COMMITTEE,
         SYNC_COMMITTEE_SIZE,
     >,
-    domain_type: DomainType,
-    epoch: Option<Epoch>,
+    proposer_slashings: &'a [ProposerSlashing],
+    attester_slashings: &'a [AttesterSlashing<MAX_VALIDATORS_PER_COMMITTEE>],
+    attestations: &'a [Attestation<MAX_VALIDATORS_PER_COMMITTEE>],
+    batch: &mut crate::signing::SignatureBatch<'a>,
     context: &Context,
-) -> Result<Domain> {
-    let epoch = epoch.unwrap_or_else(|| get_current_epoch(state, context));
-    let fork_version = if epoch < state.fork.epoch {
-        state.fork.previous_version
-    } else {
-        state.fork.current_version
-    };
-    compute_domain(domain_type, Some(fork_version), Some(state.genesis_validators_root), context)
+) -> Result<()> {
+    for proposer_slashing in proposer_slashings {
+        let header_1 = &proposer_slashing.signed_header_1.message;
+        let public_key =
+            state.validators.get(header_1.proposer_index).map(|v| &v.public_key).ok_or_else(
+                || {
+                    invalid_operation_error(InvalidOperation::ProposerSlashing(
+                        InvalidProposerSlashing::InvalidIndex(header_1.proposer_index),
+                    ))
+                },
+            )?;
+        let epoch = compute_epoch_at_slot(header_1.slot, context);
+        let domain = get_domain(state, DomainType::BeaconProposer, Some(epoch), context)?;
+        for signed_header in
+            [&proposer_slashing.signed_header_1, &proposer_slashing.signed_header_2]
+        {
+            let signing_root = compute_signing_root(&mut signed_header.message.clone(), domain)?;
+            let error = invalid_operation_error(InvalidOperation::ProposerSlashing(
+                InvalidProposerSlashing::InvalidSignature(signed_header.signature.clone()),
+            ));
+            batch.push(vec![public_key], signing_root, &signed_header.signature, error);
+        }
+    }
+    let mut signed_attestations =
+        Vec::with_capacity(2 * attester_slashings.len() + attestations.len());
+    for attester_slashing in attester_slashings {
+        for indexed_attestation in
+            [&attester_slashing.attestation_1, &attester_slashing.attestation_2]
+        {
+            signed_attestations.push((
+                indexed_attestation.attesting_indices.to_vec(),
+                &indexed_attestation.data,
+                &indexed_attestation.signature,
+            ));
+        }
+    }
+    for attestation in attestations {
+        let indexed_attestation = get_indexed_attestation(state, attestation, context)?;
+        signed_attestations.push((
+            indexed_attestation.attesting_indices.to_vec(),
+            &attestation.data,
+            &attestation.signature,
+        ));
+    }
+    for (attesting_indices, data, signature) in signed_attestations {
+        let public_keys = attesting_indices
+            .into_iter()
+            .map(|index| {
+                state.validators.get(index).map(|v| &v.public_key).ok_or_else(|| {
+                    invalid_operation_error(InvalidOperation::IndexedAttestation(
+                        InvalidIndexedAttestation::InvalidIndex(index),
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let domain =
+            get_domain(state, DomainType::BeaconAttester, Some(data.target.epoch), context)?;
+        let signing_root = compute_signing_root(&mut data.clone(), domain)?;
+        let error = crate::crypto::Error::InvalidSignature.into();
+        batch.push(public_keys, signing_root, signature, error);
+    }
+    Ok(())
 }
-pub fn get_current_epoch<
+pub fn collect_voluntary_exit_signature_sets<
+    'a,
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1111,7 +1575,7 @@ pub fn get_current_epoch<
     const MAX_VALIDATORS_PER_COMMITTEE: usize,
     const SYNC_COMMITTEE_SIZE: usize,
 >(
-    state: &BeaconState<
+    state: &'a BeaconState<
         SLOTS_PER_HISTORICAL_ROOT,
         HISTORICAL_ROOTS_LIMIT,
         ETH1_DATA_VOTES_BOUND,
@@ -1121,21 +1585,98 @@ pub fn get_current_epoch<
         MAX_VALIDATORS_PER_COMMITTEE,
         SYNC_COMMITTEE_SIZE,
     >,
+    voluntary_exits: &'a [SignedVoluntaryExit],
+    batch: &mut crate::signing::SignatureBatch<'a>,
     context: &Context,
-) -> Epoch {
-    compute_epoch_at_slot(state.slot, context)
-}
-pub fn compute_shuffled_index(
-    mut index: usize,
-    index_count: usize,
-    seed: &Bytes32,
-    context: &Context,
-) -> Result<usize> {
-    if index >= index_count {
-        return Err(Error::InvalidShufflingIndex { index, total: index_count })
+) -> Result<()> {
+    for signed_voluntary_exit in voluntary_exits {
+        let voluntary_exit = &signed_voluntary_exit.message;
+        let public_key = state
+            .validators
+            .get(voluntary_exit.validator_index)
+            .map(|v| &v.public_key)
+            .ok_or_else(|| {
+                invalid_operation_error(InvalidOperation::VoluntaryExit(
+                    InvalidVoluntaryExit::InvalidIndex(voluntary_exit.validator_index),
+                ))
+            })?;
+        let domain =
+            get_domain(state, DomainType::VoluntaryExit, Some(voluntary_exit.epoch), context)?;
+        let signing_root = compute_signing_root(&mut voluntary_exit.clone(), domain)?;
+        let error = invalid_operation_error(InvalidOperation::VoluntaryExit(
+            InvalidVoluntaryExit::InvalidSignature(signed_voluntary_exit.signature.clone()),
+        ));
+        batch.push(vec![public_key], signing_root, &signed_voluntary_exit.signature, error);
     }
-    let mut pivot_input = [0u8; 33];
-    pivot_input[..32].copy_from_slice(seed.as_ref());
+    Ok(())
+}
+pub fn get_domain<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    domain_type: DomainType,
+    epoch: Option<Epoch>,
+    context: &Context,
+) -> Result<Domain> {
+    let epoch = epoch.unwrap_or_else(|| get_current_epoch(state, context));
+    let fork_version = if epoch < state.fork.epoch {
+        state.fork.previous_version
+    } else {
+        state.fork.current_version
+    };
+    compute_domain(domain_type, Some(fork_version), Some(state.genesis_validators_root), context)
+}
+pub fn get_current_epoch<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Epoch {
+    compute_epoch_at_slot(state.slot, context)
+}
+pub fn compute_shuffled_index(
+    mut index: usize,
+    index_count: usize,
+    seed: &Bytes32,
+    context: &Context,
+) -> Result<usize> {
+    if index >= index_count {
+        return Err(Error::InvalidShufflingIndex { index, total: index_count })
+    }
+    let mut pivot_input = [0u8; 33];
+    pivot_input[..32].copy_from_slice(seed.as_ref());
     let mut source_input = [0u8; 37];
     source_input[..32].copy_from_slice(seed.as_ref());
     for current_round in 0..context.shuffle_round_count {
@@ -1154,6 +1695,86 @@ pub fn compute_shuffled_index(
     }
     Ok(index)
 }
+pub fn compute_shuffling_pivots(
+    seed: &Bytes32,
+    index_count: usize,
+    context: &Context,
+) -> Vec<usize> {
+    let mut pivot_input = [0u8; 33];
+    pivot_input[..32].copy_from_slice(seed.as_ref());
+    (0..context.shuffle_round_count)
+        .map(|current_round| {
+            pivot_input[32] = current_round as u8;
+            let pivot_bytes: [u8; 8] = hash(pivot_input).as_ref()[..8].try_into().unwrap();
+            (u64::from_le_bytes(pivot_bytes) as usize) % index_count
+        })
+        .collect()
+}
+pub fn compute_shuffled_index_with_pivots(
+    mut index: usize,
+    index_count: usize,
+    seed: &Bytes32,
+    pivots: &[usize],
+) -> Result<usize> {
+    if index >= index_count {
+        return Err(Error::InvalidShufflingIndex { index, total: index_count })
+    }
+    let mut source_input = [0u8; 37];
+    source_input[..32].copy_from_slice(seed.as_ref());
+    for (current_round, &pivot) in pivots.iter().enumerate() {
+        let flip = (pivot + index_count - index) % index_count;
+        let position = cmp::max(index, flip);
+        let position_bytes: [u8; 4] = ((position / 256) as u32).to_le_bytes();
+        source_input[32] = current_round as u8;
+        source_input[33..].copy_from_slice(&position_bytes);
+        let source = hash(source_input);
+        let byte = source.as_ref()[(position % 256) / 8];
+        let bit = (byte >> (position % 8)) % 2;
+        index = if bit != 0 { flip } else { index };
+    }
+    Ok(index)
+}
+pub fn compute_shuffled_indices(
+    indices: &[ValidatorIndex],
+    seed: &Bytes32,
+    context: &Context,
+) -> Vec<ValidatorIndex> {
+    let mut shuffled = indices.to_vec();
+    let index_count = shuffled.len();
+    if index_count <= 1 {
+        return shuffled
+    }
+    let mut pivot_input = [0u8; 33];
+    pivot_input[..32].copy_from_slice(seed.as_ref());
+    let mut source_input = [0u8; 37];
+    source_input[..32].copy_from_slice(seed.as_ref());
+    for current_round in (0..context.shuffle_round_count).rev() {
+        pivot_input[32] = current_round as u8;
+        let pivot_bytes: [u8; 8] = hash(pivot_input).as_ref()[..8].try_into().unwrap();
+        let pivot = (u64::from_le_bytes(pivot_bytes) as usize) % index_count;
+        source_input[32] = current_round as u8;
+        let mut source_block = None;
+        let mut source = Bytes32::default();
+        for index in 0..index_count {
+            let flip = (pivot + index_count - index) % index_count;
+            if flip <= index {
+                continue;
+            }
+            let position = flip;
+            if source_block != Some(position / 256) {
+                let position_bytes: [u8; 4] = ((position / 256) as u32).to_le_bytes();
+                source_input[33..].copy_from_slice(&position_bytes);
+                source = hash(source_input);
+                source_block = Some(position / 256);
+            }
+            let byte = source.as_ref()[(position % 256) / 8];
+            if (byte >> (position % 8)) & 1 != 0 {
+                shuffled.swap(index, flip);
+            }
+        }
+    }
+    shuffled
+}
 pub fn compute_proposer_index<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -1199,6 +1820,56 @@ pub fn compute_proposer_index<
         i += 1;
     }
 }
+pub fn compute_proposer_index_with_pivots<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    indices: &[ValidatorIndex],
+    seed: &Bytes32,
+    pivots: &[usize],
+    context: &Context,
+) -> Result<ValidatorIndex> {
+    if indices.is_empty() {
+        return Err(Error::CollectionCannotBeEmpty)
+    }
+    let max_byte = u8::MAX as u64;
+    let mut i = 0;
+    let total = indices.len();
+    let mut hash_input = [0u8; 40];
+    hash_input[..32].copy_from_slice(seed.as_ref());
+    let mut random_bytes = Bytes32::default();
+    loop {
+        let shuffled_index = compute_shuffled_index_with_pivots(i % total, total, seed, pivots)?;
+        let candidate_index = indices[shuffled_index];
+        if i % 32 == 0 {
+            let i_bytes: [u8; 8] = (i / 32).to_le_bytes();
+            hash_input[32..].copy_from_slice(&i_bytes);
+            random_bytes = hash(hash_input);
+        }
+        let random_byte = random_bytes.as_ref()[i % 32] as u64;
+        let effective_balance = state.validators[candidate_index].effective_balance;
+        if effective_balance * max_byte >= context.max_effective_balance * random_byte {
+            return Ok(candidate_index)
+        }
+        i += 1;
+    }
+}
 pub fn compute_committee(
     indices: &[ValidatorIndex],
     seed: &Bytes32,
@@ -1517,9 +2188,52 @@ pub fn get_beacon_committee<
     let committees_per_slot = get_committee_count_per_slot(state, epoch, context);
     let indices = get_active_validator_indices(state, epoch);
     let seed = get_seed(state, epoch, DomainType::BeaconAttester, context);
-    let index = (slot % context.slots_per_epoch) * committees_per_slot as u64 + index as u64;
-    let count = committees_per_slot as u64 * context.slots_per_epoch;
-    compute_committee(&indices, &seed, index as usize, count as usize, context)
+    let index = (slot % context.slots_per_epoch) as usize * committees_per_slot + index;
+    let count = committees_per_slot * context.slots_per_epoch as usize;
+    let shuffling = context.committee_cache.get_or_compute(epoch, &seed, indices, context)?;
+    let start = (shuffling.len() * index) / count;
+    let end = shuffling.len() * (index + 1) / count;
+    Ok(shuffling[start..end].to_vec())
+}
+pub fn get_committee_assignment<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    epoch: Epoch,
+    validator_index: ValidatorIndex,
+    context: &Context,
+) -> Result<Option<(Vec<ValidatorIndex>, CommitteeIndex, Slot)>> {
+    let next_epoch = get_current_epoch(state, context) + 1;
+    if epoch > next_epoch {
+        return Err(Error::EpochAfterNextEpoch { requested: epoch, next: next_epoch })
+    }
+    let start_slot = compute_start_slot_at_epoch(epoch, context);
+    let committee_count_per_slot = get_committee_count_per_slot(state, epoch, context);
+    for slot in start_slot..start_slot + context.slots_per_epoch {
+        for index in 0..committee_count_per_slot {
+            let committee = get_beacon_committee(state, slot, index, context)?;
+            if committee.contains(&validator_index) {
+                return Ok(Some((committee, index, slot)))
+            }
+        }
+    }
+    Ok(None)
 }
 pub fn get_beacon_proposer_index<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -1552,6 +2266,50 @@ pub fn get_beacon_proposer_index<
     let indices = get_active_validator_indices(state, epoch);
     compute_proposer_index(state, &indices, &seed, context)
 }
+pub fn get_beacon_proposer_indices<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    epoch: Epoch,
+    context: &Context,
+) -> Result<Vec<ValidatorIndex>> {
+    let current_epoch = get_current_epoch(state, context);
+    if epoch != current_epoch && epoch != current_epoch + 1 {
+        return Err(Error::EpochNotCurrentOrNext { requested: epoch, current: current_epoch })
+    }
+    let epoch_seed = get_seed(state, epoch, DomainType::BeaconProposer, context);
+    let indices = get_active_validator_indices(state, epoch);
+    if indices.is_empty() {
+        return Err(Error::CollectionCannotBeEmpty)
+    }
+    let start_slot = compute_start_slot_at_epoch(epoch, context);
+    let mut input = [0u8; 40];
+    input[..32].copy_from_slice(epoch_seed.as_ref());
+    (start_slot..start_slot + context.slots_per_epoch)
+        .map(|slot| {
+            input[32..40].copy_from_slice(&slot.to_le_bytes());
+            let seed = hash(input);
+            let pivots = compute_shuffling_pivots(&seed, indices.len(), context);
+            compute_proposer_index_with_pivots(state, &indices, &seed, &pivots, context)
+        })
+        .collect()
+}
 pub fn get_total_balance<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -1603,8 +2361,48 @@ pub fn get_total_active_balance<
     >,
     context: &Context,
 ) -> Result<Gwei> {
+    let key = get_total_active_balance_cache_key(state, context);
+    if let Some(total) =
+        key.and_then(|(epoch, root)| context.total_active_balance_cache.get(epoch, &root))
+    {
+        return Ok(total)
+    }
     let indices = get_active_validator_indices(state, get_current_epoch(state, context));
-    get_total_balance(state, &HashSet::from_iter(indices), context)
+    let total = get_total_balance(state, &HashSet::from_iter(indices), context)?;
+    if let Some((epoch, root)) = key {
+        context.total_active_balance_cache.insert(epoch, root, total);
+    }
+    Ok(total)
+}
+pub fn get_total_active_balance_cache_key<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    context: &Context,
+) -> Option<(Epoch, Root)> {
+    let epoch = get_current_epoch(state, context);
+    if epoch == GENESIS_EPOCH {
+        return None
+    }
+    let slot = compute_start_slot_at_epoch(epoch, context) - 1;
+    let root = state.state_roots[(slot % context.slots_per_historical_root) as usize];
+    (root != Root::default()).then_some((epoch, root))
 }
 pub fn get_indexed_attestation<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -1832,14 +2630,44 @@ pub fn process_slots<
     >,
     slot: Slot,
     context: &Context,
+) -> Result<()> {
+    process_slots_with_hook(state, slot, context, |_, _, _| {})
+}
+pub fn process_slots_with_hook<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    F: FnMut(Slot, Root, Root),
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    slot: Slot,
+    context: &Context,
+    mut on_slot: F,
 ) -> Result<()> {
     if state.slot >= slot {
         return Err(Error::TransitionToPreviousSlot { requested: slot, current: state.slot })
     }
     while state.slot < slot {
         process_slot(state, context)?;
+        let root_index = (state.slot % context.slots_per_historical_root) as usize;
+        on_slot(state.slot, state.state_roots[root_index], state.block_roots[root_index]);
         if (state.slot + 1) % context.slots_per_epoch == 0 {
             process_epoch(state, context)?;
+            context.committee_cache.prune((state.slot + 1) / context.slots_per_epoch);
         }
         state.slot += 1;
     }
@@ -1920,7 +2748,7 @@ pub fn state_transition_block_in_slot<
         Validation::Disabled => false,
     };
     if validate_result {
-        verify_block_signature(state, signed_block, context)?;
+        verify_block_signatures(state, signed_block, context)?;
     }
     let block = &mut signed_block.message;
     process_block(state, block, context)?;
@@ -1970,3 +2798,194 @@ pub fn state_transition<
     process_slots(state, signed_block.message.slot, context)?;
     state_transition_block_in_slot(state, signed_block, validation, context)
 }
+pub fn try_state_transition<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    let crosses_epoch = compute_epoch_at_slot(state.slot, context) !=
+        compute_epoch_at_slot(signed_block.message.slot, context);
+    let validators = std::mem::take(&mut state.validators);
+    let mut pre_state = state.clone();
+    state.validators = validators;
+    let registry_len = state.validators.len();
+    let saved_validators = if crosses_epoch {
+        pre_state.validators = state.validators.clone();
+        vec![]
+    } else {
+        get_validator_indices_touched_by_operations(&signed_block.message.body)
+            .into_iter()
+            .filter_map(|index| Some((index, state.validators.get(index)?.clone())))
+            .collect::<Vec<_>>()
+    };
+    let result = state_transition(state, signed_block, validation, context);
+    if result.is_err() {
+        if !crosses_epoch {
+            let mut validators = std::mem::take(&mut state.validators);
+            while validators.len() > registry_len {
+                validators.pop();
+            }
+            for (index, validator) in saved_validators {
+                validators[index] = validator;
+            }
+            pre_state.validators = validators;
+        }
+        *state = pre_state;
+    }
+    result
+}
+pub fn replay_blocks<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+    >],
+    target_slot: crate::primitives::Slot,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, validation, context)?;
+    }
+    if state.slot != target_slot {
+        process_slots(state, target_slot, context)?;
+    }
+    Ok(())
+}
+pub fn verify_blocks_parallel<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    F: Fn(
+            &SignedBeaconBlock<
+                MAX_PROPOSER_SLASHINGS,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                MAX_ATTESTER_SLASHINGS,
+                MAX_ATTESTATIONS,
+                MAX_DEPOSITS,
+                MAX_VOLUNTARY_EXITS,
+                SYNC_COMMITTEE_SIZE,
+            >,
+        ) -> Result<
+            BeaconState<
+                SLOTS_PER_HISTORICAL_ROOT,
+                HISTORICAL_ROOTS_LIMIT,
+                ETH1_DATA_VOTES_BOUND,
+                VALIDATOR_REGISTRY_LIMIT,
+                EPOCHS_PER_HISTORICAL_VECTOR,
+                EPOCHS_PER_SLASHINGS_VECTOR,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                SYNC_COMMITTEE_SIZE,
+            >,
+        > + Send
+        + Sync,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+    >],
+    state_provider: F,
+    context: &Context,
+) -> Result<()> {
+    crate::state_transition::for_each_mut(signed_blocks, |signed_block| {
+        let mut pre_state = match state_provider(signed_block) {
+            Ok(pre_state) => pre_state,
+            Err(_) => return,
+        };
+        if pre_state.slot < signed_block.message.slot &&
+            process_slots(&mut pre_state, signed_block.message.slot, context).is_err()
+        {
+            return
+        }
+        let _ = verify_block_signatures(&pre_state, signed_block, context);
+    });
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, Validation::Enabled, context)?;
+    }
+    Ok(())
+}
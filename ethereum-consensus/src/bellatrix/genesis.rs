@@ -90,6 +90,8 @@ pub fn initialize_beacon_state_from_eth1<
         ..Default::default()
     };
 
+    // the deposits build a new registry
+    context.pubkey_cache.clear();
     let mut leaves = List::<DepositData, DEPOSIT_DATA_LIST_BOUND>::default();
     for deposit in deposits.iter_mut() {
         leaves.push(deposit.data.clone());
@@ -1,16 +1,16 @@
 use crate::{
     bellatrix::{
-        decrease_balance, get_beacon_proposer_index, get_current_epoch,
-        get_eligible_validator_indices, get_previous_epoch, get_unslashed_participating_indices,
-        increase_balance, initiate_validator_exit, BeaconBlockBody, BeaconState, ExecutionPayload,
-        ExecutionPayloadHeader, PROPOSER_WEIGHT, TIMELY_TARGET_FLAG_INDEX, WEIGHT_DENOMINATOR,
+        decrease_balance, get_beacon_proposer_index, get_current_epoch, get_total_active_balance,
+        increase_balance, initiate_validator_exit, BeaconBlockBody, BeaconState,
+        EpochProcessingContext, ExecutionPayload, ExecutionPayloadHeader, ParticipationCache,
+        PROPOSER_WEIGHT, TIMELY_TARGET_FLAG_INDEX, WEIGHT_DENOMINATOR,
     },
     primitives::{Gwei, Slot, ValidatorIndex, GENESIS_SLOT},
     state_transition::{Context, Result},
     Error,
 };
 
-pub fn get_inactivity_penalty_deltas<
+pub fn compute_inactivity_penalty_deltas<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -34,28 +34,79 @@ pub fn get_inactivity_penalty_deltas<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
+    participation: &ParticipationCache,
+    scratch: &mut EpochProcessingContext,
     context: &Context,
-) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
-    let validator_count = state.validators.len();
-    let rewards = vec![0; validator_count];
-    let mut penalties = vec![0; validator_count];
-    let previous_epoch = get_previous_epoch(state, context);
-    let matching_target_indices = get_unslashed_participating_indices(
-        state,
-        TIMELY_TARGET_FLAG_INDEX,
-        previous_epoch,
-        context,
-    )?;
-    for i in get_eligible_validator_indices(state, context) {
-        if !matching_target_indices.contains(&i) {
+) -> Result<()> {
+    scratch.reset_deltas(state.validators.len());
+    let previous_epoch = participation.previous_epoch;
+    let penalty_deltas =
+        crate::state_transition::map_validator_indices(&scratch.eligible_indices, |i| {
+            if participation.is_unslashed_participating(
+                i,
+                TIMELY_TARGET_FLAG_INDEX,
+                previous_epoch,
+            )? {
+                return Ok(0)
+            }
             let penalty_numerator =
                 state.validators[i].effective_balance * state.inactivity_scores[i];
             let penalty_denominator =
                 context.inactivity_score_bias * context.inactivity_penalty_quotient_bellatrix;
-            penalties[i] += penalty_numerator / penalty_denominator;
+            Ok(penalty_numerator / penalty_denominator)
+        })?;
+    for (&i, penalty) in scratch.eligible_indices.iter().zip(penalty_deltas) {
+        scratch.penalties[i] += penalty;
+    }
+    Ok(())
+}
+
+pub fn process_slashings_with_context<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    epoch_context: &EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    let epoch = get_current_epoch(state, context);
+    let total_balance = get_total_active_balance(state, context)?;
+    let adjusted_total_slashing_balance = Gwei::min(
+        state.slashings.iter().sum::<Gwei>() * context.proportional_slashing_multiplier_bellatrix,
+        total_balance,
+    );
+    for i in 0..state.validators.len() {
+        let validator = &state.validators[i];
+        if validator.slashed &&
+            (epoch + context.epochs_per_slashings_vector / 2) == validator.withdrawable_epoch
+        {
+            let increment = context.effective_balance_increment;
+            let penalty_numerator =
+                epoch_context.effective_balance_increments[i] * adjusted_total_slashing_balance;
+            let penalty = penalty_numerator / total_balance * increment;
+            decrease_balance(state, i, penalty);
         }
     }
-    Ok((rewards, penalties))
+    Ok(())
 }
 
 pub fn slash_validator<
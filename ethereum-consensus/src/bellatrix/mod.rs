@@ -19,4 +19,4 @@ pub mod state_transition;
 
 pub use spec::*;
 
-pub use presets::{mainnet, minimal, Preset};
+pub use presets::{gnosis, mainnet, minimal, Preset};
@@ -1,3 +1,4 @@
+pub mod gnosis;
 pub mod mainnet;
 pub mod minimal;
 
@@ -1,8 +1,9 @@
 use crate::{
     bellatrix::{
-        process_block, process_slots, verify_block_signature, BeaconState, ExecutionEngine,
-        SignedBeaconBlock,
+        compute_epoch_at_slot, get_validator_indices_touched_by_operations, process_block,
+        process_slots, verify_block_signatures, BeaconState, ExecutionEngine, SignedBeaconBlock,
     },
+    primitives::Slot,
     ssz::prelude::Merkleized,
     state_transition::{Context, Result, Validation},
     Error,
@@ -70,7 +71,9 @@ pub fn state_transition_block_in_slot<
         Validation::Disabled => false,
     };
     if validate_result {
-        verify_block_signature(state, signed_block, context)?;
+        // Check every signature in the block in one batch. `process_block` then skips the
+        // signatures recorded as verified in `context.verified_signature_cache`.
+        verify_block_signatures(state, signed_block, context)?;
     }
     let block = &mut signed_block.message;
     process_block(state, block, execution_engine, context)?;
@@ -139,3 +142,268 @@ pub fn state_transition<
 
     state_transition_block_in_slot(state, signed_block, execution_engine, validation, context)
 }
+
+// Like `state_transition` but leaves `state` as it was before the call if the transition fails.
+//
+// Everything but the validator registry is copied up front. Of the registry, only the validators
+// the operations of `signed_block` may slash or exit are saved, and validators appended by
+// deposits are dropped on failure. Epoch processing may update any validator, so a transition
+// across an epoch boundary saves the whole registry.
+pub fn try_state_transition<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    E: ExecutionEngine<
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+    >,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+    >,
+    execution_engine: &E,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    let crosses_epoch = compute_epoch_at_slot(state.slot, context) !=
+        compute_epoch_at_slot(signed_block.message.slot, context);
+    let validators = std::mem::take(&mut state.validators);
+    let mut pre_state = state.clone();
+    state.validators = validators;
+
+    let registry_len = state.validators.len();
+    let saved_validators = if crosses_epoch {
+        pre_state.validators = state.validators.clone();
+        vec![]
+    } else {
+        get_validator_indices_touched_by_operations(&signed_block.message.body)
+            .into_iter()
+            .filter_map(|index| Some((index, state.validators.get(index)?.clone())))
+            .collect::<Vec<_>>()
+    };
+
+    let result = state_transition(state, signed_block, execution_engine, validation, context);
+    if result.is_err() {
+        if !crosses_epoch {
+            let mut validators = std::mem::take(&mut state.validators);
+            while validators.len() > registry_len {
+                validators.pop();
+            }
+            for (index, validator) in saved_validators {
+                validators[index] = validator;
+            }
+            pre_state.validators = validators;
+        }
+        *state = pre_state;
+    }
+    result
+}
+
+// Apply each of `signed_blocks` in order, then advance through any empty slots
+// up to `target_slot`. Use `Validation::Disabled` to skip signature and state
+// root checks when replaying blocks from a trusted source.
+pub fn replay_blocks<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    E: ExecutionEngine<
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+    >,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+    >],
+    target_slot: Slot,
+    execution_engine: &E,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, execution_engine, validation, context)?;
+    }
+    if state.slot != target_slot {
+        process_slots(state, target_slot, context)?;
+    }
+    Ok(())
+}
+
+// Apply each of `signed_blocks` to `state` in order with full validation, verifying the
+// signatures of all blocks up front on a best-effort basis. See the phase0 version for details.
+pub fn verify_blocks_parallel<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    E: ExecutionEngine<
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+    >,
+    F: Fn(
+            &SignedBeaconBlock<
+                MAX_PROPOSER_SLASHINGS,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                MAX_ATTESTER_SLASHINGS,
+                MAX_ATTESTATIONS,
+                MAX_DEPOSITS,
+                MAX_VOLUNTARY_EXITS,
+                SYNC_COMMITTEE_SIZE,
+                BYTES_PER_LOGS_BLOOM,
+                MAX_EXTRA_DATA_BYTES,
+                MAX_BYTES_PER_TRANSACTION,
+                MAX_TRANSACTIONS_PER_PAYLOAD,
+            >,
+        ) -> Result<
+            BeaconState<
+                SLOTS_PER_HISTORICAL_ROOT,
+                HISTORICAL_ROOTS_LIMIT,
+                ETH1_DATA_VOTES_BOUND,
+                VALIDATOR_REGISTRY_LIMIT,
+                EPOCHS_PER_HISTORICAL_VECTOR,
+                EPOCHS_PER_SLASHINGS_VECTOR,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                SYNC_COMMITTEE_SIZE,
+                BYTES_PER_LOGS_BLOOM,
+                MAX_EXTRA_DATA_BYTES,
+            >,
+        > + Send
+        + Sync,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+    >],
+    state_provider: F,
+    execution_engine: &E,
+    context: &Context,
+) -> Result<()> {
+    crate::state_transition::for_each_mut(signed_blocks, |signed_block| {
+        let mut pre_state = match state_provider(signed_block) {
+            Ok(pre_state) => pre_state,
+            Err(_) => return,
+        };
+        if pre_state.slot < signed_block.message.slot &&
+            process_slots(&mut pre_state, signed_block.message.slot, context).is_err()
+        {
+            return
+        }
+        // only warms the cache; the sequential pass below reports any invalid signature
+        let _ = verify_block_signatures(&pre_state, signed_block, context);
+    });
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, execution_engine, Validation::Enabled, context)?;
+    }
+    Ok(())
+}
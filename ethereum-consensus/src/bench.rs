@@ -0,0 +1,150 @@
+//! Synthetic mainnet-scale states for benchmarking the state transition.
+//!
+//! States are built directly rather than from deposits, so a registry of a million validators
+//! is ready in seconds. Validators share a small pool of deterministic keys so blocks carrying
+//! valid signatures can still be produced for them.
+use crate::{
+    altair::mainnet::{
+        get_beacon_proposer_index, get_current_epoch, get_domain, get_next_sync_committee,
+        process_slots, BeaconBlock, BeaconState, Validator,
+    },
+    crypto::SecretKey,
+    domains::DomainType,
+    primitives::{Bytes32, ParticipationFlags, FAR_FUTURE_EPOCH},
+    signing::sign_with_domain,
+    ssz::prelude::*,
+    state_transition::{Context, Result},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Debug, Clone)]
+pub struct StateGenerator {
+    pub validator_count: usize,
+    // probability that a validator has each participation flag set, in each epoch
+    pub participation_rate: f64,
+    // number of distinct keys shared round-robin across the registry
+    pub key_count: usize,
+    pub epoch: u64,
+    pub seed: u64,
+}
+
+impl Default for StateGenerator {
+    fn default() -> Self {
+        Self {
+            validator_count: 1_000_000,
+            participation_rate: 0.95,
+            key_count: 256,
+            epoch: 10,
+            seed: 0,
+        }
+    }
+}
+
+impl StateGenerator {
+    pub fn new(validator_count: usize) -> Self {
+        Self { validator_count, ..Default::default() }
+    }
+
+    // Return the key of the validator at `index`.
+    pub fn secret_key(&self, index: usize) -> SecretKey {
+        let mut ikm = [0u8; 32];
+        ikm[..8].copy_from_slice(&((index % self.key_count) as u64).to_le_bytes());
+        SecretKey::key_gen(&ikm).expect("ikm is long enough")
+    }
+
+    // Build an altair state at the last slot of `epoch`, so the next slot processed runs an epoch
+    // transition.
+    pub fn altair_state(&self, context: &Context) -> Result<BeaconState> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let public_keys =
+            (0..self.key_count).map(|i| self.secret_key(i).public_key()).collect::<Vec<_>>();
+
+        let mut state = BeaconState {
+            slot: (self.epoch + 1) * context.slots_per_epoch - 1,
+            ..Default::default()
+        };
+        state.fork.previous_version = context.genesis_fork_version;
+        state.fork.current_version = context.altair_fork_version;
+        rng.fill::<[u8]>(state.genesis_validators_root.as_mut());
+        for mix in state.randao_mixes.iter_mut() {
+            *mix = Bytes32::try_from(rng.gen::<[u8; 32]>().as_ref()).expect("correct length");
+        }
+
+        let mut participation = || {
+            (0..3).fold(0 as ParticipationFlags, |flags, flag_index| {
+                if rng.gen_bool(self.participation_rate) {
+                    flags | (1 << flag_index)
+                } else {
+                    flags
+                }
+            })
+        };
+        let mut flags = Vec::with_capacity(self.validator_count * 2);
+        for _ in 0..self.validator_count * 2 {
+            flags.push(participation());
+        }
+        for (i, flags) in flags.chunks(2).enumerate() {
+            state.validators.push(Validator {
+                public_key: public_keys[i % self.key_count].clone(),
+                effective_balance: context.max_effective_balance,
+                activation_eligibility_epoch: 0,
+                activation_epoch: 0,
+                exit_epoch: FAR_FUTURE_EPOCH,
+                withdrawable_epoch: FAR_FUTURE_EPOCH,
+                ..Default::default()
+            });
+            state.balances.push(context.max_effective_balance + rng.gen_range(0..1_000_000));
+            state.previous_epoch_participation.push(flags[0]);
+            state.current_epoch_participation.push(flags[1]);
+            state.inactivity_scores.push(0);
+        }
+
+        let sync_committee = get_next_sync_committee(&state, context)?;
+        state.current_sync_committee = sync_committee.clone();
+        state.next_sync_committee = sync_committee;
+        Ok(state)
+    }
+
+    // Advance `state` to the next slot and return an empty block for it with a valid RANDAO
+    // reveal, ready for `process_block`.
+    pub fn next_block(&self, state: &mut BeaconState, context: &Context) -> Result<BeaconBlock> {
+        process_slots(state, state.slot + 1, context)?;
+        let proposer_index = get_beacon_proposer_index(state, context)?;
+        let mut epoch = get_current_epoch(state, context);
+        let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
+        let mut block = BeaconBlock {
+            slot: state.slot,
+            proposer_index,
+            parent_root: state.latest_block_header.hash_tree_root()?,
+            ..Default::default()
+        };
+        block.body.randao_reveal =
+            sign_with_domain(&mut epoch, &self.secret_key(proposer_index), domain)?;
+        block.body.eth1_data = state.eth1_data.clone();
+        // an empty sync aggregate carries the point at infinity
+        let mut infinity = [0u8; 96];
+        infinity[0] = 0xc0;
+        block.body.sync_aggregate.sync_committee_signature = infinity.as_ref().try_into()?;
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::altair::mainnet::{process_block, process_epoch};
+
+    #[test]
+    fn test_generated_state_transitions() {
+        let context = Context::for_mainnet();
+        let generator = StateGenerator { key_count: 8, ..StateGenerator::new(256) };
+        let mut state = generator.altair_state(&context).unwrap();
+        assert_eq!(state.validators.len(), 256);
+
+        let mut epoch_state = state.clone();
+        process_epoch(&mut epoch_state, &context).unwrap();
+
+        let mut block = generator.next_block(&mut state, &context).unwrap();
+        process_block(&mut state, &mut block, &context).unwrap();
+    }
+}
@@ -9,12 +9,12 @@ use crate::{
         ExecutionPayload, ExecutionPayloadHeader, NewPayloadRequest, SignedBlsToExecutionChange,
         Withdrawal,
     },
-    crypto::{hash, verify_signature},
+    crypto::hash,
     error::{
         invalid_operation_error, InvalidBlsToExecutionChange, InvalidDeposit,
         InvalidExecutionPayload, InvalidOperation, InvalidWithdrawals,
     },
-    primitives::{BLS_WITHDRAWAL_PREFIX, ETH1_ADDRESS_WITHDRAWAL_PREFIX},
+    primitives::{ValidatorIndex, BLS_WITHDRAWAL_PREFIX, ETH1_ADDRESS_WITHDRAWAL_PREFIX},
     ssz::prelude::*,
     state_transition::{Context, Result},
 };
@@ -79,7 +79,7 @@ pub fn process_bls_to_execution_change<
         )))
     }
 
-    verify_signature(public_key, signing_root.as_ref(), signature)?;
+    context.verified_signature_cache.verify_signature(public_key, &signing_root, signature)?;
 
     withdrawal_credentials[0] = ETH1_ADDRESS_WITHDRAWAL_PREFIX;
     withdrawal_credentials[1..12].fill(0);
@@ -88,6 +88,57 @@ pub fn process_bls_to_execution_change<
     Ok(())
 }
 
+// Return the indices of the validators that the operations in `body` may slash, exit or change
+// the withdrawal credentials of. Other operations only append to the registry. The indices may
+// repeat or be out of range.
+pub fn get_validator_indices_touched_by_operations<
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+>(
+    body: &BeaconBlockBody<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+    >,
+) -> Vec<ValidatorIndex> {
+    let mut indices = vec![];
+    for proposer_slashing in body.proposer_slashings.iter() {
+        indices.push(proposer_slashing.signed_header_1.message.proposer_index);
+    }
+    for attester_slashing in body.attester_slashings.iter() {
+        // only validators in both attestations are slashed
+        indices.extend(attester_slashing.attestation_1.attesting_indices.iter().copied());
+    }
+    for voluntary_exit in body.voluntary_exits.iter() {
+        indices.push(voluntary_exit.message.validator_index);
+    }
+    for bls_to_execution_change in body.bls_to_execution_changes.iter() {
+        indices.push(bls_to_execution_change.message.validator_index);
+    }
+    indices
+}
+
 pub fn process_operations<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
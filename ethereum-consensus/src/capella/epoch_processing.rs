@@ -1,10 +1,12 @@
 use crate::{
     capella::{
-        get_current_epoch, process_effective_balance_updates, process_eth1_data_reset,
-        process_inactivity_updates, process_justification_and_finalization,
-        process_participation_flag_updates, process_randao_mixes_reset, process_registry_updates,
-        process_rewards_and_penalties, process_slashings, process_slashings_reset,
-        process_sync_committee_updates, BeaconState, HistoricalSummary,
+        get_current_epoch, get_epoch_processing_context, process_effective_balance_updates,
+        process_eth1_data_reset, process_inactivity_updates,
+        process_justification_and_finalization, process_participation_flag_updates,
+        process_randao_mixes_reset, process_registry_updates,
+        process_rewards_and_penalties_with_context, process_slashings_reset,
+        process_slashings_with_context, process_sync_committee_updates, BeaconState,
+        HistoricalSummary,
     },
     ssz::prelude::*,
     state_transition::{Context, Result},
@@ -75,9 +77,10 @@ pub fn process_epoch<
 ) -> Result<()> {
     process_justification_and_finalization(state, context)?;
     process_inactivity_updates(state, context)?;
-    process_rewards_and_penalties(state, context)?;
+    let mut epoch_context = get_epoch_processing_context(state, context);
+    process_rewards_and_penalties_with_context(state, &mut epoch_context, context)?;
     process_registry_updates(state, context);
-    process_slashings(state, context)?;
+    process_slashings_with_context(state, &epoch_context, context)?;
     process_eth1_data_reset(state, context);
     process_effective_balance_updates(state, context);
     process_slashings_reset(state, context);
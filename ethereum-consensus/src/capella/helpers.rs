@@ -1,7 +1,17 @@
 use crate::{
+    capella::{
+        beacon_block::SignedBeaconBlock, beacon_state::BeaconState,
+        bls_to_execution_change::SignedBlsToExecutionChange, collect_operation_signature_sets,
+        collect_sync_aggregate_signature_sets, collect_voluntary_exit_signature_sets,
+        compute_domain, get_beacon_proposer_index, get_current_epoch, get_domain,
+    },
+    domains::DomainType,
+    error::{invalid_operation_error, InvalidOperation},
     phase0::validator::Validator,
     primitives::{Epoch, Gwei, ETH1_ADDRESS_WITHDRAWAL_PREFIX},
-    state_transition::Context,
+    signing::compute_signing_root,
+    state_transition::{Context, Result},
+    Error,
 };
 
 pub fn has_eth1_withdrawal_credential(validator: &Validator) -> bool {
@@ -23,3 +33,146 @@ pub fn is_partially_withdrawable_validator(
     let has_excess_balance = balance > context.max_effective_balance;
     has_eth1_withdrawal_credential(validator) && has_max_effective_balance && has_excess_balance
 }
+
+// Add the signatures of the BLS to execution changes in a block to `batch`.
+pub fn collect_bls_to_execution_change_signature_sets<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    bls_to_execution_changes: &'a [SignedBlsToExecutionChange],
+    batch: &mut crate::signing::SignatureBatch<'a>,
+    context: &Context,
+) -> Result<()> {
+    let domain = compute_domain(
+        DomainType::BlsToExecutionChange,
+        None,
+        Some(state.genesis_validators_root),
+        context,
+    )?;
+    for signed_address_change in bls_to_execution_changes {
+        let address_change = &signed_address_change.message;
+        let signing_root = compute_signing_root(&mut address_change.clone(), domain)?;
+        let error = crate::crypto::Error::InvalidSignature.into();
+        batch.push(
+            vec![&address_change.from_bls_public_key],
+            signing_root,
+            &signed_address_change.signature,
+            error,
+        );
+    }
+    Ok(())
+}
+
+// Verify every signature in `signed_block` that `process_block` would check, using a single
+// batched verification. See the phase0 version for details.
+pub fn verify_block_signatures<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+    >,
+    context: &Context,
+) -> Result<()> {
+    let proposer_index = signed_block.message.proposer_index;
+    let proposer = state
+        .validators
+        .get(proposer_index)
+        .ok_or(Error::OutOfBounds { requested: proposer_index, bound: state.validators.len() })?;
+    let domain = get_domain(state, DomainType::BeaconProposer, None, context)?;
+    let signing_root = compute_signing_root(&mut signed_block.message, domain)?;
+
+    let signed_block = &*signed_block;
+    let body = &signed_block.message.body;
+    let mut batch = crate::signing::SignatureBatch::default();
+    let error = crate::crypto::Error::InvalidSignature.into();
+    batch.push(vec![&proposer.public_key], signing_root, &signed_block.signature, error);
+
+    let mut epoch = get_current_epoch(state, context);
+    let randao_proposer = &state.validators[get_beacon_proposer_index(state, context)?];
+    let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
+    let signing_root = compute_signing_root(&mut epoch, domain)?;
+    let error = invalid_operation_error(InvalidOperation::Randao(body.randao_reveal.clone()));
+    batch.push(vec![&randao_proposer.public_key], signing_root, &body.randao_reveal, error);
+
+    collect_operation_signature_sets(
+        state,
+        &body.proposer_slashings,
+        &body.attester_slashings,
+        &body.attestations,
+        &mut batch,
+        context,
+    )?;
+    collect_voluntary_exit_signature_sets(state, &body.voluntary_exits, &mut batch, context)?;
+    collect_sync_aggregate_signature_sets(state, &body.sync_aggregate, &mut batch, context)?;
+    collect_bls_to_execution_change_signature_sets(
+        state,
+        &body.bls_to_execution_changes,
+        &mut batch,
+        context,
+    )?;
+
+    batch.verify(&context.verified_signature_cache)
+}
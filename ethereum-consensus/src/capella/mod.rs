@@ -18,4 +18,4 @@ pub mod withdrawal;
 
 pub use spec::*;
 
-pub use presets::{mainnet, minimal, Preset};
+pub use presets::{gnosis, mainnet, minimal, Preset};
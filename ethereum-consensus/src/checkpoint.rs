@@ -0,0 +1,637 @@
+//! A single-file bundle of trusted checkpoint data that can be exchanged
+//! between operators and fully verified on import.
+use crate::{
+    altair::{
+        helpers::compute_sync_committee_period_at_slot,
+        light_client::{
+            LightClientBootstrap, CURRENT_SYNC_COMMITTEE_INDEX,
+            CURRENT_SYNC_COMMITTEE_INDEX_FLOOR_LOG_2,
+        },
+    },
+    domains::DomainType,
+    phase0::{compute_domain, BeaconBlockHeader, Validator},
+    primitives::{Root, Slot, ValidatorIndex, GENESIS_SLOT},
+    signing::compute_signing_root,
+    ssz::prelude::*,
+    state_transition::Context,
+    types::{BeaconState, SignedBeaconBlock},
+    Fork,
+};
+use std::io::{Read, Write};
+use thiserror::Error;
+
+pub const CHECKPOINT_BUNDLE_FORMAT_VERSION: u64 = 1;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Merkleization(#[from] MerkleizationError),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Consensus(#[from] crate::Error),
+    #[error("unsupported bundle format version {0}")]
+    UnsupportedFormatVersion(u64),
+    #[error("expected fork {expected:?} for slot {slot} but bundle contains {provided:?}")]
+    ForkMismatch { slot: Slot, expected: Fork, provided: Fork },
+    #[error("block slot {block_slot} is after state slot {state_slot}")]
+    SlotMismatch { state_slot: Slot, block_slot: Slot },
+    #[error("block commits to state root {expected} but the bundled state records {provided}")]
+    StateRootMismatch { expected: Root, provided: Root },
+    #[error("latest block header in state has root {provided} but the bundled block has root {expected}")]
+    BlockRootMismatch { expected: Root, provided: Root },
+    #[error("metadata does not match the bundled data")]
+    MetadataMismatch,
+    #[error("light client bootstrap does not match the bundled block")]
+    BootstrapHeaderMismatch,
+    #[error("light client bootstrap sync committee does not match the bundled state")]
+    BootstrapSyncCommitteeMismatch,
+    #[error("light client bootstrap has an invalid sync committee branch")]
+    InvalidBootstrapBranch,
+    #[error("light client bootstrap is required for fork {0:?}")]
+    MissingBootstrap(Fork),
+    #[error(
+        "block at slot {slot} has root {provided} but its child expects parent root {expected}"
+    )]
+    BackfillParentMismatch { slot: Slot, expected: Root, provided: Root },
+    #[error("block at slot {slot} is not older than its child at slot {child_slot}")]
+    BackfillSlotNotDecreasing { slot: Slot, child_slot: Slot },
+    #[error("proposer with index {0} is not in the validator registry")]
+    UnknownProposer(ValidatorIndex),
+    #[error("block at slot {slot} has an invalid signature from proposer {proposer_index}")]
+    InvalidProposerSignature { slot: Slot, proposer_index: ValidatorIndex },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CheckpointMetadata {
+    #[serde(with = "crate::serde::as_str")]
+    pub format_version: u64,
+    pub fork: Fork,
+    #[serde(with = "crate::serde::as_str")]
+    pub slot: Slot,
+    pub block_root: Root,
+    pub state_root: Root,
+    pub genesis_validators_root: Root,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CheckpointBundle<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+> {
+    pub metadata: CheckpointMetadata,
+    pub state: BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    pub block: SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >,
+    // `None` only for phase0 checkpoints, which predate sync committees.
+    pub bootstrap: Option<LightClientBootstrap<SYNC_COMMITTEE_SIZE>>,
+}
+
+impl<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+        const MAX_PROPOSER_SLASHINGS: usize,
+        const MAX_ATTESTER_SLASHINGS: usize,
+        const MAX_ATTESTATIONS: usize,
+        const MAX_DEPOSITS: usize,
+        const MAX_VOLUNTARY_EXITS: usize,
+        const MAX_BYTES_PER_TRANSACTION: usize,
+        const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+        const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+        const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+        const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+    >
+    CheckpointBundle<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_PROPOSER_SLASHINGS,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >
+{
+    // Assemble a bundle from a finalized state and the block that produced it,
+    // computing the metadata from the provided data.
+    pub fn new(
+        mut state: BeaconState<
+            SLOTS_PER_HISTORICAL_ROOT,
+            HISTORICAL_ROOTS_LIMIT,
+            ETH1_DATA_VOTES_BOUND,
+            VALIDATOR_REGISTRY_LIMIT,
+            EPOCHS_PER_HISTORICAL_VECTOR,
+            EPOCHS_PER_SLASHINGS_VECTOR,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            PENDING_ATTESTATIONS_BOUND,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+        >,
+        mut block: SignedBeaconBlock<
+            MAX_PROPOSER_SLASHINGS,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            MAX_ATTESTER_SLASHINGS,
+            MAX_ATTESTATIONS,
+            MAX_DEPOSITS,
+            MAX_VOLUNTARY_EXITS,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+            MAX_BYTES_PER_TRANSACTION,
+            MAX_TRANSACTIONS_PER_PAYLOAD,
+            MAX_WITHDRAWALS_PER_PAYLOAD,
+            MAX_BLS_TO_EXECUTION_CHANGES,
+            MAX_BLOB_COMMITMENTS_PER_BLOCK,
+        >,
+        bootstrap: Option<LightClientBootstrap<SYNC_COMMITTEE_SIZE>>,
+    ) -> Result<Self, Error> {
+        let metadata = CheckpointMetadata {
+            format_version: CHECKPOINT_BUNDLE_FORMAT_VERSION,
+            fork: state.version(),
+            slot: state.slot(),
+            block_root: block.message_mut().hash_tree_root()?,
+            state_root: state.hash_tree_root()?,
+            genesis_validators_root: state.genesis_validators_root(),
+        };
+        Ok(Self { metadata, state, block, bootstrap })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer, self).map_err(Into::into)
+    }
+
+    // Read a bundle from `reader` and verify it before returning it to the caller.
+    pub fn read_from<R: Read>(reader: R, context: &Context) -> Result<Self, Error> {
+        let mut bundle: Self = serde_json::from_reader(reader)?;
+        bundle.verify(context)?;
+        Ok(bundle)
+    }
+
+    pub fn verify(&mut self, context: &Context) -> Result<(), Error> {
+        if self.metadata.format_version != CHECKPOINT_BUNDLE_FORMAT_VERSION {
+            return Err(Error::UnsupportedFormatVersion(self.metadata.format_version))
+        }
+
+        // The state may be advanced past the block through empty slots, e.g. to the first slot
+        // of an epoch whose boundary block is missing.
+        let state_slot = self.state.slot();
+        let block_slot = self.block.message().slot();
+        if state_slot < block_slot {
+            return Err(Error::SlotMismatch { state_slot, block_slot })
+        }
+
+        for (slot, provided) in
+            [(state_slot, self.state.version()), (block_slot, self.block.version())]
+        {
+            let expected = context.fork_for(slot);
+            if provided != expected {
+                return Err(Error::ForkMismatch { slot, expected, provided })
+            }
+        }
+
+        // The latest header of a post-state is missing the state root until the next slot is
+        // processed, at which point it is filled in with the root of that post-state.
+        let state_root = self.state.hash_tree_root()?;
+        let mut header = self.state.latest_block_header().clone();
+        if header.state_root == Root::default() {
+            header.state_root = state_root;
+        }
+        let expected = self.block.message().state_root();
+        if header.state_root != expected {
+            return Err(Error::StateRootMismatch { expected, provided: header.state_root })
+        }
+
+        let header_root = header.hash_tree_root()?;
+        let block_root = self.block.message_mut().hash_tree_root()?;
+        if header_root != block_root {
+            return Err(Error::BlockRootMismatch { expected: block_root, provided: header_root })
+        }
+
+        let metadata = &self.metadata;
+        if metadata.fork != self.state.version() ||
+            metadata.slot != state_slot ||
+            metadata.block_root != block_root ||
+            metadata.state_root != state_root ||
+            metadata.genesis_validators_root != self.state.genesis_validators_root()
+        {
+            return Err(Error::MetadataMismatch)
+        }
+
+        match self.bootstrap.as_mut() {
+            Some(bootstrap) => verify_bootstrap(bootstrap, &header, &self.state, context),
+            None if self.state.version() == Fork::Phase0 => Ok(()),
+            None => Err(Error::MissingBootstrap(self.state.version())),
+        }
+    }
+}
+
+fn verify_bootstrap<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    bootstrap: &mut LightClientBootstrap<SYNC_COMMITTEE_SIZE>,
+    header: &BeaconBlockHeader,
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<(), Error> {
+    if &bootstrap.header.beacon != header {
+        return Err(Error::BootstrapHeaderMismatch)
+    }
+
+    // A state advanced into the next sync committee period has rotated its committees, in which
+    // case the branch below is the only check.
+    let same_period = compute_sync_committee_period_at_slot(state.slot(), context) ==
+        compute_sync_committee_period_at_slot(header.slot, context);
+    if same_period && state.current_sync_committee() != Some(&bootstrap.current_sync_committee) {
+        return Err(Error::BootstrapSyncCommitteeMismatch)
+    }
+
+    let leaf = bootstrap.current_sync_committee.hash_tree_root()?;
+    let branch = bootstrap
+        .current_sync_committee_branch
+        .iter()
+        .map(|node| Node::try_from(node.as_ref()).expect("is valid instance"))
+        .collect::<Vec<_>>();
+    let depth = CURRENT_SYNC_COMMITTEE_INDEX_FLOOR_LOG_2;
+    let index = CURRENT_SYNC_COMMITTEE_INDEX % 2usize.pow(depth as u32);
+    if !is_valid_merkle_branch(&leaf, branch.iter(), depth, index, &header.state_root) {
+        return Err(Error::InvalidBootstrapBranch)
+    }
+    Ok(())
+}
+
+// Verify a batch of historical blocks walking backwards from `anchor_block`.
+// `blocks` must yield blocks in descending slot order, starting with the parent
+// of `anchor_block`. Only the parent-root linkage, the fork of each block and the
+// proposer signatures are checked; no state transitions are executed. The validator registry of any
+// later state suffices as the registry only ever grows.
+//
+// Returns the parent root the next (older) batch must connect to.
+pub fn verify_backfill<
+    I,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+>(
+    blocks: I,
+    anchor_block: &SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >,
+    validators: &[Validator],
+    genesis_validators_root: Root,
+    context: &Context,
+) -> Result<Root, Error>
+where
+    I: IntoIterator<
+        Item = SignedBeaconBlock<
+            MAX_PROPOSER_SLASHINGS,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            MAX_ATTESTER_SLASHINGS,
+            MAX_ATTESTATIONS,
+            MAX_DEPOSITS,
+            MAX_VOLUNTARY_EXITS,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+            MAX_BYTES_PER_TRANSACTION,
+            MAX_TRANSACTIONS_PER_PAYLOAD,
+            MAX_WITHDRAWALS_PER_PAYLOAD,
+            MAX_BLS_TO_EXECUTION_CHANGES,
+            MAX_BLOB_COMMITMENTS_PER_BLOCK,
+        >,
+    >,
+{
+    let mut expected_root = anchor_block.message().parent_root();
+    let mut child_slot = anchor_block.message().slot();
+    for mut block in blocks {
+        let slot = block.message().slot();
+        if slot >= child_slot {
+            return Err(Error::BackfillSlotNotDecreasing { slot, child_slot })
+        }
+
+        let fork = context.fork_for(slot);
+        if block.version() != fork {
+            return Err(Error::ForkMismatch { slot, expected: fork, provided: block.version() })
+        }
+
+        let block_root = block.message_mut().hash_tree_root()?;
+        if block_root != expected_root {
+            return Err(Error::BackfillParentMismatch {
+                slot,
+                expected: expected_root,
+                provided: block_root,
+            })
+        }
+
+        // The genesis block is not signed.
+        if slot != GENESIS_SLOT {
+            let proposer_index = block.message().proposer_index();
+            let proposer =
+                validators.get(proposer_index).ok_or(Error::UnknownProposer(proposer_index))?;
+            let fork_version = context.fork_version_for(fork);
+            let domain = compute_domain(
+                DomainType::BeaconProposer,
+                Some(fork_version),
+                Some(genesis_validators_root),
+                context,
+            )?;
+            let signing_root = compute_signing_root(&mut block.message_mut(), domain)?;
+            crate::crypto::verify_signature(
+                &proposer.public_key,
+                signing_root.as_ref(),
+                block.signature(),
+            )
+            .map_err(|_| Error::InvalidProposerSignature { slot, proposer_index })?;
+        }
+
+        expected_root = block.message().parent_root();
+        child_slot = slot;
+    }
+    Ok(expected_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        altair::minimal as altair,
+        crypto::SecretKey,
+        phase0::minimal as phase0,
+        primitives::FAR_FUTURE_EPOCH,
+        signing::sign_with_domain,
+        types::minimal::{BeaconState, SignedBeaconBlock},
+    };
+
+    fn phase0_context() -> Context {
+        let mut context = Context::for_minimal();
+        context.altair_fork_epoch = FAR_FUTURE_EPOCH;
+        context.bellatrix_fork_epoch = FAR_FUTURE_EPOCH;
+        context.capella_fork_epoch = FAR_FUTURE_EPOCH;
+        context.deneb_fork_epoch = FAR_FUTURE_EPOCH;
+        context
+    }
+
+    // A post-state at `slot` and the block that produced it.
+    fn post_state(slot: Slot) -> (phase0::BeaconState, phase0::BeaconBlock) {
+        let mut block = phase0::BeaconBlock { slot, proposer_index: 1, ..Default::default() };
+        let state = phase0::BeaconState {
+            slot,
+            latest_block_header: BeaconBlockHeader {
+                slot,
+                proposer_index: block.proposer_index,
+                parent_root: block.parent_root,
+                state_root: Root::default(),
+                body_root: block.body.hash_tree_root().unwrap(),
+            },
+            ..Default::default()
+        };
+        block.state_root = state.clone().hash_tree_root().unwrap();
+        (state, block)
+    }
+
+    fn verify(
+        state: phase0::BeaconState,
+        block: phase0::BeaconBlock,
+        context: &Context,
+    ) -> Result<(), Error> {
+        let block = phase0::SignedBeaconBlock { message: block, ..Default::default() };
+        let mut bundle = CheckpointBundle::new(
+            BeaconState::Phase0(state),
+            SignedBeaconBlock::Phase0(block),
+            None,
+        )?;
+        bundle.verify(context)
+    }
+
+    #[test]
+    fn test_verify_checkpoint_at_block_slot() {
+        let context = phase0_context();
+        let (state, block) = post_state(16);
+        verify(state.clone(), block.clone(), &context).unwrap();
+
+        let mut other = block;
+        other.state_root = Root::try_from([1u8; 32].as_ref()).unwrap();
+        assert!(matches!(verify(state, other, &context), Err(Error::StateRootMismatch { .. })));
+    }
+
+    #[test]
+    fn test_verify_checkpoint_after_skipped_slots() {
+        let context = phase0_context();
+        // the boundary slot 16 is empty so the checkpoint block is the one at slot 13
+        let (mut state, block) = post_state(13);
+        state.latest_block_header.state_root = block.state_root;
+        state.slot = 16;
+        verify(state.clone(), block.clone(), &context).unwrap();
+
+        let mut early = state.clone();
+        early.slot = 12;
+        assert!(matches!(verify(early, block.clone(), &context), Err(Error::SlotMismatch { .. })));
+
+        // the latest header must record the post-state root of the block
+        let mut unfilled = state;
+        unfilled.latest_block_header.state_root = Root::default();
+        assert!(matches!(
+            verify(unfilled, block, &context),
+            Err(Error::StateRootMismatch { .. })
+        ));
+    }
+
+    // Signed blocks at slots `1..=count`, each the parent of the next, newest first.
+    fn signed_chain(
+        count: Slot,
+        secret_key: &SecretKey,
+        genesis_validators_root: Root,
+        context: &Context,
+    ) -> Vec<SignedBeaconBlock> {
+        let domain = compute_domain(
+            DomainType::BeaconProposer,
+            Some(context.genesis_fork_version),
+            Some(genesis_validators_root),
+            context,
+        )
+        .unwrap();
+        let mut parent_root = Root::default();
+        let mut blocks = (1..=count)
+            .map(|slot| {
+                let mut message = phase0::BeaconBlock { slot, parent_root, ..Default::default() };
+                parent_root = message.hash_tree_root().unwrap();
+                let signature = sign_with_domain(&mut message, secret_key, domain).unwrap();
+                SignedBeaconBlock::Phase0(phase0::SignedBeaconBlock { message, signature })
+            })
+            .collect::<Vec<_>>();
+        blocks.reverse();
+        blocks
+    }
+
+    // An anchor block at slot 4 with its ancestors, newest first, and the registry of the
+    // proposer that signed them all.
+    fn backfill_chain(
+        context: &Context,
+    ) -> (SignedBeaconBlock, Vec<SignedBeaconBlock>, Vec<Validator>, Root) {
+        let secret_key = SecretKey::key_gen(&[1u8; 32]).unwrap();
+        let validators =
+            vec![Validator { public_key: secret_key.public_key(), ..Default::default() }];
+        let genesis_validators_root = Root::try_from([2u8; 32].as_ref()).unwrap();
+        let mut blocks = signed_chain(4, &secret_key, genesis_validators_root, context);
+        let anchor = blocks.remove(0);
+        (anchor, blocks, validators, genesis_validators_root)
+    }
+
+    #[test]
+    fn test_verify_backfill() {
+        let context = phase0_context();
+        let (anchor, blocks, validators, genesis_validators_root) = backfill_chain(&context);
+
+        let root =
+            verify_backfill(blocks.clone(), &anchor, &validators, genesis_validators_root, &context)
+                .unwrap();
+        assert_eq!(root, Root::default());
+
+        // signed for another chain
+        let other_root = Root::try_from([3u8; 32].as_ref()).unwrap();
+        assert!(matches!(
+            verify_backfill(blocks, &anchor, &validators, other_root, &context),
+            Err(Error::InvalidProposerSignature { slot: 3, proposer_index: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_verify_backfill_rejects_broken_parent_link() {
+        let context = phase0_context();
+        let (anchor, mut blocks, validators, genesis_validators_root) = backfill_chain(&context);
+
+        // drop the parent of the anchor so the chain skips a link
+        blocks.remove(0);
+        assert!(matches!(
+            verify_backfill(blocks, &anchor, &validators, genesis_validators_root, &context),
+            Err(Error::BackfillParentMismatch { slot: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_backfill_rejects_wrong_fork() {
+        let context = phase0_context();
+        let (anchor, _, validators, genesis_validators_root) = backfill_chain(&context);
+
+        let message = altair::BeaconBlock { slot: 3, ..Default::default() };
+        let block =
+            SignedBeaconBlock::Altair(altair::SignedBeaconBlock { message, ..Default::default() });
+        assert!(matches!(
+            verify_backfill([block], &anchor, &validators, genesis_validators_root, &context),
+            Err(Error::ForkMismatch { slot: 3, expected: Fork::Phase0, provided: Fork::Altair })
+        ));
+    }
+}
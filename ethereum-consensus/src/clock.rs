@@ -2,6 +2,7 @@
 use crate::{
     configs, phase0 as presets,
     primitives::{Epoch, Slot},
+    state_transition::Context,
 };
 use std::{
     ops::Deref,
@@ -13,6 +14,7 @@ pub const MAINNET_GENESIS_TIME: u64 = 1606824023;
 pub const SEPOLIA_GENESIS_TIME: u64 = 1655733600;
 pub const GOERLI_GENESIS_TIME: u64 = 1616508000;
 pub const HOLESKY_GENESIS_TIME: u64 = 1694786400;
+pub const GNOSIS_GENESIS_TIME: u64 = 1638993340;
 
 fn slot_to_nanos(slot: Slot, seconds_per_slot: u128, genesis_time: u128) -> u128 {
     u128::from(slot) * seconds_per_slot + genesis_time
@@ -45,6 +47,47 @@ pub fn convert_timestamp_nanos_to_slot(
     Some(u128_to_u64(delta / seconds_per_slot))
 }
 
+// Unix time in seconds at the start of `slot`.
+pub fn compute_timestamp_at_slot(slot: Slot, genesis_time: u64, context: &Context) -> u64 {
+    genesis_time + slot * context.seconds_per_slot
+}
+
+// Unix time in seconds at the start of `epoch`.
+pub fn compute_time_at_epoch(epoch: Epoch, genesis_time: u64, context: &Context) -> u64 {
+    compute_timestamp_at_slot(epoch * context.slots_per_epoch, genesis_time, context)
+}
+
+// Slot containing `unix_time` (in seconds), or `None` if before genesis.
+pub fn slot_at_time(unix_time: u64, genesis_time: u64, context: &Context) -> Option<Slot> {
+    convert_timestamp_to_slot(unix_time, genesis_time, context.seconds_per_slot)
+}
+
+// Unix time in milliseconds at the start of `slot`.
+pub fn compute_timestamp_millis_at_slot(slot: Slot, genesis_time: u64, context: &Context) -> u64 {
+    compute_timestamp_at_slot(slot, genesis_time, context) * 1000
+}
+
+// Slot containing `unix_time_millis`, or `None` if before genesis.
+pub fn slot_at_time_millis(
+    unix_time_millis: u64,
+    genesis_time: u64,
+    context: &Context,
+) -> Option<Slot> {
+    let delta = unix_time_millis.checked_sub(genesis_time * 1000)?;
+    Some(delta / (context.seconds_per_slot * 1000))
+}
+
+// Milliseconds elapsed since the start of the slot containing `unix_time_millis`,
+// or `None` if before genesis.
+pub fn millis_into_slot(
+    unix_time_millis: u64,
+    genesis_time: u64,
+    context: &Context,
+) -> Option<u64> {
+    let delta = unix_time_millis.checked_sub(genesis_time * 1000)?;
+    Some(delta % (context.seconds_per_slot * 1000))
+}
+
 pub fn get_current_unix_time_in_nanos() -> u128 {
     SystemTime::now().duration_since(UNIX_EPOCH).expect("after `UNIX_EPOCH`").as_nanos()
 }
@@ -118,6 +161,13 @@ pub fn for_holesky() -> Clock<SystemTimeProvider> {
     from_system_time(genesis_time, seconds_per_slot, slots_per_epoch)
 }
 
+pub fn for_gnosis() -> Clock<SystemTimeProvider> {
+    let genesis_time = GNOSIS_GENESIS_TIME;
+    let seconds_per_slot = configs::gnosis::SECONDS_PER_SLOT;
+    let slots_per_epoch = presets::gnosis::SLOTS_PER_EPOCH;
+    from_system_time(genesis_time, seconds_per_slot, slots_per_epoch)
+}
+
 impl<T: TimeProvider + Send + Sync> Clock<T> {
     pub fn new(
         genesis_time: u64,
@@ -212,6 +262,123 @@ impl<T: TimeProvider + Send + Sync> Clock<T> {
     }
 }
 
+// Points within a slot where validator duties are due
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickKind {
+    SlotStart,
+    // 1/3 of the way into the slot
+    AttestationDeadline,
+    // 2/3 of the way into the slot
+    AggregationDeadline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick {
+    pub slot: Slot,
+    pub kind: TickKind,
+}
+
+const TICKS_PER_SLOT: u128 = 3;
+
+impl Tick {
+    fn from_index(index: u128) -> Self {
+        let slot = u128_to_u64(index / TICKS_PER_SLOT);
+        let kind = match index % TICKS_PER_SLOT {
+            0 => TickKind::SlotStart,
+            1 => TickKind::AttestationDeadline,
+            _ => TickKind::AggregationDeadline,
+        };
+        Self { slot, kind }
+    }
+}
+
+// A `Clock` that also tracks the attestation and aggregation deadlines of each slot.
+//
+// `tolerance` bounds how late a tick may be delivered, e.g. after the process was
+// suspended; later ticks are skipped rather than delivered for a past deadline.
+#[derive(Clone)]
+pub struct SlotClock<T: TimeProvider + Send + Sync> {
+    clock: Clock<T>,
+    tolerance: Duration,
+}
+
+impl<T: TimeProvider + Send + Sync> SlotClock<T> {
+    pub fn new(clock: Clock<T>, tolerance: Duration) -> Self {
+        Self { clock, tolerance }
+    }
+
+    pub fn clock(&self) -> &Clock<T> {
+        &self.clock
+    }
+
+    fn nanos_per_tick(&self) -> u128 {
+        self.clock.seconds_per_slot / TICKS_PER_SLOT
+    }
+
+    fn time_of_tick(&self, index: u128) -> u128 {
+        self.clock.genesis_time + index * self.nanos_per_tick()
+    }
+
+    // Index of the earliest tick that can still be delivered at `current_time`
+    fn first_tick_at(&self, current_time: u128) -> u128 {
+        let earliest = current_time.saturating_sub(self.tolerance.as_nanos());
+        match earliest.checked_sub(self.clock.genesis_time) {
+            Some(delta) => {
+                let nanos_per_tick = self.nanos_per_tick();
+                let index = delta / nanos_per_tick;
+                if delta % nanos_per_tick == 0 {
+                    index
+                } else {
+                    index + 1
+                }
+            }
+            None => 0,
+        }
+    }
+
+    // Whether `slot` is the current slot, allowing for `tolerance` of clock disparity
+    // on either side of the slot boundaries.
+    pub fn is_current_slot(&self, slot: Slot) -> bool {
+        let current_time = self.clock.get_current_time();
+        let tolerance = self.tolerance.as_nanos();
+        let start = slot_to_nanos(slot, self.clock.seconds_per_slot, self.clock.genesis_time);
+        let end = start + self.clock.seconds_per_slot;
+        start.saturating_sub(tolerance) <= current_time && current_time < end + tolerance
+    }
+
+    // The next tick at or after the current time, with the `Duration` until it is due
+    pub fn next_tick(&self) -> (Tick, Duration) {
+        let current_time = self.clock.get_current_time();
+        let index = self.first_tick_at(current_time + self.tolerance.as_nanos());
+        let until = self.time_of_tick(index).saturating_sub(current_time);
+        (Tick::from_index(index), Duration::from_nanos(u128_to_u64(until)))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: TimeProvider + Send + Sync> SlotClock<T> {
+    pub fn stream_ticks(&self) -> impl Stream<Item = Tick> + '_ {
+        async_stream::stream! {
+            let mut index = self.first_tick_at(self.clock.get_current_time());
+            loop {
+                let current_time = self.clock.get_current_time();
+                let target = self.time_of_tick(index);
+                if current_time < target {
+                    let duration = Duration::from_nanos(u128_to_u64(target - current_time));
+                    tokio::time::sleep(duration).await;
+                    continue
+                }
+                if current_time - target <= self.tolerance.as_nanos() {
+                    yield Tick::from_index(index);
+                    index += 1;
+                } else {
+                    index = self.first_tick_at(current_time);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,6 +450,26 @@ mod tests {
         assert!(clock.current_slot().is_none());
     }
 
+    #[test]
+    fn test_slot_time_conversions() {
+        let context = Context::for_mainnet();
+        let genesis_time = MAINNET_GENESIS_TIME;
+        assert_eq!(compute_timestamp_at_slot(0, genesis_time, &context), genesis_time);
+        assert_eq!(compute_timestamp_at_slot(10, genesis_time, &context), genesis_time + 120);
+        assert_eq!(compute_time_at_epoch(2, genesis_time, &context), genesis_time + 2 * 32 * 12);
+
+        assert_eq!(slot_at_time(genesis_time - 1, genesis_time, &context), None);
+        assert_eq!(slot_at_time(genesis_time + 11, genesis_time, &context), Some(0));
+        assert_eq!(slot_at_time(genesis_time + 12, genesis_time, &context), Some(1));
+
+        let slot_start = compute_timestamp_millis_at_slot(5, genesis_time, &context);
+        assert_eq!(slot_start, (genesis_time + 60) * 1000);
+        assert_eq!(slot_at_time_millis(slot_start - 1, genesis_time, &context), Some(4));
+        assert_eq!(slot_at_time_millis(slot_start + 4_000, genesis_time, &context), Some(5));
+        assert_eq!(millis_into_slot(slot_start + 4_000, genesis_time, &context), Some(4_000));
+        assert_eq!(millis_into_slot(genesis_time * 1000 - 1, genesis_time, &context), None);
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     async fn test_slot_stream() {
@@ -309,6 +496,70 @@ mod tests {
         assert_eq!(slots, (current_slot..target_slot).collect::<Vec<_>>());
     }
 
+    #[test]
+    fn test_slot_clock_tolerance() {
+        let seconds_per_slot: u64 = 12;
+        let time_provider = new_ticker(seconds_per_slot);
+        let clock = Clock::new(0, seconds_per_slot, 32, time_provider.clone());
+        let slot_clock = SlotClock::new(clock, Duration::from_millis(500));
+
+        assert!(slot_clock.is_current_slot(0));
+        assert!(!slot_clock.is_current_slot(1));
+        let (tick, until) = slot_clock.next_tick();
+        assert_eq!(tick, Tick { slot: 0, kind: TickKind::SlotStart });
+        assert_eq!(until, Duration::default());
+
+        for _ in 0..5 {
+            time_provider.tick();
+        }
+        let (tick, until) = slot_clock.next_tick();
+        assert_eq!(tick, Tick { slot: 0, kind: TickKind::AggregationDeadline });
+        assert_eq!(until, Duration::from_secs(3));
+
+        for _ in 0..7 {
+            time_provider.tick();
+        }
+        // 12s into the chain, slot 1 begins but slot 0 is within the disparity
+        assert!(slot_clock.is_current_slot(0));
+        assert!(slot_clock.is_current_slot(1));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_tick_stream() {
+        use tokio_stream::StreamExt;
+
+        let seconds_per_slot: u64 = 1200000000;
+        let time_provider = new_ticker(seconds_per_slot);
+        let clock = Clock::new(0, seconds_per_slot, 12, time_provider.clone());
+        let slot_clock = SlotClock::new(clock, Duration::from_secs(1));
+        let tick_stream = slot_clock.stream_ticks();
+
+        tokio::pin!(tick_stream);
+
+        let mut ticks = vec![];
+        for _ in 0..3 {
+            ticks.push(tick_stream.next().await.unwrap());
+            let mut tick = time_provider.tick.lock().unwrap();
+            *tick += time_provider.seconds_per_slot / 3;
+        }
+        // skip a whole slot, missing its deadlines
+        {
+            let mut tick = time_provider.tick.lock().unwrap();
+            *tick += time_provider.seconds_per_slot;
+        }
+        ticks.push(tick_stream.next().await.unwrap());
+
+        let kinds =
+            [TickKind::SlotStart, TickKind::AttestationDeadline, TickKind::AggregationDeadline];
+        let expected = kinds
+            .iter()
+            .map(|&kind| Tick { slot: 0, kind })
+            .chain([Tick { slot: 2, kind: TickKind::SlotStart }])
+            .collect::<Vec<_>>();
+        assert_eq!(ticks, expected);
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     #[ignore = "uses wall clock time for mainnet params"]
@@ -1,3 +1,4 @@
+pub mod gnosis;
 pub mod goerli;
 pub mod holesky;
 pub mod mainnet;
@@ -2,25 +2,98 @@
 use crate::serde::{try_bytes_from_hex_str, HexError};
 use crate::{primitives::Bytes32, ssz::prelude::*};
 use blst::{min_pk as bls_impl, BLST_ERROR};
+use rand::Rng;
 use sha2::{Digest, Sha256};
 use std::{
     fmt,
     ops::{Deref, DerefMut},
 };
 use thiserror::Error;
+use zeroize::Zeroizing;
+
+#[cfg(all(feature = "sha256-simd", target_arch = "x86_64"))]
+mod sha256_avx2;
+
+// A SHA-256 implementation.
+pub trait HashBackend {
+    fn hash(data: &[u8]) -> Bytes32;
+
+    // Hash each 64 byte chunk of `input` into the matching 32 byte chunk of `output`, e.g. the
+    // pairs of sibling nodes in a layer of a merkle tree. `input` must be made of whole chunks
+    // and `output` must have room for exactly one digest per chunk.
+    fn hash_64b_blocks(input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        check_64b_blocks(input, output)?;
+        for (block, out) in input.chunks_exact(64).zip(output.chunks_exact_mut(32)) {
+            out.copy_from_slice(Self::hash(block).as_ref());
+        }
+        Ok(())
+    }
+}
+
+fn check_64b_blocks(input: &[u8], output: &[u8]) -> Result<(), Error> {
+    if input.len() % 64 != 0 || output.len() * 2 != input.len() {
+        return Err(Error::InvalidHashInput { input: input.len(), output: output.len() })
+    }
+    Ok(())
+}
+
+// SHA-256 from the `sha2` crate. It uses the SHA extensions on x86 and aarch64 when the CPU
+// supports them, and the `sha256-asm` feature swaps its portable fallback for assembly.
+pub struct Sha2Backend;
+
+impl HashBackend for Sha2Backend {
+    fn hash(data: &[u8]) -> Bytes32 {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+
+        let mut result = Bytes32::default();
+        let inner = &mut result[..];
+        hasher.finalize_into(inner.into());
+        result
+    }
+}
+
+// `Sha2Backend`, except that on x86_64 CPUs with AVX2 but without the SHA extensions the
+// blocks of `hash_64b_blocks` are hashed eight at a time across the lanes of AVX2 registers.
+#[cfg(all(feature = "sha256-simd", target_arch = "x86_64"))]
+pub struct Avx2Backend;
+
+#[cfg(all(feature = "sha256-simd", target_arch = "x86_64"))]
+impl HashBackend for Avx2Backend {
+    fn hash(data: &[u8]) -> Bytes32 {
+        Sha2Backend::hash(data)
+    }
+
+    fn hash_64b_blocks(input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        check_64b_blocks(input, output)?;
+        let hashed = if !is_x86_feature_detected!("sha") && sha256_avx2::is_available() {
+            sha256_avx2::hash_64b_blocks(input, output)
+        } else {
+            0
+        };
+        Sha2Backend::hash_64b_blocks(&input[64 * hashed..], &mut output[32 * hashed..])
+    }
+}
+
+#[cfg(all(feature = "sha256-simd", target_arch = "x86_64"))]
+pub type DefaultHashBackend = Avx2Backend;
+
+#[cfg(not(all(feature = "sha256-simd", target_arch = "x86_64")))]
+pub type DefaultHashBackend = Sha2Backend;
 
 pub fn hash<D: AsRef<[u8]>>(data: D) -> Bytes32 {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
+    DefaultHashBackend::hash(data.as_ref())
+}
 
-    let mut result = Bytes32::default();
-    let inner = &mut result[..];
-    hasher.finalize_into(inner.into());
-    result
+// Hash each 64 byte chunk of `input` into the matching 32 byte chunk of `output` with the
+// `DefaultHashBackend`.
+pub fn hash_64b_blocks(input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+    DefaultHashBackend::hash_64b_blocks(input, output)
 }
 
 const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
 const BLS_PUBLIC_KEY_BYTES_LEN: usize = 48;
+pub const BLS_UNCOMPRESSED_PUBLIC_KEY_BYTES_LEN: usize = 96;
 const BLS_SECRET_KEY_BYTES_LEN: usize = 32;
 const BLS_SIGNATURE_BYTES_LEN: usize = 96;
 
@@ -37,8 +110,18 @@ pub enum Error {
     Randomness(#[from] rand::Error),
     #[error("blst error: {0}")]
     BLST(#[from] BLSTError),
+    #[error("invalid public key {public_key}: {source}")]
+    InvalidPublicKey { public_key: PublicKey, source: BLSTError },
     #[error("invalid signature")]
     InvalidSignature,
+    #[error("participant {index} is out of bounds for {count} participants")]
+    ParticipantOutOfBounds { index: usize, count: usize },
+    #[error("participant {0} is already included in the aggregate")]
+    DuplicateParticipant(usize),
+    #[error("aggregates with different participant counts cannot be merged")]
+    ParticipantCountMismatch,
+    #[error("cannot hash {input} bytes of 64 byte blocks into {output} bytes of digests")]
+    InvalidHashInput { input: usize, output: usize },
 }
 
 #[derive(Debug, Error)]
@@ -131,6 +214,23 @@ pub fn fast_aggregate_verify(
     }
 }
 
+// Like `fast_aggregate_verify` but with public keys that have already been decompressed and
+// validated, e.g. by a `DecompressedPubkeyCache`.
+pub fn fast_aggregate_verify_decompressed(
+    public_keys: &[&DecompressedPublicKey],
+    msg: &[u8],
+    signature: &Signature,
+) -> Result<(), Error> {
+    let public_keys: Vec<&bls_impl::PublicKey> = public_keys.iter().map(|key| &key.0).collect();
+    let signature: bls_impl::Signature = signature.try_into()?;
+    let res = signature.fast_aggregate_verify(true, msg, BLS_DST, &public_keys);
+    if res == BLST_ERROR::BLST_SUCCESS {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
 // Return the aggregate public key for the public keys in `pks`
 pub fn eth_aggregate_public_keys(public_keys: &[PublicKey]) -> Result<PublicKey, Error> {
     if public_keys.is_empty() {
@@ -147,6 +247,20 @@ pub fn eth_aggregate_public_keys(public_keys: &[PublicKey]) -> Result<PublicKey,
         .map_err(|e| BLSTError::from(e).into())
 }
 
+// Return the aggregate public key for the already decompressed `public_keys`
+pub fn eth_aggregate_decompressed_public_keys(
+    public_keys: &[DecompressedPublicKey],
+) -> Result<PublicKey, Error> {
+    if public_keys.is_empty() {
+        return Err(Error::EmptyAggregate)
+    }
+    let public_keys: Vec<&bls_impl::PublicKey> = public_keys.iter().map(|key| &key.0).collect();
+
+    bls_impl::AggregatePublicKey::aggregate(&public_keys, false)
+        .map(|agg_pk| PublicKey::try_from(agg_pk.to_public_key().to_bytes().as_ref()).unwrap())
+        .map_err(|e| BLSTError::from(e).into())
+}
+
 pub fn eth_fast_aggregate_verify(
     public_keys: &[&PublicKey],
     message: &[u8],
@@ -159,7 +273,231 @@ pub fn eth_fast_aggregate_verify(
     }
 }
 
-#[derive(Clone, Default, serde::Deserialize)]
+// A `message` signed by the aggregate of `public_keys`
+#[derive(Debug, Clone)]
+pub struct SignatureSet<'a> {
+    pub public_keys: Vec<&'a PublicKey>,
+    pub message: &'a [u8],
+    pub signature: &'a Signature,
+}
+
+// Verify every set in `sets` with a single (randomized) multi-pairing. This is much cheaper
+// than verifying each set in turn, but does not identify which set is invalid.
+pub fn verify_signature_sets(sets: &[SignatureSet<'_>]) -> Result<(), Error> {
+    if sets.is_empty() {
+        return Ok(())
+    }
+
+    let mut public_keys = Vec::with_capacity(sets.len());
+    let mut signatures = Vec::with_capacity(sets.len());
+    for set in sets {
+        let set_public_keys = set
+            .public_keys
+            .iter()
+            .cloned()
+            .map(bls_impl::PublicKey::try_from)
+            .collect::<Result<Vec<bls_impl::PublicKey>, Error>>()?;
+        let set_public_keys: Vec<&bls_impl::PublicKey> = set_public_keys.iter().collect();
+        let public_key = bls_impl::AggregatePublicKey::aggregate(&set_public_keys, true)
+            .map_err(BLSTError::from)?;
+        public_keys.push(public_key.to_public_key());
+        signatures.push(bls_impl::Signature::try_from(set.signature)?);
+    }
+    let public_keys: Vec<&bls_impl::PublicKey> = public_keys.iter().collect();
+    let signatures: Vec<&bls_impl::Signature> = signatures.iter().collect();
+    let messages = sets.iter().map(|set| set.message).collect::<Vec<_>>();
+
+    let mut rng = rand::thread_rng();
+    let scalars = sets
+        .iter()
+        .map(|_| {
+            let mut scalar = blst::blst_scalar::default();
+            let r = rng.gen_range(1..=u64::MAX);
+            scalar.b[..8].copy_from_slice(&r.to_le_bytes());
+            scalar
+        })
+        .collect::<Vec<_>>();
+
+    let res = bls_impl::Signature::verify_multiple_aggregate_signatures(
+        &messages,
+        BLS_DST,
+        &public_keys,
+        false,
+        &signatures,
+        true,
+        &scalars,
+        64,
+    );
+    if res == BLST_ERROR::BLST_SUCCESS {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature)
+    }
+}
+
+// Tracks which of a fixed number of participants (e.g. the members of a committee) have been
+// included in an aggregate. The bits line up with `aggregation_bits` of the matching message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Participation(Vec<bool>);
+
+impl Participation {
+    // The bit of the participant at `index`, if it has not been included yet. Callers set it only
+    // once the participant has been aggregated so a failure leaves the participation untouched.
+    fn vacancy(&mut self, index: usize) -> Result<&mut bool, Error> {
+        let count = self.0.len();
+        match self.0.get_mut(index) {
+            None => Err(Error::ParticipantOutOfBounds { index, count }),
+            Some(true) => Err(Error::DuplicateParticipant(index)),
+            Some(bit) => Ok(bit),
+        }
+    }
+
+    fn merge(&mut self, other: &Self) -> Result<(), Error> {
+        if self.0.len() != other.0.len() {
+            return Err(Error::ParticipantCountMismatch)
+        }
+        if let Some(index) = self.0.iter().zip(&other.0).position(|(a, b)| *a && *b) {
+            return Err(Error::DuplicateParticipant(index))
+        }
+        for (bit, other) in self.0.iter_mut().zip(&other.0) {
+            *bit |= *other;
+        }
+        Ok(())
+    }
+}
+
+// Aggregate signature built up one participant at a time, e.g. while collecting attestations
+// or sync committee messages for the same data.
+#[derive(Debug, Clone)]
+pub struct AggregateSignature {
+    inner: Option<bls_impl::AggregateSignature>,
+    participation: Participation,
+}
+
+impl AggregateSignature {
+    pub fn new(participant_count: usize) -> Self {
+        Self { inner: None, participation: Participation(vec![false; participant_count]) }
+    }
+
+    // Add the `signature` of the participant at `index`. Each participant may only be included
+    // once.
+    pub fn add(&mut self, index: usize, signature: &Signature) -> Result<(), Error> {
+        let signature = bls_impl::Signature::try_from(signature)?;
+        signature.validate(false).map_err(BLSTError::from)?;
+        let bit = self.participation.vacancy(index)?;
+        match self.inner.as_mut() {
+            Some(inner) => inner.add_signature(&signature, false).map_err(BLSTError::from)?,
+            None => self.inner = Some(bls_impl::AggregateSignature::from_signature(&signature)),
+        }
+        *bit = true;
+        Ok(())
+    }
+
+    // Fold `other` into this aggregate; the participants of both must be disjoint.
+    pub fn merge(&mut self, other: &Self) -> Result<(), Error> {
+        self.participation.merge(&other.participation)?;
+        match (self.inner.as_mut(), other.inner.as_ref()) {
+            (Some(inner), Some(other)) => inner.add_aggregate(other),
+            (None, other) => self.inner = other.copied(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn participants(&self) -> &[bool] {
+        &self.participation.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    pub fn to_signature(&self) -> Result<Signature, Error> {
+        let inner = self.inner.as_ref().ok_or(Error::EmptyAggregate)?;
+        Ok(Signature::try_from(inner.to_signature().to_bytes().as_ref()).unwrap())
+    }
+}
+
+// Aggregate public key built up one participant at a time, e.g. to verify an aggregate
+// assembled with `AggregateSignature`.
+#[derive(Debug, Clone)]
+pub struct AggregatePublicKey {
+    inner: Option<bls_impl::AggregatePublicKey>,
+    participation: Participation,
+}
+
+impl AggregatePublicKey {
+    pub fn new(participant_count: usize) -> Self {
+        Self { inner: None, participation: Participation(vec![false; participant_count]) }
+    }
+
+    // Add the `public_key` of the participant at `index`. Each participant may only be included
+    // once.
+    pub fn add(&mut self, index: usize, public_key: &PublicKey) -> Result<(), Error> {
+        let public_key = DecompressedPublicKey::try_from(public_key)?;
+        self.add_decompressed(index, &public_key)
+    }
+
+    // Like `add` but with a public key that has already been decompressed and validated.
+    pub fn add_decompressed(
+        &mut self,
+        index: usize,
+        public_key: &DecompressedPublicKey,
+    ) -> Result<(), Error> {
+        let bit = self.participation.vacancy(index)?;
+        match self.inner.as_mut() {
+            Some(inner) => inner.add_public_key(&public_key.0, false).map_err(BLSTError::from)?,
+            None => self.inner = Some(bls_impl::AggregatePublicKey::from_public_key(&public_key.0)),
+        }
+        *bit = true;
+        Ok(())
+    }
+
+    // Fold `other` into this aggregate; the participants of both must be disjoint.
+    pub fn merge(&mut self, other: &Self) -> Result<(), Error> {
+        self.participation.merge(&other.participation)?;
+        match (self.inner.as_mut(), other.inner.as_ref()) {
+            (Some(inner), Some(other)) => inner.add_aggregate(other),
+            (None, other) => self.inner = other.copied(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn participants(&self) -> &[bool] {
+        &self.participation.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    pub fn to_public_key(&self) -> Result<PublicKey, Error> {
+        let inner = self.inner.as_ref().ok_or(Error::EmptyAggregate)?;
+        Ok(PublicKey::try_from(inner.to_public_key().to_bytes().as_ref()).unwrap())
+    }
+}
+
+// KZG commitments to blobs, introduced in Deneb
+pub mod kzg {
+    pub use crate::deneb::polynomial_commitments::{
+        blob_to_kzg_commitment, compute_blob_kzg_proof, compute_kzg_proof, load_trusted_setup_file,
+        verify_blob_kzg_proof, verify_blob_kzg_proof_batch, verify_kzg_proof, Error, FieldElement,
+        KzgCommitment, KzgProof, KzgSettings, ProofAndEvaluation, TrustedSetup,
+    };
+}
+
+// Produces signatures without handing out the key material behind them, so signing code can be
+// written against a key that never leaves its owner (e.g. a remote signer).
+pub trait Signer {
+    fn public_key(&self) -> PublicKey;
+
+    fn sign(&self, msg: &[u8]) -> Signature;
+}
+
+// NOTE: `SecretKey` is intentionally not `Clone`; the inner key is zeroized on drop and callers
+// that need the raw bytes get them back in a `Zeroizing` container.
+#[derive(Default, serde::Deserialize)]
 #[serde(try_from = "String")]
 pub struct SecretKey(bls_impl::SecretKey);
 
@@ -199,9 +537,9 @@ impl TryFrom<&[u8]> for SecretKey {
 
 impl SecretKey {
     pub fn random<R: rand::Rng>(rng: &mut R) -> Result<Self, Error> {
-        let mut ikm = [0u8; BLS_SECRET_KEY_BYTES_LEN];
-        rng.try_fill_bytes(&mut ikm)?;
-        Self::key_gen(&ikm)
+        let mut ikm = Zeroizing::new([0u8; BLS_SECRET_KEY_BYTES_LEN]);
+        rng.try_fill_bytes(ikm.as_mut())?;
+        Self::key_gen(ikm.as_ref())
     }
 
     pub fn key_gen(ikm: &[u8]) -> Result<Self, Error> {
@@ -219,8 +557,18 @@ impl SecretKey {
         Signature::try_from(inner.to_bytes().as_ref()).unwrap()
     }
 
-    pub fn to_bytes(self) -> [u8; 32] {
-        self.0.to_bytes()
+    pub fn to_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.0.to_bytes())
+    }
+}
+
+impl Signer for SecretKey {
+    fn public_key(&self) -> PublicKey {
+        SecretKey::public_key(self)
+    }
+
+    fn sign(&self, msg: &[u8]) -> Signature {
+        SecretKey::sign(self, msg)
     }
 }
 
@@ -271,7 +619,37 @@ impl TryFrom<&PublicKey> for bls_impl::PublicKey {
     type Error = Error;
 
     fn try_from(public_key: &PublicKey) -> Result<Self, Error> {
-        Self::key_validate(public_key.0.as_ref()).map_err(|err| BLSTError::from(err).into())
+        Self::key_validate(public_key.0.as_ref()).map_err(|err| Error::InvalidPublicKey {
+            public_key: public_key.clone(),
+            source: BLSTError::from(err),
+        })
+    }
+}
+
+// A `PublicKey` decompressed to an affine point and checked to be a valid group element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecompressedPublicKey(bls_impl::PublicKey);
+
+impl DecompressedPublicKey {
+    pub fn to_uncompressed_bytes(&self) -> [u8; BLS_UNCOMPRESSED_PUBLIC_KEY_BYTES_LEN] {
+        self.0.serialize()
+    }
+
+    // Decode the output of `to_uncompressed_bytes`. The point is checked to be on the curve but,
+    // unlike decompression, not to be in the subgroup, so only decode bytes this crate produced.
+    pub fn from_uncompressed_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != BLS_UNCOMPRESSED_PUBLIC_KEY_BYTES_LEN {
+            return Err(BLSTError::from(BLST_ERROR::BLST_BAD_ENCODING).into())
+        }
+        bls_impl::PublicKey::deserialize(bytes).map(Self).map_err(|err| BLSTError::from(err).into())
+    }
+}
+
+impl TryFrom<&PublicKey> for DecompressedPublicKey {
+    type Error = Error;
+
+    fn try_from(public_key: &PublicKey) -> Result<Self, Error> {
+        bls_impl::PublicKey::try_from(public_key).map(Self)
     }
 }
 
@@ -350,6 +728,47 @@ mod tests {
         0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
     ];
 
+    fn check_hash_64b_blocks<B: HashBackend>() {
+        // more than one group of eight blocks, with a remainder
+        for count in [0, 5, 8, 19] {
+            let input = (0..=255u8).cycle().take(64 * count).collect::<Vec<_>>();
+            let mut output = vec![0u8; 32 * count];
+            B::hash_64b_blocks(&input, &mut output).unwrap();
+            for (block, out) in input.chunks(64).zip(output.chunks(32)) {
+                assert_eq!(Sha2Backend::hash(block).as_ref(), out);
+            }
+        }
+
+        let input = vec![0u8; 64 + 32];
+        let mut output = vec![0u8; 48];
+        assert!(matches!(
+            B::hash_64b_blocks(&input, &mut output),
+            Err(Error::InvalidHashInput { input: 96, output: 48 })
+        ));
+        let mut output = vec![0u8; 64];
+        assert!(B::hash_64b_blocks(&input[..64], &mut output).is_err());
+    }
+
+    #[test]
+    fn test_hash_64b_blocks() {
+        check_hash_64b_blocks::<Sha2Backend>();
+        check_hash_64b_blocks::<DefaultHashBackend>();
+    }
+
+    #[cfg(all(feature = "sha256-simd", target_arch = "x86_64"))]
+    #[test]
+    fn test_sha256_avx2() {
+        if !sha256_avx2::is_available() {
+            return
+        }
+        let input = (0..64 * 19).map(|i| (i * 31 + 7) as u8).collect::<Vec<_>>();
+        let mut output = vec![0u8; 32 * 19];
+        assert_eq!(sha256_avx2::hash_64b_blocks(&input, &mut output), 16);
+        for (block, out) in input.chunks(64).zip(output.chunks(32)).take(16) {
+            assert_eq!(Sha2Backend::hash(block).as_ref(), out);
+        }
+    }
+
     #[test]
     fn signature() {
         let mut rng = thread_rng();
@@ -514,6 +933,140 @@ mod tests {
         assert!(v.is_ok());
     }
 
+    #[test]
+    fn test_fast_aggregate_verify_decompressed() {
+        let mut rng = thread_rng();
+        let secret_keys = (0..4).map(|_| SecretKey::random(&mut rng).unwrap()).collect::<Vec<_>>();
+        let public_keys = secret_keys
+            .iter()
+            .map(|key| DecompressedPublicKey::try_from(&key.public_key()).unwrap())
+            .collect::<Vec<_>>();
+        let public_keys = public_keys.iter().collect::<Vec<_>>();
+        let msg = "message".as_bytes();
+        let signatures = secret_keys.iter().map(|key| key.sign(msg)).collect::<Vec<_>>();
+        let signature = aggregate(&signatures).unwrap();
+        assert!(fast_aggregate_verify_decompressed(&public_keys, msg, &signature).is_ok());
+        assert!(fast_aggregate_verify_decompressed(&public_keys[1..], msg, &signature).is_err());
+    }
+
+    #[test]
+    fn test_eth_aggregate_decompressed_public_keys() {
+        let mut rng = thread_rng();
+        let public_keys =
+            (0..4).map(|_| SecretKey::random(&mut rng).unwrap().public_key()).collect::<Vec<_>>();
+        let decompressed = public_keys
+            .iter()
+            .map(|key| DecompressedPublicKey::try_from(key).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            eth_aggregate_decompressed_public_keys(&decompressed).unwrap(),
+            eth_aggregate_public_keys(&public_keys).unwrap()
+        );
+        assert!(eth_aggregate_decompressed_public_keys(&[]).is_err());
+    }
+
+    #[test]
+    fn test_decompressed_public_key_uncompressed_bytes() {
+        let mut rng = thread_rng();
+        let public_key = SecretKey::random(&mut rng).unwrap().public_key();
+        let key = DecompressedPublicKey::try_from(&public_key).unwrap();
+        let bytes = key.to_uncompressed_bytes();
+        assert_eq!(DecompressedPublicKey::from_uncompressed_bytes(&bytes).unwrap(), key);
+        assert!(DecompressedPublicKey::from_uncompressed_bytes(public_key.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_invalid_public_key() {
+        let infinity = PublicKey::try_from(INFINITY_COMPRESSED_PUBLIC_KEY.as_ref()).unwrap();
+        let public_key = SecretKey::random(&mut thread_rng()).unwrap().public_key();
+        let result = eth_aggregate_public_keys(&[public_key, infinity.clone()]);
+        assert!(
+            matches!(result, Err(Error::InvalidPublicKey { public_key, .. }) if public_key == infinity)
+        );
+        assert!(matches!(eth_aggregate_public_keys(&[]), Err(Error::EmptyAggregate)));
+    }
+
+    #[test]
+    fn test_verify_signature_sets() {
+        let mut rng = thread_rng();
+        let sks: Vec<_> = (0..6).map(|_| SecretKey::random(&mut rng).unwrap()).collect();
+        let pks: Vec<_> = sks.iter().map(|sk| sk.public_key()).collect();
+        let msgs = ["a".as_bytes(), "b".as_bytes(), "c".as_bytes()];
+
+        let signatures: Vec<_> = msgs
+            .iter()
+            .enumerate()
+            .map(|(i, msg)| aggregate(&[sks[2 * i].sign(msg), sks[2 * i + 1].sign(msg)]).unwrap())
+            .collect();
+        let mut sets: Vec<_> = msgs
+            .iter()
+            .zip(signatures.iter())
+            .enumerate()
+            .map(|(i, (msg, signature))| SignatureSet {
+                public_keys: vec![&pks[2 * i], &pks[2 * i + 1]],
+                message: msg,
+                signature,
+            })
+            .collect();
+        assert!(verify_signature_sets(&sets).is_ok());
+        assert!(verify_signature_sets(&[]).is_ok());
+
+        sets[1].message = msgs[0];
+        assert!(verify_signature_sets(&sets).is_err());
+    }
+
+    #[test]
+    fn test_incremental_aggregation() {
+        let mut rng = thread_rng();
+        let sks: Vec<_> = (0..4).map(|_| SecretKey::random(&mut rng).unwrap()).collect();
+        let pks: Vec<_> = sks.iter().map(|sk| sk.public_key()).collect();
+        let msg = "message".as_bytes();
+
+        let mut signature = AggregateSignature::new(sks.len());
+        let mut public_key = AggregatePublicKey::new(sks.len());
+        assert!(matches!(signature.to_signature(), Err(Error::EmptyAggregate)));
+        for i in [0, 2] {
+            signature.add(i, &sks[i].sign(msg)).unwrap();
+            public_key.add(i, &pks[i]).unwrap();
+        }
+        assert!(matches!(signature.add(2, &sks[2].sign(msg)), Err(Error::DuplicateParticipant(2))));
+        assert!(matches!(
+            public_key.add(4, &pks[0]),
+            Err(Error::ParticipantOutOfBounds { index: 4, count: 4 })
+        ));
+        // a rejected participant is not marked as included
+        let infinity = PublicKey::try_from(INFINITY_COMPRESSED_PUBLIC_KEY.as_ref()).unwrap();
+        assert!(public_key.add(1, &infinity).is_err());
+        assert!(signature.add(1, &Signature::try_from([0xff; 96].as_ref()).unwrap()).is_err());
+        assert_eq!(signature.participants(), &[true, false, true, false]);
+        assert_eq!(public_key.participants(), signature.participants());
+
+        let mut other_signature = AggregateSignature::new(sks.len());
+        other_signature.add(3, &sks[3].sign(msg)).unwrap();
+        let mut other_public_key = AggregatePublicKey::new(sks.len());
+        other_public_key.add(3, &pks[3]).unwrap();
+        signature.merge(&other_signature).unwrap();
+        public_key.merge(&other_public_key).unwrap();
+        assert!(matches!(signature.merge(&other_signature), Err(Error::DuplicateParticipant(3))));
+        assert!(matches!(
+            signature.merge(&AggregateSignature::new(2)),
+            Err(Error::ParticipantCountMismatch)
+        ));
+
+        assert_eq!(signature.participants(), &[true, false, true, true]);
+        assert_eq!(public_key.participants(), signature.participants());
+        let expected_signature =
+            aggregate(&[sks[0].sign(msg), sks[2].sign(msg), sks[3].sign(msg)]).unwrap();
+        let signature = signature.to_signature().unwrap();
+        assert_eq!(signature, expected_signature);
+        let public_key = public_key.to_public_key().unwrap();
+        assert_eq!(
+            public_key,
+            eth_aggregate_public_keys(&[pks[0].clone(), pks[2].clone(), pks[3].clone()]).unwrap()
+        );
+        assert!(verify_signature(&public_key, msg, &signature).is_ok());
+    }
+
     #[test]
     fn test_can_make_default_signature() {
         let _ = Signature::default();
@@ -563,9 +1116,11 @@ mod tests {
         let sig = sk.sign(msg);
 
         let serialized_key = serde_json::to_string(&pk).unwrap();
+        assert_eq!(serialized_key, format!("\"0x{}\"", hex::encode(pk.as_ref())));
         let recovered_key: PublicKey = serde_json::from_str(&serialized_key).unwrap();
         assert_eq!(pk, recovered_key);
         let serialized_signature = serde_json::to_string(&sig).unwrap();
+        assert_eq!(serialized_signature, format!("\"0x{}\"", hex::encode(sig.as_ref())));
         let recovered_signature: Signature = serde_json::from_str(&serialized_signature).unwrap();
         assert_eq!(sig, recovered_signature);
     }
@@ -0,0 +1,147 @@
+//! Multi-buffer SHA-256 with AVX2, hashing eight 64 byte messages at once.
+//!
+//! Each message is exactly one block, so the second block of every message is the same padding
+//! and its message schedule is computed once at compile time, as in `hashtree`.
+use std::arch::x86_64::*;
+
+const LANES: usize = 8;
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+// The message schedule of the padding block of a 64 byte message with the round constants added.
+const PADDING_SCHEDULE: [u32; 64] = padding_schedule();
+
+const fn padding_schedule() -> [u32; 64] {
+    let mut w = [0u32; 64];
+    w[0] = 0x80000000;
+    w[15] = 512;
+    let mut t = 16;
+    while t < 64 {
+        let s0 = w[t - 15].rotate_right(7) ^ w[t - 15].rotate_right(18) ^ (w[t - 15] >> 3);
+        let s1 = w[t - 2].rotate_right(17) ^ w[t - 2].rotate_right(19) ^ (w[t - 2] >> 10);
+        w[t] = w[t - 16].wrapping_add(s0).wrapping_add(w[t - 7]).wrapping_add(s1);
+        t += 1;
+    }
+    let mut t = 0;
+    while t < 64 {
+        w[t] = w[t].wrapping_add(K[t]);
+        t += 1;
+    }
+    w
+}
+
+macro_rules! rotr {
+    ($x:expr, $n:literal) => {
+        _mm256_or_si256(_mm256_srli_epi32($x, $n), _mm256_slli_epi32($x, 32 - $n))
+    };
+}
+
+#[inline(always)]
+unsafe fn add(a: __m256i, b: __m256i) -> __m256i {
+    _mm256_add_epi32(a, b)
+}
+
+#[inline(always)]
+unsafe fn xor3(a: __m256i, b: __m256i, c: __m256i) -> __m256i {
+    _mm256_xor_si256(_mm256_xor_si256(a, b), c)
+}
+
+// One round over the state `s` with `kw` the sum of the round constant and message word.
+#[inline(always)]
+unsafe fn round(s: &mut [__m256i; 8], kw: __m256i) {
+    let [a, b, c, d, e, f, g, h] = *s;
+    let big_s1 = xor3(rotr!(e, 6), rotr!(e, 11), rotr!(e, 25));
+    let ch = _mm256_xor_si256(_mm256_and_si256(e, f), _mm256_andnot_si256(e, g));
+    let t1 = add(add(h, big_s1), add(ch, kw));
+    let big_s0 = xor3(rotr!(a, 2), rotr!(a, 13), rotr!(a, 22));
+    let maj = xor3(_mm256_and_si256(a, b), _mm256_and_si256(a, c), _mm256_and_si256(b, c));
+    let t2 = add(big_s0, maj);
+    *s = [add(t1, t2), a, b, c, add(d, t1), e, f, g];
+}
+
+// Hash the eight 64 byte messages in `input` into the eight digests of `output`.
+#[target_feature(enable = "avx2")]
+unsafe fn hash_lanes(input: &[u8], output: &mut [u8]) {
+    debug_assert_eq!((input.len(), output.len()), (64 * LANES, 32 * LANES));
+    let word = |lane: usize, t: usize| {
+        let offset = 64 * lane + 4 * t;
+        i32::from_be_bytes(input[offset..offset + 4].try_into().expect("correct length"))
+    };
+
+    let mut w = [_mm256_setzero_si256(); 16];
+    for (t, w) in w.iter_mut().enumerate() {
+        *w = _mm256_setr_epi32(
+            word(0, t),
+            word(1, t),
+            word(2, t),
+            word(3, t),
+            word(4, t),
+            word(5, t),
+            word(6, t),
+            word(7, t),
+        );
+    }
+
+    let mut initial = [_mm256_setzero_si256(); 8];
+    for (s, h) in initial.iter_mut().zip(INITIAL_STATE) {
+        *s = _mm256_set1_epi32(h as i32);
+    }
+    let mut state = initial;
+    for t in 0..64 {
+        if t >= 16 {
+            let w15 = w[(t - 15) % 16];
+            let w2 = w[(t - 2) % 16];
+            let s0 = xor3(rotr!(w15, 7), rotr!(w15, 18), _mm256_srli_epi32(w15, 3));
+            let s1 = xor3(rotr!(w2, 17), rotr!(w2, 19), _mm256_srli_epi32(w2, 10));
+            w[t % 16] = add(add(w[t % 16], s0), add(w[(t - 7) % 16], s1));
+        }
+        round(&mut state, add(w[t % 16], _mm256_set1_epi32(K[t] as i32)));
+    }
+    for (s, h) in state.iter_mut().zip(initial) {
+        *s = add(*s, h);
+    }
+
+    let intermediate = state;
+    for kw in PADDING_SCHEDULE {
+        round(&mut state, _mm256_set1_epi32(kw as i32));
+    }
+
+    for (i, (s, h)) in state.into_iter().zip(intermediate).enumerate() {
+        let mut words = [0u32; LANES];
+        _mm256_storeu_si256(words.as_mut_ptr().cast(), add(s, h));
+        for (lane, word) in words.into_iter().enumerate() {
+            let offset = 32 * lane + 4 * i;
+            output[offset..offset + 4].copy_from_slice(&word.to_be_bytes());
+        }
+    }
+}
+
+pub(super) fn is_available() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+// Hash as many groups of eight blocks of `input` as there are and return the number of blocks
+// hashed. `input` and `output` must already be checked to hold one digest per block.
+pub(super) fn hash_64b_blocks(input: &[u8], output: &mut [u8]) -> usize {
+    assert!(is_available());
+    let mut count = 0;
+    for (input, output) in input.chunks_exact(64 * LANES).zip(output.chunks_exact_mut(32 * LANES)) {
+        // SAFETY: AVX2 support was checked above
+        unsafe { hash_lanes(input, output) };
+        count += LANES;
+    }
+    count
+}
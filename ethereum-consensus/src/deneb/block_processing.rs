@@ -17,7 +17,7 @@ use crate::{
         InvalidVoluntaryExit,
     },
     primitives::FAR_FUTURE_EPOCH,
-    signing::verify_signed_data,
+    signing::compute_signing_root,
     ssz::prelude::*,
     state_transition::{Context, Result},
 };
@@ -342,8 +342,11 @@ pub fn process_voluntary_exit<
         Some(state.genesis_validators_root),
         context,
     )?;
+    let signing_root = compute_signing_root(voluntary_exit, domain)?;
     let public_key = &validator.public_key;
-    verify_signed_data(voluntary_exit, &signed_voluntary_exit.signature, public_key, domain)
+    context
+        .verified_signature_cache
+        .verify_signature(public_key, &signing_root, &signed_voluntary_exit.signature)
         .map_err(|_| {
             invalid_operation_error(InvalidOperation::VoluntaryExit(
                 InvalidVoluntaryExit::InvalidSignature(signed_voluntary_exit.signature.clone()),
@@ -0,0 +1,308 @@
+//! Gossip validation for the `blob_sidecar_{subnet_id}` topics.
+//!
+//! Rules that depend on fork choice (the parent block being known and valid, the sidecar
+//! descending from the finalized checkpoint) are left to the caller.
+use crate::{
+    deneb::{
+        beacon_state::BeaconState,
+        blob_sidecar::SignedBlobSidecar,
+        compute_epoch_at_slot, get_beacon_proposer_index, get_domain,
+        networking::BLOB_SIDECAR_SUBNET_COUNT,
+        polynomial_commitments::{self, verify_blob_kzg_proof, KzgSettings},
+    },
+    domains::DomainType,
+    primitives::{BlobIndex, Slot, ValidatorIndex},
+    signing::verify_signed_data,
+    state_transition::Context,
+};
+use std::collections::HashSet;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationResult {
+    Accept,
+    Ignore,
+    Reject,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("blob index {index} is not less than {max_blobs_per_block}")]
+    IndexOutOfBounds { index: BlobIndex, max_blobs_per_block: usize },
+    #[error(
+        "blob index {index} belongs on subnet {expected} but was received on subnet {subnet_id}"
+    )]
+    WrongSubnet { index: BlobIndex, subnet_id: u64, expected: u64 },
+    #[error("sidecar at slot {slot} is ahead of the current slot {current_slot}")]
+    FutureSlot { slot: Slot, current_slot: Slot },
+    #[error("sidecar at slot {slot} is not after the finalized slot {finalized_slot}")]
+    NotAfterFinalized { slot: Slot, finalized_slot: Slot },
+    #[error("state at slot {state_slot} cannot be used to validate a sidecar at slot {slot}")]
+    StateSlotMismatch { slot: Slot, state_slot: Slot },
+    #[error(
+        "sidecar for slot {slot}, proposer {proposer_index} and index {index} was already seen"
+    )]
+    AlreadySeen { slot: Slot, proposer_index: ValidatorIndex, index: BlobIndex },
+    #[error("expected proposer {expected} but sidecar is from proposer {proposer_index}")]
+    UnexpectedProposer { proposer_index: ValidatorIndex, expected: ValidatorIndex },
+    #[error("invalid proposer signature: {0}")]
+    InvalidSignature(#[source] crate::Error),
+    #[error("invalid KZG proof: {0}")]
+    InvalidKzgProof(#[from] polynomial_commitments::Error),
+    #[error("{0}")]
+    Consensus(#[from] crate::Error),
+}
+
+impl Error {
+    // How the sidecar should be treated by the gossip layer given this error
+    pub fn result(&self) -> ValidationResult {
+        match self {
+            Self::FutureSlot { .. } |
+            Self::NotAfterFinalized { .. } |
+            Self::StateSlotMismatch { .. } |
+            Self::AlreadySeen { .. } |
+            Self::Consensus(..) => ValidationResult::Ignore,
+            Self::IndexOutOfBounds { .. } |
+            Self::WrongSubnet { .. } |
+            Self::UnexpectedProposer { .. } |
+            Self::InvalidSignature(..) |
+            Self::InvalidKzgProof(..) => ValidationResult::Reject,
+        }
+    }
+}
+
+pub fn compute_subnet_for_blob_sidecar(blob_index: BlobIndex) -> u64 {
+    (blob_index % BLOB_SIDECAR_SUBNET_COUNT) as u64
+}
+
+// Tracks the `(slot, proposer_index, index)` of sidecars that passed validation, so that
+// only the first one for each is propagated.
+#[derive(Debug, Default)]
+pub struct SeenBlobSidecars {
+    seen: HashSet<(Slot, ValidatorIndex, BlobIndex)>,
+}
+
+impl SeenBlobSidecars {
+    pub fn contains(&self, slot: Slot, proposer_index: ValidatorIndex, index: BlobIndex) -> bool {
+        self.seen.contains(&(slot, proposer_index, index))
+    }
+
+    // Returns `false` if this sidecar was already seen
+    pub fn insert(&mut self, slot: Slot, proposer_index: ValidatorIndex, index: BlobIndex) -> bool {
+        self.seen.insert((slot, proposer_index, index))
+    }
+
+    // Drop entries that can no longer pass validation
+    pub fn prune(&mut self, finalized_slot: Slot) {
+        self.seen.retain(|(slot, ..)| *slot > finalized_slot);
+    }
+}
+
+// Validate a sidecar received on `subnet_id`, recording it in `seen` if it is accepted.
+//
+// `state` must be at the slot of the sidecar, so that its proposer can be computed.
+// `current_slot` should already allow for `MAXIMUM_GOSSIP_CLOCK_DISPARITY`.
+#[allow(clippy::too_many_arguments)]
+pub fn validate_blob_sidecar<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const BYTES_PER_BLOB: usize,
+>(
+    signed_blob_sidecar: &mut SignedBlobSidecar<BYTES_PER_BLOB>,
+    subnet_id: u64,
+    current_slot: Slot,
+    finalized_slot: Slot,
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    seen: &mut SeenBlobSidecars,
+    kzg_settings: &KzgSettings,
+    context: &Context,
+) -> Result<(), Error> {
+    let sidecar = &mut signed_blob_sidecar.message;
+    let index = sidecar.index;
+    if index >= context.max_blobs_per_block {
+        return Err(Error::IndexOutOfBounds {
+            index,
+            max_blobs_per_block: context.max_blobs_per_block,
+        })
+    }
+    let expected = compute_subnet_for_blob_sidecar(index);
+    if subnet_id != expected {
+        return Err(Error::WrongSubnet { index, subnet_id, expected })
+    }
+
+    let slot = sidecar.slot;
+    if slot > current_slot {
+        return Err(Error::FutureSlot { slot, current_slot })
+    }
+    if slot <= finalized_slot {
+        return Err(Error::NotAfterFinalized { slot, finalized_slot })
+    }
+
+    let proposer_index = sidecar.proposer_index;
+    if seen.contains(slot, proposer_index, index) {
+        return Err(Error::AlreadySeen { slot, proposer_index, index })
+    }
+
+    if state.slot != slot {
+        return Err(Error::StateSlotMismatch { slot, state_slot: state.slot })
+    }
+    let expected = get_beacon_proposer_index(state, context)?;
+    if proposer_index != expected {
+        return Err(Error::UnexpectedProposer { proposer_index, expected })
+    }
+
+    let epoch = compute_epoch_at_slot(slot, context);
+    let domain = get_domain(state, DomainType::BlobSidecar, Some(epoch), context)?;
+    let public_key = &state.validators[proposer_index].public_key;
+    verify_signed_data(sidecar, &signed_blob_sidecar.signature, public_key, domain)
+        .map_err(Error::InvalidSignature)?;
+
+    let sidecar = &signed_blob_sidecar.message;
+    verify_blob_kzg_proof(
+        &sidecar.blob,
+        &sidecar.kzg_commitment,
+        &sidecar.kzg_proof,
+        kzg_settings,
+    )?;
+
+    seen.insert(slot, proposer_index, index);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        deneb::{
+            mainnet,
+            test_utils::{
+                constant_blob, kzg_settings, sign_blob_sidecar, signed_blob_sidecar, state_at,
+            },
+        },
+        primitives::Root,
+    };
+
+    const SLOT: Slot = 10;
+
+    fn result(result: Result<(), Error>) -> ValidationResult {
+        match result {
+            Ok(()) => ValidationResult::Accept,
+            Err(err) => err.result(),
+        }
+    }
+
+    #[test]
+    fn test_validate_blob_sidecar() {
+        let context = Context::for_minimal();
+        let kzg_settings = kzg_settings();
+        let state = state_at(SLOT, &context);
+        let mut seen = SeenBlobSidecars::default();
+        let index = 1;
+        let subnet_id = compute_subnet_for_blob_sidecar(index);
+        let mut sidecar =
+            signed_blob_sidecar(&state, index, constant_blob(1), &kzg_settings, &context);
+
+        let mut validate =
+            |sidecar: &mut mainnet::SignedBlobSidecar, current_slot, finalized_slot| {
+                result(validate_blob_sidecar(
+                    sidecar,
+                    subnet_id,
+                    current_slot,
+                    finalized_slot,
+                    &state,
+                    &mut seen,
+                    &kzg_settings,
+                    &context,
+                ))
+            };
+
+        // from the future, then no longer after the finalized slot
+        assert_eq!(validate(&mut sidecar, SLOT - 1, 0), ValidationResult::Ignore);
+        assert_eq!(validate(&mut sidecar, SLOT, SLOT), ValidationResult::Ignore);
+
+        // only the first copy of a valid sidecar is accepted
+        assert_eq!(validate(&mut sidecar, SLOT, 0), ValidationResult::Accept);
+        assert_eq!(validate(&mut sidecar, SLOT, 0), ValidationResult::Ignore);
+        assert!(seen.contains(SLOT, sidecar.message.proposer_index, index));
+    }
+
+    #[test]
+    fn test_validate_blob_sidecar_rejects_invalid_sidecars() {
+        let context = Context::for_minimal();
+        let kzg_settings = kzg_settings();
+        let state = state_at(SLOT, &context);
+        let mut seen = SeenBlobSidecars::default();
+        let sidecar = signed_blob_sidecar(&state, 0, constant_blob(1), &kzg_settings, &context);
+
+        let mut validate = |sidecar: &mut mainnet::SignedBlobSidecar, subnet_id| {
+            result(validate_blob_sidecar(
+                sidecar,
+                subnet_id,
+                SLOT,
+                0,
+                &state,
+                &mut seen,
+                &kzg_settings,
+                &context,
+            ))
+        };
+
+        let mut out_of_range = sidecar.message.clone();
+        out_of_range.index = context.max_blobs_per_block as BlobIndex;
+        let subnet_id = compute_subnet_for_blob_sidecar(out_of_range.index);
+        let mut out_of_range = sign_blob_sidecar(out_of_range, &state, &context);
+        assert_eq!(validate(&mut out_of_range, subnet_id), ValidationResult::Reject);
+
+        assert_eq!(validate(&mut sidecar.clone(), 1), ValidationResult::Reject);
+
+        let mut wrong_proposer = sidecar.message.clone();
+        wrong_proposer.proposer_index =
+            (wrong_proposer.proposer_index + 1) % state.validators.len();
+        let mut wrong_proposer = sign_blob_sidecar(wrong_proposer, &state, &context);
+        assert_eq!(validate(&mut wrong_proposer, 0), ValidationResult::Reject);
+
+        let mut bad_signature = sidecar.clone();
+        bad_signature.message.block_root = Root::try_from([1u8; 32].as_ref()).unwrap();
+        assert_eq!(validate(&mut bad_signature, 0), ValidationResult::Reject);
+
+        // the proof of a constant blob is the point at infinity, so any other point is invalid
+        let mut bad_proof = sidecar.message.clone();
+        bad_proof.kzg_proof = bad_proof.kzg_commitment.clone();
+        let mut bad_proof = sign_blob_sidecar(bad_proof, &state, &context);
+        assert_eq!(validate(&mut bad_proof, 0), ValidationResult::Reject);
+
+        // none of the rejected sidecars were recorded as seen
+        assert_eq!(validate(&mut sidecar.clone(), 0), ValidationResult::Accept);
+    }
+
+    #[test]
+    fn test_seen_blob_sidecars_prune() {
+        let mut seen = SeenBlobSidecars::default();
+        assert!(seen.insert(SLOT, 3, 0));
+        assert!(!seen.insert(SLOT, 3, 0));
+        assert!(seen.insert(SLOT + 1, 3, 0));
+
+        seen.prune(SLOT);
+        assert!(!seen.contains(SLOT, 3, 0));
+        assert!(seen.contains(SLOT + 1, 3, 0));
+    }
+}
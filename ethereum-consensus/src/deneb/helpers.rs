@@ -2,23 +2,60 @@ use crate::{
     altair::constants::{
         TIMELY_HEAD_FLAG_INDEX, TIMELY_SOURCE_FLAG_INDEX, TIMELY_TARGET_FLAG_INDEX,
     },
-    crypto::hash,
     deneb::{
         beacon_state::BeaconState,
-        get_block_root, get_block_root_at_slot, get_current_epoch, get_validator_churn_limit,
+        compute_domain, get_block_root, get_block_root_at_slot, get_current_epoch,
+        get_validator_churn_limit,
         polynomial_commitments::{KzgCommitment, VersionedHash},
-        AttestationData, VERSIONED_HASH_VERSION_KZG,
+        AttestationData, SignedVoluntaryExit,
     },
-    error::{invalid_operation_error, InvalidAttestation, InvalidOperation},
+    domains::DomainType,
+    error::{invalid_operation_error, InvalidAttestation, InvalidOperation, InvalidVoluntaryExit},
+    primitives::Root,
+    signing::compute_signing_root,
+    ssz::prelude::*,
     state_transition::{Context, Result},
 };
 use integer_sqrt::IntegerSquareRoot;
 
 pub fn kzg_commitment_to_versioned_hash(kzg_commitment: &KzgCommitment) -> VersionedHash {
-    let mut result = VersionedHash::default();
-    result[0] = VERSIONED_HASH_VERSION_KZG;
-    result[1..].copy_from_slice(&hash(kzg_commitment.as_ref())[1..]);
-    result
+    VersionedHash::from_kzg_commitment(kzg_commitment)
+}
+
+// The `parent_beacon_block_root` (EIP-4788) to give the execution layer when building a
+// payload on top of `state`, i.e. the root of the block `state` was produced by.
+pub fn get_parent_beacon_block_root<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+) -> Result<Root> {
+    let mut header = state.latest_block_header.clone();
+    // the state root is only filled in by `process_slot`, so use the root of `state` if
+    // it has not been advanced past the slot of its latest block
+    if header.state_root == Root::default() {
+        header.state_root = state.hash_tree_root()?;
+    }
+    Ok(header.hash_tree_root()?)
 }
 
 pub fn get_attestation_participation_flag_indices<
@@ -113,3 +150,84 @@ pub fn get_validator_activation_churn_limit<
     let limit = context.max_per_epoch_activation_churn_limit as usize;
     limit.min(get_validator_churn_limit(state, context))
 }
+
+// Add the signatures of the voluntary exits in a block to `batch`, using the capella fork
+// version for the domain as in `process_voluntary_exit`.
+pub fn collect_voluntary_exit_signature_sets<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &'a BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    voluntary_exits: &'a [SignedVoluntaryExit],
+    batch: &mut crate::signing::SignatureBatch<'a>,
+    context: &Context,
+) -> Result<()> {
+    for signed_voluntary_exit in voluntary_exits {
+        let voluntary_exit = &signed_voluntary_exit.message;
+        let public_key = state
+            .validators
+            .get(voluntary_exit.validator_index)
+            .map(|v| &v.public_key)
+            .ok_or_else(|| {
+                invalid_operation_error(InvalidOperation::VoluntaryExit(
+                    InvalidVoluntaryExit::InvalidIndex(voluntary_exit.validator_index),
+                ))
+            })?;
+        let domain = compute_domain(
+            DomainType::VoluntaryExit,
+            Some(context.capella_fork_version),
+            Some(state.genesis_validators_root),
+            context,
+        )?;
+        let signing_root = compute_signing_root(&mut voluntary_exit.clone(), domain)?;
+        let error = invalid_operation_error(InvalidOperation::VoluntaryExit(
+            InvalidVoluntaryExit::InvalidSignature(signed_voluntary_exit.signature.clone()),
+        ));
+        batch.push(vec![public_key], signing_root, &signed_voluntary_exit.signature, error);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deneb::{minimal::process_slots, test_utils::state_at};
+
+    #[test]
+    fn test_get_parent_beacon_block_root() {
+        let context = Context::for_minimal();
+        // as left by processing a block at slot 5
+        let mut state = state_at(5, &context);
+        state.latest_block_header.slot = 5;
+        state.latest_block_header.proposer_index = 3;
+        let root = get_parent_beacon_block_root(&mut state).unwrap();
+
+        // the root `process_slot` records for the block once the state is advanced
+        let mut advanced = state.clone();
+        process_slots(&mut advanced, 7, &context).unwrap();
+        assert_eq!(advanced.block_roots[5], root);
+        // and keeps returning through empty slots
+        assert_eq!(advanced.block_roots[6], root);
+        assert_eq!(get_parent_beacon_block_root(&mut advanced).unwrap(), root);
+    }
+}
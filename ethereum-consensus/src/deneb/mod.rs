@@ -12,12 +12,15 @@ pub mod execution_engine;
 pub mod execution_payload;
 pub mod fork;
 pub mod genesis;
+pub mod gossip;
 pub mod helpers;
 pub mod networking;
 pub mod polynomial_commitments;
 pub mod presets;
 pub mod spec;
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 pub use spec::*;
 
-pub use presets::{mainnet, minimal, Preset};
+pub use presets::{gnosis, mainnet, minimal, Preset};
@@ -1,16 +1,85 @@
-use crate::{deneb::blob_sidecar::Blob, primitives::Bytes32, ssz::prelude::*};
+use crate::{
+    crypto::hash,
+    deneb::blob_sidecar::{Blob, VERSIONED_HASH_VERSION_KZG},
+    primitives::Bytes32,
+    ssz::prelude::*,
+};
 pub use c_kzg::KzgSettings;
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    path::Path,
+};
 use thiserror::Error;
 
 pub const BYTES_PER_FIELD_ELEMENT: usize = 32;
 pub const BYTES_PER_COMMITMENT: usize = 48;
 pub const BYTES_PER_PROOF: usize = 48;
+pub const BYTES_PER_G2_POINT: usize = 96;
 
-pub type VersionedHash = Bytes32;
 pub type FieldElement = Bytes32;
 pub type KzgCommitment = ByteVector<BYTES_PER_COMMITMENT>;
 pub type KzgProof = ByteVector<BYTES_PER_PROOF>;
 
+// A commitment hash prefixed with a version byte, as referenced by blob transactions
+#[derive(
+    Default, Clone, PartialEq, Eq, Hash, SimpleSerialize, serde::Serialize, serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct VersionedHash(Bytes32);
+
+impl VersionedHash {
+    pub fn from_kzg_commitment(kzg_commitment: &KzgCommitment) -> Self {
+        let mut inner = hash(kzg_commitment.as_ref());
+        inner[0] = VERSIONED_HASH_VERSION_KZG;
+        Self(inner)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.0[0]
+    }
+}
+
+impl TryFrom<&[u8]> for VersionedHash {
+    type Error = DeserializeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Bytes32::try_from(bytes).map(Self)
+    }
+}
+
+impl From<VersionedHash> for Bytes32 {
+    fn from(versioned_hash: VersionedHash) -> Self {
+        versioned_hash.0
+    }
+}
+
+impl fmt::Debug for VersionedHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl fmt::Display for VersionedHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Deref for VersionedHash {
+    type Target = Bytes32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for VersionedHash {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
@@ -19,6 +88,34 @@ pub enum Error {
     InvalidProof,
 }
 
+// A trusted setup in the JSON format distributed with the consensus specs
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrustedSetup {
+    pub g1_lagrange: Vec<ByteVector<BYTES_PER_COMMITMENT>>,
+    pub g2_monomial: Vec<ByteVector<BYTES_PER_G2_POINT>>,
+}
+
+impl TrustedSetup {
+    pub fn to_kzg_settings(&self) -> Result<KzgSettings, Error> {
+        let g1_points = self
+            .g1_lagrange
+            .iter()
+            .map(|point| point.as_ref().try_into().expect("correct size"))
+            .collect::<Vec<[u8; BYTES_PER_COMMITMENT]>>();
+        let g2_points = self
+            .g2_monomial
+            .iter()
+            .map(|point| point.as_ref().try_into().expect("correct size"))
+            .collect::<Vec<[u8; BYTES_PER_G2_POINT]>>();
+        KzgSettings::load_trusted_setup(&g1_points, &g2_points).map_err(Into::into)
+    }
+}
+
+// Load a trusted setup from a file in the text format used by `c-kzg`
+pub fn load_trusted_setup_file<P: AsRef<Path>>(path: P) -> Result<KzgSettings, Error> {
+    KzgSettings::load_trusted_setup_file(path.as_ref()).map_err(Into::into)
+}
+
 pub struct ProofAndEvaluation {
     pub proof: KzgProof,
     pub evaluation: FieldElement,
@@ -135,3 +232,134 @@ pub fn verify_blob_kzg_proof_batch<const BYTES_PER_BLOB: usize>(
 
     res.then_some(()).ok_or(Error::InvalidProof)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deneb::{
+        mainnet::{Blob, BYTES_PER_BLOB},
+        test_utils::{constant_blob, kzg_settings, TRUSTED_SETUP_PATH},
+    };
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        let hex = hex.trim_start_matches("0x");
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_versioned_hash_from_kzg_commitment() {
+        // the commitment to the zero blob, i.e. the point at infinity, and the G1 generator
+        let vectors = [
+            (
+                "0xc00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+                "0x010657f37554c781402a22917dee2f75def7ab966d7b770905398eba3c444014",
+            ),
+            (
+                "0x97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb",
+                "0x01cf478a431837728dcec3461f4f53b8749cdc4e03496dcaed459dea82b82eb8",
+            ),
+        ];
+        for (commitment, expected) in vectors {
+            let commitment = KzgCommitment::try_from(from_hex(commitment).as_ref()).unwrap();
+            let versioned_hash = VersionedHash::from_kzg_commitment(&commitment);
+            assert_eq!(versioned_hash.version(), VERSIONED_HASH_VERSION_KZG);
+            let expected = VersionedHash::try_from(from_hex(expected).as_ref()).unwrap();
+            assert_eq!(versioned_hash, expected);
+        }
+    }
+
+    // The setup of `TRUSTED_SETUP_PATH`, read from the text format: the point counts followed by
+    // the G1 points in lagrange form and the G2 points in monomial form, one hex string per line.
+    fn trusted_setup() -> TrustedSetup {
+        let contents = std::fs::read_to_string(TRUSTED_SETUP_PATH).unwrap();
+        let mut lines = contents.lines();
+        let g1_count = lines.next().unwrap().parse::<usize>().unwrap();
+        let g2_count = lines.next().unwrap().parse::<usize>().unwrap();
+        let g1_lagrange = (&mut lines)
+            .take(g1_count)
+            .map(|line| ByteVector::try_from(from_hex(line).as_ref()).unwrap())
+            .collect::<Vec<_>>();
+        let g2_monomial = lines
+            .take(g2_count)
+            .map(|line| ByteVector::try_from(from_hex(line).as_ref()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!((g1_lagrange.len(), g2_monomial.len()), (g1_count, g2_count));
+        TrustedSetup { g1_lagrange, g2_monomial }
+    }
+
+    fn check_kzg_settings(kzg_settings: &KzgSettings) {
+        // the constant polynomial `c` commits to `c` times the G1 generator, and the proof of
+        // its evaluation anywhere is the point at infinity
+        let generators = [
+            "0x97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb",
+            "0xa572cbea904d67468808c8eb50a9450c9721db309128012543902d0ac358a62ae28f75bb8f1c7c42c39a8c5529bf0f4e",
+        ];
+        let infinity = "0xc00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+        for (value, expected) in (1..).zip(generators) {
+            let blob = constant_blob(value);
+            let commitment = blob_to_kzg_commitment(&blob, kzg_settings).unwrap();
+            assert_eq!(commitment.as_ref(), from_hex(expected).as_slice());
+            let proof = compute_blob_kzg_proof(&blob, &commitment, kzg_settings).unwrap();
+            assert_eq!(proof.as_ref(), from_hex(infinity).as_slice());
+            verify_blob_kzg_proof(&blob, &commitment, &proof, kzg_settings).unwrap();
+        }
+    }
+
+    // A blob with a different value in each field element.
+    fn varied_blob() -> Blob {
+        let mut bytes = vec![0u8; BYTES_PER_BLOB];
+        for (i, element) in bytes.chunks_mut(BYTES_PER_FIELD_ELEMENT).enumerate() {
+            element[BYTES_PER_FIELD_ELEMENT - 2..].copy_from_slice(&(i as u16).to_be_bytes());
+        }
+        Blob::try_from(bytes.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_trusted_setup_formats() {
+        let from_file = kzg_settings();
+        let from_setup = trusted_setup().to_kzg_settings().unwrap();
+        check_kzg_settings(&from_file);
+        check_kzg_settings(&from_setup);
+
+        let blob = varied_blob();
+        let commitment = blob_to_kzg_commitment(&blob, &from_file).unwrap();
+        assert_eq!(blob_to_kzg_commitment(&blob, &from_setup).unwrap(), commitment);
+        let proof = compute_blob_kzg_proof(&blob, &commitment, &from_file).unwrap();
+        verify_blob_kzg_proof(&blob, &commitment, &proof, &from_setup).unwrap();
+
+        assert!(load_trusted_setup_file("does/not/exist.txt").is_err());
+        let truncated = TrustedSetup { g1_lagrange: vec![], ..trusted_setup() };
+        assert!(truncated.to_kzg_settings().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_trusted_setup_from_json() {
+        let setup = trusted_setup();
+        let to_json = |points: Vec<&[u8]>| {
+            points
+                .iter()
+                .map(|point| {
+                    let hex = point.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+                    format!("\"0x{hex}\"")
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        // the layout of `trusted_setup_4096.json` in the consensus specs
+        let json = format!(
+            "{{\"g1_lagrange\": [{}], \"g2_monomial\": [{}]}}",
+            to_json(setup.g1_lagrange.iter().map(|point| point.as_ref()).collect()),
+            to_json(setup.g2_monomial.iter().map(|point| point.as_ref()).collect())
+        );
+        let from_json =
+            serde_json::from_str::<TrustedSetup>(&json).unwrap().to_kzg_settings().unwrap();
+        check_kzg_settings(&from_json);
+
+        let blob = varied_blob();
+        assert_eq!(
+            blob_to_kzg_commitment(&blob, &from_json).unwrap(),
+            blob_to_kzg_commitment(&blob, &kzg_settings()).unwrap()
+        );
+    }
+}
@@ -7,6 +7,8 @@ pub use crate::{
             TIMELY_SOURCE_FLAG_INDEX, TIMELY_SOURCE_WEIGHT, TIMELY_TARGET_FLAG_INDEX,
             TIMELY_TARGET_WEIGHT, WEIGHT_DENOMINATOR,
         },
+        epoch_processing::{EpochProcessingContext, ParticipationCache},
+        helpers::{RewardsReport, ValidatorRewards},
         light_client::{
             LightClientBootstrap, LightClientFinalityUpdate, LightClientHeader,
             LightClientOptimisticUpdate, LightClientStore, LightClientUpdate,
@@ -45,7 +47,8 @@ pub use crate::{
         fork::upgrade_to_deneb,
         genesis::initialize_beacon_state_from_eth1,
         helpers::{
-            get_attestation_participation_flag_indices, get_validator_activation_churn_limit,
+            collect_voluntary_exit_signature_sets, get_attestation_participation_flag_indices,
+            get_parent_beacon_block_root, get_validator_activation_churn_limit,
             kzg_commitment_to_versioned_hash,
         },
     },
@@ -57,10 +60,12 @@ pub use crate::{
             BASE_REWARDS_PER_EPOCH, DEPOSIT_CONTRACT_TREE_DEPTH, DEPOSIT_DATA_LIST_BOUND,
             JUSTIFICATION_BITS_LENGTH,
         },
+        epoch_processing::{EpochTransitionSummary, JustificationAndFinalization},
+        helpers::JustificationBits,
         operations::{
             Attestation, AttestationData, AttesterSlashing, Checkpoint, Deposit, DepositData,
             DepositMessage, Eth1Data, IndexedAttestation, PendingAttestation, ProposerSlashing,
-            SignedVoluntaryExit, VoluntaryExit,
+            SignedVoluntaryExit, ValidAttestation, VoluntaryExit,
         },
         validator::{AggregateAndProof, Eth1Block, SignedAggregateAndProof, Validator},
     },
@@ -69,13 +74,16 @@ pub use crate::{
     state_transition::{Context, Result, Validation},
 };
 use crate::{
-    crypto::{eth_aggregate_public_keys, eth_fast_aggregate_verify, fast_aggregate_verify, hash},
+    crypto::{
+        eth_aggregate_decompressed_public_keys, eth_fast_aggregate_verify, fast_aggregate_verify,
+        hash,
+    },
     ssz::prelude::*,
 };
 use integer_sqrt::IntegerSquareRoot;
 use std::{
     cmp,
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     iter::zip,
     mem,
 };
@@ -133,12 +141,60 @@ pub fn process_bls_to_execution_change<
             InvalidBlsToExecutionChange::PublicKeyMismatch(public_key.clone()),
         )))
     }
-    verify_signature(public_key, signing_root.as_ref(), signature)?;
+    context.verified_signature_cache.verify_signature(public_key, &signing_root, signature)?;
     withdrawal_credentials[0] = ETH1_ADDRESS_WITHDRAWAL_PREFIX;
     withdrawal_credentials[1..12].fill(0);
     withdrawal_credentials[12..].copy_from_slice(address_change.to_execution_address.as_ref());
     Ok(())
 }
+pub fn get_validator_indices_touched_by_operations<
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+>(
+    body: &BeaconBlockBody<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >,
+) -> Vec<ValidatorIndex> {
+    let mut indices = vec![];
+    for proposer_slashing in body.proposer_slashings.iter() {
+        indices.push(proposer_slashing.signed_header_1.message.proposer_index);
+    }
+    for attester_slashing in body.attester_slashings.iter() {
+        indices.extend(attester_slashing.attestation_1.attesting_indices.iter().copied());
+    }
+    for voluntary_exit in body.voluntary_exits.iter() {
+        indices.push(voluntary_exit.message.validator_index);
+    }
+    for bls_to_execution_change in body.bls_to_execution_changes.iter() {
+        indices.push(bls_to_execution_change.message.validator_index);
+    }
+    indices
+}
 pub fn process_operations<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -389,9 +445,9 @@ pub fn process_deposit<
     state.eth1_deposit_index += 1;
     let public_key = &deposit.data.public_key;
     let amount = deposit.data.amount;
-    let validator_public_keys: HashSet<&BlsPublicKey> =
-        HashSet::from_iter(state.validators.iter().map(|v| &v.public_key));
-    if !validator_public_keys.contains(public_key) {
+    if let Some(index) = context.pubkey_cache.get_validator_index(&state.validators, public_key) {
+        increase_balance(state, index, amount);
+    } else {
         let mut deposit_message = DepositMessage {
             public_key: public_key.clone(),
             withdrawal_credentials: deposit.data.withdrawal_credentials.clone(),
@@ -402,14 +458,12 @@ pub fn process_deposit<
         if verify_signature(public_key, signing_root.as_ref(), &deposit.data.signature).is_err() {
             return Ok(())
         }
+        context.pubkey_cache.insert(public_key.clone(), state.validators.len());
         state.validators.push(get_validator_from_deposit(deposit, context));
         state.balances.push(amount);
         state.previous_epoch_participation.push(ParticipationFlags::default());
         state.current_epoch_participation.push(ParticipationFlags::default());
         state.inactivity_scores.push(0)
-    } else {
-        let index = state.validators.iter().position(|v| &v.public_key == public_key).unwrap();
-        increase_balance(state, index, amount);
     }
     Ok(())
 }
@@ -454,12 +508,15 @@ pub fn process_sync_aggregate<
     )?;
     let mut root_at_slot = *get_block_root_at_slot(state, previous_slot)?;
     let signing_root = compute_signing_root(&mut root_at_slot, domain)?;
-    if eth_fast_aggregate_verify(
-        participant_public_keys.as_slice(),
-        signing_root.as_ref(),
-        &sync_aggregate.sync_committee_signature,
-    )
-    .is_err()
+    let signature = &sync_aggregate.sync_committee_signature;
+    let is_verified = context.verified_signature_cache.contains(
+        &participant_public_keys,
+        &signing_root,
+        signature,
+    );
+    if !is_verified &&
+        eth_fast_aggregate_verify(&participant_public_keys, signing_root.as_ref(), signature)
+            .is_err()
     {
         return Err(invalid_operation_error(InvalidOperation::SyncAggregate(
             InvalidSyncAggregate::InvalidSignature {
@@ -477,16 +534,14 @@ pub fn process_sync_aggregate<
     let participant_reward = max_participant_rewards / context.sync_committee_size as u64;
     let proposer_reward =
         participant_reward * PROPOSER_WEIGHT / (WEIGHT_DENOMINATOR - PROPOSER_WEIGHT);
-    let all_public_keys = state
-        .validators
-        .iter()
-        .enumerate()
-        .map(|(i, v)| (&v.public_key, i))
-        .collect::<HashMap<&BlsPublicKey, usize>>();
     let mut committee_indices: Vec<ValidatorIndex> = Vec::default();
     for public_key in state.current_sync_committee.public_keys.iter() {
-        committee_indices
-            .push(*all_public_keys.get(public_key).expect("validator public_key should exist"));
+        committee_indices.push(
+            context
+                .pubkey_cache
+                .get_validator_index(&state.validators, public_key)
+                .expect("validator public_key should exist"),
+        );
     }
     for (participant_index, participation_bit) in
         zip(committee_indices.iter(), sync_aggregate.sync_committee_bits.iter())
@@ -565,7 +620,11 @@ pub fn process_proposer_slashing<
     {
         let signing_root = compute_signing_root(&mut signed_header.message, domain)?;
         let public_key = &proposer.public_key;
-        if verify_signature(public_key, signing_root.as_ref(), &signed_header.signature).is_err() {
+        if context
+            .verified_signature_cache
+            .verify_signature(public_key, &signing_root, &signed_header.signature)
+            .is_err()
+        {
             return Err(invalid_operation_error(InvalidOperation::ProposerSlashing(
                 InvalidProposerSlashing::InvalidSignature(signed_header.signature.clone()),
             )))
@@ -612,11 +671,7 @@ pub fn process_attester_slashing<
     }
     is_valid_indexed_attestation(state, attestation_1, context)?;
     is_valid_indexed_attestation(state, attestation_2, context)?;
-    let indices_1: HashSet<ValidatorIndex> =
-        HashSet::from_iter(attestation_1.attesting_indices.iter().cloned());
-    let indices_2 = HashSet::from_iter(attestation_2.attesting_indices.iter().cloned());
-    let mut indices = indices_1.intersection(&indices_2).cloned().collect::<Vec<_>>();
-    indices.sort_unstable();
+    let indices = attester_slashing.slashable_indices();
     let mut slashed_any = false;
     let current_epoch = get_current_epoch(state, context);
     for &index in &indices {
@@ -633,6 +688,207 @@ pub fn process_attester_slashing<
         Ok(())
     }
 }
+pub fn validate_attestation_without_signature<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    attestation: &Attestation<MAX_VALIDATORS_PER_COMMITTEE>,
+    committees: &mut HashMap<(Slot, CommitteeIndex), Vec<ValidatorIndex>>,
+    target_roots: &mut HashMap<Epoch, Option<Root>>,
+    context: &Context,
+) -> Result<(IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>, Root)> {
+    let previous_epoch = get_previous_epoch(state, context);
+    let current_epoch = get_current_epoch(state, context);
+    let data = &attestation.data;
+    let is_current = data.target.epoch == current_epoch;
+    if !is_current && data.target.epoch != previous_epoch {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidTargetEpoch {
+                target: data.target.epoch,
+                current: current_epoch,
+            },
+        )))
+    }
+    let attestation_epoch = compute_epoch_at_slot(data.slot, context);
+    if data.target.epoch != attestation_epoch {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidSlot {
+                slot: data.slot,
+                epoch: attestation_epoch,
+                target: data.target.epoch,
+            },
+        )))
+    }
+    let expected_source = if is_current {
+        &state.current_justified_checkpoint
+    } else {
+        &state.previous_justified_checkpoint
+    };
+    if data.source != *expected_source {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidSource {
+                expected: expected_source.clone(),
+                source_checkpoint: data.source.clone(),
+                current: current_epoch,
+            },
+        )))
+    }
+    let target_root = match target_roots.entry(data.target.epoch) {
+        Entry::Occupied(entry) => *entry.get(),
+        Entry::Vacant(entry) => {
+            let start_slot = compute_start_slot_at_epoch(data.target.epoch, context);
+            let root = if start_slot < state.slot {
+                Some(*get_block_root(state, data.target.epoch, context)?)
+            } else {
+                None
+            };
+            *entry.insert(root)
+        }
+    };
+    if let Some(expected) = target_root {
+        if data.target.root != expected {
+            return Err(invalid_operation_error(InvalidOperation::Attestation(
+                InvalidAttestation::InvalidTargetRoot { expected, target_root: data.target.root },
+            )))
+        }
+    }
+    let committee_count = get_committee_count_per_slot(state, data.target.epoch, context);
+    if data.index >= committee_count {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidIndex { index: data.index, upper_bound: committee_count },
+        )))
+    }
+    let committee = match committees.entry((data.slot, data.index)) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            entry.insert(get_beacon_committee(state, data.slot, data.index, context)?)
+        }
+    };
+    if attestation.aggregation_bits.len() != committee.len() {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::Bitfield {
+                expected_length: committee.len(),
+                length: attestation.aggregation_bits.len(),
+            },
+        )))
+    }
+    let mut attesting_indices = committee
+        .iter()
+        .enumerate()
+        .filter_map(|(i, index)| attestation.aggregation_bits[i].then_some(*index))
+        .collect::<Vec<_>>();
+    if attesting_indices.is_empty() {
+        return Err(invalid_operation_error(InvalidOperation::IndexedAttestation(
+            InvalidIndexedAttestation::AttestingIndicesEmpty,
+        )))
+    }
+    attesting_indices.sort_unstable();
+    let indexed_attestation = IndexedAttestation {
+        attesting_indices: attesting_indices.try_into().expect("at most committee size"),
+        data: data.clone(),
+        signature: attestation.signature.clone(),
+    };
+    let domain = get_domain(state, DomainType::BeaconAttester, Some(data.target.epoch), context)?;
+    let signing_root = compute_signing_root(&mut data.clone(), domain)?;
+    Ok((indexed_attestation, signing_root))
+}
+pub fn validate_attestations<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    attestations: &[Attestation<MAX_VALIDATORS_PER_COMMITTEE>],
+    context: &Context,
+) -> Vec<Result<ValidAttestation<MAX_VALIDATORS_PER_COMMITTEE>>> {
+    let mut committees = HashMap::new();
+    let mut target_roots = HashMap::new();
+    let results = attestations
+        .iter()
+        .map(|attestation| {
+            validate_attestation_without_signature(
+                state,
+                attestation,
+                &mut committees,
+                &mut target_roots,
+                context,
+            )
+        })
+        .collect::<Vec<_>>();
+    let sets = results
+        .iter()
+        .filter_map(|result| result.as_ref().ok())
+        .map(|(indexed_attestation, signing_root)| crate::crypto::SignatureSet {
+            public_keys: indexed_attestation
+                .attesting_indices
+                .iter()
+                .map(|&index| &state.validators[index].public_key)
+                .collect(),
+            message: signing_root.as_ref(),
+            signature: &indexed_attestation.signature,
+        })
+        .collect::<Vec<_>>();
+    let all_valid = crate::crypto::verify_signature_sets(&sets).is_ok();
+    drop(sets);
+    results
+        .into_iter()
+        .zip(attestations)
+        .map(|(result, attestation)| {
+            let (indexed_attestation, signing_root) = result?;
+            if !all_valid {
+                let public_keys = indexed_attestation
+                    .attesting_indices
+                    .iter()
+                    .map(|&index| &state.validators[index].public_key)
+                    .collect::<Vec<_>>();
+                fast_aggregate_verify(
+                    &public_keys,
+                    signing_root.as_ref(),
+                    &indexed_attestation.signature,
+                )?;
+            }
+            Ok(ValidAttestation { attestation: attestation.clone(), indexed_attestation })
+        })
+        .collect()
+}
 pub fn get_validator_from_deposit(deposit: &Deposit, context: &Context) -> Validator {
     let amount = deposit.data.amount;
     let effective_balance = Gwei::min(
@@ -802,7 +1058,11 @@ pub fn process_randao<
     let proposer = &state.validators[proposer_index];
     let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
     let signing_root = compute_signing_root(&mut epoch, domain)?;
-    if verify_signature(&proposer.public_key, signing_root.as_ref(), &body.randao_reveal).is_err() {
+    if context
+        .verified_signature_cache
+        .verify_signature(&proposer.public_key, &signing_root, &body.randao_reveal)
+        .is_err()
+    {
         return Err(invalid_operation_error(InvalidOperation::Randao(body.randao_reveal.clone())))
     }
     let mix = xor(get_randao_mix(state, epoch), &hash(body.randao_reveal.as_ref()));
@@ -933,9 +1193,10 @@ pub fn process_epoch<
 ) -> Result<()> {
     process_justification_and_finalization(state, context)?;
     process_inactivity_updates(state, context)?;
-    process_rewards_and_penalties(state, context)?;
+    let mut epoch_context = get_epoch_processing_context(state, context);
+    process_rewards_and_penalties_with_context(state, &mut epoch_context, context)?;
     process_registry_updates(state, context);
-    process_slashings(state, context)?;
+    process_slashings_with_context(state, &epoch_context, context)?;
     process_eth1_data_reset(state, context);
     process_effective_balance_updates(state, context);
     process_slashings_reset(state, context);
@@ -945,7 +1206,7 @@ pub fn process_epoch<
     process_sync_committee_updates(state, context)?;
     Ok(())
 }
-pub fn process_slashings<
+pub fn get_base_reward<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -957,7 +1218,7 @@ pub fn process_slashings<
     const BYTES_PER_LOGS_BLOOM: usize,
     const MAX_EXTRA_DATA_BYTES: usize,
 >(
-    state: &mut BeaconState<
+    state: &BeaconState<
         SLOTS_PER_HISTORICAL_ROOT,
         HISTORICAL_ROOTS_LIMIT,
         ETH1_DATA_VOTES_BOUND,
@@ -969,29 +1230,14 @@ pub fn process_slashings<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
+    index: ValidatorIndex,
     context: &Context,
-) -> Result<()> {
-    let epoch = get_current_epoch(state, context);
-    let total_balance = get_total_active_balance(state, context)?;
-    let adjusted_total_slashing_balance = Gwei::min(
-        state.slashings.iter().sum::<Gwei>() * context.proportional_slashing_multiplier_bellatrix,
-        total_balance,
-    );
-    for i in 0..state.validators.len() {
-        let validator = &state.validators[i];
-        if validator.slashed &&
-            (epoch + context.epochs_per_slashings_vector / 2) == validator.withdrawable_epoch
-        {
-            let increment = context.effective_balance_increment;
-            let penalty_numerator =
-                validator.effective_balance / increment * adjusted_total_slashing_balance;
-            let penalty = penalty_numerator / total_balance * increment;
-            decrease_balance(state, i, penalty);
-        }
-    }
-    Ok(())
+) -> Result<Gwei> {
+    let increments =
+        state.validators[index].effective_balance / context.effective_balance_increment;
+    Ok(increments * get_base_reward_per_increment(state, context)?)
 }
-pub fn get_base_reward<
+pub fn process_justification_and_finalization<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1003,7 +1249,7 @@ pub fn get_base_reward<
     const BYTES_PER_LOGS_BLOOM: usize,
     const MAX_EXTRA_DATA_BYTES: usize,
 >(
-    state: &BeaconState<
+    state: &mut BeaconState<
         SLOTS_PER_HISTORICAL_ROOT,
         HISTORICAL_ROOTS_LIMIT,
         ETH1_DATA_VOTES_BOUND,
@@ -1015,14 +1261,23 @@ pub fn get_base_reward<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
-    index: ValidatorIndex,
     context: &Context,
-) -> Result<Gwei> {
-    let increments =
-        state.validators[index].effective_balance / context.effective_balance_increment;
-    Ok(increments * get_base_reward_per_increment(state, context)?)
+) -> Result<()> {
+    let current_epoch = get_current_epoch(state, context);
+    if current_epoch <= GENESIS_EPOCH + 1 {
+        return Ok(())
+    }
+    let (total_active_balance, previous_target_balance, current_target_balance) =
+        get_justification_balances(state, context)?;
+    weigh_justification_and_finalization(
+        state,
+        total_active_balance,
+        previous_target_balance,
+        current_target_balance,
+        context,
+    )
 }
-pub fn process_justification_and_finalization<
+pub fn get_justification_balances<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1034,7 +1289,7 @@ pub fn process_justification_and_finalization<
     const BYTES_PER_LOGS_BLOOM: usize,
     const MAX_EXTRA_DATA_BYTES: usize,
 >(
-    state: &mut BeaconState<
+    state: &BeaconState<
         SLOTS_PER_HISTORICAL_ROOT,
         HISTORICAL_ROOTS_LIMIT,
         ETH1_DATA_VOTES_BOUND,
@@ -1047,33 +1302,13 @@ pub fn process_justification_and_finalization<
         MAX_EXTRA_DATA_BYTES,
     >,
     context: &Context,
-) -> Result<()> {
-    let current_epoch = get_current_epoch(state, context);
-    if current_epoch <= GENESIS_EPOCH + 1 {
-        return Ok(())
-    }
-    let previous_indices = get_unslashed_participating_indices(
-        state,
-        TIMELY_TARGET_FLAG_INDEX,
-        get_previous_epoch(state, context),
-        context,
-    )?;
-    let current_indices = get_unslashed_participating_indices(
-        state,
-        TIMELY_TARGET_FLAG_INDEX,
-        current_epoch,
-        context,
-    )?;
-    let total_active_balance = get_total_active_balance(state, context)?;
-    let previous_target_balance = get_total_balance(state, &previous_indices, context)?;
-    let current_target_balance = get_total_balance(state, &current_indices, context)?;
-    weigh_justification_and_finalization(
-        state,
-        total_active_balance,
-        previous_target_balance,
-        current_target_balance,
-        context,
-    )
+) -> Result<(Gwei, Gwei, Gwei)> {
+    let participation = get_participation_cache(state, context)?;
+    let previous_target_balance = participation
+        .unslashed_participating_balance(TIMELY_TARGET_FLAG_INDEX, participation.previous_epoch)?;
+    let current_target_balance = participation
+        .unslashed_participating_balance(TIMELY_TARGET_FLAG_INDEX, participation.current_epoch)?;
+    Ok((participation.total_active_balance, previous_target_balance, current_target_balance))
 }
 pub fn process_inactivity_updates<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -1153,23 +1388,8 @@ pub fn process_rewards_and_penalties<
     >,
     context: &Context,
 ) -> Result<()> {
-    let current_epoch = get_current_epoch(state, context);
-    if current_epoch == GENESIS_EPOCH {
-        return Ok(())
-    }
-    let mut deltas = Vec::new();
-    for flag_index in 0..PARTICIPATION_FLAG_WEIGHTS.len() {
-        let flag_index_delta = get_flag_index_deltas(state, flag_index, context)?;
-        deltas.push(flag_index_delta);
-    }
-    deltas.push(get_inactivity_penalty_deltas(state, context)?);
-    for (rewards, penalties) in deltas {
-        for index in 0..state.validators.len() {
-            increase_balance(state, index, rewards[index]);
-            decrease_balance(state, index, penalties[index]);
-        }
-    }
-    Ok(())
+    let mut epoch_context = get_epoch_processing_context(state, context);
+    process_rewards_and_penalties_with_context(state, &mut epoch_context, context)
 }
 pub fn process_participation_flag_updates<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -1203,7 +1423,7 @@ pub fn process_participation_flag_updates<
         rotate_participation.try_into().expect("should convert from Vec to List");
     Ok(())
 }
-pub fn process_sync_committee_updates<
+pub fn process_slashings<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1229,16 +1449,10 @@ pub fn process_sync_committee_updates<
     >,
     context: &Context,
 ) -> Result<()> {
-    let next_epoch = get_current_epoch(state, context) + 1;
-    if next_epoch % context.epochs_per_sync_committee_period == 0 {
-        let next_sync_committee = get_next_sync_committee(state, context)?;
-        let current_sync_committee =
-            mem::replace(&mut state.next_sync_committee, next_sync_committee);
-        state.current_sync_committee = current_sync_committee;
-    }
-    Ok(())
+    let epoch_context = get_epoch_processing_context(state, context);
+    process_slashings_with_context(state, &epoch_context, context)
 }
-pub fn process_eth1_data_reset<
+pub fn process_sync_committee_updates<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1263,13 +1477,88 @@ pub fn process_eth1_data_reset<
         MAX_EXTRA_DATA_BYTES,
     >,
     context: &Context,
-) {
+) -> Result<()> {
     let next_epoch = get_current_epoch(state, context) + 1;
-    if next_epoch % context.epochs_per_eth1_voting_period == 0 {
-        state.eth1_data_votes.clear();
+    if next_epoch % context.epochs_per_sync_committee_period == 0 {
+        let next_sync_committee = get_next_sync_committee(state, context)?;
+        let current_sync_committee =
+            mem::replace(&mut state.next_sync_committee, next_sync_committee);
+        state.current_sync_committee = current_sync_committee;
     }
+    Ok(())
 }
-pub fn process_effective_balance_updates<
+pub fn get_unrealized_checkpoints<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<(Checkpoint, Checkpoint)> {
+    if get_current_epoch(state, context) <= GENESIS_EPOCH + 1 {
+        return Ok((state.current_justified_checkpoint.clone(), state.finalized_checkpoint.clone()))
+    }
+    let (total_active_balance, previous_target_balance, current_target_balance) =
+        get_justification_balances(state, context)?;
+    let ffg = compute_justification_and_finalization(
+        state,
+        total_active_balance,
+        previous_target_balance,
+        current_target_balance,
+        context,
+    )?;
+    Ok((ffg.current_justified_checkpoint, ffg.finalized_checkpoint))
+}
+pub fn process_eth1_data_reset<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) {
+    let next_epoch = get_current_epoch(state, context) + 1;
+    if next_epoch % context.epochs_per_eth1_voting_period == 0 {
+        state.eth1_data_votes.clear();
+    }
+}
+pub fn process_effective_balance_updates<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1310,6 +1599,9 @@ pub fn process_effective_balance_updates<
             );
         }
     }
+    if let Some((epoch, root)) = get_total_active_balance_cache_key(state, context) {
+        context.total_active_balance_cache.invalidate(epoch, root);
+    }
 }
 pub fn process_slashings_reset<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -1438,41 +1730,96 @@ pub fn weigh_justification_and_finalization<
     current_epoch_target_balance: Gwei,
     context: &Context,
 ) -> Result<()> {
+    let ffg = compute_justification_and_finalization(
+        state,
+        total_active_balance,
+        previous_epoch_target_balance,
+        current_epoch_target_balance,
+        context,
+    )?;
+    state.justification_bits = ffg.justification_bits;
+    state.previous_justified_checkpoint = ffg.previous_justified_checkpoint;
+    state.current_justified_checkpoint = ffg.current_justified_checkpoint;
+    state.finalized_checkpoint = ffg.finalized_checkpoint;
+    Ok(())
+}
+pub fn compute_justification_and_finalization<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    total_active_balance: Gwei,
+    previous_epoch_target_balance: Gwei,
+    current_epoch_target_balance: Gwei,
+    context: &Context,
+) -> Result<JustificationAndFinalization> {
     let previous_epoch = get_previous_epoch(state, context);
     let current_epoch = get_current_epoch(state, context);
-    let old_previous_justified_checkpoint = state.previous_justified_checkpoint.clone();
-    let old_current_justified_checkpoint = state.current_justified_checkpoint.clone();
-    state.previous_justified_checkpoint = state.current_justified_checkpoint.clone();
-    state.justification_bits.copy_within(..JUSTIFICATION_BITS_LENGTH - 1, 1);
-    state.justification_bits.set(0, false);
-    if previous_epoch_target_balance * 3 >= total_active_balance * 2 {
-        state.current_justified_checkpoint = Checkpoint {
+    let old_previous_justified_checkpoint = &state.previous_justified_checkpoint;
+    let old_current_justified_checkpoint = &state.current_justified_checkpoint;
+    let mut justification_bits = JustificationBits::from(state.justification_bits.clone());
+    let mut ffg = JustificationAndFinalization {
+        justification_bits: Default::default(),
+        previous_justified_checkpoint: state.current_justified_checkpoint.clone(),
+        current_justified_checkpoint: state.current_justified_checkpoint.clone(),
+        finalized_checkpoint: state.finalized_checkpoint.clone(),
+    };
+    let previous_epoch_justified = previous_epoch_target_balance * 3 >= total_active_balance * 2;
+    let current_epoch_justified = current_epoch_target_balance * 3 >= total_active_balance * 2;
+    justification_bits.shift_and_set(previous_epoch_justified, current_epoch_justified);
+    if previous_epoch_justified {
+        ffg.current_justified_checkpoint = Checkpoint {
             epoch: previous_epoch,
             root: *get_block_root(state, previous_epoch, context)?,
         };
-        state.justification_bits.set(1, true);
     }
-    if current_epoch_target_balance * 3 >= total_active_balance * 2 {
-        state.current_justified_checkpoint = Checkpoint {
+    if current_epoch_justified {
+        ffg.current_justified_checkpoint = Checkpoint {
             epoch: current_epoch,
             root: *get_block_root(state, current_epoch, context)?,
         };
-        state.justification_bits.set(0, true);
     }
-    let bits = &state.justification_bits;
-    if bits[1..4].all() && old_previous_justified_checkpoint.epoch + 3 == current_epoch {
-        state.finalized_checkpoint = old_previous_justified_checkpoint.clone();
+    if (1..4).all(|n| justification_bits.is_justified(n)) &&
+        old_previous_justified_checkpoint.epoch + 3 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_previous_justified_checkpoint.clone();
     }
-    if bits[1..3].all() && old_previous_justified_checkpoint.epoch + 2 == current_epoch {
-        state.finalized_checkpoint = old_previous_justified_checkpoint;
+    if (1..3).all(|n| justification_bits.is_justified(n)) &&
+        old_previous_justified_checkpoint.epoch + 2 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_previous_justified_checkpoint.clone();
     }
-    if bits[0..3].all() && old_current_justified_checkpoint.epoch + 2 == current_epoch {
-        state.finalized_checkpoint = old_current_justified_checkpoint.clone();
+    if (0..3).all(|n| justification_bits.is_justified(n)) &&
+        old_current_justified_checkpoint.epoch + 2 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_current_justified_checkpoint.clone();
     }
-    if bits[0..2].all() && old_current_justified_checkpoint.epoch + 1 == current_epoch {
-        state.finalized_checkpoint = old_current_justified_checkpoint;
+    if (0..2).all(|n| justification_bits.is_justified(n)) &&
+        old_current_justified_checkpoint.epoch + 1 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_current_justified_checkpoint.clone();
     }
-    Ok(())
+    ffg.justification_bits = justification_bits.into();
+    Ok(ffg)
 }
 pub fn get_proposer_reward<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -1559,6 +1906,64 @@ pub fn is_in_inactivity_leak<
 ) -> bool {
     get_finality_delay(state, context) > context.min_epochs_to_inactivity_penalty
 }
+pub fn process_epoch_with_summary<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<EpochTransitionSummary> {
+    let epoch = get_current_epoch(state, context);
+    let total_slashings = state.slashings.iter().sum();
+    let balances = state.balances.to_vec();
+    let registry_epochs = state
+        .validators
+        .iter()
+        .map(|validator| (validator.activation_epoch, validator.exit_epoch))
+        .collect::<Vec<_>>();
+    process_epoch(state, context)?;
+    let mut summary = EpochTransitionSummary {
+        epoch,
+        total_slashings,
+        finalized_checkpoint: state.finalized_checkpoint.clone(),
+        ..Default::default()
+    };
+    for (index, (&previous, &current)) in balances.iter().zip(state.balances.iter()).enumerate() {
+        if current > previous {
+            summary.total_rewards += current - previous;
+        } else {
+            summary.total_penalties += previous - current;
+        }
+        let (activation_epoch, exit_epoch) = registry_epochs[index];
+        let validator = &state.validators[index];
+        if activation_epoch == FAR_FUTURE_EPOCH && validator.activation_epoch != FAR_FUTURE_EPOCH {
+            summary.activations.push(index);
+        }
+        if exit_epoch == FAR_FUTURE_EPOCH && validator.exit_epoch != FAR_FUTURE_EPOCH {
+            summary.exits.push(index);
+        }
+    }
+    Ok(summary)
+}
 pub fn is_valid_genesis_state<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -1662,7 +2067,8 @@ pub fn is_partially_withdrawable_validator(
     let has_excess_balance = balance > context.max_effective_balance;
     has_eth1_withdrawal_credential(validator) && has_max_effective_balance && has_excess_balance
 }
-pub fn get_inactivity_penalty_deltas<
+pub fn collect_bls_to_execution_change_signature_sets<
+    'a,
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1686,30 +2092,30 @@ pub fn get_inactivity_penalty_deltas<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
+    bls_to_execution_changes: &'a [SignedBlsToExecutionChange],
+    batch: &mut crate::signing::SignatureBatch<'a>,
     context: &Context,
-) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
-    let validator_count = state.validators.len();
-    let rewards = vec![0; validator_count];
-    let mut penalties = vec![0; validator_count];
-    let previous_epoch = get_previous_epoch(state, context);
-    let matching_target_indices = get_unslashed_participating_indices(
-        state,
-        TIMELY_TARGET_FLAG_INDEX,
-        previous_epoch,
+) -> Result<()> {
+    let domain = compute_domain(
+        DomainType::BlsToExecutionChange,
+        None,
+        Some(state.genesis_validators_root),
         context,
     )?;
-    for i in get_eligible_validator_indices(state, context) {
-        if !matching_target_indices.contains(&i) {
-            let penalty_numerator =
-                state.validators[i].effective_balance * state.inactivity_scores[i];
-            let penalty_denominator =
-                context.inactivity_score_bias * context.inactivity_penalty_quotient_bellatrix;
-            penalties[i] += penalty_numerator / penalty_denominator;
-        }
+    for signed_address_change in bls_to_execution_changes {
+        let address_change = &signed_address_change.message;
+        let signing_root = compute_signing_root(&mut address_change.clone(), domain)?;
+        let error = crate::crypto::Error::InvalidSignature.into();
+        batch.push(
+            vec![&address_change.from_bls_public_key],
+            signing_root,
+            &signed_address_change.signature,
+            error,
+        );
     }
-    Ok((rewards, penalties))
+    Ok(())
 }
-pub fn slash_validator<
+pub fn verify_block_signatures<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1720,8 +2126,18 @@ pub fn slash_validator<
     const SYNC_COMMITTEE_SIZE: usize,
     const BYTES_PER_LOGS_BLOOM: usize,
     const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
 >(
-    state: &mut BeaconState<
+    state: &BeaconState<
         SLOTS_PER_HISTORICAL_ROOT,
         HISTORICAL_ROOTS_LIMIT,
         ETH1_DATA_VOTES_BOUND,
@@ -1733,36 +2149,61 @@ pub fn slash_validator<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
-    slashed_index: ValidatorIndex,
-    whistleblower_index: Option<ValidatorIndex>,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >,
     context: &Context,
 ) -> Result<()> {
-    let epoch = get_current_epoch(state, context);
-    initiate_validator_exit(state, slashed_index, context);
-    state.validators[slashed_index].slashed = true;
-    state.validators[slashed_index].withdrawable_epoch = u64::max(
-        state.validators[slashed_index].withdrawable_epoch,
-        epoch + context.epochs_per_slashings_vector,
-    );
-    let slashings_index = epoch as usize % EPOCHS_PER_SLASHINGS_VECTOR;
-    state.slashings[slashings_index] += state.validators[slashed_index].effective_balance;
-    decrease_balance(
+    let proposer_index = signed_block.message.proposer_index;
+    let proposer = state
+        .validators
+        .get(proposer_index)
+        .ok_or(Error::OutOfBounds { requested: proposer_index, bound: state.validators.len() })?;
+    let domain = get_domain(state, DomainType::BeaconProposer, None, context)?;
+    let signing_root = compute_signing_root(&mut signed_block.message, domain)?;
+    let signed_block = &*signed_block;
+    let body = &signed_block.message.body;
+    let mut batch = crate::signing::SignatureBatch::default();
+    let error = crate::crypto::Error::InvalidSignature.into();
+    batch.push(vec![&proposer.public_key], signing_root, &signed_block.signature, error);
+    let mut epoch = get_current_epoch(state, context);
+    let randao_proposer = &state.validators[get_beacon_proposer_index(state, context)?];
+    let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
+    let signing_root = compute_signing_root(&mut epoch, domain)?;
+    let error = invalid_operation_error(InvalidOperation::Randao(body.randao_reveal.clone()));
+    batch.push(vec![&randao_proposer.public_key], signing_root, &body.randao_reveal, error);
+    collect_operation_signature_sets(
         state,
-        slashed_index,
-        state.validators[slashed_index].effective_balance /
-            context.min_slashing_penalty_quotient_bellatrix,
-    );
-    let proposer_index = get_beacon_proposer_index(state, context)?;
-    let whistleblower_index = whistleblower_index.unwrap_or(proposer_index);
-    let whistleblower_reward =
-        state.validators[slashed_index].effective_balance / context.whistleblower_reward_quotient;
-    let proposer_reward_scaling_factor = PROPOSER_WEIGHT / WEIGHT_DENOMINATOR;
-    let proposer_reward = whistleblower_reward * proposer_reward_scaling_factor;
-    increase_balance(state, proposer_index, proposer_reward);
-    increase_balance(state, whistleblower_index, whistleblower_reward - proposer_reward);
-    Ok(())
+        &body.proposer_slashings,
+        &body.attester_slashings,
+        &body.attestations,
+        &mut batch,
+        context,
+    )?;
+    collect_voluntary_exit_signature_sets(state, &body.voluntary_exits, &mut batch, context)?;
+    collect_sync_aggregate_signature_sets(state, &body.sync_aggregate, &mut batch, context)?;
+    collect_bls_to_execution_change_signature_sets(
+        state,
+        &body.bls_to_execution_changes,
+        &mut batch,
+        context,
+    )?;
+    batch.verify(&context.verified_signature_cache)
 }
-pub fn is_merge_transition_complete<
+pub fn compute_inactivity_penalty_deltas<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1786,10 +2227,33 @@ pub fn is_merge_transition_complete<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
-) -> bool {
-    state.latest_execution_payload_header != ExecutionPayloadHeader::default()
+    participation: &ParticipationCache,
+    scratch: &mut EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    scratch.reset_deltas(state.validators.len());
+    let previous_epoch = participation.previous_epoch;
+    let penalty_deltas =
+        crate::state_transition::map_validator_indices(&scratch.eligible_indices, |i| {
+            if participation.is_unslashed_participating(
+                i,
+                TIMELY_TARGET_FLAG_INDEX,
+                previous_epoch,
+            )? {
+                return Ok(0)
+            }
+            let penalty_numerator =
+                state.validators[i].effective_balance * state.inactivity_scores[i];
+            let penalty_denominator =
+                context.inactivity_score_bias * context.inactivity_penalty_quotient_bellatrix;
+            Ok(penalty_numerator / penalty_denominator)
+        })?;
+    for (&i, penalty) in scratch.eligible_indices.iter().zip(penalty_deltas) {
+        scratch.penalties[i] += penalty;
+    }
+    Ok(())
 }
-pub fn is_merge_transition_block<
+pub fn process_slashings_with_context<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -1800,18 +2264,8 @@ pub fn is_merge_transition_block<
     const SYNC_COMMITTEE_SIZE: usize,
     const BYTES_PER_LOGS_BLOOM: usize,
     const MAX_EXTRA_DATA_BYTES: usize,
-    const MAX_PROPOSER_SLASHINGS: usize,
-    const MAX_ATTESTER_SLASHINGS: usize,
-    const MAX_ATTESTATIONS: usize,
-    const MAX_DEPOSITS: usize,
-    const MAX_VOLUNTARY_EXITS: usize,
-    const MAX_BYTES_PER_TRANSACTION: usize,
-    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
-    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
-    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
-    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
 >(
-    state: &BeaconState<
+    state: &mut BeaconState<
         SLOTS_PER_HISTORICAL_ROOT,
         HISTORICAL_ROOTS_LIMIT,
         ETH1_DATA_VOTES_BOUND,
@@ -1823,8 +2277,145 @@ pub fn is_merge_transition_block<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
-    body: &BeaconBlockBody<
-        MAX_PROPOSER_SLASHINGS,
+    epoch_context: &EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    let epoch = get_current_epoch(state, context);
+    let total_balance = get_total_active_balance(state, context)?;
+    let adjusted_total_slashing_balance = Gwei::min(
+        state.slashings.iter().sum::<Gwei>() * context.proportional_slashing_multiplier_bellatrix,
+        total_balance,
+    );
+    for i in 0..state.validators.len() {
+        let validator = &state.validators[i];
+        if validator.slashed &&
+            (epoch + context.epochs_per_slashings_vector / 2) == validator.withdrawable_epoch
+        {
+            let increment = context.effective_balance_increment;
+            let penalty_numerator =
+                epoch_context.effective_balance_increments[i] * adjusted_total_slashing_balance;
+            let penalty = penalty_numerator / total_balance * increment;
+            decrease_balance(state, i, penalty);
+        }
+    }
+    Ok(())
+}
+pub fn slash_validator<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    slashed_index: ValidatorIndex,
+    whistleblower_index: Option<ValidatorIndex>,
+    context: &Context,
+) -> Result<()> {
+    let epoch = get_current_epoch(state, context);
+    initiate_validator_exit(state, slashed_index, context);
+    state.validators[slashed_index].slashed = true;
+    state.validators[slashed_index].withdrawable_epoch = u64::max(
+        state.validators[slashed_index].withdrawable_epoch,
+        epoch + context.epochs_per_slashings_vector,
+    );
+    let slashings_index = epoch as usize % EPOCHS_PER_SLASHINGS_VECTOR;
+    state.slashings[slashings_index] += state.validators[slashed_index].effective_balance;
+    decrease_balance(
+        state,
+        slashed_index,
+        state.validators[slashed_index].effective_balance /
+            context.min_slashing_penalty_quotient_bellatrix,
+    );
+    let proposer_index = get_beacon_proposer_index(state, context)?;
+    let whistleblower_index = whistleblower_index.unwrap_or(proposer_index);
+    let whistleblower_reward =
+        state.validators[slashed_index].effective_balance / context.whistleblower_reward_quotient;
+    let proposer_reward_scaling_factor = PROPOSER_WEIGHT / WEIGHT_DENOMINATOR;
+    let proposer_reward = whistleblower_reward * proposer_reward_scaling_factor;
+    increase_balance(state, proposer_index, proposer_reward);
+    increase_balance(state, whistleblower_index, whistleblower_reward - proposer_reward);
+    Ok(())
+}
+pub fn is_merge_transition_complete<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+) -> bool {
+    state.latest_execution_payload_header != ExecutionPayloadHeader::default()
+}
+pub fn is_merge_transition_block<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    body: &BeaconBlockBody<
+        MAX_PROPOSER_SLASHINGS,
         MAX_VALIDATORS_PER_COMMITTEE,
         MAX_ATTESTER_SLASHINGS,
         MAX_ATTESTATIONS,
@@ -1938,6 +2529,16 @@ pub fn has_flag(flags: ParticipationFlags, flag_index: usize) -> bool {
     let flag = 2u8.pow(flag_index as u32);
     flags & flag == flag
 }
+pub fn compute_sync_committee_period(epoch: Epoch, context: &Context) -> u64 {
+    epoch / context.epochs_per_sync_committee_period
+}
+pub fn compute_sync_committee_period_at_slot(slot: Slot, context: &Context) -> u64 {
+    compute_sync_committee_period(compute_epoch_at_slot(slot, context), context)
+}
+pub fn sync_committee_period_boundaries(period: u64, context: &Context) -> std::ops::Range<Epoch> {
+    let start_epoch = period * context.epochs_per_sync_committee_period;
+    start_epoch..start_epoch + context.epochs_per_sync_committee_period
+}
 pub fn get_next_sync_committee_indices<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -2019,11 +2620,19 @@ pub fn get_next_sync_committee<
     context: &Context,
 ) -> Result<SyncCommittee<SYNC_COMMITTEE_SIZE>> {
     let indices = get_next_sync_committee_indices(state, context)?;
+    let mut decompressed_public_keys = Vec::with_capacity(indices.len());
+    for &index in &indices {
+        let public_key = context
+            .decompressed_pubkey_cache
+            .get(&state.validators, index)
+            .ok_or(Error::OutOfBounds { requested: index, bound: state.validators.len() })??;
+        decompressed_public_keys.push(public_key);
+    }
+    let aggregate_public_key = eth_aggregate_decompressed_public_keys(&decompressed_public_keys)?;
     let public_keys =
         indices.into_iter().map(|i| state.validators[i].public_key.clone()).collect::<Vec<_>>();
     let public_keys = Vector::<BlsPublicKey, SYNC_COMMITTEE_SIZE>::try_from(public_keys)
         .map_err(|(_, err)| err)?;
-    let aggregate_public_key = eth_aggregate_public_keys(&public_keys)?;
     Ok(SyncCommittee::<SYNC_COMMITTEE_SIZE> { public_keys, aggregate_public_key })
 }
 pub fn get_base_reward_per_increment<
@@ -2134,32 +2743,396 @@ pub fn get_flag_index_deltas<
     flag_index: usize,
     context: &Context,
 ) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
-    let validator_count = state.validators.len();
-    let mut rewards = vec![0; validator_count];
-    let mut penalties = vec![0; validator_count];
+    let participation = get_participation_cache(state, context)?;
+    get_flag_index_deltas_from_cache(state, flag_index, &participation, context)
+}
+pub fn get_participation_cache<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<ParticipationCache> {
     let previous_epoch = get_previous_epoch(state, context);
-    let unslashed_participating_indices =
-        get_unslashed_participating_indices(state, flag_index, previous_epoch, context)?;
+    let current_epoch = get_current_epoch(state, context);
+    let validator_count = state.validators.len();
+    let mut participation = ParticipationCache {
+        previous_epoch,
+        current_epoch,
+        previous_epoch_flags: vec![0; validator_count],
+        current_epoch_flags: vec![0; validator_count],
+        ..Default::default()
+    };
+    let mut total_active_balance = 0;
+    for (index, validator) in state.validators.iter().enumerate() {
+        let effective_balance = validator.effective_balance;
+        if is_active_validator(validator, current_epoch) {
+            total_active_balance += effective_balance;
+        }
+        if validator.slashed {
+            continue;
+        }
+        if is_active_validator(validator, previous_epoch) {
+            let flags = state.previous_epoch_participation[index];
+            participation.previous_epoch_flags[index] = flags;
+            for (flag_index, balance) in
+                participation.previous_epoch_flag_balances.iter_mut().enumerate()
+            {
+                if has_flag(flags, flag_index) {
+                    *balance += effective_balance;
+                }
+            }
+        }
+        if is_active_validator(validator, current_epoch) {
+            let flags = state.current_epoch_participation[index];
+            participation.current_epoch_flags[index] = flags;
+            for (flag_index, balance) in
+                participation.current_epoch_flag_balances.iter_mut().enumerate()
+            {
+                if has_flag(flags, flag_index) {
+                    *balance += effective_balance;
+                }
+            }
+        }
+    }
+    participation.total_active_balance =
+        u64::max(context.effective_balance_increment, total_active_balance);
+    for balance in participation
+        .previous_epoch_flag_balances
+        .iter_mut()
+        .chain(participation.current_epoch_flag_balances.iter_mut())
+    {
+        *balance = u64::max(context.effective_balance_increment, *balance);
+    }
+    Ok(participation)
+}
+pub fn get_epoch_processing_context<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> EpochProcessingContext {
+    let mut epoch_context =
+        EpochProcessingContext::new(get_eligible_validator_indices(state, context).collect());
+    epoch_context.effective_balance_increments = state
+        .validators
+        .iter()
+        .map(|validator| validator.effective_balance / context.effective_balance_increment)
+        .collect();
+    epoch_context
+}
+pub fn get_flag_index_deltas_from_cache<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    flag_index: usize,
+    participation: &ParticipationCache,
+    context: &Context,
+) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
+    let mut scratch = get_epoch_processing_context(state, context);
+    compute_flag_index_deltas(state, flag_index, participation, &mut scratch, context)?;
+    Ok((scratch.rewards, scratch.penalties))
+}
+pub fn compute_flag_index_deltas<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    flag_index: usize,
+    participation: &ParticipationCache,
+    scratch: &mut EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    scratch.reset_deltas(state.validators.len());
+    let previous_epoch = participation.previous_epoch;
     let weight = PARTICIPATION_FLAG_WEIGHTS[flag_index];
     let unslashed_participating_balance =
-        get_total_balance(state, &unslashed_participating_indices, context)?;
+        participation.unslashed_participating_balance(flag_index, previous_epoch)?;
     let unslashed_participating_increments =
         unslashed_participating_balance / context.effective_balance_increment;
     let active_increments =
-        get_total_active_balance(state, context)? / context.effective_balance_increment;
+        participation.total_active_balance / context.effective_balance_increment;
+    let base_reward_per_increment = context.effective_balance_increment *
+        context.base_reward_factor /
+        participation.total_active_balance.integer_sqrt();
     let not_leaking = !is_in_inactivity_leak(state, context);
-    for index in get_eligible_validator_indices(state, context) {
-        let base_reward = get_base_reward(state, index, context)?;
-        if unslashed_participating_indices.contains(&index) {
-            if not_leaking {
-                let reward_numerator = base_reward * weight * unslashed_participating_increments;
-                rewards[index] += reward_numerator / (active_increments * WEIGHT_DENOMINATOR);
+    let deltas =
+        crate::state_transition::map_validator_indices(&scratch.eligible_indices, |index| {
+            let base_reward = scratch.base_reward(index, base_reward_per_increment);
+            if participation.is_unslashed_participating(index, flag_index, previous_epoch)? {
+                if not_leaking {
+                    let reward_numerator =
+                        base_reward * weight * unslashed_participating_increments;
+                    return Ok((reward_numerator / (active_increments * WEIGHT_DENOMINATOR), 0))
+                }
+            } else if flag_index != TIMELY_HEAD_FLAG_INDEX {
+                return Ok((0, base_reward * weight / WEIGHT_DENOMINATOR))
             }
-        } else if flag_index != TIMELY_HEAD_FLAG_INDEX {
-            penalties[index] += base_reward * weight / WEIGHT_DENOMINATOR;
+            Ok((0, 0))
+        })?;
+    for (&index, (reward, penalty)) in scratch.eligible_indices.iter().zip(deltas) {
+        scratch.rewards[index] += reward;
+        scratch.penalties[index] += penalty;
+    }
+    Ok(())
+}
+pub fn get_inactivity_penalty_deltas<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<(Vec<Gwei>, Vec<Gwei>)> {
+    let participation = get_participation_cache(state, context)?;
+    let mut scratch = get_epoch_processing_context(state, context);
+    compute_inactivity_penalty_deltas(state, &participation, &mut scratch, context)?;
+    Ok((scratch.rewards, scratch.penalties))
+}
+pub fn process_rewards_and_penalties_with_context<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    epoch_context: &mut EpochProcessingContext,
+    context: &Context,
+) -> Result<()> {
+    let current_epoch = get_current_epoch(state, context);
+    if current_epoch == GENESIS_EPOCH {
+        return Ok(())
+    }
+    let participation = get_participation_cache(state, context)?;
+    for flag_index in 0..=PARTICIPATION_FLAG_WEIGHTS.len() {
+        if flag_index < PARTICIPATION_FLAG_WEIGHTS.len() {
+            compute_flag_index_deltas(state, flag_index, &participation, epoch_context, context)?;
+        } else {
+            compute_inactivity_penalty_deltas(state, &participation, epoch_context, context)?;
         }
+        let (rewards, penalties) = (&epoch_context.rewards, &epoch_context.penalties);
+        crate::state_transition::update_balances(&mut state.balances, |index, balance| {
+            (balance + rewards[index]).saturating_sub(penalties[index])
+        });
+    }
+    Ok(())
+}
+pub fn epoch_rewards<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<RewardsReport> {
+    let mut rewards = (0..state.validators.len())
+        .map(|validator_index| ValidatorRewards { validator_index, ..Default::default() })
+        .collect::<Vec<_>>();
+    let report_epoch = get_previous_epoch(state, context);
+    if get_current_epoch(state, context) == GENESIS_EPOCH {
+        return Ok(RewardsReport { epoch: report_epoch, rewards })
+    }
+    let net = |reward: Gwei, penalty: Gwei| reward as i64 - penalty as i64;
+    let (source_rewards, source_penalties) =
+        get_flag_index_deltas(state, TIMELY_SOURCE_FLAG_INDEX, context)?;
+    let (target_rewards, target_penalties) =
+        get_flag_index_deltas(state, TIMELY_TARGET_FLAG_INDEX, context)?;
+    let (head_rewards, head_penalties) =
+        get_flag_index_deltas(state, TIMELY_HEAD_FLAG_INDEX, context)?;
+    let (inactivity_rewards, inactivity_penalties) = get_inactivity_penalty_deltas(state, context)?;
+    for (index, entry) in rewards.iter_mut().enumerate() {
+        entry.source = net(source_rewards[index], source_penalties[index]);
+        entry.target = net(target_rewards[index], target_penalties[index]);
+        entry.head = net(head_rewards[index], head_penalties[index]);
+        entry.inactivity = net(inactivity_rewards[index], inactivity_penalties[index]);
+    }
+    Ok(RewardsReport { epoch: report_epoch, rewards })
+}
+pub fn collect_sync_aggregate_signature_sets<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &'a BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    sync_aggregate: &'a SyncAggregate<SYNC_COMMITTEE_SIZE>,
+    batch: &mut crate::signing::SignatureBatch<'a>,
+    context: &Context,
+) -> Result<()> {
+    let participant_public_keys = state
+        .current_sync_committee
+        .public_keys
+        .iter()
+        .zip(sync_aggregate.sync_committee_bits.iter())
+        .filter_map(|(public_key, bit)| if *bit { Some(public_key) } else { None })
+        .collect::<Vec<_>>();
+    let signature = &sync_aggregate.sync_committee_signature;
+    if participant_public_keys.is_empty() && signature.is_infinity() {
+        return Ok(())
     }
-    Ok((rewards, penalties))
+    let previous_slot = u64::max(state.slot, 1) - 1;
+    let domain = get_domain(
+        state,
+        DomainType::SyncCommittee,
+        Some(compute_epoch_at_slot(previous_slot, context)),
+        context,
+    )?;
+    let mut root_at_slot = *get_block_root_at_slot(state, previous_slot)?;
+    let signing_root = compute_signing_root(&mut root_at_slot, domain)?;
+    let error = invalid_operation_error(InvalidOperation::SyncAggregate(
+        InvalidSyncAggregate::InvalidSignature { signature: signature.clone(), root: signing_root },
+    ));
+    batch.push(participant_public_keys, signing_root, signature, error);
+    Ok(())
 }
 pub fn is_active_validator(validator: &Validator, epoch: Epoch) -> bool {
     validator.activation_epoch <= epoch && epoch < validator.exit_epoch
@@ -2259,14 +3232,14 @@ pub fn is_valid_indexed_attestation<
             InvalidIndexedAttestation::DuplicateIndices(Vec::from_iter(duplicates)),
         )))
     }
-    let mut public_keys = vec![];
-    for &index in &attesting_indices[..] {
-        let public_key = state.validators.get(index).map(|v| &v.public_key).ok_or_else(|| {
+    let mut signers = Vec::with_capacity(attesting_indices.len());
+    for &index in attesting_indices.iter() {
+        let validator = state.validators.get(index).ok_or_else(|| {
             invalid_operation_error(InvalidOperation::IndexedAttestation(
                 InvalidIndexedAttestation::InvalidIndex(index),
             ))
         })?;
-        public_keys.push(public_key);
+        signers.push(&validator.public_key);
     }
     let domain = get_domain(
         state,
@@ -2275,8 +3248,26 @@ pub fn is_valid_indexed_attestation<
         context,
     )?;
     let signing_root = compute_signing_root(&mut indexed_attestation.data, domain)?;
-    fast_aggregate_verify(&public_keys, signing_root.as_ref(), &indexed_attestation.signature)
-        .map_err(Into::into)
+    if context.verified_signature_cache.contains(
+        &signers,
+        &signing_root,
+        &indexed_attestation.signature,
+    ) {
+        return Ok(())
+    }
+    let public_keys = attesting_indices
+        .iter()
+        .map(|&index| {
+            context.decompressed_pubkey_cache.get(&state.validators, index).expect("index is valid")
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let public_keys = public_keys.iter().collect::<Vec<_>>();
+    crate::crypto::fast_aggregate_verify_decompressed(
+        &public_keys,
+        signing_root.as_ref(),
+        &indexed_attestation.signature,
+    )
+    .map_err(Into::into)
 }
 pub fn verify_block_signature<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -2340,6 +3331,99 @@ pub fn verify_block_signature<
     let public_key = &proposer.public_key;
     verify_signature(public_key, signing_root.as_ref(), &signed_block.signature).map_err(Into::into)
 }
+pub fn collect_operation_signature_sets<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &'a BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    proposer_slashings: &'a [ProposerSlashing],
+    attester_slashings: &'a [AttesterSlashing<MAX_VALIDATORS_PER_COMMITTEE>],
+    attestations: &'a [Attestation<MAX_VALIDATORS_PER_COMMITTEE>],
+    batch: &mut crate::signing::SignatureBatch<'a>,
+    context: &Context,
+) -> Result<()> {
+    for proposer_slashing in proposer_slashings {
+        let header_1 = &proposer_slashing.signed_header_1.message;
+        let public_key =
+            state.validators.get(header_1.proposer_index).map(|v| &v.public_key).ok_or_else(
+                || {
+                    invalid_operation_error(InvalidOperation::ProposerSlashing(
+                        InvalidProposerSlashing::InvalidIndex(header_1.proposer_index),
+                    ))
+                },
+            )?;
+        let epoch = compute_epoch_at_slot(header_1.slot, context);
+        let domain = get_domain(state, DomainType::BeaconProposer, Some(epoch), context)?;
+        for signed_header in
+            [&proposer_slashing.signed_header_1, &proposer_slashing.signed_header_2]
+        {
+            let signing_root = compute_signing_root(&mut signed_header.message.clone(), domain)?;
+            let error = invalid_operation_error(InvalidOperation::ProposerSlashing(
+                InvalidProposerSlashing::InvalidSignature(signed_header.signature.clone()),
+            ));
+            batch.push(vec![public_key], signing_root, &signed_header.signature, error);
+        }
+    }
+    let mut signed_attestations =
+        Vec::with_capacity(2 * attester_slashings.len() + attestations.len());
+    for attester_slashing in attester_slashings {
+        for indexed_attestation in
+            [&attester_slashing.attestation_1, &attester_slashing.attestation_2]
+        {
+            signed_attestations.push((
+                indexed_attestation.attesting_indices.to_vec(),
+                &indexed_attestation.data,
+                &indexed_attestation.signature,
+            ));
+        }
+    }
+    for attestation in attestations {
+        let indexed_attestation = get_indexed_attestation(state, attestation, context)?;
+        signed_attestations.push((
+            indexed_attestation.attesting_indices.to_vec(),
+            &attestation.data,
+            &attestation.signature,
+        ));
+    }
+    for (attesting_indices, data, signature) in signed_attestations {
+        let public_keys = attesting_indices
+            .into_iter()
+            .map(|index| {
+                state.validators.get(index).map(|v| &v.public_key).ok_or_else(|| {
+                    invalid_operation_error(InvalidOperation::IndexedAttestation(
+                        InvalidIndexedAttestation::InvalidIndex(index),
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let domain =
+            get_domain(state, DomainType::BeaconAttester, Some(data.target.epoch), context)?;
+        let signing_root = compute_signing_root(&mut data.clone(), domain)?;
+        let error = crate::crypto::Error::InvalidSignature.into();
+        batch.push(public_keys, signing_root, signature, error);
+    }
+    Ok(())
+}
 pub fn get_domain<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -2433,6 +3517,86 @@ pub fn compute_shuffled_index(
     }
     Ok(index)
 }
+pub fn compute_shuffling_pivots(
+    seed: &Bytes32,
+    index_count: usize,
+    context: &Context,
+) -> Vec<usize> {
+    let mut pivot_input = [0u8; 33];
+    pivot_input[..32].copy_from_slice(seed.as_ref());
+    (0..context.shuffle_round_count)
+        .map(|current_round| {
+            pivot_input[32] = current_round as u8;
+            let pivot_bytes: [u8; 8] = hash(pivot_input).as_ref()[..8].try_into().unwrap();
+            (u64::from_le_bytes(pivot_bytes) as usize) % index_count
+        })
+        .collect()
+}
+pub fn compute_shuffled_index_with_pivots(
+    mut index: usize,
+    index_count: usize,
+    seed: &Bytes32,
+    pivots: &[usize],
+) -> Result<usize> {
+    if index >= index_count {
+        return Err(Error::InvalidShufflingIndex { index, total: index_count })
+    }
+    let mut source_input = [0u8; 37];
+    source_input[..32].copy_from_slice(seed.as_ref());
+    for (current_round, &pivot) in pivots.iter().enumerate() {
+        let flip = (pivot + index_count - index) % index_count;
+        let position = cmp::max(index, flip);
+        let position_bytes: [u8; 4] = ((position / 256) as u32).to_le_bytes();
+        source_input[32] = current_round as u8;
+        source_input[33..].copy_from_slice(&position_bytes);
+        let source = hash(source_input);
+        let byte = source.as_ref()[(position % 256) / 8];
+        let bit = (byte >> (position % 8)) % 2;
+        index = if bit != 0 { flip } else { index };
+    }
+    Ok(index)
+}
+pub fn compute_shuffled_indices(
+    indices: &[ValidatorIndex],
+    seed: &Bytes32,
+    context: &Context,
+) -> Vec<ValidatorIndex> {
+    let mut shuffled = indices.to_vec();
+    let index_count = shuffled.len();
+    if index_count <= 1 {
+        return shuffled
+    }
+    let mut pivot_input = [0u8; 33];
+    pivot_input[..32].copy_from_slice(seed.as_ref());
+    let mut source_input = [0u8; 37];
+    source_input[..32].copy_from_slice(seed.as_ref());
+    for current_round in (0..context.shuffle_round_count).rev() {
+        pivot_input[32] = current_round as u8;
+        let pivot_bytes: [u8; 8] = hash(pivot_input).as_ref()[..8].try_into().unwrap();
+        let pivot = (u64::from_le_bytes(pivot_bytes) as usize) % index_count;
+        source_input[32] = current_round as u8;
+        let mut source_block = None;
+        let mut source = Bytes32::default();
+        for index in 0..index_count {
+            let flip = (pivot + index_count - index) % index_count;
+            if flip <= index {
+                continue;
+            }
+            let position = flip;
+            if source_block != Some(position / 256) {
+                let position_bytes: [u8; 4] = ((position / 256) as u32).to_le_bytes();
+                source_input[33..].copy_from_slice(&position_bytes);
+                source = hash(source_input);
+                source_block = Some(position / 256);
+            }
+            let byte = source.as_ref()[(position % 256) / 8];
+            if (byte >> (position % 8)) & 1 != 0 {
+                shuffled.swap(index, flip);
+            }
+        }
+    }
+    shuffled
+}
 pub fn compute_proposer_index<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -2482,6 +3646,60 @@ pub fn compute_proposer_index<
         i += 1;
     }
 }
+pub fn compute_proposer_index_with_pivots<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    indices: &[ValidatorIndex],
+    seed: &Bytes32,
+    pivots: &[usize],
+    context: &Context,
+) -> Result<ValidatorIndex> {
+    if indices.is_empty() {
+        return Err(Error::CollectionCannotBeEmpty)
+    }
+    let max_byte = u8::MAX as u64;
+    let mut i = 0;
+    let total = indices.len();
+    let mut hash_input = [0u8; 40];
+    hash_input[..32].copy_from_slice(seed.as_ref());
+    let mut random_bytes = Bytes32::default();
+    loop {
+        let shuffled_index = compute_shuffled_index_with_pivots(i % total, total, seed, pivots)?;
+        let candidate_index = indices[shuffled_index];
+        if i % 32 == 0 {
+            let i_bytes: [u8; 8] = (i / 32).to_le_bytes();
+            hash_input[32..].copy_from_slice(&i_bytes);
+            random_bytes = hash(hash_input);
+        }
+        let random_byte = random_bytes.as_ref()[i % 32] as u64;
+        let effective_balance = state.validators[candidate_index].effective_balance;
+        if effective_balance * max_byte >= context.max_effective_balance * random_byte {
+            return Ok(candidate_index)
+        }
+        i += 1;
+    }
+}
 pub fn compute_committee(
     indices: &[ValidatorIndex],
     seed: &Bytes32,
@@ -2836,11 +4054,141 @@ pub fn get_beacon_committee<
     let committees_per_slot = get_committee_count_per_slot(state, epoch, context);
     let indices = get_active_validator_indices(state, epoch);
     let seed = get_seed(state, epoch, DomainType::BeaconAttester, context);
-    let index = (slot % context.slots_per_epoch) * committees_per_slot as u64 + index as u64;
-    let count = committees_per_slot as u64 * context.slots_per_epoch;
-    compute_committee(&indices, &seed, index as usize, count as usize, context)
+    let index = (slot % context.slots_per_epoch) as usize * committees_per_slot + index;
+    let count = committees_per_slot * context.slots_per_epoch as usize;
+    let shuffling = context.committee_cache.get_or_compute(epoch, &seed, indices, context)?;
+    let start = (shuffling.len() * index) / count;
+    let end = shuffling.len() * (index + 1) / count;
+    Ok(shuffling[start..end].to_vec())
+}
+pub fn get_committee_assignment<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    epoch: Epoch,
+    validator_index: ValidatorIndex,
+    context: &Context,
+) -> Result<Option<(Vec<ValidatorIndex>, CommitteeIndex, Slot)>> {
+    let next_epoch = get_current_epoch(state, context) + 1;
+    if epoch > next_epoch {
+        return Err(Error::EpochAfterNextEpoch { requested: epoch, next: next_epoch })
+    }
+    let start_slot = compute_start_slot_at_epoch(epoch, context);
+    let committee_count_per_slot = get_committee_count_per_slot(state, epoch, context);
+    for slot in start_slot..start_slot + context.slots_per_epoch {
+        for index in 0..committee_count_per_slot {
+            let committee = get_beacon_committee(state, slot, index, context)?;
+            if committee.contains(&validator_index) {
+                return Ok(Some((committee, index, slot)))
+            }
+        }
+    }
+    Ok(None)
+}
+pub fn get_beacon_proposer_index<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<ValidatorIndex> {
+    let epoch = get_current_epoch(state, context);
+    let mut input = [0u8; 40];
+    input[..32]
+        .copy_from_slice(get_seed(state, epoch, DomainType::BeaconProposer, context).as_ref());
+    input[32..40].copy_from_slice(&state.slot.to_le_bytes());
+    let seed = hash(input);
+    let indices = get_active_validator_indices(state, epoch);
+    compute_proposer_index(state, &indices, &seed, context)
+}
+pub fn get_beacon_proposer_indices<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    epoch: Epoch,
+    context: &Context,
+) -> Result<Vec<ValidatorIndex>> {
+    let current_epoch = get_current_epoch(state, context);
+    if epoch != current_epoch && epoch != current_epoch + 1 {
+        return Err(Error::EpochNotCurrentOrNext { requested: epoch, current: current_epoch })
+    }
+    let epoch_seed = get_seed(state, epoch, DomainType::BeaconProposer, context);
+    let indices = get_active_validator_indices(state, epoch);
+    if indices.is_empty() {
+        return Err(Error::CollectionCannotBeEmpty)
+    }
+    let start_slot = compute_start_slot_at_epoch(epoch, context);
+    let mut input = [0u8; 40];
+    input[..32].copy_from_slice(epoch_seed.as_ref());
+    (start_slot..start_slot + context.slots_per_epoch)
+        .map(|slot| {
+            input[32..40].copy_from_slice(&slot.to_le_bytes());
+            let seed = hash(input);
+            let pivots = compute_shuffling_pivots(&seed, indices.len(), context);
+            compute_proposer_index_with_pivots(state, &indices, &seed, &pivots, context)
+        })
+        .collect()
 }
-pub fn get_beacon_proposer_index<
+pub fn get_total_balance<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -2864,18 +4212,16 @@ pub fn get_beacon_proposer_index<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
+    indices: &HashSet<ValidatorIndex>,
     context: &Context,
-) -> Result<ValidatorIndex> {
-    let epoch = get_current_epoch(state, context);
-    let mut input = [0u8; 40];
-    input[..32]
-        .copy_from_slice(get_seed(state, epoch, DomainType::BeaconProposer, context).as_ref());
-    input[32..40].copy_from_slice(&state.slot.to_le_bytes());
-    let seed = hash(input);
-    let indices = get_active_validator_indices(state, epoch);
-    compute_proposer_index(state, &indices, &seed, context)
+) -> Result<Gwei> {
+    let total_balance = indices
+        .iter()
+        .try_fold(Gwei::default(), |acc, i| acc.checked_add(state.validators[*i].effective_balance))
+        .ok_or(Error::Overflow)?;
+    Ok(u64::max(total_balance, context.effective_balance_increment))
 }
-pub fn get_total_balance<
+pub fn get_total_active_balance<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -2899,16 +4245,22 @@ pub fn get_total_balance<
         BYTES_PER_LOGS_BLOOM,
         MAX_EXTRA_DATA_BYTES,
     >,
-    indices: &HashSet<ValidatorIndex>,
     context: &Context,
 ) -> Result<Gwei> {
-    let total_balance = indices
-        .iter()
-        .try_fold(Gwei::default(), |acc, i| acc.checked_add(state.validators[*i].effective_balance))
-        .ok_or(Error::Overflow)?;
-    Ok(u64::max(total_balance, context.effective_balance_increment))
+    let key = get_total_active_balance_cache_key(state, context);
+    if let Some(total) =
+        key.and_then(|(epoch, root)| context.total_active_balance_cache.get(epoch, &root))
+    {
+        return Ok(total)
+    }
+    let indices = get_active_validator_indices(state, get_current_epoch(state, context));
+    let total = get_total_balance(state, &HashSet::from_iter(indices), context)?;
+    if let Some((epoch, root)) = key {
+        context.total_active_balance_cache.insert(epoch, root, total);
+    }
+    Ok(total)
 }
-pub fn get_total_active_balance<
+pub fn get_total_active_balance_cache_key<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
     const ETH1_DATA_VOTES_BOUND: usize,
@@ -2933,9 +4285,14 @@ pub fn get_total_active_balance<
         MAX_EXTRA_DATA_BYTES,
     >,
     context: &Context,
-) -> Result<Gwei> {
-    let indices = get_active_validator_indices(state, get_current_epoch(state, context));
-    get_total_balance(state, &HashSet::from_iter(indices), context)
+) -> Option<(Epoch, Root)> {
+    let epoch = get_current_epoch(state, context);
+    if epoch == GENESIS_EPOCH {
+        return None
+    }
+    let slot = compute_start_slot_at_epoch(epoch, context) - 1;
+    let root = state.state_roots[(slot % context.slots_per_historical_root) as usize];
+    (root != Root::default()).then_some((epoch, root))
 }
 pub fn get_indexed_attestation<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -3191,14 +4548,48 @@ pub fn process_slots<
     >,
     slot: Slot,
     context: &Context,
+) -> Result<()> {
+    process_slots_with_hook(state, slot, context, |_, _, _| {})
+}
+pub fn process_slots_with_hook<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    F: FnMut(Slot, Root, Root),
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    slot: Slot,
+    context: &Context,
+    mut on_slot: F,
 ) -> Result<()> {
     if state.slot >= slot {
         return Err(Error::TransitionToPreviousSlot { requested: slot, current: state.slot })
     }
     while state.slot < slot {
         process_slot(state, context)?;
+        let root_index = (state.slot % context.slots_per_historical_root) as usize;
+        on_slot(state.slot, state.state_roots[root_index], state.block_roots[root_index]);
         if (state.slot + 1) % context.slots_per_epoch == 0 {
             process_epoch(state, context)?;
+            context.committee_cache.prune((state.slot + 1) / context.slots_per_epoch);
         }
         state.slot += 1;
     }
@@ -3307,7 +4698,7 @@ pub fn state_transition_block_in_slot<
         Validation::Disabled => false,
     };
     if validate_result {
-        verify_block_signature(state, signed_block, context)?;
+        verify_block_signatures(state, signed_block, context)?;
     }
     let block = &mut signed_block.message;
     process_block(state, block, execution_engine, context)?;
@@ -3381,4 +4772,276 @@ pub fn state_transition<
     process_slots(state, signed_block.message.slot, context)?;
     state_transition_block_in_slot(state, signed_block, execution_engine, validation, context)
 }
+pub fn try_state_transition<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+    E: ExecutionEngine<
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+    >,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >,
+    execution_engine: &E,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    let crosses_epoch = compute_epoch_at_slot(state.slot, context) !=
+        compute_epoch_at_slot(signed_block.message.slot, context);
+    let validators = std::mem::take(&mut state.validators);
+    let mut pre_state = state.clone();
+    state.validators = validators;
+    let registry_len = state.validators.len();
+    let saved_validators = if crosses_epoch {
+        pre_state.validators = state.validators.clone();
+        vec![]
+    } else {
+        get_validator_indices_touched_by_operations(&signed_block.message.body)
+            .into_iter()
+            .filter_map(|index| Some((index, state.validators.get(index)?.clone())))
+            .collect::<Vec<_>>()
+    };
+    let result = state_transition(state, signed_block, execution_engine, validation, context);
+    if result.is_err() {
+        if !crosses_epoch {
+            let mut validators = std::mem::take(&mut state.validators);
+            while validators.len() > registry_len {
+                validators.pop();
+            }
+            for (index, validator) in saved_validators {
+                validators[index] = validator;
+            }
+            pre_state.validators = validators;
+        }
+        *state = pre_state;
+    }
+    result
+}
+pub fn replay_blocks<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+    E: ExecutionEngine<
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+    >,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >],
+    target_slot: crate::primitives::Slot,
+    execution_engine: &E,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, execution_engine, validation, context)?;
+    }
+    if state.slot != target_slot {
+        process_slots(state, target_slot, context)?;
+    }
+    Ok(())
+}
+pub fn verify_blocks_parallel<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    const MAX_BYTES_PER_TRANSACTION: usize,
+    const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+    const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+    const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+    E: ExecutionEngine<
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+    >,
+    F: Fn(
+            &SignedBeaconBlock<
+                MAX_PROPOSER_SLASHINGS,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                MAX_ATTESTER_SLASHINGS,
+                MAX_ATTESTATIONS,
+                MAX_DEPOSITS,
+                MAX_VOLUNTARY_EXITS,
+                SYNC_COMMITTEE_SIZE,
+                BYTES_PER_LOGS_BLOOM,
+                MAX_EXTRA_DATA_BYTES,
+                MAX_BYTES_PER_TRANSACTION,
+                MAX_TRANSACTIONS_PER_PAYLOAD,
+                MAX_WITHDRAWALS_PER_PAYLOAD,
+                MAX_BLS_TO_EXECUTION_CHANGES,
+                MAX_BLOB_COMMITMENTS_PER_BLOCK,
+            >,
+        ) -> Result<
+            BeaconState<
+                SLOTS_PER_HISTORICAL_ROOT,
+                HISTORICAL_ROOTS_LIMIT,
+                ETH1_DATA_VOTES_BOUND,
+                VALIDATOR_REGISTRY_LIMIT,
+                EPOCHS_PER_HISTORICAL_VECTOR,
+                EPOCHS_PER_SLASHINGS_VECTOR,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                SYNC_COMMITTEE_SIZE,
+                BYTES_PER_LOGS_BLOOM,
+                MAX_EXTRA_DATA_BYTES,
+            >,
+        > + Send
+        + Sync,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >],
+    state_provider: F,
+    execution_engine: &E,
+    context: &Context,
+) -> Result<()> {
+    crate::state_transition::for_each_mut(signed_blocks, |signed_block| {
+        let mut pre_state = match state_provider(signed_block) {
+            Ok(pre_state) => pre_state,
+            Err(_) => return,
+        };
+        if pre_state.slot < signed_block.message.slot &&
+            process_slots(&mut pre_state, signed_block.message.slot, context).is_err()
+        {
+            return
+        }
+        let _ = verify_block_signatures(&pre_state, signed_block, context);
+    });
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, execution_engine, Validation::Enabled, context)?;
+    }
+    Ok(())
+}
 pub use crate::deneb::execution_engine::ExecutionEngine;
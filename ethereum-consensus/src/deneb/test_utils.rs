@@ -0,0 +1,127 @@
+//! Deterministic states, attestations, blobs and sidecars for unit tests of the deneb fork.
+use crate::{
+    crypto::aggregate,
+    deneb::{
+        mainnet::{Blob, BlobSidecar, SignedBlobSidecar, BYTES_PER_BLOB},
+        minimal::{
+            compute_epoch_at_slot, compute_start_slot_at_epoch, get_beacon_committee,
+            get_beacon_proposer_index, get_block_root, get_domain, Attestation, AttestationData,
+            BeaconState, Checkpoint,
+        },
+        polynomial_commitments::{
+            blob_to_kzg_commitment, compute_blob_kzg_proof, load_trusted_setup_file, KzgSettings,
+            BYTES_PER_FIELD_ELEMENT,
+        },
+    },
+    domains::DomainType,
+    phase0::{
+        test_utils::{secret_key, VALIDATOR_COUNT},
+        Validator,
+    },
+    primitives::{BlobIndex, CommitteeIndex, Root, Slot, FAR_FUTURE_EPOCH},
+    signing::sign_with_domain,
+    state_transition::Context,
+};
+
+// The mainnet trusted setup in the text format read by `c-kzg`, with the G1 points in lagrange
+// form in their natural order.
+pub(crate) const TRUSTED_SETUP_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/src/deneb/trusted_setup.txt");
+
+pub(crate) fn kzg_settings() -> KzgSettings {
+    load_trusted_setup_file(TRUSTED_SETUP_PATH).unwrap()
+}
+
+// A blob where every field element is `value`, i.e. the constant polynomial `value`.
+pub(crate) fn constant_blob(value: u8) -> Blob {
+    let mut bytes = vec![0u8; BYTES_PER_BLOB];
+    for element in bytes.chunks_mut(BYTES_PER_FIELD_ELEMENT) {
+        element[BYTES_PER_FIELD_ELEMENT - 1] = value;
+    }
+    Blob::try_from(bytes.as_ref()).unwrap()
+}
+
+// A state at `slot` with `VALIDATOR_COUNT` active validators keyed by `secret_key`.
+pub(crate) fn state_at(slot: Slot, context: &Context) -> BeaconState {
+    let mut state = BeaconState { slot, ..Default::default() };
+    for index in 0..VALIDATOR_COUNT {
+        state.validators.push(Validator {
+            public_key: secret_key(index).public_key(),
+            effective_balance: context.max_effective_balance,
+            exit_epoch: FAR_FUTURE_EPOCH,
+            withdrawable_epoch: FAR_FUTURE_EPOCH,
+            ..Default::default()
+        });
+        state.balances.push(context.max_effective_balance);
+    }
+    state
+}
+
+// An attestation to `slot` from every member of committee `index`, voting for the current
+// justified checkpoint of `state` as source.
+pub(crate) fn attestation(
+    state: &BeaconState,
+    slot: Slot,
+    index: CommitteeIndex,
+    context: &Context,
+) -> Attestation {
+    let epoch = compute_epoch_at_slot(slot, context);
+    let target_root = if compute_start_slot_at_epoch(epoch, context) < state.slot {
+        *get_block_root(state, epoch, context).unwrap()
+    } else {
+        Root::default()
+    };
+    let mut data = AttestationData {
+        slot,
+        index,
+        source: state.current_justified_checkpoint.clone(),
+        target: Checkpoint { epoch, root: target_root },
+        ..Default::default()
+    };
+    let domain = get_domain(state, DomainType::BeaconAttester, Some(epoch), context).unwrap();
+    let committee = get_beacon_committee(state, slot, index, context).unwrap();
+    let mut attestation = Attestation { data: data.clone(), ..Default::default() };
+    let mut signatures = Vec::with_capacity(committee.len());
+    for validator_index in committee {
+        attestation.aggregation_bits.push(true);
+        let signature = sign_with_domain(&mut data, &secret_key(validator_index), domain).unwrap();
+        signatures.push(signature);
+    }
+    attestation.signature = aggregate(&signatures).unwrap();
+    attestation
+}
+
+// Sign `sidecar` with the key of its proposer.
+pub(crate) fn sign_blob_sidecar(
+    mut sidecar: BlobSidecar,
+    state: &BeaconState,
+    context: &Context,
+) -> SignedBlobSidecar {
+    let epoch = compute_epoch_at_slot(sidecar.slot, context);
+    let domain = get_domain(state, DomainType::BlobSidecar, Some(epoch), context).unwrap();
+    let signature =
+        sign_with_domain(&mut sidecar, &secret_key(sidecar.proposer_index), domain).unwrap();
+    SignedBlobSidecar { message: sidecar, signature }
+}
+
+// A valid signed sidecar for `blob` at `index`, from the proposer of the slot of `state`.
+pub(crate) fn signed_blob_sidecar(
+    state: &BeaconState,
+    index: BlobIndex,
+    blob: Blob,
+    kzg_settings: &KzgSettings,
+    context: &Context,
+) -> SignedBlobSidecar {
+    let kzg_commitment = blob_to_kzg_commitment(&blob, kzg_settings).unwrap();
+    let kzg_proof = compute_blob_kzg_proof(&blob, &kzg_commitment, kzg_settings).unwrap();
+    let sidecar = BlobSidecar {
+        index,
+        slot: state.slot,
+        proposer_index: get_beacon_proposer_index(state, context).unwrap(),
+        blob,
+        kzg_commitment,
+        kzg_proof,
+        ..Default::default()
+    };
+    sign_blob_sidecar(sidecar, state, context)
+}
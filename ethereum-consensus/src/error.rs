@@ -45,6 +45,12 @@ pub enum Error {
     "the requested epoch {requested} is not in the required current epoch {current} or previous epoch {previous}"
     )]
     InvalidEpoch { requested: Epoch, previous: Epoch, current: Epoch },
+    #[error("the requested epoch {requested} is later than the next epoch {next}")]
+    EpochAfterNextEpoch { requested: Epoch, next: Epoch },
+    #[error(
+        "the requested epoch {requested} is not the current epoch {current} or the next epoch"
+    )]
+    EpochNotCurrentOrNext { requested: Epoch, current: Epoch },
     #[error(
         "transition requested from a later fork {destination_fork:?} to an earlier fork {source_fork:?}"
     )]
@@ -54,6 +60,8 @@ pub enum Error {
     #[cfg(feature = "serde")]
     #[error("an unknown preset {0} was supplied when constructing context")]
     UnknownPreset(String),
+    #[error("cache snapshot does not match the data it was derived from: {0}")]
+    InvalidCacheSnapshot(String),
     #[error(transparent)]
     ExecutionEngine(#[from] ExecutionEngineError),
     #[error(transparent)]
@@ -124,6 +132,8 @@ pub enum InvalidAttestation {
     InvalidSource { expected: Checkpoint, source_checkpoint: Checkpoint, current: Epoch },
     #[error("attestation in slot {attestation_slot} does not have the minimum delay {required_delay} against state {state_slot}")]
     NoDelay { attestation_slot: Slot, state_slot: Slot, required_delay: Slot },
+    #[error("attestation's target root {target_root:?} does not match the block root {expected:?} at the start of the target epoch")]
+    InvalidTargetRoot { expected: Root, target_root: Root },
 }
 
 #[derive(Debug, Error)]
@@ -170,6 +180,8 @@ pub enum InvalidAttesterSlashing {
     NotSlashable(Box<AttestationData>, Box<AttestationData>),
     #[error("no validator was slashed across indices: {0:?}")]
     NoSlashings(Vec<ValidatorIndex>),
+    #[error("attestations have no attesting indices in common")]
+    DisjointAttestingIndices,
 }
 
 #[derive(Debug, Error)]
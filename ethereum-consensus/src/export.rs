@@ -0,0 +1,224 @@
+//! Per-validator epoch summaries and CSV writers for offline analysis.
+use crate::{
+    altair::{self, ValidatorRewards},
+    bellatrix, capella, deneb,
+    primitives::{Epoch, Gwei, ParticipationFlags, ValidatorIndex},
+    state_transition::{Context, Result},
+    types::BeaconState,
+};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorEpochSummary {
+    pub epoch: Epoch,
+    pub index: ValidatorIndex,
+    pub balance: Gwei,
+    pub effective_balance: Gwei,
+    pub slashed: bool,
+    // `None` before `altair`
+    pub previous_epoch_participation: Option<ParticipationFlags>,
+    // Change in balance since the earlier state, capturing rewards and penalties
+    // (and any deposits or withdrawals in between); `None` for new validators.
+    pub balance_change: Option<i64>,
+    // The rewards and penalties for `previous_epoch_participation`, applied at the end of
+    // `epoch`; `None` before `altair`
+    pub rewards: Option<ValidatorRewards>,
+}
+
+// Summarize every validator in `state`, using `previous_state` (e.g. the state one
+// epoch earlier) to compute the balance change.
+pub fn epoch_summaries<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    previous_state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    context: &Context,
+) -> Result<Vec<ValidatorEpochSummary>> {
+    let epoch = state.slot() / context.slots_per_epoch;
+    let report = match state {
+        BeaconState::Phase0(_) => None,
+        BeaconState::Altair(state) => Some(altair::epoch_rewards(state, context)?),
+        BeaconState::Bellatrix(state) => Some(bellatrix::epoch_rewards(state, context)?),
+        BeaconState::Capella(state) => Some(capella::epoch_rewards(state, context)?),
+        BeaconState::Deneb(state) => Some(deneb::epoch_rewards(state, context)?),
+    };
+    let balances = state.balances();
+    let previous_balances = previous_state.balances();
+    let participation = state.previous_epoch_participation();
+    let summaries = state
+        .validators()
+        .iter()
+        .enumerate()
+        .map(|(index, validator)| {
+            let balance = balances[index];
+            ValidatorEpochSummary {
+                epoch,
+                index,
+                balance,
+                effective_balance: validator.effective_balance,
+                slashed: validator.slashed,
+                previous_epoch_participation: participation.map(|flags| flags[index]),
+                balance_change: previous_balances
+                    .get(index)
+                    .map(|previous| balance as i64 - *previous as i64),
+                rewards: report.as_ref().map(|report| report.rewards[index].clone()),
+            }
+        })
+        .collect();
+    Ok(summaries)
+}
+
+const CSV_HEADER: &str = "epoch,validator_index,balance,effective_balance,slashed,\
+    previous_epoch_participation,balance_change,source_reward,target_reward,head_reward,\
+    inactivity_penalty";
+
+// Write `summaries` as CSV with a header row; missing values are left empty.
+pub fn write_csv<'a, W: Write>(
+    mut writer: W,
+    summaries: impl IntoIterator<Item = &'a ValidatorEpochSummary>,
+) -> io::Result<()> {
+    writeln!(writer, "{CSV_HEADER}")?;
+    for summary in summaries {
+        let participation =
+            summary.previous_epoch_participation.map(|flags| flags.to_string()).unwrap_or_default();
+        let balance_change =
+            summary.balance_change.map(|change| change.to_string()).unwrap_or_default();
+        let rewards = match &summary.rewards {
+            Some(rewards) => format!(
+                "{},{},{},{}",
+                rewards.source, rewards.target, rewards.head, rewards.inactivity
+            ),
+            None => ",,,".to_string(),
+        };
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{}",
+            summary.epoch,
+            summary.index,
+            summary.balance,
+            summary.effective_balance,
+            summary.slashed,
+            participation,
+            balance_change,
+            rewards,
+        )?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{phase0::Validator, primitives::FAR_FUTURE_EPOCH, types::mainnet::BeaconState};
+
+    #[test]
+    fn test_epoch_summaries_to_csv() {
+        let context = Context::for_mainnet();
+        let mut previous = crate::altair::mainnet::BeaconState::default();
+        previous.validators.push(Validator {
+            effective_balance: context.max_effective_balance,
+            exit_epoch: FAR_FUTURE_EPOCH,
+            withdrawable_epoch: FAR_FUTURE_EPOCH,
+            ..Default::default()
+        });
+        previous.balances.push(32_000_000_000);
+        previous.previous_epoch_participation.push(0);
+        previous.current_epoch_participation.push(0);
+        previous.inactivity_scores.push(0);
+
+        let mut current = crate::altair::mainnet::BeaconState {
+            slot: 2 * context.slots_per_epoch,
+            ..previous.clone()
+        };
+        current.validators.push(Validator { slashed: true, ..Default::default() });
+        current.balances[0] = 32_000_001_000;
+        current.balances.push(32_000_000_000);
+        current.previous_epoch_participation[0] = 0b111;
+        current.previous_epoch_participation.push(0);
+        current.current_epoch_participation.push(0);
+        current.inactivity_scores.push(0);
+        let report = altair::epoch_rewards(&current, &context).unwrap();
+
+        let summaries = epoch_summaries(
+            &BeaconState::Altair(previous),
+            &BeaconState::Altair(current),
+            &context,
+        )
+        .unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].balance_change, Some(1_000));
+        assert_eq!(summaries[1].balance_change, None);
+        let rewards = summaries[0].rewards.clone().unwrap();
+        assert_eq!(rewards, report.rewards[0]);
+        assert!(rewards.source > 0 && rewards.target > 0 && rewards.head > 0);
+        assert_eq!(summaries[1].rewards, Some(report.rewards[1].clone()));
+
+        let mut buffer = vec![];
+        write_csv(&mut buffer, &summaries).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines[0].split(',').count(), 11);
+        assert_eq!(
+            lines[1],
+            format!(
+                "2,0,32000001000,32000000000,false,7,1000,{},{},{},0",
+                rewards.source, rewards.target, rewards.head
+            )
+        );
+        assert_eq!(lines[2], "2,1,32000000000,0,true,0,,0,0,0,0");
+
+        // no rewards are reported before `altair`
+        let phase0 = BeaconState::Phase0(Default::default());
+        let summaries = epoch_summaries(&phase0, &phase0, &context).unwrap();
+        assert!(summaries.is_empty());
+        let summary = ValidatorEpochSummary {
+            epoch: 0,
+            index: 0,
+            balance: 0,
+            effective_balance: 0,
+            slashed: false,
+            previous_epoch_participation: None,
+            balance_change: None,
+            rewards: None,
+        };
+        let mut buffer = vec![];
+        write_csv(&mut buffer, [&summary]).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        assert_eq!(csv.lines().nth(1), Some("0,0,0,0,false,,,,,,"));
+    }
+}
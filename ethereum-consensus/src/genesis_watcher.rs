@@ -0,0 +1,357 @@
+//! Detect the genesis of a chain by following the deposit contract on eth1.
+//!
+//! Callers feed eth1 blocks, in order, along with the deposits logged in each block.
+//! Once a block satisfies the genesis conditions, the genesis state is produced
+//! with `initialize_beacon_state_from_eth1`.
+use crate::{
+    crypto::hash,
+    phase0::{
+        beacon_state::BeaconState,
+        constants::DEPOSIT_CONTRACT_TREE_DEPTH,
+        genesis::{initialize_beacon_state_from_eth1, is_valid_genesis_state},
+        operations::{Deposit, DepositData},
+    },
+    primitives::{Bytes32, Hash32, Root},
+    ssz::prelude::*,
+    state_transition::{Context, Result},
+};
+#[cfg(feature = "async")]
+use tokio_stream::{Stream, StreamExt};
+
+// The incremental Merkle tree maintained by the deposit contract, used to produce the
+// proof for each deposit against the deposit root just after it is included.
+#[derive(Debug, Clone)]
+pub struct DepositTree {
+    branch: [Bytes32; DEPOSIT_CONTRACT_TREE_DEPTH],
+    zero_hashes: [Bytes32; DEPOSIT_CONTRACT_TREE_DEPTH],
+    count: u64,
+}
+
+impl Default for DepositTree {
+    fn default() -> Self {
+        let mut zero_hashes: [Bytes32; DEPOSIT_CONTRACT_TREE_DEPTH] = Default::default();
+        for i in 1..DEPOSIT_CONTRACT_TREE_DEPTH {
+            zero_hashes[i] = hash_pair(&zero_hashes[i - 1], &zero_hashes[i - 1]);
+        }
+        Self { branch: Default::default(), zero_hashes, count: 0 }
+    }
+}
+
+fn hash_pair(left: &Bytes32, right: &Bytes32) -> Bytes32 {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(left.as_ref());
+    data[32..].copy_from_slice(right.as_ref());
+    hash(data)
+}
+
+fn mix_in_count(count: u64) -> Bytes32 {
+    let mut length = Bytes32::default();
+    length[..8].copy_from_slice(&count.to_le_bytes());
+    length
+}
+
+impl DepositTree {
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    // Append the `deposit_data`, returning it with a proof against the updated tree.
+    pub fn push(&mut self, mut data: DepositData) -> Result<Deposit> {
+        let mut leaf = Bytes32::try_from(data.hash_tree_root()?.as_ref()).expect("is valid root");
+        let index = self.count;
+        let mut proof = Vec::with_capacity(DEPOSIT_CONTRACT_TREE_DEPTH + 1);
+        for height in 0..DEPOSIT_CONTRACT_TREE_DEPTH {
+            if (index >> height) & 1 == 1 {
+                proof.push(self.branch[height].clone());
+            } else {
+                proof.push(self.zero_hashes[height].clone());
+            }
+        }
+        self.count += 1;
+        proof.push(mix_in_count(self.count));
+
+        let mut size = self.count;
+        for height in 0..DEPOSIT_CONTRACT_TREE_DEPTH {
+            if size & 1 == 1 {
+                self.branch[height] = leaf;
+                break
+            }
+            leaf = hash_pair(&self.branch[height], &leaf);
+            size >>= 1;
+        }
+
+        let proof = Vector::try_from(proof).expect("is correct length");
+        Ok(Deposit { proof, data })
+    }
+
+    pub fn root(&self) -> Root {
+        let mut node = Bytes32::default();
+        let mut size = self.count;
+        for height in 0..DEPOSIT_CONTRACT_TREE_DEPTH {
+            if size & 1 == 1 {
+                node = hash_pair(&self.branch[height], &node);
+            } else {
+                node = hash_pair(&node, &self.zero_hashes[height]);
+            }
+            size >>= 1;
+        }
+        let root = hash_pair(&node, &mix_in_count(self.count));
+        Root::try_from(root.as_ref()).expect("is valid root")
+    }
+}
+
+// An eth1 block along with the deposits logged in it, in log order.
+#[derive(Debug, Clone, Default)]
+pub struct Eth1Block {
+    pub hash: Hash32,
+    pub timestamp: u64,
+    pub deposits: Vec<DepositData>,
+}
+
+#[derive(Debug, Default)]
+pub struct GenesisWatcher {
+    tree: DepositTree,
+    deposits: Vec<Deposit>,
+}
+
+impl GenesisWatcher {
+    pub fn deposit_count(&self) -> u64 {
+        self.tree.count()
+    }
+
+    // Process the next eth1 `block`, returning the genesis state if `block` triggers genesis.
+    //
+    // A genesis state is only built for candidate blocks, i.e. once enough time has passed and
+    // enough deposits were made, so most blocks only extend the deposit tree.
+    pub fn process_block<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const MAX_PROPOSER_SLASHINGS: usize,
+        const MAX_ATTESTER_SLASHINGS: usize,
+        const MAX_ATTESTATIONS: usize,
+        const MAX_DEPOSITS: usize,
+        const MAX_VOLUNTARY_EXITS: usize,
+    >(
+        &mut self,
+        block: Eth1Block,
+        context: &Context,
+    ) -> Result<
+        Option<
+            BeaconState<
+                SLOTS_PER_HISTORICAL_ROOT,
+                HISTORICAL_ROOTS_LIMIT,
+                ETH1_DATA_VOTES_BOUND,
+                VALIDATOR_REGISTRY_LIMIT,
+                EPOCHS_PER_HISTORICAL_VECTOR,
+                EPOCHS_PER_SLASHINGS_VECTOR,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                PENDING_ATTESTATIONS_BOUND,
+            >,
+        >,
+    > {
+        for data in block.deposits {
+            let deposit = self.tree.push(data)?;
+            self.deposits.push(deposit);
+        }
+
+        if block.timestamp + context.genesis_delay < context.min_genesis_time ||
+            self.deposits.len() < context.min_genesis_active_validator_count
+        {
+            return Ok(None)
+        }
+
+        let mut deposits = self.deposits.clone();
+        let state = initialize_beacon_state_from_eth1::<
+            SLOTS_PER_HISTORICAL_ROOT,
+            HISTORICAL_ROOTS_LIMIT,
+            ETH1_DATA_VOTES_BOUND,
+            VALIDATOR_REGISTRY_LIMIT,
+            EPOCHS_PER_HISTORICAL_VECTOR,
+            EPOCHS_PER_SLASHINGS_VECTOR,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            PENDING_ATTESTATIONS_BOUND,
+            MAX_PROPOSER_SLASHINGS,
+            MAX_ATTESTER_SLASHINGS,
+            MAX_ATTESTATIONS,
+            MAX_DEPOSITS,
+            MAX_VOLUNTARY_EXITS,
+        >(block.hash, block.timestamp, &mut deposits, context)?;
+        if is_valid_genesis_state(&state, context) {
+            Ok(Some(state))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Consume `blocks` until genesis is reached, or return `None` if the stream ends first.
+    #[cfg(feature = "async")]
+    pub async fn wait_for_genesis<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const MAX_PROPOSER_SLASHINGS: usize,
+        const MAX_ATTESTER_SLASHINGS: usize,
+        const MAX_ATTESTATIONS: usize,
+        const MAX_DEPOSITS: usize,
+        const MAX_VOLUNTARY_EXITS: usize,
+    >(
+        &mut self,
+        blocks: impl Stream<Item = Eth1Block>,
+        context: &Context,
+    ) -> Result<
+        Option<
+            BeaconState<
+                SLOTS_PER_HISTORICAL_ROOT,
+                HISTORICAL_ROOTS_LIMIT,
+                ETH1_DATA_VOTES_BOUND,
+                VALIDATOR_REGISTRY_LIMIT,
+                EPOCHS_PER_HISTORICAL_VECTOR,
+                EPOCHS_PER_SLASHINGS_VECTOR,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                PENDING_ATTESTATIONS_BOUND,
+            >,
+        >,
+    > {
+        tokio::pin!(blocks);
+        while let Some(block) = blocks.next().await {
+            let state = self.process_block::<
+                SLOTS_PER_HISTORICAL_ROOT,
+                HISTORICAL_ROOTS_LIMIT,
+                ETH1_DATA_VOTES_BOUND,
+                VALIDATOR_REGISTRY_LIMIT,
+                EPOCHS_PER_HISTORICAL_VECTOR,
+                EPOCHS_PER_SLASHINGS_VECTOR,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                PENDING_ATTESTATIONS_BOUND,
+                MAX_PROPOSER_SLASHINGS,
+                MAX_ATTESTER_SLASHINGS,
+                MAX_ATTESTATIONS,
+                MAX_DEPOSITS,
+                MAX_VOLUNTARY_EXITS,
+            >(block, context)?;
+            if state.is_some() {
+                return Ok(state)
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::SecretKey,
+        phase0::{
+            compute_domain,
+            constants::DEPOSIT_DATA_LIST_BOUND,
+            mainnet::{
+                BeaconState, EPOCHS_PER_HISTORICAL_VECTOR, EPOCHS_PER_SLASHINGS_VECTOR,
+                ETH1_DATA_VOTES_BOUND, HISTORICAL_ROOTS_LIMIT, MAX_ATTESTATIONS,
+                MAX_ATTESTER_SLASHINGS, MAX_DEPOSITS, MAX_PROPOSER_SLASHINGS,
+                MAX_VALIDATORS_PER_COMMITTEE, MAX_VOLUNTARY_EXITS, PENDING_ATTESTATIONS_BOUND,
+                SLOTS_PER_HISTORICAL_ROOT, VALIDATOR_REGISTRY_LIMIT,
+            },
+            operations::DepositMessage,
+        },
+        primitives::DomainType,
+        signing::sign_with_domain,
+    };
+
+    fn deposit_data(i: u8, context: &Context) -> DepositData {
+        let secret_key = SecretKey::key_gen(&[i + 1; 32]).unwrap();
+        let mut message = DepositMessage {
+            public_key: secret_key.public_key(),
+            withdrawal_credentials: Default::default(),
+            amount: context.max_effective_balance,
+        };
+        let domain = compute_domain(DomainType::Deposit, None, None, context).unwrap();
+        let signature = sign_with_domain(&mut message, &secret_key, domain).unwrap();
+        DepositData {
+            public_key: message.public_key,
+            withdrawal_credentials: message.withdrawal_credentials,
+            amount: message.amount,
+            signature,
+        }
+    }
+
+    #[test]
+    fn test_deposit_tree_root() {
+        let context = Context::for_mainnet();
+        let mut tree = DepositTree::default();
+        let mut leaves = List::<DepositData, DEPOSIT_DATA_LIST_BOUND>::default();
+        assert_eq!(tree.root(), leaves.hash_tree_root().unwrap());
+        for i in 0..5 {
+            let data = deposit_data(i, &context);
+            leaves.push(data.clone());
+            let mut deposit = tree.push(data).unwrap();
+            let root = leaves.hash_tree_root().unwrap();
+            assert_eq!(tree.root(), root);
+
+            let leaf = deposit.data.hash_tree_root().unwrap();
+            let branch = deposit
+                .proof
+                .iter()
+                .map(|node| Node::try_from(node.as_ref()).unwrap())
+                .collect::<Vec<_>>();
+            let depth = DEPOSIT_CONTRACT_TREE_DEPTH + 1;
+            assert!(is_valid_merkle_branch(&leaf, branch.iter(), depth, i as usize, &root));
+        }
+    }
+
+    #[test]
+    fn test_genesis_watcher() {
+        let mut context = Context::for_mainnet();
+        context.min_genesis_active_validator_count = 4;
+        let mut watcher = GenesisWatcher::default();
+
+        let timestamp = context.min_genesis_time - context.genesis_delay;
+        let block = |i: u8, timestamp, deposits| Eth1Block {
+            hash: Hash32::try_from([i; 32].as_ref()).unwrap(),
+            timestamp,
+            deposits,
+        };
+        let process = |watcher: &mut GenesisWatcher, block| {
+            watcher
+                .process_block::<
+                    SLOTS_PER_HISTORICAL_ROOT,
+                    HISTORICAL_ROOTS_LIMIT,
+                    ETH1_DATA_VOTES_BOUND,
+                    VALIDATOR_REGISTRY_LIMIT,
+                    EPOCHS_PER_HISTORICAL_VECTOR,
+                    EPOCHS_PER_SLASHINGS_VECTOR,
+                    MAX_VALIDATORS_PER_COMMITTEE,
+                    PENDING_ATTESTATIONS_BOUND,
+                    MAX_PROPOSER_SLASHINGS,
+                    MAX_ATTESTER_SLASHINGS,
+                    MAX_ATTESTATIONS,
+                    MAX_DEPOSITS,
+                    MAX_VOLUNTARY_EXITS,
+                >(block, &context)
+                .unwrap()
+        };
+
+        // enough deposits, but too early
+        let deposits = (0..4).map(|i| deposit_data(i, &context)).collect();
+        assert!(process(&mut watcher, block(1, timestamp - 1, deposits)).is_none());
+
+        // a duplicate deposit tops up an existing validator
+        let deposits = vec![deposit_data(0, &context)];
+        let state: BeaconState = process(&mut watcher, block(2, timestamp, deposits)).unwrap();
+        assert_eq!(state.genesis_time, context.min_genesis_time);
+        assert_eq!(state.validators.len(), 4);
+        assert_eq!(state.eth1_deposit_index, 5);
+        assert_eq!(state.eth1_data.deposit_root, watcher.tree.root());
+    }
+}
@@ -0,0 +1,128 @@
+//! Hierarchical derivation of BLS secret keys from a seed following EIP-2333.
+use crate::{
+    crypto::{hash, SecretKey},
+    keys::Error,
+};
+use hkdf::Hkdf;
+use ruint::{aliases::U256, uint, Uint};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+type U384 = Uint<384, 6>;
+
+const SALT: &[u8; 20] = b"BLS-SIG-KEYGEN-SALT-";
+const L: usize = 48;
+const R: U384 =
+    uint!(52435875175126190479447740508185965837690552500527637822603658699938581184513_U384);
+const K: usize = 32;
+const LAMPORT_COUNT: usize = 255;
+const LAMPORT_L: usize = K * LAMPORT_COUNT;
+const MIN_SEED_LEN: usize = 32;
+
+fn ikm_to_lamport_secret_key(ikm: &[u8], salt: &[u8], output: &mut [u8; LAMPORT_L]) {
+    let hk = Hkdf::<Sha256>::new(Some(salt), ikm);
+    hk.expand(&[], output).expect("length L is valid");
+}
+
+fn parent_sk_to_lamport_public_key(parent_key: &U256, index: u32) -> [u8; 32] {
+    let salt = index.to_be_bytes();
+    let ikm: [u8; 32] = parent_key.to_be_bytes();
+    let not_ikm = ikm.map(|byte| !byte);
+    let mut lamport_public_key = Vec::with_capacity(2 * LAMPORT_L);
+    let mut lamport_secret_key = [0u8; LAMPORT_L];
+    for ikm in [ikm, not_ikm] {
+        ikm_to_lamport_secret_key(&ikm, &salt, &mut lamport_secret_key);
+        for chunk in lamport_secret_key.chunks_exact(K) {
+            lamport_public_key.extend_from_slice(hash(chunk).as_ref());
+        }
+    }
+    hash(&lamport_public_key).as_ref().try_into().expect("hash is 32 bytes")
+}
+
+fn hkdf_mod_r(ikm: &[u8]) -> U256 {
+    let mut key = U384::ZERO;
+    let mut salt = hash(SALT);
+    let key_info = [0, L as u8];
+    let mut ikm = ikm.to_vec();
+    ikm.push(0);
+
+    while key == U384::ZERO {
+        let hk = Hkdf::<Sha256>::new(Some(salt.as_ref()), &ikm);
+        let mut okm = [0u8; L];
+        hk.expand(&key_info, &mut okm).expect("length L is valid");
+        key = U384::from_be_bytes(okm) % R;
+        salt = hash(salt.as_ref());
+    }
+
+    // `R` fits in 255 bits so the upper bytes are zero
+    let key_bytes: [u8; L] = key.to_be_bytes();
+    U256::from_be_bytes::<32>(key_bytes[L - 32..].try_into().expect("slice has 32 bytes"))
+}
+
+fn to_secret_key(key: U256) -> SecretKey {
+    let key = Zeroizing::new(key.to_be_bytes::<32>());
+    SecretKey::try_from(key.as_ref()).expect("key is a non-zero element of the field")
+}
+
+// Derive the root key of the tree of keys for `seed`, which must have at least 32 bytes.
+pub fn derive_master_sk(seed: &[u8]) -> Result<SecretKey, Error> {
+    if seed.len() < MIN_SEED_LEN {
+        return Err(Error::SeedTooShort { minimum: MIN_SEED_LEN, provided: seed.len() })
+    }
+    Ok(to_secret_key(hkdf_mod_r(seed)))
+}
+
+pub fn derive_child_sk(parent: &SecretKey, index: u32) -> SecretKey {
+    let parent_key = U256::from_be_bytes(*parent.to_bytes());
+    let compressed_lamport_public_key = parent_sk_to_lamport_public_key(&parent_key, index);
+    to_secret_key(hkdf_mod_r(&compressed_lamport_public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_of(key: U256) -> [u8; 32] {
+        key.to_be_bytes()
+    }
+
+    fn decode_hex(input: &str) -> Vec<u8> {
+        (0..input.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&input[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // test vectors from EIP-2333
+    #[test]
+    fn test_derive_keys() {
+        let vectors = [
+            (
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+                uint!(6083874454709270928345386274498605044986640685124978867557563392430687146096_U256),
+                0,
+                uint!(20397789859736650942317412262472558107875392172444076792671091975210932703118_U256),
+            ),
+            (
+                "3141592653589793238462643383279502884197169399375105820974944592",
+                uint!(29757020647961307431480504535336562678282505419141012933316116377660817309383_U256),
+                3141592653,
+                uint!(25457201688850691947727629385191704516744796114925897962676248250929345014287_U256),
+            ),
+        ];
+        for (seed, master_sk, index, child_sk) in vectors {
+            let seed = decode_hex(seed);
+            let master = derive_master_sk(&seed).unwrap();
+            assert_eq!(*master.to_bytes(), key_of(master_sk));
+            assert_eq!(*derive_child_sk(&master, index).to_bytes(), key_of(child_sk));
+        }
+    }
+
+    #[test]
+    fn test_short_seed() {
+        assert!(matches!(
+            derive_master_sk(&[0u8; 31]),
+            Err(Error::SeedTooShort { minimum: 32, provided: 31 })
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+//! The deterministic validator keys used by client interop devnets and tests, following the
+//! `eth2.0-pm` interop specification.
+use crate::crypto::{hash, SecretKey};
+use ruint::{aliases::U256, uint};
+
+const CURVE_ORDER: U256 =
+    uint!(52435875175126190479447740508185965837690552500527637822603658699938581184513_U256);
+
+// Return the secret key of the validator at `index` of an interop genesis, the little-endian
+// SHA-256 of `index` reduced modulo the curve order.
+pub fn interop_secret_key(index: u64) -> SecretKey {
+    let mut preimage = [0u8; 32];
+    preimage[..8].copy_from_slice(&index.to_le_bytes());
+    let digest: [u8; 32] = hash(preimage).as_ref().try_into().expect("hash is 32 bytes");
+    let key = U256::from_le_bytes(digest) % CURVE_ORDER;
+    SecretKey::try_from(key.to_be_bytes::<32>().as_ref()).expect("key is a non-zero field element")
+}
+
+pub fn interop_secret_keys(count: u64) -> Vec<SecretKey> {
+    (0..count).map(interop_secret_key).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interop_secret_keys() {
+        let expected = [
+            "25295f0d1d592a90b333e26e85149708208e9f8e8bc18f6c77bd62f8ad7a6866",
+            "51d0b65185db6989ab0b560d6deed19c7ead0e24b9b6372cbecb1f26bdfad000",
+            "315ed405fafe339603932eebe8dbfd650ce5dafa561f6928664c75db85f97857",
+            "25b1166a43c109cb330af8945d364722757c65ed2bfed5444b5a2f057f82d391",
+        ];
+        let keys = interop_secret_keys(expected.len() as u64);
+        for (key, expected) in keys.into_iter().zip(expected) {
+            assert_eq!(hex::encode(key.to_bytes()), expected);
+        }
+        assert_eq!(
+            hex::encode(interop_secret_key(0).public_key().as_ref()),
+            "a99a76ed7796f7be22d5b7e85deeb7c5677e88e511e0b337618f8c4eb61349b4bf2d153f649f7b53359fe8b94a38e44c"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Secret keys encrypted under a password in the EIP-2335 keystore format.
+use crate::{
+    crypto::{hash, PublicKey, SecretKey},
+    keys::{Error, Path},
+};
+use aes::cipher::{KeyIvInit, StreamCipher};
+use rand::Rng;
+use sha2::Sha256;
+use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+const VERSION: u64 = 4;
+const DECRYPTION_KEY_LEN: usize = 32;
+const SALT_LEN: usize = 32;
+const IV_LEN: usize = 16;
+const PBKDF2_PRF: &str = "hmac-sha256";
+
+// the scrypt parameters recommended by EIP-2335
+const SCRYPT_N: u32 = 262144;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+mod hex_bytes {
+    use serde::Deserialize;
+
+    pub fn serialize<S: serde::Serializer, T: AsRef<[u8]>>(
+        data: T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&hex::encode(data))
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: for<'a> TryFrom<&'a [u8]>,
+    {
+        let s = <String>::deserialize(deserializer)?;
+        // keystores do not prefix hex data but tolerate those that do
+        let data =
+            hex::decode(s.strip_prefix("0x").unwrap_or(&s)).map_err(serde::de::Error::custom)?;
+        T::try_from(&data).map_err(|_| serde::de::Error::custom("unexpected length of hex data"))
+    }
+}
+
+fn serialize_public_key<S: serde::Serializer>(
+    public_key: &PublicKey,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let public_key: &[u8] = public_key;
+    hex_bytes::serialize(public_key, serializer)
+}
+
+// An empty `path` marks a key that was not derived from a seed.
+mod optional_path {
+    use crate::keys::Path;
+    use serde::Deserialize;
+
+    pub fn serialize<S: serde::Serializer>(
+        path: &Option<Path>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match path {
+            Some(path) => serializer.collect_str(path),
+            None => serializer.serialize_str(""),
+        }
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Path>, D::Error> {
+        let s = <String>::deserialize(deserializer)?;
+        if s.is_empty() {
+            return Ok(None)
+        }
+        s.parse().map(Some).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "function", content = "params", rename_all = "lowercase")]
+pub enum Kdf {
+    Scrypt {
+        dklen: usize,
+        n: u32,
+        p: u32,
+        r: u32,
+        #[serde(with = "hex_bytes")]
+        salt: Vec<u8>,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,
+        prf: String,
+        #[serde(with = "hex_bytes")]
+        salt: Vec<u8>,
+    },
+}
+
+impl Kdf {
+    pub fn scrypt(salt: Vec<u8>) -> Self {
+        Self::Scrypt { dklen: DECRYPTION_KEY_LEN, n: SCRYPT_N, p: SCRYPT_P, r: SCRYPT_R, salt }
+    }
+
+    pub fn pbkdf2(c: u32, salt: Vec<u8>) -> Self {
+        Self::Pbkdf2 { dklen: DECRYPTION_KEY_LEN, c, prf: PBKDF2_PRF.to_string(), salt }
+    }
+
+    fn decryption_key(&self, password: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        match self {
+            Self::Scrypt { dklen, n, p, r, salt } => {
+                if *dklen < DECRYPTION_KEY_LEN || !n.is_power_of_two() || *n < 2 {
+                    return Err(Error::InvalidKdfParams(format!("{self:?}")))
+                }
+                let log_n = n.trailing_zeros() as u8;
+                let params = scrypt::Params::new(log_n, *r, *p, *dklen)
+                    .map_err(|err| Error::InvalidKdfParams(err.to_string()))?;
+                let mut key = Zeroizing::new(vec![0u8; *dklen]);
+                scrypt::scrypt(password, salt, &params, &mut key)
+                    .map_err(|err| Error::InvalidKdfParams(err.to_string()))?;
+                Ok(key)
+            }
+            Self::Pbkdf2 { dklen, c, prf, salt } => {
+                if *dklen < DECRYPTION_KEY_LEN || prf != PBKDF2_PRF {
+                    return Err(Error::InvalidKdfParams(format!("{self:?}")))
+                }
+                let mut key = Zeroizing::new(vec![0u8; *dklen]);
+                pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, *c, &mut key);
+                Ok(key)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct KdfModule {
+    #[serde(flatten)]
+    pub kdf: Kdf,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "function", content = "params")]
+pub enum Checksum {
+    #[serde(rename = "sha256")]
+    Sha256 {},
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ChecksumModule {
+    #[serde(flatten)]
+    pub checksum: Checksum,
+    #[serde(with = "hex_bytes")]
+    pub message: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "function", content = "params")]
+pub enum Cipher {
+    #[serde(rename = "aes-128-ctr")]
+    Aes128Ctr {
+        #[serde(with = "hex_bytes")]
+        iv: [u8; IV_LEN],
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CipherModule {
+    #[serde(flatten)]
+    pub cipher: Cipher,
+    #[serde(with = "hex_bytes")]
+    pub message: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct KeystoreCrypto {
+    pub kdf: KdfModule,
+    pub checksum: ChecksumModule,
+    pub cipher: CipherModule,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Keystore {
+    pub crypto: KeystoreCrypto,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(
+        rename = "pubkey",
+        serialize_with = "serialize_public_key",
+        deserialize_with = "hex_bytes::deserialize"
+    )]
+    pub public_key: PublicKey,
+    #[serde(with = "optional_path")]
+    pub path: Option<Path>,
+    pub uuid: Uuid,
+    pub version: u64,
+}
+
+// Passwords are NFKD normalized with control codes removed.
+fn normalize_password(password: &str) -> Zeroizing<Vec<u8>> {
+    let mut password = Zeroizing::new(password.nfkd().collect::<String>());
+    password.retain(|c| !c.is_control());
+    Zeroizing::new(password.as_bytes().to_vec())
+}
+
+fn compute_checksum(decryption_key: &[u8], cipher_message: &[u8]) -> Vec<u8> {
+    let mut pre_image = Zeroizing::new(decryption_key[16..32].to_vec());
+    pre_image.extend_from_slice(cipher_message);
+    hash(pre_image).as_ref().to_vec()
+}
+
+fn apply_cipher(decryption_key: &[u8], iv: &[u8; IV_LEN], message: &mut [u8]) {
+    let mut cipher =
+        Aes128Ctr::new_from_slices(&decryption_key[..16], iv).expect("key and iv have 16 bytes");
+    cipher.apply_keystream(message);
+}
+
+impl Keystore {
+    // Encrypt `secret_key` under `password` using scrypt with the parameters recommended by
+    // EIP-2335 and a random salt and IV.
+    pub fn encrypt(
+        secret_key: &SecretKey,
+        password: &str,
+        path: Option<Path>,
+    ) -> Result<Self, Error> {
+        let mut rng = rand::thread_rng();
+        let kdf = Kdf::scrypt(rng.gen::<[u8; SALT_LEN]>().to_vec());
+        Self::encrypt_with(secret_key, password, path, kdf, rng.gen())
+    }
+
+    pub fn encrypt_with(
+        secret_key: &SecretKey,
+        password: &str,
+        path: Option<Path>,
+        kdf: Kdf,
+        iv: [u8; IV_LEN],
+    ) -> Result<Self, Error> {
+        let decryption_key = kdf.decryption_key(&normalize_password(password))?;
+        let mut message = secret_key.to_bytes().to_vec();
+        apply_cipher(&decryption_key, &iv, &mut message);
+        let checksum = compute_checksum(&decryption_key, &message);
+        Ok(Self {
+            crypto: KeystoreCrypto {
+                kdf: KdfModule { kdf, message: String::new() },
+                checksum: ChecksumModule { checksum: Checksum::Sha256 {}, message: checksum },
+                cipher: CipherModule { cipher: Cipher::Aes128Ctr { iv }, message },
+            },
+            description: None,
+            public_key: secret_key.public_key(),
+            path,
+            uuid: Uuid::new_v4(),
+            version: VERSION,
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<SecretKey, Error> {
+        if self.version != VERSION {
+            return Err(Error::UnsupportedKeystoreVersion(self.version))
+        }
+        let crypto = &self.crypto;
+        let decryption_key = crypto.kdf.kdf.decryption_key(&normalize_password(password))?;
+        let Checksum::Sha256 {} = crypto.checksum.checksum;
+        if compute_checksum(&decryption_key, &crypto.cipher.message) != crypto.checksum.message {
+            return Err(Error::InvalidKeystoreChecksum)
+        }
+        let Cipher::Aes128Ctr { iv } = &crypto.cipher.cipher;
+        let mut secret = Zeroizing::new(crypto.cipher.message.clone());
+        apply_cipher(&decryption_key, iv, &mut secret);
+        let secret_key = SecretKey::try_from(secret.as_ref())?;
+        if secret_key.public_key() != self.public_key {
+            return Err(Error::KeystorePublicKeyMismatch)
+        }
+        Ok(secret_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "𝔱𝔢𝔰𝔱𝔭𝔞𝔰𝔰𝔴𝔬𝔯𝔡🔑";
+    const SECRET: &str = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+
+    // Test vectors from EIP-2335:
+    const SCRYPT_KEYSTORE: &str = r#"{
+        "crypto": {
+            "kdf": {
+                "function": "scrypt",
+                "params": {
+                    "dklen": 32,
+                    "n": 262144,
+                    "p": 1,
+                    "r": 8,
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "d2217fe5f3e9a1e34581ef8a78f7c9928e436d36dacc5e846690a5581e8ea484"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": {
+                    "iv": "264daa3f303d7259501c93d997d84fe6"
+                },
+                "message": "06ae90d55fe0a6e9c5c3bc5b170827b2e5cce3929ed3f116c2811e6366dfe20f"
+            }
+        },
+        "description": "This is a test keystore that uses scrypt to secure the secret.",
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "path": "m/12381/60/3141592653/589793238",
+        "uuid": "1d85ae20-35c5-4611-98e8-aa14a633906f",
+        "version": 4
+    }"#;
+
+    const PBKDF2_KEYSTORE: &str = r#"{
+        "crypto": {
+            "kdf": {
+                "function": "pbkdf2",
+                "params": {
+                    "dklen": 32,
+                    "c": 262144,
+                    "prf": "hmac-sha256",
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "8a9f5d9912ed7e75ea794bc5a89bca5f193721d30868ade6f73043c6ea6febf1"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": {
+                    "iv": "264daa3f303d7259501c93d997d84fe6"
+                },
+                "message": "cee03fde2af33149775b7223e7845e4fb2c8ae1792e5f99fe9ecf474cc8c16ad"
+            }
+        },
+        "description": "This is a test keystore that uses PBKDF2 to secure the secret.",
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "path": "m/12381/60/0/0",
+        "uuid": "64625def-3331-4eea-ab6f-782f3ed16a83",
+        "version": 4
+    }"#;
+
+    fn expected_secret_key() -> SecretKey {
+        SecretKey::try_from(hex::decode(SECRET).unwrap().as_ref()).unwrap()
+    }
+
+    #[test]
+    #[ignore = "strong key parameters take a long time to run"]
+    fn test_decrypt_keystores() {
+        for json in [SCRYPT_KEYSTORE, PBKDF2_KEYSTORE] {
+            let keystore: Keystore = serde_json::from_str(json).unwrap();
+            let secret_key = keystore.decrypt(PASSWORD).unwrap();
+            assert_eq!(secret_key.to_bytes(), expected_secret_key().to_bytes());
+
+            let encrypted = Keystore::encrypt_with(
+                &expected_secret_key(),
+                PASSWORD,
+                keystore.path.clone(),
+                keystore.crypto.kdf.kdf.clone(),
+                hex::decode("264daa3f303d7259501c93d997d84fe6").unwrap().try_into().unwrap(),
+            )
+            .unwrap();
+            assert_eq!(encrypted.crypto, keystore.crypto);
+        }
+    }
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let keystore: Keystore = serde_json::from_str(PBKDF2_KEYSTORE).unwrap();
+        assert_eq!(keystore.public_key, expected_secret_key().public_key());
+        assert_eq!(keystore.path, Some("m/12381/60/0/0".parse().unwrap()));
+        let json = serde_json::to_string(&keystore).unwrap();
+        assert_eq!(serde_json::from_str::<Keystore>(&json).unwrap(), keystore);
+
+        let kdf = Kdf::pbkdf2(2, vec![7u8; SALT_LEN]);
+        let keystore =
+            Keystore::encrypt_with(&expected_secret_key(), "password", None, kdf, [1u8; IV_LEN])
+                .unwrap();
+        let json = serde_json::to_string(&keystore).unwrap();
+        assert!(json.contains(r#""path":"""#));
+        let keystore: Keystore = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            keystore.decrypt("pass\u{7f}word").unwrap().to_bytes(),
+            expected_secret_key().to_bytes()
+        );
+        assert!(matches!(keystore.decrypt("wrong"), Err(Error::InvalidKeystoreChecksum)));
+
+        let scrypt = Kdf::Scrypt { dklen: 32, n: 3, p: 1, r: 8, salt: vec![] };
+        assert!(Keystore::encrypt_with(&expected_secret_key(), "", None, scrypt, [0u8; IV_LEN])
+            .is_err());
+    }
+}
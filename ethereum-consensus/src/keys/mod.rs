@@ -0,0 +1,31 @@
+//! Validator key management following EIP-2333, EIP-2334 and EIP-2335, along with the
+//! deterministic keys of interop devnets.
+mod derivation;
+mod interop;
+mod keystore;
+mod path;
+
+pub use derivation::*;
+pub use interop::*;
+pub use keystore::*;
+pub use path::*;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("seed must be at least {minimum} bytes but has {provided}")]
+    SeedTooShort { minimum: usize, provided: usize },
+    #[error("invalid key path `{0}`")]
+    InvalidPath(String),
+    #[error("unsupported keystore version {0}")]
+    UnsupportedKeystoreVersion(u64),
+    #[error("invalid key derivation function parameters: {0}")]
+    InvalidKdfParams(String),
+    #[error("keystore checksum does not match, the password is likely incorrect")]
+    InvalidKeystoreChecksum,
+    #[error("keystore public key does not match the decrypted secret key")]
+    KeystorePublicKeyMismatch,
+    #[error("{0}")]
+    Crypto(#[from] crate::crypto::Error),
+}
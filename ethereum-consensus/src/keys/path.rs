@@ -0,0 +1,123 @@
+//! Key paths in the tree of EIP-2333 keys, following EIP-2334.
+use crate::{
+    crypto::SecretKey,
+    keys::{derive_child_sk, Error},
+};
+use std::{fmt, str::FromStr};
+
+const PURPOSE: u32 = 12381;
+const COIN_TYPE: u32 = 3600;
+
+// A path of child indices from the master key, written `m/12381/3600/0/0/0`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Path(Vec<u32>);
+
+impl Path {
+    pub fn new(indices: Vec<u32>) -> Self {
+        Self(indices)
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+
+    pub fn child(&self, index: u32) -> Self {
+        let mut indices = self.0.clone();
+        indices.push(index);
+        Self(indices)
+    }
+
+    // Derive the key at this path in the tree rooted at `master_sk`.
+    pub fn derive(&self, master_sk: &SecretKey) -> SecretKey {
+        let Some((&first, rest)) = self.0.split_first() else {
+            return SecretKey::try_from(master_sk.to_bytes().as_ref())
+                .expect("bytes of a valid secret key")
+        };
+        rest.iter()
+            .fold(derive_child_sk(master_sk, first), |key, &index| derive_child_sk(&key, index))
+    }
+}
+
+// Path of the withdrawal key of the validator at `index` of a tree.
+pub fn withdrawal_key_path(index: u32) -> Path {
+    Path(vec![PURPOSE, COIN_TYPE, index, 0])
+}
+
+// Path of the signing key of the validator at `index` of a tree, the first child of its
+// withdrawal key.
+pub fn signing_key_path(index: u32) -> Path {
+    withdrawal_key_path(index).child(0)
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{index}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = s.split('/');
+        if components.next() != Some("m") {
+            return Err(Error::InvalidPath(s.to_string()))
+        }
+        components
+            .map(|component| {
+                // reject the signs and padding `u32::from_str` tolerates
+                if component.is_empty() || !component.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(Error::InvalidPath(s.to_string()))
+                }
+                component.parse().map_err(|_| Error::InvalidPath(s.to_string()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl serde::Serialize for Path {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Path {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::derive_master_sk;
+
+    #[test]
+    fn test_parse_path() {
+        let path: Path = "m/12381/3600/7/0/0".parse().unwrap();
+        assert_eq!(path.indices(), &[12381, 3600, 7, 0, 0]);
+        assert_eq!(path, signing_key_path(7));
+        assert_eq!(path.to_string(), "m/12381/3600/7/0/0");
+        assert_eq!(withdrawal_key_path(7).to_string(), "m/12381/3600/7/0");
+        assert_eq!("m".parse::<Path>().unwrap(), Path::default());
+
+        for invalid in ["", "12381/3600", "m/", "m//0", "m/+1", "m/-1", "m/4294967296", "m/a"] {
+            assert!(invalid.parse::<Path>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_derive_path() {
+        let master_sk = derive_master_sk(&[7u8; 32]).unwrap();
+        let withdrawal_key = withdrawal_key_path(1).derive(&master_sk);
+        let signing_key = signing_key_path(1).derive(&master_sk);
+        assert_eq!(derive_child_sk(&withdrawal_key, 0).to_bytes(), signing_key.to_bytes());
+        assert_eq!(Path::default().derive(&master_sk).to_bytes(), master_sk.to_bytes());
+    }
+}
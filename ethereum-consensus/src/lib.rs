@@ -1,24 +1,44 @@
 pub mod altair;
 pub mod bellatrix;
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod builder;
 pub mod capella;
+#[cfg(feature = "serde")]
+pub mod checkpoint;
 pub mod clock;
 pub mod configs;
 pub mod crypto;
 pub mod deneb;
 pub mod domains;
 pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
 mod fork;
+pub mod genesis_watcher;
+#[cfg(feature = "keys")]
+pub mod keys;
+pub mod liveness;
 pub mod networking;
 pub mod networks;
+#[cfg(feature = "peerdas")]
+pub mod peerdas;
 pub mod phase0;
 pub mod primitives;
+pub mod proofs;
+pub mod registry;
 #[cfg(feature = "serde")]
 pub mod serde;
 pub mod signing;
 pub mod ssz;
 pub mod state_transition;
+pub mod tree_hash_cache;
 pub mod types;
+pub mod validator_monitor;
+#[cfg(all(feature = "parallel", feature = "async"))]
+pub mod verification_pool;
+#[cfg(feature = "serde")]
+pub mod web3signer;
 
 pub use error::Error;
 pub use fork::Fork;
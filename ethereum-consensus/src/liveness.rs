@@ -0,0 +1,200 @@
+//! Track on-chain activity of validators by epoch, e.g. for doppelganger protection
+//! when starting a validator client.
+use crate::{
+    primitives::{Epoch, ParticipationFlags, ValidatorIndex},
+    state_transition::Context,
+    types::BeaconState,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::RangeInclusive,
+};
+
+pub const DEFAULT_DOPPELGANGER_EPOCHS: u64 = 2;
+
+// The validators seen live in each epoch, from participation data or a liveness feed
+#[derive(Debug, Clone, Default)]
+pub struct LivenessTracker {
+    live: BTreeMap<Epoch, HashSet<ValidatorIndex>>,
+}
+
+impl LivenessTracker {
+    pub fn record(&mut self, epoch: Epoch, indices: impl IntoIterator<Item = ValidatorIndex>) {
+        self.live.entry(epoch).or_default().extend(indices);
+    }
+
+    // Any participation flag set for a validator counts as activity in `epoch`
+    pub fn record_participation(&mut self, epoch: Epoch, participation: &[ParticipationFlags]) {
+        let indices = participation
+            .iter()
+            .enumerate()
+            .filter(|(_, &flags)| flags != 0)
+            .map(|(index, _)| index);
+        self.record(epoch, indices);
+    }
+
+    // Record the participation for the previous and current epochs of `state`.
+    // Returns `false` for states before `altair`, which do not track participation flags.
+    pub fn record_state<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+    >(
+        &mut self,
+        state: &BeaconState<
+            SLOTS_PER_HISTORICAL_ROOT,
+            HISTORICAL_ROOTS_LIMIT,
+            ETH1_DATA_VOTES_BOUND,
+            VALIDATOR_REGISTRY_LIMIT,
+            EPOCHS_PER_HISTORICAL_VECTOR,
+            EPOCHS_PER_SLASHINGS_VECTOR,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            PENDING_ATTESTATIONS_BOUND,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+        >,
+        context: &Context,
+    ) -> bool {
+        let (Some(previous), Some(current)) =
+            (state.previous_epoch_participation(), state.current_epoch_participation())
+        else {
+            return false
+        };
+        let current_epoch = state.slot() / context.slots_per_epoch;
+        if let Some(previous_epoch) = current_epoch.checked_sub(1) {
+            self.record_participation(previous_epoch, previous);
+        }
+        self.record_participation(current_epoch, current);
+        true
+    }
+
+    // The earliest epoch in `epochs` where the validator was seen live
+    pub fn first_live_epoch(
+        &self,
+        index: ValidatorIndex,
+        epochs: RangeInclusive<Epoch>,
+    ) -> Option<Epoch> {
+        self.live
+            .range(epochs)
+            .find_map(|(epoch, indices)| indices.contains(&index).then_some(*epoch))
+    }
+
+    pub fn is_live(&self, index: ValidatorIndex, epochs: RangeInclusive<Epoch>) -> bool {
+        self.first_live_epoch(index, epochs).is_some()
+    }
+
+    // Drop data for epochs before `epoch`
+    pub fn prune(&mut self, epoch: Epoch) {
+        self.live = self.live.split_off(&epoch);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoppelgangerStatus {
+    // not enough epochs have been observed yet
+    Pending,
+    // no activity was seen, so it is safe to start signing
+    Safe,
+    // another instance appears to be running this validator
+    Detected(Epoch),
+}
+
+// Validators wait out a number of epochs after startup and must not be seen live in that
+// window, or in the epoch before they started, before they begin signing.
+#[derive(Debug, Clone)]
+pub struct DoppelgangerDetector {
+    epochs_to_check: u64,
+    windows: HashMap<ValidatorIndex, RangeInclusive<Epoch>>,
+}
+
+impl Default for DoppelgangerDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_DOPPELGANGER_EPOCHS)
+    }
+}
+
+impl DoppelgangerDetector {
+    pub fn new(epochs_to_check: u64) -> Self {
+        Self { epochs_to_check, windows: Default::default() }
+    }
+
+    pub fn register(&mut self, index: ValidatorIndex, current_epoch: Epoch) {
+        let start = current_epoch.saturating_sub(1);
+        let end = current_epoch + self.epochs_to_check.saturating_sub(1);
+        self.windows.insert(index, start..=end);
+    }
+
+    pub fn unregister(&mut self, index: ValidatorIndex) {
+        self.windows.remove(&index);
+    }
+
+    // `complete_epoch` is the latest epoch for which `liveness` has all data, e.g. the
+    // previous epoch when recording from a state's `previous_epoch_participation`.
+    pub fn status(
+        &self,
+        index: ValidatorIndex,
+        liveness: &LivenessTracker,
+        complete_epoch: Epoch,
+    ) -> Option<DoppelgangerStatus> {
+        let window = self.windows.get(&index)?;
+        let status = if let Some(epoch) = liveness.first_live_epoch(index, window.clone()) {
+            DoppelgangerStatus::Detected(epoch)
+        } else if complete_epoch >= *window.end() {
+            DoppelgangerStatus::Safe
+        } else {
+            DoppelgangerStatus::Pending
+        };
+        Some(status)
+    }
+
+    // Registered validators that were seen live during their window
+    pub fn detected(&self, liveness: &LivenessTracker) -> Vec<(ValidatorIndex, Epoch)> {
+        let mut detected = self
+            .windows
+            .iter()
+            .filter_map(|(&index, window)| {
+                liveness.first_live_epoch(index, window.clone()).map(|epoch| (index, epoch))
+            })
+            .collect::<Vec<_>>();
+        detected.sort_unstable();
+        detected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doppelganger_detection() {
+        let mut liveness = LivenessTracker::default();
+        let mut detector = DoppelgangerDetector::default();
+        detector.register(1, 10);
+        detector.register(2, 10);
+        assert_eq!(detector.status(3, &liveness, 10), None);
+
+        liveness.record_participation(9, &[0b001, 0, 0]);
+        liveness.record(10, [0]);
+        assert_eq!(detector.status(1, &liveness, 10), Some(DoppelgangerStatus::Pending));
+
+        liveness.record_participation(11, &[0, 0, 0b100]);
+        assert_eq!(detector.status(1, &liveness, 11), Some(DoppelgangerStatus::Safe));
+        assert_eq!(detector.status(2, &liveness, 11), Some(DoppelgangerStatus::Detected(11)));
+        assert_eq!(detector.detected(&liveness), vec![(2, 11)]);
+
+        assert!(liveness.is_live(0, 9..=9));
+        assert!(!liveness.is_live(0, 11..=20));
+        liveness.prune(10);
+        assert!(!liveness.is_live(0, 0..=9));
+        assert!(liveness.is_live(0, 10..=10));
+    }
+}
@@ -0,0 +1,296 @@
+//! PeerDAS data column sidecars and custody assignments: the custody groups a node is
+//! responsible for and the data columns in each group.
+use crate::{
+    crypto::hash,
+    deneb::polynomial_commitments::{KzgCommitment, KzgProof, BYTES_PER_FIELD_ELEMENT},
+    phase0::SignedBeaconBlockHeader,
+    primitives::{Bytes32, Root},
+    proofs::MerkleBranch,
+    ssz::prelude::*,
+};
+use thiserror::Error;
+
+pub type CustodyIndex = u64;
+pub type ColumnIndex = u64;
+pub type RowIndex = u64;
+
+pub const NUMBER_OF_COLUMNS: u64 = 128;
+pub const NUMBER_OF_CUSTODY_GROUPS: u64 = 128;
+pub const FIELD_ELEMENTS_PER_CELL: usize = 64;
+pub const BYTES_PER_CELL: usize = FIELD_ELEMENTS_PER_CELL * BYTES_PER_FIELD_ELEMENT;
+pub const KZG_COMMITMENTS_INCLUSION_PROOF_DEPTH: usize = 4;
+// Index of `blob_kzg_commitments` among the fields of the `deneb` `BeaconBlockBody`
+pub const BLOB_KZG_COMMITMENTS_INDEX: usize = 11;
+
+pub type Cell<const BYTES_PER_CELL: usize> = ByteVector<BYTES_PER_CELL>;
+pub type DataColumn<const BYTES_PER_CELL: usize, const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize> =
+    List<Cell<BYTES_PER_CELL>, MAX_BLOB_COMMITMENTS_PER_BLOCK>;
+
+#[derive(
+    Default, Debug, Clone, SimpleSerialize, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub struct DataColumnSidecar<
+    const BYTES_PER_CELL: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+> {
+    #[serde(with = "crate::serde::as_str")]
+    pub index: ColumnIndex,
+    pub column: DataColumn<BYTES_PER_CELL, MAX_BLOB_COMMITMENTS_PER_BLOCK>,
+    pub kzg_commitments: List<KzgCommitment, MAX_BLOB_COMMITMENTS_PER_BLOCK>,
+    pub kzg_proofs: List<KzgProof, MAX_BLOB_COMMITMENTS_PER_BLOCK>,
+    pub signed_block_header: SignedBeaconBlockHeader,
+    pub kzg_commitments_inclusion_proof: Vector<Bytes32, KZG_COMMITMENTS_INCLUSION_PROOF_DEPTH>,
+}
+
+#[derive(
+    Default, Debug, Clone, SimpleSerialize, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub struct DataColumnIdentifier {
+    pub block_root: Root,
+    #[serde(with = "crate::serde::as_str")]
+    pub index: ColumnIndex,
+}
+
+#[derive(
+    Default, Debug, Clone, SimpleSerialize, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub struct MatrixEntry<const BYTES_PER_CELL: usize> {
+    pub cell: Cell<BYTES_PER_CELL>,
+    pub kzg_proof: KzgProof,
+    #[serde(with = "crate::serde::as_str")]
+    pub column_index: ColumnIndex,
+    #[serde(with = "crate::serde::as_str")]
+    pub row_index: RowIndex,
+}
+
+pub mod mainnet {
+    use crate::deneb::mainnet::MAX_BLOB_COMMITMENTS_PER_BLOCK;
+
+    pub use super::BYTES_PER_CELL;
+
+    pub type Cell = super::Cell<BYTES_PER_CELL>;
+    pub type DataColumn = super::DataColumn<BYTES_PER_CELL, MAX_BLOB_COMMITMENTS_PER_BLOCK>;
+    pub type DataColumnSidecar =
+        super::DataColumnSidecar<BYTES_PER_CELL, MAX_BLOB_COMMITMENTS_PER_BLOCK>;
+    pub type MatrixEntry = super::MatrixEntry<BYTES_PER_CELL>;
+}
+
+pub mod minimal {
+    use crate::deneb::minimal::MAX_BLOB_COMMITMENTS_PER_BLOCK;
+
+    pub use super::BYTES_PER_CELL;
+
+    pub type Cell = super::Cell<BYTES_PER_CELL>;
+    pub type DataColumn = super::DataColumn<BYTES_PER_CELL, MAX_BLOB_COMMITMENTS_PER_BLOCK>;
+    pub type DataColumnSidecar =
+        super::DataColumnSidecar<BYTES_PER_CELL, MAX_BLOB_COMMITMENTS_PER_BLOCK>;
+    pub type MatrixEntry = super::MatrixEntry<BYTES_PER_CELL>;
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(
+        "custody group count {0} exceeds the number of custody groups {NUMBER_OF_CUSTODY_GROUPS}"
+    )]
+    CustodyGroupCountTooLarge(u64),
+    #[error("custody group {0} is not less than the number of custody groups {NUMBER_OF_CUSTODY_GROUPS}")]
+    InvalidCustodyGroup(CustodyIndex),
+    #[error("column index {0} is not less than the number of columns {NUMBER_OF_COLUMNS}")]
+    InvalidColumnIndex(ColumnIndex),
+    #[error("data column sidecar has no KZG commitments")]
+    EmptyColumn,
+    #[error("data column sidecar has {cells} cells and {proofs} proofs for {commitments} KZG commitments")]
+    LengthMismatch { cells: usize, commitments: usize, proofs: usize },
+    #[error("KZG commitments inclusion proof does not verify against the block body root")]
+    InvalidInclusionProof,
+    #[error("{0}")]
+    Merkleization(#[from] MerkleizationError),
+}
+
+// Structural checks on a sidecar; the cell proofs are checked separately against the
+// commitments with KZG.
+pub fn verify_data_column_sidecar<
+    const BYTES_PER_CELL: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+>(
+    sidecar: &DataColumnSidecar<BYTES_PER_CELL, MAX_BLOB_COMMITMENTS_PER_BLOCK>,
+) -> Result<(), Error> {
+    if sidecar.index >= NUMBER_OF_COLUMNS {
+        return Err(Error::InvalidColumnIndex(sidecar.index))
+    }
+    let commitments = sidecar.kzg_commitments.len();
+    if commitments == 0 {
+        return Err(Error::EmptyColumn)
+    }
+    let cells = sidecar.column.len();
+    let proofs = sidecar.kzg_proofs.len();
+    if cells != commitments || proofs != commitments {
+        return Err(Error::LengthMismatch { cells, commitments, proofs })
+    }
+    Ok(())
+}
+
+// Check that `kzg_commitments` are those committed to by the block body in the sidecar's header
+pub fn verify_data_column_sidecar_inclusion_proof<
+    const BYTES_PER_CELL: usize,
+    const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+>(
+    sidecar: &DataColumnSidecar<BYTES_PER_CELL, MAX_BLOB_COMMITMENTS_PER_BLOCK>,
+) -> Result<(), Error> {
+    let leaf = sidecar.kzg_commitments.clone().hash_tree_root()?;
+    let branch = MerkleBranch {
+        index: BLOB_KZG_COMMITMENTS_INDEX,
+        branch: sidecar
+            .kzg_commitments_inclusion_proof
+            .iter()
+            .map(|node| Node::try_from(node.as_ref()).expect("correct length"))
+            .collect(),
+    };
+    if branch.compute_root(&leaf) != sidecar.signed_block_header.message.body_root {
+        return Err(Error::InvalidInclusionProof)
+    }
+    Ok(())
+}
+
+// Increment a little-endian `uint256`, wrapping to zero past the maximum
+fn increment(value: &mut [u8; 32]) {
+    for byte in value.iter_mut() {
+        let (next, overflow) = byte.overflowing_add(1);
+        *byte = next;
+        if !overflow {
+            return
+        }
+    }
+}
+
+pub fn get_custody_groups(
+    node_id: &U256,
+    custody_group_count: u64,
+) -> Result<Vec<CustodyIndex>, Error> {
+    if custody_group_count > NUMBER_OF_CUSTODY_GROUPS {
+        return Err(Error::CustodyGroupCountTooLarge(custody_group_count))
+    }
+    if custody_group_count == NUMBER_OF_CUSTODY_GROUPS {
+        return Ok((0..NUMBER_OF_CUSTODY_GROUPS).collect())
+    }
+
+    let mut current_id = [0u8; 32];
+    let bytes = node_id.to_bytes_le();
+    current_id[..bytes.len()].copy_from_slice(&bytes);
+
+    let mut custody_groups = Vec::with_capacity(custody_group_count as usize);
+    while (custody_groups.len() as u64) < custody_group_count {
+        let digest = hash(current_id);
+        let value = u64::from_le_bytes(digest[..8].try_into().expect("correct length"));
+        let custody_group = value % NUMBER_OF_CUSTODY_GROUPS;
+        if !custody_groups.contains(&custody_group) {
+            custody_groups.push(custody_group);
+        }
+        increment(&mut current_id);
+    }
+    custody_groups.sort_unstable();
+    Ok(custody_groups)
+}
+
+pub fn compute_columns_for_custody_group(
+    custody_group: CustodyIndex,
+) -> Result<Vec<ColumnIndex>, Error> {
+    if custody_group >= NUMBER_OF_CUSTODY_GROUPS {
+        return Err(Error::InvalidCustodyGroup(custody_group))
+    }
+    let columns_per_group = NUMBER_OF_COLUMNS / NUMBER_OF_CUSTODY_GROUPS;
+    Ok((0..columns_per_group).map(|i| NUMBER_OF_CUSTODY_GROUPS * i + custody_group).collect())
+}
+
+// The columns a node with `node_id` must custody and serve, in ascending order
+pub fn get_custody_columns(
+    node_id: &U256,
+    custody_group_count: u64,
+) -> Result<Vec<ColumnIndex>, Error> {
+    let mut columns = Vec::new();
+    for custody_group in get_custody_groups(node_id, custody_group_count)? {
+        columns.extend(compute_columns_for_custody_group(custody_group)?);
+    }
+    columns.sort_unstable();
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custody_groups() {
+        let node_id = U256::from(42u64);
+        let groups = get_custody_groups(&node_id, 4).unwrap();
+        assert_eq!(groups.len(), 4);
+        assert!(groups.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(groups.iter().all(|&group| group < NUMBER_OF_CUSTODY_GROUPS));
+        assert_eq!(groups, get_custody_groups(&node_id, 4).unwrap());
+        let fewer = get_custody_groups(&node_id, 2).unwrap();
+        assert!(fewer.iter().all(|group| groups.contains(group)));
+
+        let all = get_custody_groups(&node_id, NUMBER_OF_CUSTODY_GROUPS).unwrap();
+        assert_eq!(all, (0..NUMBER_OF_CUSTODY_GROUPS).collect::<Vec<_>>());
+        assert!(get_custody_groups(&node_id, NUMBER_OF_CUSTODY_GROUPS + 1).is_err());
+
+        // the maximum node id wraps around to zero
+        let max = U256::from_bytes_le([0xff; 32]);
+        assert_eq!(get_custody_groups(&max, 8).unwrap().len(), 8);
+
+        assert_eq!(compute_columns_for_custody_group(3).unwrap(), vec![3]);
+        assert!(compute_columns_for_custody_group(NUMBER_OF_CUSTODY_GROUPS).is_err());
+        assert_eq!(get_custody_columns(&node_id, 4).unwrap(), groups);
+    }
+
+    #[test]
+    fn test_verify_data_column_sidecar() {
+        let mut sidecar = minimal::DataColumnSidecar::default();
+        assert!(matches!(verify_data_column_sidecar(&sidecar), Err(Error::EmptyColumn)));
+
+        sidecar.kzg_commitments.push(KzgCommitment::default());
+        sidecar.kzg_proofs.push(KzgProof::default());
+        assert!(matches!(
+            verify_data_column_sidecar(&sidecar),
+            Err(Error::LengthMismatch { cells: 0, commitments: 1, proofs: 1 })
+        ));
+        sidecar.column.push(minimal::Cell::default());
+        verify_data_column_sidecar(&sidecar).unwrap();
+
+        sidecar.index = NUMBER_OF_COLUMNS;
+        assert!(matches!(verify_data_column_sidecar(&sidecar), Err(Error::InvalidColumnIndex(_))));
+    }
+
+    #[test]
+    fn test_inclusion_proof() {
+        let mut sidecar = minimal::DataColumnSidecar::default();
+        sidecar.kzg_commitments.push(KzgCommitment::default());
+        let mut leaves = vec![Node::default(); 1 << KZG_COMMITMENTS_INCLUSION_PROOF_DEPTH];
+        leaves[BLOB_KZG_COMMITMENTS_INDEX] = sidecar.kzg_commitments.hash_tree_root().unwrap();
+        leaves[0] = Node::try_from([1u8; 32].as_ref()).unwrap();
+        let branch = MerkleBranch::new(
+            &leaves,
+            KZG_COMMITMENTS_INCLUSION_PROOF_DEPTH,
+            BLOB_KZG_COMMITMENTS_INDEX,
+        );
+        for (i, node) in branch.branch.iter().enumerate() {
+            sidecar.kzg_commitments_inclusion_proof[i] = Bytes32::try_from(node.as_ref()).unwrap();
+        }
+        assert!(verify_data_column_sidecar_inclusion_proof(&sidecar).is_err());
+
+        sidecar.signed_block_header.message.body_root =
+            branch.compute_root(&leaves[BLOB_KZG_COMMITMENTS_INDEX]);
+        verify_data_column_sidecar_inclusion_proof(&sidecar).unwrap();
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut value = [0u8; 32];
+        value[0] = 0xff;
+        increment(&mut value);
+        assert_eq!(&value[..2], &[0, 1]);
+        let mut value = [0xff; 32];
+        increment(&mut value);
+        assert_eq!(value, [0; 32]);
+    }
+}
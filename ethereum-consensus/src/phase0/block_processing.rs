@@ -1,33 +1,37 @@
 use crate::{
-    crypto::{hash, verify_signature},
+    crypto::{fast_aggregate_verify, hash, verify_signature},
     error::{
         invalid_header_error, invalid_operation_error, InvalidAttestation, InvalidAttesterSlashing,
-        InvalidBeaconBlockHeader, InvalidDeposit, InvalidOperation, InvalidProposerSlashing,
-        InvalidVoluntaryExit,
+        InvalidBeaconBlockHeader, InvalidDeposit, InvalidIndexedAttestation, InvalidOperation,
+        InvalidProposerSlashing, InvalidVoluntaryExit,
     },
     phase0::{
         beacon_block::{BeaconBlock, BeaconBlockBody, BeaconBlockHeader},
         beacon_state::BeaconState,
         constants::DEPOSIT_CONTRACT_TREE_DEPTH,
         helpers::{
-            compute_domain, compute_epoch_at_slot, get_beacon_committee, get_beacon_proposer_index,
+            compute_domain, compute_epoch_at_slot, compute_start_slot_at_epoch,
+            get_beacon_committee, get_beacon_proposer_index, get_block_root,
             get_committee_count_per_slot, get_current_epoch, get_domain, get_indexed_attestation,
             get_previous_epoch, get_randao_mix, increase_balance, initiate_validator_exit,
             is_active_validator, is_slashable_attestation_data, is_slashable_validator,
             is_valid_indexed_attestation, slash_validator,
         },
         operations::{
-            Attestation, AttesterSlashing, Deposit, DepositMessage, PendingAttestation,
-            ProposerSlashing, SignedVoluntaryExit,
+            Attestation, AttesterSlashing, Deposit, DepositMessage, IndexedAttestation,
+            PendingAttestation, ProposerSlashing, SignedVoluntaryExit, ValidAttestation,
         },
         validator::Validator,
     },
-    primitives::{BlsPublicKey, Bytes32, DomainType, Gwei, ValidatorIndex, FAR_FUTURE_EPOCH},
-    signing::{compute_signing_root, verify_signed_data},
+    primitives::{
+        Bytes32, CommitteeIndex, DomainType, Epoch, Gwei, Root, Slot, ValidatorIndex,
+        FAR_FUTURE_EPOCH,
+    },
+    signing::compute_signing_root,
     ssz::prelude::*,
     state_transition::{Context, Result},
 };
-use std::collections::HashSet;
+use std::collections::{hash_map::Entry, HashMap};
 
 pub fn process_proposer_slashing<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -95,7 +99,11 @@ pub fn process_proposer_slashing<
     {
         let signing_root = compute_signing_root(&mut signed_header.message, domain)?;
         let public_key = &proposer.public_key;
-        if verify_signature(public_key, signing_root.as_ref(), &signed_header.signature).is_err() {
+        if context
+            .verified_signature_cache
+            .verify_signature(public_key, &signing_root, &signed_header.signature)
+            .is_err()
+        {
             return Err(invalid_operation_error(InvalidOperation::ProposerSlashing(
                 InvalidProposerSlashing::InvalidSignature(signed_header.signature.clone()),
             )))
@@ -144,11 +152,7 @@ pub fn process_attester_slashing<
 
     is_valid_indexed_attestation(state, attestation_2, context)?;
 
-    let indices_1: HashSet<ValidatorIndex> =
-        HashSet::from_iter(attestation_1.attesting_indices.iter().cloned());
-    let indices_2 = HashSet::from_iter(attestation_2.attesting_indices.iter().cloned());
-    let mut indices = indices_1.intersection(&indices_2).cloned().collect::<Vec<_>>();
-    indices.sort_unstable();
+    let indices = attester_slashing.slashable_indices();
 
     let mut slashed_any = false;
     let current_epoch = get_current_epoch(state, context);
@@ -290,6 +294,219 @@ pub fn process_attestation<
     Ok(())
 }
 
+// Check `attestation` for `validate_attestations`, returning its indexed form and the root its
+// signature should be over. The signature itself is not verified.
+//
+// `committees` and `target_roots` memoize lookups against `state` across calls.
+pub fn validate_attestation_without_signature<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    attestation: &Attestation<MAX_VALIDATORS_PER_COMMITTEE>,
+    committees: &mut HashMap<(Slot, CommitteeIndex), Vec<ValidatorIndex>>,
+    target_roots: &mut HashMap<Epoch, Option<Root>>,
+    context: &Context,
+) -> Result<(IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>, Root)> {
+    let previous_epoch = get_previous_epoch(state, context);
+    let current_epoch = get_current_epoch(state, context);
+    let data = &attestation.data;
+    let is_current = data.target.epoch == current_epoch;
+    if !is_current && data.target.epoch != previous_epoch {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidTargetEpoch {
+                target: data.target.epoch,
+                current: current_epoch,
+            },
+        )))
+    }
+
+    let attestation_epoch = compute_epoch_at_slot(data.slot, context);
+    if data.target.epoch != attestation_epoch {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidSlot {
+                slot: data.slot,
+                epoch: attestation_epoch,
+                target: data.target.epoch,
+            },
+        )))
+    }
+
+    let expected_source = if is_current {
+        &state.current_justified_checkpoint
+    } else {
+        &state.previous_justified_checkpoint
+    };
+    if data.source != *expected_source {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidSource {
+                expected: expected_source.clone(),
+                source_checkpoint: data.source.clone(),
+                current: current_epoch,
+            },
+        )))
+    }
+
+    let target_root = match target_roots.entry(data.target.epoch) {
+        Entry::Occupied(entry) => *entry.get(),
+        Entry::Vacant(entry) => {
+            let start_slot = compute_start_slot_at_epoch(data.target.epoch, context);
+            let root = if start_slot < state.slot {
+                Some(*get_block_root(state, data.target.epoch, context)?)
+            } else {
+                None
+            };
+            *entry.insert(root)
+        }
+    };
+    if let Some(expected) = target_root {
+        if data.target.root != expected {
+            return Err(invalid_operation_error(InvalidOperation::Attestation(
+                InvalidAttestation::InvalidTargetRoot { expected, target_root: data.target.root },
+            )))
+        }
+    }
+
+    let committee_count = get_committee_count_per_slot(state, data.target.epoch, context);
+    if data.index >= committee_count {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::InvalidIndex { index: data.index, upper_bound: committee_count },
+        )))
+    }
+    let committee = match committees.entry((data.slot, data.index)) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            entry.insert(get_beacon_committee(state, data.slot, data.index, context)?)
+        }
+    };
+    if attestation.aggregation_bits.len() != committee.len() {
+        return Err(invalid_operation_error(InvalidOperation::Attestation(
+            InvalidAttestation::Bitfield {
+                expected_length: committee.len(),
+                length: attestation.aggregation_bits.len(),
+            },
+        )))
+    }
+
+    let mut attesting_indices = committee
+        .iter()
+        .enumerate()
+        .filter_map(|(i, index)| attestation.aggregation_bits[i].then_some(*index))
+        .collect::<Vec<_>>();
+    if attesting_indices.is_empty() {
+        return Err(invalid_operation_error(InvalidOperation::IndexedAttestation(
+            InvalidIndexedAttestation::AttestingIndicesEmpty,
+        )))
+    }
+    attesting_indices.sort_unstable();
+    let indexed_attestation = IndexedAttestation {
+        attesting_indices: attesting_indices.try_into().expect("at most committee size"),
+        data: data.clone(),
+        signature: attestation.signature.clone(),
+    };
+
+    let domain = get_domain(state, DomainType::BeaconAttester, Some(data.target.epoch), context)?;
+    let signing_root = compute_signing_root(&mut data.clone(), domain)?;
+    Ok((indexed_attestation, signing_root))
+}
+
+// Check each of `attestations` against `state` as `process_attestation` would, ignoring
+// inclusion delay, and also require the target root to match `state` when it is known.
+//
+// Committees and target roots are computed once for the whole batch and all signatures are
+// verified together, falling back to verifying individually to locate any invalid ones.
+pub fn validate_attestations<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    attestations: &[Attestation<MAX_VALIDATORS_PER_COMMITTEE>],
+    context: &Context,
+) -> Vec<Result<ValidAttestation<MAX_VALIDATORS_PER_COMMITTEE>>> {
+    let mut committees = HashMap::new();
+    let mut target_roots = HashMap::new();
+
+    let results = attestations
+        .iter()
+        .map(|attestation| {
+            validate_attestation_without_signature(
+                state,
+                attestation,
+                &mut committees,
+                &mut target_roots,
+                context,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let sets = results
+        .iter()
+        .filter_map(|result| result.as_ref().ok())
+        .map(|(indexed_attestation, signing_root)| crate::crypto::SignatureSet {
+            public_keys: indexed_attestation
+                .attesting_indices
+                .iter()
+                .map(|&index| &state.validators[index].public_key)
+                .collect(),
+            message: signing_root.as_ref(),
+            signature: &indexed_attestation.signature,
+        })
+        .collect::<Vec<_>>();
+    let all_valid = crate::crypto::verify_signature_sets(&sets).is_ok();
+    drop(sets);
+
+    results
+        .into_iter()
+        .zip(attestations)
+        .map(|(result, attestation)| {
+            let (indexed_attestation, signing_root) = result?;
+            if !all_valid {
+                let public_keys = indexed_attestation
+                    .attesting_indices
+                    .iter()
+                    .map(|&index| &state.validators[index].public_key)
+                    .collect::<Vec<_>>();
+                fast_aggregate_verify(
+                    &public_keys,
+                    signing_root.as_ref(),
+                    &indexed_attestation.signature,
+                )?;
+            }
+            Ok(ValidAttestation { attestation: attestation.clone(), indexed_attestation })
+        })
+        .collect()
+}
+
 pub fn get_validator_from_deposit(deposit: &Deposit, context: &Context) -> Validator {
     let amount = deposit.data.amount;
     let effective_balance = Gwei::min(
@@ -351,9 +568,9 @@ pub fn process_deposit<
 
     let public_key = &deposit.data.public_key;
     let amount = deposit.data.amount;
-    let validator_public_keys: HashSet<&BlsPublicKey> =
-        HashSet::from_iter(state.validators.iter().map(|v| &v.public_key));
-    if !validator_public_keys.contains(public_key) {
+    if let Some(index) = context.pubkey_cache.get_validator_index(&state.validators, public_key) {
+        increase_balance(state, index, amount);
+    } else {
         let mut deposit_message = DepositMessage {
             public_key: public_key.clone(),
             withdrawal_credentials: deposit.data.withdrawal_credentials.clone(),
@@ -367,12 +584,9 @@ pub fn process_deposit<
             return Ok(())
         }
 
+        context.pubkey_cache.insert(public_key.clone(), state.validators.len());
         state.validators.push(get_validator_from_deposit(deposit, context));
         state.balances.push(amount);
-    } else {
-        let index = state.validators.iter().position(|v| &v.public_key == public_key).unwrap();
-
-        increase_balance(state, index, amount);
     }
 
     Ok(())
@@ -442,8 +656,11 @@ pub fn process_voluntary_exit<
     }
 
     let domain = get_domain(state, DomainType::VoluntaryExit, Some(voluntary_exit.epoch), context)?;
+    let signing_root = compute_signing_root(voluntary_exit, domain)?;
     let public_key = &validator.public_key;
-    verify_signed_data(voluntary_exit, &signed_voluntary_exit.signature, public_key, domain)
+    context
+        .verified_signature_cache
+        .verify_signature(public_key, &signing_root, &signed_voluntary_exit.signature)
         .map_err(|_| {
             invalid_operation_error(InvalidOperation::VoluntaryExit(
                 InvalidVoluntaryExit::InvalidSignature(signed_voluntary_exit.signature.clone()),
@@ -582,7 +799,11 @@ pub fn process_randao<
     let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
     let signing_root = compute_signing_root(&mut epoch, domain)?;
 
-    if verify_signature(&proposer.public_key, signing_root.as_ref(), &body.randao_reveal).is_err() {
+    if context
+        .verified_signature_cache
+        .verify_signature(&proposer.public_key, &signing_root, &body.randao_reveal)
+        .is_err()
+    {
         return Err(invalid_operation_error(InvalidOperation::Randao(body.randao_reveal.clone())))
     }
 
@@ -637,6 +858,39 @@ pub fn process_eth1_data<
     }
 }
 
+// Return the indices of the validators that the operations in `body` may slash or exit. Other
+// operations only append to the registry. The indices may repeat or be out of range.
+pub fn get_validator_indices_touched_by_operations<
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+>(
+    body: &BeaconBlockBody<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+    >,
+) -> Vec<ValidatorIndex> {
+    let mut indices = vec![];
+    for proposer_slashing in body.proposer_slashings.iter() {
+        indices.push(proposer_slashing.signed_header_1.message.proposer_index);
+    }
+    for attester_slashing in body.attester_slashings.iter() {
+        // only validators in both attestations are slashed
+        indices.extend(attester_slashing.attestation_1.attesting_indices.iter().copied());
+    }
+    for voluntary_exit in body.voluntary_exits.iter() {
+        indices.push(voluntary_exit.message.validator_index);
+    }
+    indices
+}
+
 pub fn process_operations<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -741,3 +995,71 @@ pub fn process_block<
     process_operations(state, &mut block.body, context)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phase0::{
+        minimal,
+        test_utils::{attestation, state_at},
+    };
+
+    const SLOT: Slot = 10;
+
+    fn attesting_indices(
+        state: &minimal::BeaconState,
+        slot: Slot,
+        context: &Context,
+    ) -> Vec<usize> {
+        let mut committee = get_beacon_committee(state, slot, 0, context).unwrap();
+        committee.sort_unstable();
+        committee
+    }
+
+    #[test]
+    fn test_validate_attestations() {
+        let context = Context::for_minimal();
+        let state = state_at(SLOT, &context);
+        let attestations = [
+            attestation(&state, SLOT - 2, 0, &context),
+            attestation(&state, SLOT - 1, 0, &context),
+        ];
+
+        let results = validate_attestations(&state, &attestations, &context);
+        assert_eq!(results.len(), 2);
+        for (result, attestation) in results.into_iter().zip(&attestations) {
+            let valid = result.unwrap();
+            assert_eq!(valid.attestation, *attestation);
+            assert_eq!(
+                valid.indexed_attestation.attesting_indices.iter().copied().collect::<Vec<_>>(),
+                attesting_indices(&state, attestation.data.slot, &context)
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_attestations_with_invalid_signature() {
+        let context = Context::for_minimal();
+        let state = state_at(SLOT, &context);
+        let valid = attestation(&state, SLOT - 2, 0, &context);
+        let mut invalid = attestation(&state, SLOT - 1, 0, &context);
+        invalid.signature = valid.signature.clone();
+
+        let results = validate_attestations(&state, &[valid, invalid], &context);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn test_validate_attestations_with_duplicates() {
+        // as in a block, a repeated attestation is valid each time it appears
+        let context = Context::for_minimal();
+        let state = state_at(SLOT, &context);
+        let attestation = attestation(&state, SLOT - 1, 0, &context);
+
+        let results = validate_attestations(&state, &[attestation.clone(), attestation], &context);
+        let first = results[0].as_ref().unwrap();
+        let second = results[1].as_ref().unwrap();
+        assert_eq!(first, second);
+    }
+}
@@ -5,13 +5,14 @@ use crate::{
         helpers::{
             compute_activation_exit_epoch, decrease_balance, get_attesting_indices, get_block_root,
             get_block_root_at_slot, get_current_epoch, get_eligible_validator_indices,
-            get_previous_epoch, get_randao_mix, get_total_active_balance, get_total_balance,
-            get_validator_churn_limit, increase_balance, initiate_validator_exit,
-            is_active_validator, is_eligible_for_activation, is_eligible_for_activation_queue,
+            get_previous_epoch, get_randao_mix, get_total_active_balance,
+            get_total_active_balance_cache_key, get_total_balance, get_validator_churn_limit,
+            initiate_validator_exit, is_active_validator, is_eligible_for_activation,
+            is_eligible_for_activation_queue, JustificationBits,
         },
         operations::{Checkpoint, PendingAttestation},
     },
-    primitives::{Epoch, Gwei, ValidatorIndex, GENESIS_EPOCH},
+    primitives::{Epoch, Gwei, ValidatorIndex, FAR_FUTURE_EPOCH, GENESIS_EPOCH},
     ssz::prelude::*,
     state_transition::{Context, Result},
     Error,
@@ -19,6 +20,33 @@ use crate::{
 use integer_sqrt::IntegerSquareRoot;
 use std::{collections::HashSet, mem};
 
+// Summary of the effects of a single call to `process_epoch`, as returned by
+// `process_epoch_with_summary`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EpochTransitionSummary {
+    pub epoch: Epoch,
+    // Sum of the net balance increases across validators
+    pub total_rewards: Gwei,
+    // Sum of the net balance decreases across validators, including slashing penalties
+    pub total_penalties: Gwei,
+    // Sum of the slashed effective balances tracked in `state.slashings` before processing
+    pub total_slashings: Gwei,
+    pub finalized_checkpoint: Checkpoint,
+    // Validators whose activation epoch was set during processing
+    pub activations: Vec<ValidatorIndex>,
+    // Validators whose exit was initiated during processing
+    pub exits: Vec<ValidatorIndex>,
+}
+
+// The FFG fields of a state, as computed by `compute_justification_and_finalization`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct JustificationAndFinalization {
+    pub justification_bits: Bitvector<JUSTIFICATION_BITS_LENGTH>,
+    pub previous_justified_checkpoint: Checkpoint,
+    pub current_justified_checkpoint: Checkpoint,
+    pub finalized_checkpoint: Checkpoint,
+}
+
 pub fn get_matching_source_attestations<
     'a,
     const SLOTS_PER_HISTORICAL_ROOT: usize,
@@ -198,6 +226,41 @@ pub fn process_justification_and_finalization<
     if get_current_epoch(state, context) <= GENESIS_EPOCH + 1 {
         return Ok(())
     }
+    let (total_active_balance, previous_target_balance, current_target_balance) =
+        get_justification_balances(state, context)?;
+    weigh_justification_and_finalization(
+        state,
+        total_active_balance,
+        previous_target_balance,
+        current_target_balance,
+        context,
+    )
+}
+
+// Return the `(total_active_balance, previous_target_balance, current_target_balance)` weighed by
+// `process_justification_and_finalization`.
+pub fn get_justification_balances<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    context: &Context,
+) -> Result<(Gwei, Gwei, Gwei)> {
     let previous_attestations =
         get_matching_target_attestations(state, get_previous_epoch(state, context), context)?;
     let current_attestations =
@@ -205,13 +268,46 @@ pub fn process_justification_and_finalization<
     let total_active_balance = get_total_active_balance(state, context)?;
     let previous_target_balance = get_attesting_balance(state, previous_attestations, context)?;
     let current_target_balance = get_attesting_balance(state, current_attestations, context)?;
-    weigh_justification_and_finalization(
+    Ok((total_active_balance, previous_target_balance, current_target_balance))
+}
+
+// Return the `(current_justified_checkpoint, finalized_checkpoint)` the `state` would reach if
+// justification and finalization were processed at its current slot.
+pub fn get_unrealized_checkpoints<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    context: &Context,
+) -> Result<(Checkpoint, Checkpoint)> {
+    if get_current_epoch(state, context) <= GENESIS_EPOCH + 1 {
+        return Ok((state.current_justified_checkpoint.clone(), state.finalized_checkpoint.clone()))
+    }
+    let (total_active_balance, previous_target_balance, current_target_balance) =
+        get_justification_balances(state, context)?;
+    let ffg = compute_justification_and_finalization(
         state,
         total_active_balance,
         previous_target_balance,
         current_target_balance,
         context,
-    )
+    )?;
+    Ok((ffg.current_justified_checkpoint, ffg.finalized_checkpoint))
 }
 
 pub fn process_rewards_and_penalties<
@@ -241,10 +337,9 @@ pub fn process_rewards_and_penalties<
     let current_epoch = get_current_epoch(state, context);
     if current_epoch != GENESIS_EPOCH {
         let (rewards, penalties) = get_attestation_deltas(state, context)?;
-        for i in 0..state.validators.len() {
-            increase_balance(state, i, rewards[i]);
-            decrease_balance(state, i, penalties[i]);
-        }
+        crate::state_transition::update_balances(&mut state.balances, |i, balance| {
+            (balance + rewards[i]).saturating_sub(penalties[i])
+        });
     }
 
     Ok(())
@@ -429,6 +524,11 @@ pub fn process_effective_balance_updates<
             );
         }
     }
+    // the state is still in the same epoch, so drop the total computed before the update rather
+    // than letting later lookups store the new total under the same key
+    if let Some((epoch, root)) = get_total_active_balance_cache_key(state, context) {
+        context.total_active_balance_cache.invalidate(epoch, root);
+    }
 }
 
 pub fn process_slashings_reset<
@@ -606,50 +706,103 @@ pub fn weigh_justification_and_finalization<
     current_epoch_target_balance: Gwei,
     context: &Context,
 ) -> Result<()> {
+    let ffg = compute_justification_and_finalization(
+        state,
+        total_active_balance,
+        previous_epoch_target_balance,
+        current_epoch_target_balance,
+        context,
+    )?;
+    state.justification_bits = ffg.justification_bits;
+    state.previous_justified_checkpoint = ffg.previous_justified_checkpoint;
+    state.current_justified_checkpoint = ffg.current_justified_checkpoint;
+    state.finalized_checkpoint = ffg.finalized_checkpoint;
+    Ok(())
+}
+
+// Return the FFG fields `weigh_justification_and_finalization` would write to `state`.
+pub fn compute_justification_and_finalization<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    total_active_balance: Gwei,
+    previous_epoch_target_balance: Gwei,
+    current_epoch_target_balance: Gwei,
+    context: &Context,
+) -> Result<JustificationAndFinalization> {
     let previous_epoch = get_previous_epoch(state, context);
     let current_epoch = get_current_epoch(state, context);
-    let old_previous_justified_checkpoint = state.previous_justified_checkpoint.clone();
-    let old_current_justified_checkpoint = state.current_justified_checkpoint.clone();
+    let old_previous_justified_checkpoint = &state.previous_justified_checkpoint;
+    let old_current_justified_checkpoint = &state.current_justified_checkpoint;
+    let mut justification_bits = JustificationBits::from(state.justification_bits.clone());
+    let mut ffg = JustificationAndFinalization {
+        justification_bits: Default::default(),
+        previous_justified_checkpoint: state.current_justified_checkpoint.clone(),
+        current_justified_checkpoint: state.current_justified_checkpoint.clone(),
+        finalized_checkpoint: state.finalized_checkpoint.clone(),
+    };
 
     // Process justifications
-    state.previous_justified_checkpoint = state.current_justified_checkpoint.clone();
-    state.justification_bits.copy_within(..JUSTIFICATION_BITS_LENGTH - 1, 1);
-    state.justification_bits.set(0, false);
-    if previous_epoch_target_balance * 3 >= total_active_balance * 2 {
-        state.current_justified_checkpoint = Checkpoint {
+    let previous_epoch_justified = previous_epoch_target_balance * 3 >= total_active_balance * 2;
+    let current_epoch_justified = current_epoch_target_balance * 3 >= total_active_balance * 2;
+    justification_bits.shift_and_set(previous_epoch_justified, current_epoch_justified);
+    if previous_epoch_justified {
+        ffg.current_justified_checkpoint = Checkpoint {
             epoch: previous_epoch,
             root: *get_block_root(state, previous_epoch, context)?,
         };
-        state.justification_bits.set(1, true);
     }
-    if current_epoch_target_balance * 3 >= total_active_balance * 2 {
-        state.current_justified_checkpoint = Checkpoint {
+    if current_epoch_justified {
+        ffg.current_justified_checkpoint = Checkpoint {
             epoch: current_epoch,
             root: *get_block_root(state, current_epoch, context)?,
         };
-        state.justification_bits.set(0, true);
     }
 
     // Process finalizations
-    let bits = &state.justification_bits;
     // The 2nd/3rd/4th most recent epochs are justified, the 2nd using the 4th as source
-    if bits[1..4].all() && old_previous_justified_checkpoint.epoch + 3 == current_epoch {
-        state.finalized_checkpoint = old_previous_justified_checkpoint.clone();
+    if (1..4).all(|n| justification_bits.is_justified(n)) &&
+        old_previous_justified_checkpoint.epoch + 3 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_previous_justified_checkpoint.clone();
     }
     // The 2nd/3rd most recent epochs are justified, the 2nd using the 3rd as source
-    if bits[1..3].all() && old_previous_justified_checkpoint.epoch + 2 == current_epoch {
-        state.finalized_checkpoint = old_previous_justified_checkpoint;
+    if (1..3).all(|n| justification_bits.is_justified(n)) &&
+        old_previous_justified_checkpoint.epoch + 2 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_previous_justified_checkpoint.clone();
     }
     // The 1st/2nd/3rd most recent epochs are justified, the 1st using the 3rd as source
-    if bits[0..3].all() && old_current_justified_checkpoint.epoch + 2 == current_epoch {
-        state.finalized_checkpoint = old_current_justified_checkpoint.clone();
+    if (0..3).all(|n| justification_bits.is_justified(n)) &&
+        old_current_justified_checkpoint.epoch + 2 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_current_justified_checkpoint.clone();
     }
     // The 1st/2nd most recent epochs are justified, the 1st using the 2nd as source
-    if bits[0..2].all() && old_current_justified_checkpoint.epoch + 1 == current_epoch {
-        state.finalized_checkpoint = old_current_justified_checkpoint;
+    if (0..2).all(|n| justification_bits.is_justified(n)) &&
+        old_current_justified_checkpoint.epoch + 1 == current_epoch
+    {
+        ffg.finalized_checkpoint = old_current_justified_checkpoint.clone();
     }
 
-    Ok(())
+    ffg.justification_bits = justification_bits.into();
+    Ok(ffg)
 }
 
 pub fn get_base_reward<
@@ -1069,3 +1222,182 @@ pub fn process_epoch<
     process_participation_record_updates(state);
     Ok(())
 }
+
+// Run `process_epoch` and report the changes it made to balances, the registry and finality.
+pub fn process_epoch_with_summary<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    context: &Context,
+) -> Result<EpochTransitionSummary> {
+    let epoch = get_current_epoch(state, context);
+    let total_slashings = state.slashings.iter().sum();
+    let balances = state.balances.to_vec();
+    let registry_epochs = state
+        .validators
+        .iter()
+        .map(|validator| (validator.activation_epoch, validator.exit_epoch))
+        .collect::<Vec<_>>();
+
+    process_epoch(state, context)?;
+
+    let mut summary = EpochTransitionSummary {
+        epoch,
+        total_slashings,
+        finalized_checkpoint: state.finalized_checkpoint.clone(),
+        ..Default::default()
+    };
+    for (index, (&previous, &current)) in balances.iter().zip(state.balances.iter()).enumerate() {
+        if current > previous {
+            summary.total_rewards += current - previous;
+        } else {
+            summary.total_penalties += previous - current;
+        }
+        let (activation_epoch, exit_epoch) = registry_epochs[index];
+        let validator = &state.validators[index];
+        if activation_epoch == FAR_FUTURE_EPOCH && validator.activation_epoch != FAR_FUTURE_EPOCH {
+            summary.activations.push(index);
+        }
+        if exit_epoch == FAR_FUTURE_EPOCH && validator.exit_epoch != FAR_FUTURE_EPOCH {
+            summary.exits.push(index);
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        phase0::{
+            get_beacon_committee, get_committee_count_per_slot, test_utils::state_at,
+            AttestationData,
+        },
+        primitives::Root,
+    };
+
+    #[test]
+    fn test_total_active_balance_across_effective_balance_updates() {
+        let context = Context::for_minimal();
+        let max_effective_balance = context.max_effective_balance;
+        // the last slot of epoch 2, where its transition runs, with a root recorded for slot 15
+        let mut state = state_at(23, &context);
+        state.state_roots[15] = Root::try_from([1u8; 32].as_ref()).unwrap();
+        assert_eq!(get_total_active_balance(&state, &context).unwrap(), 64 * max_effective_balance);
+        assert_eq!(context.total_active_balance_cache.len(), 1);
+        let before_update = state.clone();
+
+        state.balances[0] = 0;
+        process_effective_balance_updates(&mut state, &context);
+        assert_eq!(state.validators[0].effective_balance, 0);
+        assert!(context.total_active_balance_cache.is_empty());
+        assert_eq!(get_total_active_balance(&state, &context).unwrap(), 63 * max_effective_balance);
+
+        // the updated total is not stored under the key shared with the state before the update
+        assert!(context.total_active_balance_cache.is_empty());
+        assert_eq!(
+            get_total_active_balance(&before_update, &context).unwrap(),
+            64 * max_effective_balance
+        );
+    }
+
+    #[test]
+    fn test_get_unrealized_checkpoints() {
+        let context = Context::for_minimal();
+        // the last slot of epoch 3, before any attestations
+        let mut state = state_at(31, &context);
+        let genesis_checkpoint = Checkpoint::default();
+        assert_eq!(
+            get_unrealized_checkpoints(&state, &context).unwrap(),
+            (genesis_checkpoint.clone(), genesis_checkpoint.clone())
+        );
+
+        // every committee of epoch 2 attests to its target
+        let target = Checkpoint { epoch: 2, root: *get_block_root(&state, 2, &context).unwrap() };
+        for slot in 16..24 {
+            for index in 0..get_committee_count_per_slot(&state, 2, &context) {
+                let committee = get_beacon_committee(&state, slot, index, &context).unwrap();
+                let mut attestation = PendingAttestation {
+                    data: AttestationData {
+                        slot,
+                        index,
+                        target: target.clone(),
+                        ..Default::default()
+                    },
+                    inclusion_delay: 1,
+                    ..Default::default()
+                };
+                for _ in committee {
+                    attestation.aggregation_bits.push(true);
+                }
+                state.previous_epoch_attestations.push(attestation);
+            }
+        }
+        let before = state.clone();
+        let (justified, finalized) = get_unrealized_checkpoints(&state, &context).unwrap();
+        assert_eq!(justified, target);
+        assert_eq!(finalized, genesis_checkpoint);
+        assert_eq!(state, before);
+
+        process_justification_and_finalization(&mut state, &context).unwrap();
+        assert_eq!(state.current_justified_checkpoint, justified);
+        assert_eq!(state.finalized_checkpoint, finalized);
+        assert_ne!(state, before);
+    }
+
+    #[test]
+    fn test_process_epoch_with_summary() {
+        let context = Context::for_minimal();
+        // the last slot of epoch 2, where its transition runs
+        let mut state = state_at(23, &context);
+        // validator 0 is ejected and a new validator 64 is activated
+        state.validators[0].effective_balance = context.ejection_balance;
+        let mut validator = state.validators[1].clone();
+        validator.activation_epoch = FAR_FUTURE_EPOCH;
+        state.validators.push(validator);
+        state.balances.push(context.max_effective_balance);
+        state.slashings[0] = context.max_effective_balance;
+        let balances = state.balances.to_vec();
+
+        let mut expected = state.clone();
+        process_epoch(&mut expected, &context).unwrap();
+        let summary = process_epoch_with_summary(&mut state, &context).unwrap();
+        assert_eq!(state, expected);
+
+        let (mut total_rewards, mut total_penalties) = (0, 0);
+        for (&previous, &current) in balances.iter().zip(state.balances.iter()) {
+            total_rewards += current.saturating_sub(previous);
+            total_penalties += previous.saturating_sub(current);
+        }
+        // nobody attested in epoch 1, so every active validator was penalized
+        assert!(total_penalties > 0);
+        assert_eq!(
+            summary,
+            EpochTransitionSummary {
+                epoch: 2,
+                total_rewards,
+                total_penalties,
+                total_slashings: context.max_effective_balance,
+                finalized_checkpoint: Checkpoint::default(),
+                activations: vec![64],
+                exits: vec![0],
+            }
+        );
+    }
+}
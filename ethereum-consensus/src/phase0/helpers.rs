@@ -1,12 +1,17 @@
 use crate::{
-    crypto::{fast_aggregate_verify, hash, verify_signature},
+    crypto::{hash, verify_signature},
     error::{
         invalid_operation_error, InvalidAttestation, InvalidIndexedAttestation, InvalidOperation,
+        InvalidProposerSlashing, InvalidVoluntaryExit,
     },
     phase0::{
         beacon_block::SignedBeaconBlock,
         beacon_state::{BeaconState, ForkData},
-        operations::{Attestation, AttestationData, IndexedAttestation},
+        constants::JUSTIFICATION_BITS_LENGTH,
+        operations::{
+            Attestation, AttestationData, AttesterSlashing, IndexedAttestation, ProposerSlashing,
+            SignedVoluntaryExit,
+        },
         validator::Validator,
     },
     primitives::{
@@ -120,14 +125,14 @@ pub fn is_valid_indexed_attestation<
         )))
     }
 
-    let mut public_keys = vec![];
-    for &index in &attesting_indices[..] {
-        let public_key = state.validators.get(index).map(|v| &v.public_key).ok_or_else(|| {
+    let mut signers = Vec::with_capacity(attesting_indices.len());
+    for &index in attesting_indices.iter() {
+        let validator = state.validators.get(index).ok_or_else(|| {
             invalid_operation_error(InvalidOperation::IndexedAttestation(
                 InvalidIndexedAttestation::InvalidIndex(index),
             ))
         })?;
-        public_keys.push(public_key);
+        signers.push(&validator.public_key);
     }
 
     let domain = get_domain(
@@ -137,8 +142,28 @@ pub fn is_valid_indexed_attestation<
         context,
     )?;
     let signing_root = compute_signing_root(&mut indexed_attestation.data, domain)?;
-    fast_aggregate_verify(&public_keys, signing_root.as_ref(), &indexed_attestation.signature)
-        .map_err(Into::into)
+    if context.verified_signature_cache.contains(
+        &signers,
+        &signing_root,
+        &indexed_attestation.signature,
+    ) {
+        return Ok(())
+    }
+
+    // only decompress the keys of a signature that still has to be verified
+    let public_keys = attesting_indices
+        .iter()
+        .map(|&index| {
+            context.decompressed_pubkey_cache.get(&state.validators, index).expect("index is valid")
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let public_keys = public_keys.iter().collect::<Vec<_>>();
+    crate::crypto::fast_aggregate_verify_decompressed(
+        &public_keys,
+        signing_root.as_ref(),
+        &indexed_attestation.signature,
+    )
+    .map_err(Into::into)
 }
 
 pub fn verify_block_signature<
@@ -188,6 +213,220 @@ pub fn verify_block_signature<
     verify_signature(public_key, signing_root.as_ref(), &signed_block.signature).map_err(Into::into)
 }
 
+// Add the signatures of the slashings and attestations in a block to `batch`.
+pub fn collect_operation_signature_sets<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &'a BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    proposer_slashings: &'a [ProposerSlashing],
+    attester_slashings: &'a [AttesterSlashing<MAX_VALIDATORS_PER_COMMITTEE>],
+    attestations: &'a [Attestation<MAX_VALIDATORS_PER_COMMITTEE>],
+    batch: &mut crate::signing::SignatureBatch<'a>,
+    context: &Context,
+) -> Result<()> {
+    for proposer_slashing in proposer_slashings {
+        let header_1 = &proposer_slashing.signed_header_1.message;
+        let public_key =
+            state.validators.get(header_1.proposer_index).map(|v| &v.public_key).ok_or_else(
+                || {
+                    invalid_operation_error(InvalidOperation::ProposerSlashing(
+                        InvalidProposerSlashing::InvalidIndex(header_1.proposer_index),
+                    ))
+                },
+            )?;
+        let epoch = compute_epoch_at_slot(header_1.slot, context);
+        let domain = get_domain(state, DomainType::BeaconProposer, Some(epoch), context)?;
+        for signed_header in
+            [&proposer_slashing.signed_header_1, &proposer_slashing.signed_header_2]
+        {
+            let signing_root = compute_signing_root(&mut signed_header.message.clone(), domain)?;
+            let error = invalid_operation_error(InvalidOperation::ProposerSlashing(
+                InvalidProposerSlashing::InvalidSignature(signed_header.signature.clone()),
+            ));
+            batch.push(vec![public_key], signing_root, &signed_header.signature, error);
+        }
+    }
+
+    let mut signed_attestations =
+        Vec::with_capacity(2 * attester_slashings.len() + attestations.len());
+    for attester_slashing in attester_slashings {
+        for indexed_attestation in
+            [&attester_slashing.attestation_1, &attester_slashing.attestation_2]
+        {
+            signed_attestations.push((
+                indexed_attestation.attesting_indices.to_vec(),
+                &indexed_attestation.data,
+                &indexed_attestation.signature,
+            ));
+        }
+    }
+    for attestation in attestations {
+        let indexed_attestation = get_indexed_attestation(state, attestation, context)?;
+        signed_attestations.push((
+            indexed_attestation.attesting_indices.to_vec(),
+            &attestation.data,
+            &attestation.signature,
+        ));
+    }
+    for (attesting_indices, data, signature) in signed_attestations {
+        let public_keys = attesting_indices
+            .into_iter()
+            .map(|index| {
+                state.validators.get(index).map(|v| &v.public_key).ok_or_else(|| {
+                    invalid_operation_error(InvalidOperation::IndexedAttestation(
+                        InvalidIndexedAttestation::InvalidIndex(index),
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let domain =
+            get_domain(state, DomainType::BeaconAttester, Some(data.target.epoch), context)?;
+        let signing_root = compute_signing_root(&mut data.clone(), domain)?;
+        let error = crate::crypto::Error::InvalidSignature.into();
+        batch.push(public_keys, signing_root, signature, error);
+    }
+    Ok(())
+}
+
+// Add the signatures of the voluntary exits in a block to `batch`.
+pub fn collect_voluntary_exit_signature_sets<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &'a BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    voluntary_exits: &'a [SignedVoluntaryExit],
+    batch: &mut crate::signing::SignatureBatch<'a>,
+    context: &Context,
+) -> Result<()> {
+    for signed_voluntary_exit in voluntary_exits {
+        let voluntary_exit = &signed_voluntary_exit.message;
+        let public_key = state
+            .validators
+            .get(voluntary_exit.validator_index)
+            .map(|v| &v.public_key)
+            .ok_or_else(|| {
+                invalid_operation_error(InvalidOperation::VoluntaryExit(
+                    InvalidVoluntaryExit::InvalidIndex(voluntary_exit.validator_index),
+                ))
+            })?;
+        let domain =
+            get_domain(state, DomainType::VoluntaryExit, Some(voluntary_exit.epoch), context)?;
+        let signing_root = compute_signing_root(&mut voluntary_exit.clone(), domain)?;
+        let error = invalid_operation_error(InvalidOperation::VoluntaryExit(
+            InvalidVoluntaryExit::InvalidSignature(signed_voluntary_exit.signature.clone()),
+        ));
+        batch.push(vec![public_key], signing_root, &signed_voluntary_exit.signature, error);
+    }
+    Ok(())
+}
+
+// Verify every signature in `signed_block` that `process_block` would check, using a single
+// batched verification. Deposits are skipped as an invalid deposit signature does not
+// invalidate the block. `state` must already be advanced to the slot of the block. Valid
+// signatures are recorded in `context.verified_signature_cache` so that `process_block` does
+// not check them again.
+pub fn verify_block_signatures<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+    >,
+    context: &Context,
+) -> Result<()> {
+    let proposer_index = signed_block.message.proposer_index;
+    let proposer = state
+        .validators
+        .get(proposer_index)
+        .ok_or(Error::OutOfBounds { requested: proposer_index, bound: state.validators.len() })?;
+    let domain = get_domain(state, DomainType::BeaconProposer, None, context)?;
+    let signing_root = compute_signing_root(&mut signed_block.message, domain)?;
+
+    let signed_block = &*signed_block;
+    let body = &signed_block.message.body;
+    let mut batch = crate::signing::SignatureBatch::default();
+    let error = crate::crypto::Error::InvalidSignature.into();
+    batch.push(vec![&proposer.public_key], signing_root, &signed_block.signature, error);
+
+    let mut epoch = get_current_epoch(state, context);
+    let randao_proposer = &state.validators[get_beacon_proposer_index(state, context)?];
+    let domain = get_domain(state, DomainType::Randao, Some(epoch), context)?;
+    let signing_root = compute_signing_root(&mut epoch, domain)?;
+    let error = invalid_operation_error(InvalidOperation::Randao(body.randao_reveal.clone()));
+    batch.push(vec![&randao_proposer.public_key], signing_root, &body.randao_reveal, error);
+
+    collect_operation_signature_sets(
+        state,
+        &body.proposer_slashings,
+        &body.attester_slashings,
+        &body.attestations,
+        &mut batch,
+        context,
+    )?;
+    collect_voluntary_exit_signature_sets(state, &body.voluntary_exits, &mut batch, context)?;
+
+    batch.verify(&context.verified_signature_cache)
+}
+
 pub fn get_domain<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -283,6 +522,105 @@ pub fn compute_shuffled_index(
     Ok(index)
 }
 
+// Return the pivot of each shuffling round for `seed` over `index_count` indices, so that
+// shuffling several indices with the same seed hashes the pivots only once.
+pub fn compute_shuffling_pivots(
+    seed: &Bytes32,
+    index_count: usize,
+    context: &Context,
+) -> Vec<usize> {
+    let mut pivot_input = [0u8; 33];
+    pivot_input[..32].copy_from_slice(seed.as_ref());
+    (0..context.shuffle_round_count)
+        .map(|current_round| {
+            pivot_input[32] = current_round as u8;
+            let pivot_bytes: [u8; 8] = hash(pivot_input).as_ref()[..8].try_into().unwrap();
+            (u64::from_le_bytes(pivot_bytes) as usize) % index_count
+        })
+        .collect()
+}
+
+// `compute_shuffled_index` with the `pivots` returned by `compute_shuffling_pivots`.
+pub fn compute_shuffled_index_with_pivots(
+    mut index: usize,
+    index_count: usize,
+    seed: &Bytes32,
+    pivots: &[usize],
+) -> Result<usize> {
+    if index >= index_count {
+        return Err(Error::InvalidShufflingIndex { index, total: index_count })
+    }
+
+    let mut source_input = [0u8; 37];
+    source_input[..32].copy_from_slice(seed.as_ref());
+    for (current_round, &pivot) in pivots.iter().enumerate() {
+        let flip = (pivot + index_count - index) % index_count;
+        let position = cmp::max(index, flip);
+        let position_bytes: [u8; 4] = ((position / 256) as u32).to_le_bytes();
+
+        source_input[32] = current_round as u8;
+        source_input[33..].copy_from_slice(&position_bytes);
+        let source = hash(source_input);
+
+        let byte = source.as_ref()[(position % 256) / 8];
+        let bit = (byte >> (position % 8)) % 2;
+        index = if bit != 0 { flip } else { index };
+    }
+
+    Ok(index)
+}
+
+// Return `indices` permuted so that the element at position `i` is
+// `indices[compute_shuffled_index(i, indices.len(), seed, context)]`.
+// Shuffles the whole list with one swap-or-not pass per round instead of
+// shuffling each position independently.
+pub fn compute_shuffled_indices(
+    indices: &[ValidatorIndex],
+    seed: &Bytes32,
+    context: &Context,
+) -> Vec<ValidatorIndex> {
+    let mut shuffled = indices.to_vec();
+    let index_count = shuffled.len();
+    if index_count <= 1 {
+        return shuffled
+    }
+
+    let mut pivot_input = [0u8; 33];
+    pivot_input[..32].copy_from_slice(seed.as_ref());
+    let mut source_input = [0u8; 37];
+    source_input[..32].copy_from_slice(seed.as_ref());
+    // `compute_shuffled_index` applies rounds to an index in increasing order so the list is
+    // permuted by the rounds in decreasing order
+    for current_round in (0..context.shuffle_round_count).rev() {
+        pivot_input[32] = current_round as u8;
+        let pivot_bytes: [u8; 8] = hash(pivot_input).as_ref()[..8].try_into().unwrap();
+        let pivot = (u64::from_le_bytes(pivot_bytes) as usize) % index_count;
+
+        source_input[32] = current_round as u8;
+        let mut source_block = None;
+        let mut source = Bytes32::default();
+        for index in 0..index_count {
+            let flip = (pivot + index_count - index) % index_count;
+            // visit each pair once, from its lower position so `flip` is `max(index, flip)`
+            if flip <= index {
+                continue
+            }
+            let position = flip;
+            if source_block != Some(position / 256) {
+                let position_bytes: [u8; 4] = ((position / 256) as u32).to_le_bytes();
+                source_input[33..].copy_from_slice(&position_bytes);
+                source = hash(source_input);
+                source_block = Some(position / 256);
+            }
+            let byte = source.as_ref()[(position % 256) / 8];
+            if (byte >> (position % 8)) & 1 != 0 {
+                shuffled.swap(index, flip);
+            }
+        }
+    }
+    shuffled
+}
+
 pub fn compute_proposer_index<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -332,6 +670,62 @@ pub fn compute_proposer_index<
     }
 }
 
+// `compute_proposer_index` with the shuffling `pivots` of `seed` precomputed and the random bytes
+// hashed once for each run of 32 candidates rather than once per candidate.
+pub fn compute_proposer_index_with_pivots<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    indices: &[ValidatorIndex],
+    seed: &Bytes32,
+    pivots: &[usize],
+    context: &Context,
+) -> Result<ValidatorIndex> {
+    if indices.is_empty() {
+        return Err(Error::CollectionCannotBeEmpty)
+    }
+    let max_byte = u8::MAX as u64;
+    let mut i = 0;
+    let total = indices.len();
+
+    let mut hash_input = [0u8; 40];
+    hash_input[..32].copy_from_slice(seed.as_ref());
+    let mut random_bytes = Bytes32::default();
+    loop {
+        let shuffled_index = compute_shuffled_index_with_pivots(i % total, total, seed, pivots)?;
+        let candidate_index = indices[shuffled_index];
+
+        if i % 32 == 0 {
+            let i_bytes: [u8; 8] = (i / 32).to_le_bytes();
+            hash_input[32..].copy_from_slice(&i_bytes);
+            random_bytes = hash(hash_input);
+        }
+        let random_byte = random_bytes.as_ref()[i % 32] as u64;
+
+        let effective_balance = state.validators[candidate_index].effective_balance;
+        if effective_balance * max_byte >= context.max_effective_balance * random_byte {
+            return Ok(candidate_index)
+        }
+        i += 1
+    }
+}
+
 pub fn compute_committee(
     indices: &[ValidatorIndex],
     seed: &Bytes32,
@@ -667,9 +1061,57 @@ pub fn get_beacon_committee<
     let committees_per_slot = get_committee_count_per_slot(state, epoch, context);
     let indices = get_active_validator_indices(state, epoch);
     let seed = get_seed(state, epoch, DomainType::BeaconAttester, context);
-    let index = (slot % context.slots_per_epoch) * committees_per_slot as u64 + index as u64;
-    let count = committees_per_slot as u64 * context.slots_per_epoch;
-    compute_committee(&indices, &seed, index as usize, count as usize, context)
+    let index = (slot % context.slots_per_epoch) as usize * committees_per_slot + index;
+    let count = committees_per_slot * context.slots_per_epoch as usize;
+    let shuffling = context.committee_cache.get_or_compute(epoch, &seed, indices, context)?;
+    let start = (shuffling.len() * index) / count;
+    let end = shuffling.len() * (index + 1) / count;
+    Ok(shuffling[start..end].to_vec())
+}
+
+// Return the committee assignment of `validator_index` in `epoch` as
+// `(committee, committee_index, slot)`, or `None` if the validator is not assigned.
+// `epoch` may be at most the next epoch.
+pub fn get_committee_assignment<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    epoch: Epoch,
+    validator_index: ValidatorIndex,
+    context: &Context,
+) -> Result<Option<(Vec<ValidatorIndex>, CommitteeIndex, Slot)>> {
+    let next_epoch = get_current_epoch(state, context) + 1;
+    if epoch > next_epoch {
+        return Err(Error::EpochAfterNextEpoch { requested: epoch, next: next_epoch })
+    }
+
+    let start_slot = compute_start_slot_at_epoch(epoch, context);
+    let committee_count_per_slot = get_committee_count_per_slot(state, epoch, context);
+    for slot in start_slot..start_slot + context.slots_per_epoch {
+        for index in 0..committee_count_per_slot {
+            let committee = get_beacon_committee(state, slot, index, context)?;
+            if committee.contains(&validator_index) {
+                return Ok(Some((committee, index, slot)))
+            }
+        }
+    }
+    Ok(None)
 }
 
 pub fn get_beacon_proposer_index<
@@ -704,6 +1146,54 @@ pub fn get_beacon_proposer_index<
     compute_proposer_index(state, &indices, &seed, context)
 }
 
+// Return the proposer index for every slot in `epoch`, computing the epoch seed and active
+// validator set only once. `epoch` must be the current or next epoch, as the proposers are
+// picked with the current effective balances.
+pub fn get_beacon_proposer_indices<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    epoch: Epoch,
+    context: &Context,
+) -> Result<Vec<ValidatorIndex>> {
+    let current_epoch = get_current_epoch(state, context);
+    if epoch != current_epoch && epoch != current_epoch + 1 {
+        return Err(Error::EpochNotCurrentOrNext { requested: epoch, current: current_epoch })
+    }
+    let epoch_seed = get_seed(state, epoch, DomainType::BeaconProposer, context);
+    let indices = get_active_validator_indices(state, epoch);
+    if indices.is_empty() {
+        return Err(Error::CollectionCannotBeEmpty)
+    }
+    let start_slot = compute_start_slot_at_epoch(epoch, context);
+    let mut input = [0u8; 40];
+    input[..32].copy_from_slice(epoch_seed.as_ref());
+    (start_slot..start_slot + context.slots_per_epoch)
+        .map(|slot| {
+            input[32..40].copy_from_slice(&slot.to_le_bytes());
+            let seed = hash(input);
+            let pivots = compute_shuffling_pivots(&seed, indices.len(), context);
+            compute_proposer_index_with_pivots(state, &indices, &seed, &pivots, context)
+        })
+        .collect()
+}
+
 pub fn get_total_balance<
     const SLOTS_PER_HISTORICAL_ROOT: usize,
     const HISTORICAL_ROOTS_LIMIT: usize,
@@ -756,8 +1246,53 @@ pub fn get_total_active_balance<
     >,
     context: &Context,
 ) -> Result<Gwei> {
+    let key = get_total_active_balance_cache_key(state, context);
+    if let Some(total) =
+        key.and_then(|(epoch, root)| context.total_active_balance_cache.get(epoch, &root))
+    {
+        return Ok(total)
+    }
+
     let indices = get_active_validator_indices(state, get_current_epoch(state, context));
-    get_total_balance(state, &HashSet::from_iter(indices), context)
+    let total = get_total_balance(state, &HashSet::from_iter(indices), context)?;
+    if let Some((epoch, root)) = key {
+        context.total_active_balance_cache.insert(epoch, root, total);
+    }
+    Ok(total)
+}
+
+// The key of the total active balance of `state` in `context.total_active_balance_cache`: the
+// current epoch and the state root at the end of the previous epoch. Synthetic states without a
+// recorded root, and states in the genesis epoch, are never cached.
+pub fn get_total_active_balance_cache_key<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    context: &Context,
+) -> Option<(Epoch, Root)> {
+    let epoch = get_current_epoch(state, context);
+    if epoch == GENESIS_EPOCH {
+        return None
+    }
+    let slot = compute_start_slot_at_epoch(epoch, context) - 1;
+    let root = state.state_roots[(slot % context.slots_per_historical_root) as usize];
+    (root != Root::default()).then_some((epoch, root))
 }
 
 pub fn get_indexed_attestation<
@@ -1034,3 +1569,211 @@ pub fn get_eligible_validator_indices<
         }
     })
 }
+
+// Named access to `BeaconState.justification_bits`, where bit `n` records whether the
+// epoch `n` epochs before the current epoch is justified.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct JustificationBits(Bitvector<JUSTIFICATION_BITS_LENGTH>);
+
+impl JustificationBits {
+    pub fn is_justified(&self, n_epochs_ago: usize) -> bool {
+        n_epochs_ago < JUSTIFICATION_BITS_LENGTH && self.0[n_epochs_ago]
+    }
+
+    // Move the window forward by one epoch, dropping the oldest bit, then mark the
+    // previous and/or current epoch as justified.
+    pub fn shift_and_set(&mut self, previous_epoch_justified: bool, current_epoch_justified: bool) {
+        self.0.copy_within(..JUSTIFICATION_BITS_LENGTH - 1, 1);
+        self.0.set(0, false);
+        if previous_epoch_justified {
+            self.0.set(1, true);
+        }
+        if current_epoch_justified {
+            self.0.set(0, true);
+        }
+    }
+}
+
+impl From<Bitvector<JUSTIFICATION_BITS_LENGTH>> for JustificationBits {
+    fn from(bits: Bitvector<JUSTIFICATION_BITS_LENGTH>) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<JustificationBits> for Bitvector<JUSTIFICATION_BITS_LENGTH> {
+    fn from(bits: JustificationBits) -> Self {
+        bits.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::InvalidBlock,
+        phase0::{
+            mainnet,
+            test_utils::{attestation, state_at, VALIDATOR_COUNT},
+        },
+    };
+
+    #[test]
+    fn test_get_committee_assignment() {
+        let context = Context::for_minimal();
+        let state = state_at(3, &context);
+        for epoch in [0, 1] {
+            let start_slot = compute_start_slot_at_epoch(epoch, &context);
+            let committees_per_slot = get_committee_count_per_slot(&state, epoch, &context);
+            for validator_index in [0, 17, VALIDATOR_COUNT - 1] {
+                let (committee, index, slot) =
+                    get_committee_assignment(&state, epoch, validator_index, &context)
+                        .unwrap()
+                        .unwrap();
+                assert_eq!(committee, get_beacon_committee(&state, slot, index, &context).unwrap());
+                // the only committee of the epoch with the validator
+                let mut assignments = vec![];
+                for slot in start_slot..start_slot + context.slots_per_epoch {
+                    for index in 0..committees_per_slot {
+                        let committee =
+                            get_beacon_committee(&state, slot, index, &context).unwrap();
+                        if committee.contains(&validator_index) {
+                            assignments.push((slot, index));
+                        }
+                    }
+                }
+                assert_eq!(assignments, vec![(slot, index)]);
+            }
+        }
+
+        assert_eq!(get_committee_assignment(&state, 0, VALIDATOR_COUNT, &context).unwrap(), None);
+        assert!(matches!(
+            get_committee_assignment(&state, 2, 0, &context),
+            Err(Error::EpochAfterNextEpoch { requested: 2, next: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_compute_shuffled_indices() {
+        let context = Context::for_minimal();
+        let seed = Bytes32::try_from([3u8; 32].as_ref()).unwrap();
+        for count in [0, 1, 2, 3, 17, 256, 300, 513] {
+            let indices = (0..count).map(|i| i * 3 + 1).collect::<Vec<_>>();
+            let expected = (0..count)
+                .map(|i| indices[compute_shuffled_index(i, count, &seed, &context).unwrap()])
+                .collect::<Vec<_>>();
+            assert_eq!(compute_shuffled_indices(&indices, &seed, &context), expected);
+        }
+    }
+
+    #[test]
+    fn test_shuffled_index_with_pivots() {
+        let context = Context::for_minimal();
+        let seed = Bytes32::try_from([5u8; 32].as_ref()).unwrap();
+        for count in [1, 2, 300] {
+            let pivots = compute_shuffling_pivots(&seed, count, &context);
+            for index in 0..count {
+                assert_eq!(
+                    compute_shuffled_index_with_pivots(index, count, &seed, &pivots).unwrap(),
+                    compute_shuffled_index(index, count, &seed, &context).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_proposer_indices_match_proposer_index() {
+        let context = Context::for_mainnet();
+        let mut state = mainnet::BeaconState::default();
+        for i in 0..64 {
+            let validator = Validator {
+                // a lower effective balance makes some candidates fail the balance check
+                effective_balance: context.max_effective_balance / (1 + i % 4),
+                exit_epoch: FAR_FUTURE_EPOCH,
+                ..Default::default()
+            };
+            state.validators.push(validator);
+            state.balances.push(context.max_effective_balance);
+        }
+        state.slot = 3 * context.slots_per_epoch;
+        let proposers = get_beacon_proposer_indices(&state, 3, &context).unwrap();
+        for (offset, &proposer) in proposers.iter().enumerate() {
+            state.slot = 3 * context.slots_per_epoch + offset as u64;
+            assert_eq!(get_beacon_proposer_index(&state, &context).unwrap(), proposer);
+        }
+
+        state.slot = 3 * context.slots_per_epoch;
+        assert!(get_beacon_proposer_indices(&state, 4, &context).is_ok());
+        for epoch in [2, 5] {
+            assert!(matches!(
+                get_beacon_proposer_indices(&state, epoch, &context),
+                Err(Error::EpochNotCurrentOrNext { requested, current: 3 }) if requested == epoch
+            ));
+        }
+    }
+
+    #[test]
+    fn test_justification_bits() {
+        let mut bits = JustificationBits::default();
+        assert!((0..JUSTIFICATION_BITS_LENGTH).all(|n| !bits.is_justified(n)));
+
+        bits.shift_and_set(true, false);
+        assert!(!bits.is_justified(0));
+        assert!(bits.is_justified(1));
+
+        bits.shift_and_set(false, true);
+        assert!(bits.is_justified(0));
+        assert!(!bits.is_justified(1));
+        assert!(bits.is_justified(2));
+
+        bits.shift_and_set(true, true);
+        assert!((0..4).all(|n| bits.is_justified(n) == [true, true, false, true][n]));
+        // the oldest bit is dropped
+        bits.shift_and_set(false, false);
+        assert!((0..4).all(|n| bits.is_justified(n) == [false, true, true, false][n]));
+        assert!(!bits.is_justified(JUSTIFICATION_BITS_LENGTH));
+
+        let raw = Bitvector::<JUSTIFICATION_BITS_LENGTH>::from(bits.clone());
+        assert_eq!((0..4).map(|n| raw[n]).collect::<Vec<_>>(), vec![false, true, true, false]);
+        assert_eq!(JustificationBits::from(raw), bits);
+    }
+
+    #[test]
+    fn test_is_valid_indexed_attestation_uses_verified_signatures() {
+        let context = Context::for_minimal();
+        let state = state_at(3, &context);
+        let attestation = attestation(&state, 2, 0, &context);
+        let mut indexed_attestation =
+            get_indexed_attestation(&state, &attestation, &context).unwrap();
+        let signer_count = indexed_attestation.attesting_indices.len();
+        is_valid_indexed_attestation(&state, &mut indexed_attestation, &context).unwrap();
+        assert_eq!(context.decompressed_pubkey_cache.len(), signer_count);
+
+        // an already verified signature needs no decompressed keys
+        let context = Context::for_minimal();
+        let signers = indexed_attestation
+            .attesting_indices
+            .iter()
+            .map(|&index| &state.validators[index].public_key)
+            .collect::<Vec<_>>();
+        let domain = get_domain(&state, DomainType::BeaconAttester, Some(0), &context).unwrap();
+        let signing_root = compute_signing_root(&mut indexed_attestation.data, domain).unwrap();
+        context.verified_signature_cache.insert(
+            &signers,
+            &signing_root,
+            &indexed_attestation.signature,
+        );
+        is_valid_indexed_attestation(&state, &mut indexed_attestation, &context).unwrap();
+        assert!(context.decompressed_pubkey_cache.is_empty());
+
+        indexed_attestation.attesting_indices.push(VALIDATOR_COUNT);
+        match is_valid_indexed_attestation(&state, &mut indexed_attestation, &context) {
+            Err(Error::InvalidBlock(err)) => assert!(matches!(
+                *err,
+                InvalidBlock::InvalidOperation(InvalidOperation::IndexedAttestation(
+                    InvalidIndexedAttestation::InvalidIndex(VALIDATOR_COUNT)
+                ))
+            )),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
@@ -14,8 +14,10 @@ pub mod presets;
 pub mod slot_processing;
 pub mod spec;
 pub mod state_transition;
+#[cfg(test)]
+pub(crate) mod test_utils;
 pub mod validator;
 
 pub use spec::*;
 
-pub use presets::{mainnet, minimal, Preset};
+pub use presets::{gnosis, mainnet, minimal, Preset};
@@ -1,11 +1,20 @@
 use crate::{
-    phase0::{beacon_block::SignedBeaconBlockHeader, constants::DEPOSIT_CONTRACT_TREE_DEPTH},
+    error::{
+        invalid_operation_error, InvalidAttesterSlashing, InvalidOperation, InvalidProposerSlashing,
+    },
+    phase0::{
+        beacon_block::SignedBeaconBlockHeader, constants::DEPOSIT_CONTRACT_TREE_DEPTH,
+        helpers::is_slashable_attestation_data,
+    },
     primitives::{
-        BlsPublicKey, BlsSignature, Bytes32, CommitteeIndex, Epoch, Gwei, Hash32, Root, Slot,
-        ValidatorIndex,
+        BlsPublicKey, BlsSignature, Bytes32, CommitteeIndex, Domain, Epoch, Gwei, Hash32, Root,
+        Slot, ValidatorIndex,
     },
+    signing::verify_signed_data,
     ssz::prelude::*,
+    Error,
 };
+use std::collections::HashSet;
 
 #[derive(
     Default, Clone, Debug, SimpleSerialize, PartialEq, Eq, serde::Serialize, serde::Deserialize,
@@ -39,6 +48,13 @@ pub struct IndexedAttestation<const MAX_VALIDATORS_PER_COMMITTEE: usize> {
     pub signature: BlsSignature,
 }
 
+// An attestation that passed `validate_attestations`, with its attesting indices resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidAttestation<const MAX_VALIDATORS_PER_COMMITTEE: usize> {
+    pub attestation: Attestation<MAX_VALIDATORS_PER_COMMITTEE>,
+    pub indexed_attestation: IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>,
+}
+
 #[derive(
     Default, Debug, SimpleSerialize, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize,
 )]
@@ -99,6 +115,52 @@ pub struct ProposerSlashing {
     pub signed_header_2: SignedBeaconBlockHeader,
 }
 
+impl ProposerSlashing {
+    // Pair two conflicting headers from the same proposer, checking both signatures
+    // against `public_key` under `domain` (the `DomainType::BeaconProposer` domain at
+    // the epoch of the headers).
+    pub fn try_from_headers(
+        mut signed_header_1: SignedBeaconBlockHeader,
+        mut signed_header_2: SignedBeaconBlockHeader,
+        public_key: &BlsPublicKey,
+        domain: Domain,
+    ) -> Result<Self, Error> {
+        let header_1 = &signed_header_1.message;
+        let header_2 = &signed_header_2.message;
+        if header_1.slot != header_2.slot {
+            return Err(invalid_operation_error(InvalidOperation::ProposerSlashing(
+                InvalidProposerSlashing::SlotMismatch(header_1.slot, header_2.slot),
+            )))
+        }
+        if header_1.proposer_index != header_2.proposer_index {
+            return Err(invalid_operation_error(InvalidOperation::ProposerSlashing(
+                InvalidProposerSlashing::ProposerMismatch(
+                    header_1.proposer_index,
+                    header_2.proposer_index,
+                ),
+            )))
+        }
+        if header_1 == header_2 {
+            return Err(invalid_operation_error(InvalidOperation::ProposerSlashing(
+                InvalidProposerSlashing::HeadersAreEqual(header_1.clone()),
+            )))
+        }
+
+        for signed_header in [&mut signed_header_1, &mut signed_header_2] {
+            let signature = &signed_header.signature;
+            if verify_signed_data(&mut signed_header.message, signature, public_key, domain)
+                .is_err()
+            {
+                return Err(invalid_operation_error(InvalidOperation::ProposerSlashing(
+                    InvalidProposerSlashing::InvalidSignature(signature.clone()),
+                )))
+            }
+        }
+
+        Ok(Self { signed_header_1, signed_header_2 })
+    }
+}
+
 #[derive(
     Default, Debug, SimpleSerialize, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize,
 )]
@@ -107,6 +169,58 @@ pub struct AttesterSlashing<const MAX_VALIDATORS_PER_COMMITTEE: usize> {
     pub attestation_2: IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>,
 }
 
+impl<const MAX_VALIDATORS_PER_COMMITTEE: usize> AttesterSlashing<MAX_VALIDATORS_PER_COMMITTEE> {
+    // Sorted indices attesting in both attestations
+    pub fn slashable_indices(&self) -> Vec<ValidatorIndex> {
+        let indices_1: HashSet<ValidatorIndex> =
+            HashSet::from_iter(self.attestation_1.attesting_indices.iter().cloned());
+        let mut indices = self
+            .attestation_2
+            .attesting_indices
+            .iter()
+            .filter(|index| indices_1.contains(index))
+            .cloned()
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+}
+
+// Pair two conflicting attestations, checking for a double or surround vote with at
+// least one common attester. Signatures are not checked as that requires the state.
+impl<const MAX_VALIDATORS_PER_COMMITTEE: usize>
+    TryFrom<(
+        IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>,
+        IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>,
+    )> for AttesterSlashing<MAX_VALIDATORS_PER_COMMITTEE>
+{
+    type Error = Error;
+
+    fn try_from(
+        (attestation_1, attestation_2): (
+            IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>,
+            IndexedAttestation<MAX_VALIDATORS_PER_COMMITTEE>,
+        ),
+    ) -> Result<Self, Self::Error> {
+        if !is_slashable_attestation_data(&attestation_1.data, &attestation_2.data) {
+            return Err(invalid_operation_error(InvalidOperation::AttesterSlashing(
+                InvalidAttesterSlashing::NotSlashable(
+                    Box::new(attestation_1.data),
+                    Box::new(attestation_2.data),
+                ),
+            )))
+        }
+        let slashing = Self { attestation_1, attestation_2 };
+        if slashing.slashable_indices().is_empty() {
+            return Err(invalid_operation_error(InvalidOperation::AttesterSlashing(
+                InvalidAttesterSlashing::DisjointAttestingIndices,
+            )))
+        }
+        Ok(slashing)
+    }
+}
+
 const fn get_deposit_proof_length() -> usize {
     DEPOSIT_CONTRACT_TREE_DEPTH + 1
 }
@@ -138,3 +252,172 @@ pub struct SignedVoluntaryExit {
     pub message: VoluntaryExit,
     pub signature: BlsSignature,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domains::DomainType,
+        error::InvalidBlock,
+        phase0::{
+            get_domain,
+            test_utils::{proposer_slashing, secret_key, state_at},
+        },
+        state_transition::Context,
+    };
+
+    fn indexed_attestation(
+        indices: &[ValidatorIndex],
+        source: Epoch,
+        target: Epoch,
+        beacon_block_root: u8,
+    ) -> IndexedAttestation<8> {
+        let data = AttestationData {
+            beacon_block_root: Root::try_from([beacon_block_root; 32].as_ref()).unwrap(),
+            source: Checkpoint { epoch: source, ..Default::default() },
+            target: Checkpoint { epoch: target, ..Default::default() },
+            ..Default::default()
+        };
+        IndexedAttestation {
+            attesting_indices: indices.to_vec().try_into().unwrap(),
+            data,
+            ..Default::default()
+        }
+    }
+
+    fn attester_slashing_error(
+        result: Result<AttesterSlashing<8>, Error>,
+    ) -> InvalidAttesterSlashing {
+        match result {
+            Err(Error::InvalidBlock(err)) => match *err {
+                InvalidBlock::InvalidOperation(InvalidOperation::AttesterSlashing(err)) => err,
+                err => panic!("unexpected error: {err:?}"),
+            },
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    fn proposer_slashing_error(result: Result<ProposerSlashing, Error>) -> InvalidProposerSlashing {
+        match result {
+            Err(Error::InvalidBlock(err)) => match *err {
+                InvalidBlock::InvalidOperation(InvalidOperation::ProposerSlashing(err)) => err,
+                err => panic!("unexpected error: {err:?}"),
+            },
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_proposer_slashing_try_from_headers() {
+        let context = Context::for_minimal();
+        let state = state_at(3, &context);
+        let domain = get_domain(&state, DomainType::BeaconProposer, Some(0), &context).unwrap();
+        let public_key = secret_key(5).public_key();
+        let slashing = proposer_slashing(&state, 5, 2, &context);
+        let ProposerSlashing { signed_header_1, signed_header_2 } = slashing.clone();
+
+        let result = ProposerSlashing::try_from_headers(
+            signed_header_1.clone(),
+            signed_header_2.clone(),
+            &public_key,
+            domain,
+        );
+        assert_eq!(result.unwrap(), slashing);
+
+        // same slot, different proposers
+        let other = proposer_slashing(&state, 6, 2, &context).signed_header_2;
+        let result =
+            ProposerSlashing::try_from_headers(signed_header_1.clone(), other, &public_key, domain);
+        assert!(matches!(
+            proposer_slashing_error(result),
+            InvalidProposerSlashing::ProposerMismatch(5, 6)
+        ));
+
+        let later = proposer_slashing(&state, 5, 3, &context).signed_header_2;
+        let result =
+            ProposerSlashing::try_from_headers(signed_header_1.clone(), later, &public_key, domain);
+        assert!(matches!(
+            proposer_slashing_error(result),
+            InvalidProposerSlashing::SlotMismatch(2, 3)
+        ));
+
+        let result = ProposerSlashing::try_from_headers(
+            signed_header_1.clone(),
+            signed_header_1.clone(),
+            &public_key,
+            domain,
+        );
+        assert!(matches!(
+            proposer_slashing_error(result),
+            InvalidProposerSlashing::HeadersAreEqual(header) if header == signed_header_1.message
+        ));
+
+        let other_key = secret_key(6).public_key();
+        let result = ProposerSlashing::try_from_headers(
+            signed_header_1,
+            signed_header_2,
+            &other_key,
+            domain,
+        );
+        assert!(matches!(
+            proposer_slashing_error(result),
+            InvalidProposerSlashing::InvalidSignature(_)
+        ));
+    }
+
+    #[test]
+    fn test_attester_slashing_try_from_attestations() {
+        // double vote: two blocks voted for in the same target epoch
+        let attestation_1 = indexed_attestation(&[1, 3, 5], 1, 2, 1);
+        let attestation_2 = indexed_attestation(&[3, 4], 1, 2, 2);
+        let slashing =
+            AttesterSlashing::try_from((attestation_1.clone(), attestation_2.clone())).unwrap();
+        assert_eq!(slashing.attestation_1, attestation_1);
+        assert_eq!(slashing.attestation_2, attestation_2);
+        assert_eq!(slashing.slashable_indices(), vec![3]);
+
+        // surround vote: the first vote surrounds the second one
+        let attestation_1 = indexed_attestation(&[2, 3], 1, 4, 1);
+        let attestation_2 = indexed_attestation(&[2], 2, 3, 1);
+        let slashing = AttesterSlashing::try_from((attestation_1, attestation_2)).unwrap();
+        assert_eq!(slashing.slashable_indices(), vec![2]);
+
+        // consecutive votes that neither conflict nor surround each other
+        let attestation_1 = indexed_attestation(&[2, 3], 1, 2, 1);
+        let attestation_2 = indexed_attestation(&[2, 3], 2, 3, 2);
+        let result = AttesterSlashing::try_from((attestation_1.clone(), attestation_2.clone()));
+        assert!(matches!(
+            attester_slashing_error(result),
+            InvalidAttesterSlashing::NotSlashable(data_1, data_2)
+                if *data_1 == attestation_1.data && *data_2 == attestation_2.data
+        ));
+
+        // the surrounded vote first is not a surround vote
+        let attestation_1 = indexed_attestation(&[2], 2, 3, 1);
+        let attestation_2 = indexed_attestation(&[2, 3], 1, 4, 1);
+        let result = AttesterSlashing::try_from((attestation_1, attestation_2));
+        assert!(matches!(
+            attester_slashing_error(result),
+            InvalidAttesterSlashing::NotSlashable(..)
+        ));
+
+        let attestation_1 = indexed_attestation(&[1, 3], 1, 2, 1);
+        let attestation_2 = indexed_attestation(&[2, 4], 1, 2, 2);
+        let result = AttesterSlashing::try_from((attestation_1.clone(), attestation_2.clone()));
+        assert!(matches!(
+            attester_slashing_error(result),
+            InvalidAttesterSlashing::DisjointAttestingIndices
+        ));
+        let slashing = AttesterSlashing { attestation_1, attestation_2 };
+        assert!(slashing.slashable_indices().is_empty());
+    }
+
+    #[test]
+    fn test_slashable_indices_are_sorted_and_unique() {
+        let slashing = AttesterSlashing {
+            attestation_1: indexed_attestation(&[7, 2, 5, 2, 9], 1, 2, 1),
+            attestation_2: indexed_attestation(&[9, 5, 5, 1, 2, 2], 1, 2, 2),
+        };
+        assert_eq!(slashing.slashable_indices(), vec![2, 5, 9]);
+    }
+}
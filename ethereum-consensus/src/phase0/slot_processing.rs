@@ -28,14 +28,47 @@ pub fn process_slots<
     >,
     slot: Slot,
     context: &Context,
+) -> Result<()> {
+    process_slots_with_hook(state, slot, context, |_, _, _| {})
+}
+
+// Like `process_slots` but calls `on_slot` with the slot, state root and block root cached by
+// `process_slot` for each slot that is advanced through.
+pub fn process_slots_with_hook<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    F: FnMut(Slot, Root, Root),
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    slot: Slot,
+    context: &Context,
+    mut on_slot: F,
 ) -> Result<()> {
     if state.slot >= slot {
         return Err(Error::TransitionToPreviousSlot { requested: slot, current: state.slot })
     }
     while state.slot < slot {
         process_slot(state, context)?;
+        let root_index = (state.slot % context.slots_per_historical_root) as usize;
+        on_slot(state.slot, state.state_roots[root_index], state.block_roots[root_index]);
         if (state.slot + 1) % context.slots_per_epoch == 0 {
             process_epoch(state, context)?;
+            context.committee_cache.prune((state.slot + 1) / context.slots_per_epoch);
         }
         state.slot += 1;
     }
@@ -78,3 +111,40 @@ pub fn process_slot<
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phase0::test_utils::state_at;
+
+    #[test]
+    fn test_process_slots_with_hook() {
+        let context = Context::for_minimal();
+        let mut state = state_at(5, &context);
+        let pre_state_root = state.hash_tree_root().unwrap();
+        let mut expected = state.clone();
+        process_slots(&mut expected, 19, &context).unwrap();
+
+        let mut calls = vec![];
+        process_slots_with_hook(&mut state, 19, &context, |slot, state_root, block_root| {
+            calls.push((slot, state_root, block_root))
+        })
+        .unwrap();
+        assert_eq!(state, expected);
+
+        // once per slot advanced through, including the last slot of each epoch
+        let slots = calls.iter().map(|&(slot, _, _)| slot).collect::<Vec<_>>();
+        assert_eq!(slots, (5..19).collect::<Vec<_>>());
+        assert_eq!(calls[0].1, pre_state_root);
+        for (slot, state_root, block_root) in calls {
+            let root_index = (slot % context.slots_per_historical_root) as usize;
+            assert_eq!(state_root, state.state_roots[root_index]);
+            assert_eq!(block_root, state.block_roots[root_index]);
+        }
+
+        assert!(matches!(
+            process_slots_with_hook(&mut state, 19, &context, |_, _, _| {}),
+            Err(Error::TransitionToPreviousSlot { requested: 19, current: 19 })
+        ));
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
     phase0::{
-        beacon_block::SignedBeaconBlock, beacon_state::BeaconState,
-        block_processing::process_block, helpers::verify_block_signature,
+        beacon_block::SignedBeaconBlock,
+        beacon_state::BeaconState,
+        block_processing::{get_validator_indices_touched_by_operations, process_block},
+        helpers::{compute_epoch_at_slot, verify_block_signatures},
         slot_processing::process_slots,
     },
+    primitives::Slot,
     ssz::prelude::*,
     state_transition::{Context, Result, Validation},
     Error,
@@ -53,7 +56,9 @@ pub fn state_transition_block_in_slot<
         Validation::Disabled => false,
     };
     if validate_result {
-        verify_block_signature(state, signed_block, context)?;
+        // Check every signature in the block in one batch. `process_block` then skips the
+        // signatures recorded as verified in `context.verified_signature_cache`.
+        verify_block_signatures(state, signed_block, context)?;
     }
     let block = &mut signed_block.message;
     process_block(state, block, context)?;
@@ -104,3 +109,411 @@ pub fn state_transition<
 
     state_transition_block_in_slot(state, signed_block, validation, context)
 }
+
+// Like `state_transition` but leaves `state` as it was before the call if the transition fails.
+//
+// Everything but the validator registry is copied up front. Of the registry, only the validators
+// the operations of `signed_block` may slash or exit are saved, and validators appended by
+// deposits are dropped on failure. Epoch processing may update any validator, so a transition
+// across an epoch boundary saves the whole registry.
+pub fn try_state_transition<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    signed_block: &mut SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+    >,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    let crosses_epoch = compute_epoch_at_slot(state.slot, context) !=
+        compute_epoch_at_slot(signed_block.message.slot, context);
+    let validators = std::mem::take(&mut state.validators);
+    let mut pre_state = state.clone();
+    state.validators = validators;
+
+    let registry_len = state.validators.len();
+    let saved_validators = if crosses_epoch {
+        pre_state.validators = state.validators.clone();
+        vec![]
+    } else {
+        get_validator_indices_touched_by_operations(&signed_block.message.body)
+            .into_iter()
+            .filter_map(|index| Some((index, state.validators.get(index)?.clone())))
+            .collect::<Vec<_>>()
+    };
+
+    let result = state_transition(state, signed_block, validation, context);
+    if result.is_err() {
+        if !crosses_epoch {
+            let mut validators = std::mem::take(&mut state.validators);
+            while validators.len() > registry_len {
+                validators.pop();
+            }
+            for (index, validator) in saved_validators {
+                validators[index] = validator;
+            }
+            pre_state.validators = validators;
+        }
+        *state = pre_state;
+    }
+    result
+}
+
+// Apply each of `signed_blocks` in order, then advance through any empty slots
+// up to `target_slot`. Use `Validation::Disabled` to skip signature and state
+// root checks when replaying blocks from a trusted source.
+pub fn replay_blocks<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+    >],
+    target_slot: Slot,
+    validation: Validation,
+    context: &Context,
+) -> Result<()> {
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, validation, context)?;
+    }
+    if state.slot != target_slot {
+        process_slots(state, target_slot, context)?;
+    }
+    Ok(())
+}
+
+// Apply each of `signed_blocks` to `state` in order with full validation, verifying the
+// signatures of all blocks up front and concurrently when the `parallel` feature is enabled.
+//
+// The signatures of each block are checked against the pre-state of that block returned by
+// `state_provider`, advanced to the slot of the block if needed, and recorded in
+// `context.verified_signature_cache` so the sequential pass does not check them again. This
+// pass is best effort: a missing or inexact pre-state, or a signature that does not verify
+// against it, is ignored and the sequential pass checks every signature it does not find in the
+// cache. The state root of every block is checked.
+pub fn verify_blocks_parallel<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const MAX_PROPOSER_SLASHINGS: usize,
+    const MAX_ATTESTER_SLASHINGS: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_DEPOSITS: usize,
+    const MAX_VOLUNTARY_EXITS: usize,
+    F: Fn(
+            &SignedBeaconBlock<
+                MAX_PROPOSER_SLASHINGS,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                MAX_ATTESTER_SLASHINGS,
+                MAX_ATTESTATIONS,
+                MAX_DEPOSITS,
+                MAX_VOLUNTARY_EXITS,
+            >,
+        ) -> Result<
+            BeaconState<
+                SLOTS_PER_HISTORICAL_ROOT,
+                HISTORICAL_ROOTS_LIMIT,
+                ETH1_DATA_VOTES_BOUND,
+                VALIDATOR_REGISTRY_LIMIT,
+                EPOCHS_PER_HISTORICAL_VECTOR,
+                EPOCHS_PER_SLASHINGS_VECTOR,
+                MAX_VALIDATORS_PER_COMMITTEE,
+                PENDING_ATTESTATIONS_BOUND,
+            >,
+        > + Send
+        + Sync,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+    >,
+    signed_blocks: &mut [SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+    >],
+    state_provider: F,
+    context: &Context,
+) -> Result<()> {
+    crate::state_transition::for_each_mut(signed_blocks, |signed_block| {
+        let mut pre_state = match state_provider(signed_block) {
+            Ok(pre_state) => pre_state,
+            Err(_) => return,
+        };
+        if pre_state.slot < signed_block.message.slot &&
+            process_slots(&mut pre_state, signed_block.message.slot, context).is_err()
+        {
+            return
+        }
+        // only warms the cache; the sequential pass below reports any invalid signature
+        let _ = verify_block_signatures(&pre_state, signed_block, context);
+    });
+    for signed_block in signed_blocks.iter_mut() {
+        state_transition(state, signed_block, Validation::Enabled, context)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{InvalidBlock, InvalidOperation, InvalidProposerSlashing},
+        phase0::{
+            minimal::{BeaconState, SignedBeaconBlock},
+            test_utils::{proposer_slashing, sign_block, signed_block, state_at},
+        },
+    };
+
+    fn invalid_block_leaves_state_unchanged(slot: Slot) {
+        let context = Context::for_minimal();
+        let mut state = state_at(1, &context);
+        let slashed = 5;
+        let slashing = proposer_slashing(&state, slashed, 1, &context);
+        let mut block = signed_block(&state, slot, vec![slashing], &context).message;
+        block.state_root = Default::default();
+        let mut block_state = state.clone();
+        process_slots(&mut block_state, slot, &context).unwrap();
+        let mut signed_block = sign_block(block, &block_state, &context);
+
+        let pre_state = state.clone();
+        let result =
+            try_state_transition(&mut state, &mut signed_block, Validation::Enabled, &context);
+        assert!(matches!(result, Err(Error::InvalidStateRoot)));
+        assert_eq!(state, pre_state);
+    }
+
+    #[test]
+    fn test_try_state_transition_rolls_back_invalid_block() {
+        invalid_block_leaves_state_unchanged(3);
+    }
+
+    #[test]
+    fn test_try_state_transition_rolls_back_invalid_block_across_epoch() {
+        invalid_block_leaves_state_unchanged(9);
+    }
+
+    #[test]
+    fn test_try_state_transition_applies_valid_block() {
+        let context = Context::for_minimal();
+        let mut state = state_at(1, &context);
+        let slashed = 5;
+        let slashing = proposer_slashing(&state, slashed, 1, &context);
+        let mut signed_block = signed_block(&state, 3, vec![slashing], &context);
+
+        try_state_transition(&mut state, &mut signed_block, Validation::Enabled, &context).unwrap();
+        assert_eq!(state.slot, 3);
+        assert!(state.validators[slashed].slashed);
+        assert_eq!(state.hash_tree_root().unwrap(), signed_block.message.state_root);
+    }
+
+    #[test]
+    fn test_state_transition_verifies_signatures_in_one_batch() {
+        let context = Context::for_minimal();
+        let state = state_at(1, &context);
+        let slashing = proposer_slashing(&state, 5, 1, &context);
+        let mut signed_block = signed_block(&state, 3, vec![slashing], &context);
+
+        let mut post_state = state.clone();
+        state_transition(&mut post_state, &mut signed_block, Validation::Enabled, &context)
+            .unwrap();
+        // the block, the RANDAO reveal and both slashed headers
+        assert_eq!(context.verified_signature_cache.len(), 4);
+
+        let context = Context::for_minimal();
+        let mut block = signed_block.message;
+        let slashing = &mut block.body.proposer_slashings[0];
+        slashing.signed_header_2.signature = slashing.signed_header_1.signature.clone();
+        let mut block_state = state.clone();
+        process_slots(&mut block_state, 3, &context).unwrap();
+        let mut signed_block = sign_block(block, &block_state, &context);
+
+        let mut post_state = state.clone();
+        let result =
+            state_transition(&mut post_state, &mut signed_block, Validation::Enabled, &context);
+        match result {
+            Err(Error::InvalidBlock(err)) => assert!(matches!(
+                *err,
+                InvalidBlock::InvalidOperation(InvalidOperation::ProposerSlashing(
+                    InvalidProposerSlashing::InvalidSignature(_)
+                ))
+            )),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    fn chain(state: &BeaconState, context: &Context) -> Vec<SignedBeaconBlock> {
+        let mut state = state.clone();
+        let mut blocks = vec![];
+        for slot in [2, 3, 5] {
+            let mut signed_block = signed_block(&state, slot, vec![], context);
+            state_transition(&mut state, &mut signed_block, Validation::Enabled, context).unwrap();
+            blocks.push(signed_block);
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_verify_blocks_parallel() {
+        let context = Context::for_minimal();
+        let genesis_state = state_at(1, &context);
+        let mut blocks = chain(&genesis_state, &context);
+
+        let context = Context::for_minimal();
+        let mut state = genesis_state.clone();
+        verify_blocks_parallel(&mut state, &mut blocks, |_| Ok(genesis_state.clone()), &context)
+            .unwrap();
+        assert_eq!(state.slot, 5);
+        assert_eq!(state.hash_tree_root().unwrap(), blocks[2].message.state_root);
+        // the block signature and RANDAO reveal of each block
+        assert_eq!(context.verified_signature_cache.len(), 6);
+    }
+
+    #[test]
+    fn test_verify_blocks_parallel_with_stale_pre_states() {
+        let context = Context::for_minimal();
+        let genesis_state = state_at(1, &context);
+        let mut expected = genesis_state.clone();
+        let mut blocks = vec![];
+        // on both sides of the boundary between epochs 0 and 1
+        for slot in [3, 6, 9, 12] {
+            let mut signed_block = signed_block(&expected, slot, vec![], &context);
+            state_transition(&mut expected, &mut signed_block, Validation::Enabled, &context)
+                .unwrap();
+            blocks.push(signed_block);
+        }
+
+        // the genesis state is stale for every block after the first and there is no pre-state
+        // for the last block, so only the sequential pass decides
+        let context = Context::for_minimal();
+        let mut state = genesis_state.clone();
+        let state_provider = |signed_block: &SignedBeaconBlock| match signed_block.message.slot {
+            12 => Err(Error::InvalidStateRoot),
+            _ => Ok(genesis_state.clone()),
+        };
+        verify_blocks_parallel(&mut state, &mut blocks, state_provider, &context).unwrap();
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_verify_blocks_parallel_checks_state_root() {
+        let context = Context::for_minimal();
+        let genesis_state = state_at(1, &context);
+        let mut blocks = chain(&genesis_state, &context);
+        let mut block = blocks[1].message.clone();
+        block.state_root = Default::default();
+        let mut block_state = genesis_state.clone();
+        state_transition(&mut block_state, &mut blocks[0], Validation::Enabled, &context).unwrap();
+        process_slots(&mut block_state, block.slot, &context).unwrap();
+        blocks[1] = sign_block(block, &block_state, &context);
+
+        let mut state = genesis_state.clone();
+        let result = verify_blocks_parallel(
+            &mut state,
+            &mut blocks,
+            |_| Ok(genesis_state.clone()),
+            &context,
+        );
+        assert!(matches!(result, Err(Error::InvalidStateRoot)));
+        assert_eq!(state.slot, 3);
+    }
+
+    #[test]
+    fn test_replay_blocks() {
+        let context = Context::for_minimal();
+        let genesis_state = state_at(1, &context);
+        let mut blocks = chain(&genesis_state, &context);
+
+        let mut expected = genesis_state.clone();
+        for signed_block in blocks.iter_mut() {
+            state_transition(&mut expected, signed_block, Validation::Enabled, &context).unwrap();
+        }
+        let mut state = genesis_state.clone();
+        replay_blocks(&mut state, &mut blocks, 5, Validation::Enabled, &context).unwrap();
+        assert_eq!(state, expected);
+
+        // advances past the last block, across an epoch boundary
+        process_slots(&mut expected, 11, &context).unwrap();
+        let mut state = genesis_state.clone();
+        replay_blocks(&mut state, &mut blocks, 11, Validation::Enabled, &context).unwrap();
+        assert_eq!(state, expected);
+
+        // neither signatures nor state roots are checked without validation
+        let expected_state = state;
+        for signed_block in blocks.iter_mut() {
+            signed_block.message.state_root = Default::default();
+        }
+        let mut state = genesis_state.clone();
+        assert!(replay_blocks(&mut state, &mut blocks, 11, Validation::Enabled, &context).is_err());
+        let mut state = genesis_state;
+        replay_blocks(&mut state, &mut blocks, 11, Validation::Disabled, &context).unwrap();
+        assert_eq!(state, expected_state);
+    }
+}
@@ -0,0 +1,137 @@
+//! Deterministic keys, states and blocks for unit tests of the phase0 state transition.
+use crate::{
+    crypto::{aggregate, SecretKey},
+    domains::DomainType,
+    phase0::minimal::{
+        compute_epoch_at_slot, compute_start_slot_at_epoch, get_beacon_committee,
+        get_beacon_proposer_index, get_block_root, get_current_epoch, get_domain, process_block,
+        process_slots, Attestation, AttestationData, BeaconBlock, BeaconBlockHeader, BeaconState,
+        Checkpoint, ProposerSlashing, SignedBeaconBlock, SignedBeaconBlockHeader, Validator,
+    },
+    primitives::{CommitteeIndex, Root, Slot, ValidatorIndex, FAR_FUTURE_EPOCH},
+    signing::sign_with_domain,
+    ssz::prelude::*,
+    state_transition::Context,
+};
+
+pub(crate) const VALIDATOR_COUNT: usize = 64;
+
+pub(crate) fn secret_key(index: ValidatorIndex) -> SecretKey {
+    let mut ikm = [0u8; 32];
+    ikm[..8].copy_from_slice(&(index as u64).to_le_bytes());
+    SecretKey::key_gen(&ikm).expect("ikm is long enough")
+}
+
+// A state at `slot` with `VALIDATOR_COUNT` active validators keyed by `secret_key`.
+pub(crate) fn state_at(slot: Slot, context: &Context) -> BeaconState {
+    let mut state = BeaconState { slot, ..Default::default() };
+    for index in 0..VALIDATOR_COUNT {
+        state.validators.push(Validator {
+            public_key: secret_key(index).public_key(),
+            effective_balance: context.max_effective_balance,
+            exit_epoch: FAR_FUTURE_EPOCH,
+            withdrawable_epoch: FAR_FUTURE_EPOCH,
+            ..Default::default()
+        });
+        state.balances.push(context.max_effective_balance);
+    }
+    state
+}
+
+// Two conflicting headers for `slot` signed by validator `slashed`.
+pub(crate) fn proposer_slashing(
+    state: &BeaconState,
+    slashed: ValidatorIndex,
+    slot: Slot,
+    context: &Context,
+) -> ProposerSlashing {
+    let epoch = compute_epoch_at_slot(slot, context);
+    let domain = get_domain(state, DomainType::BeaconProposer, Some(epoch), context).unwrap();
+    let signed_header = |body_root: u8| {
+        let mut message = BeaconBlockHeader {
+            slot,
+            proposer_index: slashed,
+            body_root: Root::try_from([body_root; 32].as_ref()).unwrap(),
+            ..Default::default()
+        };
+        let signature = sign_with_domain(&mut message, &secret_key(slashed), domain).unwrap();
+        SignedBeaconBlockHeader { message, signature }
+    };
+    ProposerSlashing { signed_header_1: signed_header(1), signed_header_2: signed_header(2) }
+}
+
+// Sign `block` with the key of its proposer.
+pub(crate) fn sign_block(
+    mut block: BeaconBlock,
+    state: &BeaconState,
+    context: &Context,
+) -> SignedBeaconBlock {
+    let epoch = compute_epoch_at_slot(block.slot, context);
+    let domain = get_domain(state, DomainType::BeaconProposer, Some(epoch), context).unwrap();
+    let signature =
+        sign_with_domain(&mut block, &secret_key(block.proposer_index), domain).unwrap();
+    SignedBeaconBlock { message: block, signature }
+}
+
+// A valid signed block for `slot` on top of `state` carrying `proposer_slashings`.
+pub(crate) fn signed_block(
+    state: &BeaconState,
+    slot: Slot,
+    proposer_slashings: Vec<ProposerSlashing>,
+    context: &Context,
+) -> SignedBeaconBlock {
+    let mut post_state = state.clone();
+    process_slots(&mut post_state, slot, context).unwrap();
+    let proposer_index = get_beacon_proposer_index(&post_state, context).unwrap();
+    let mut epoch = get_current_epoch(&post_state, context);
+    let domain = get_domain(&post_state, DomainType::Randao, Some(epoch), context).unwrap();
+    let mut block = BeaconBlock {
+        slot,
+        proposer_index,
+        parent_root: post_state.latest_block_header.hash_tree_root().unwrap(),
+        ..Default::default()
+    };
+    block.body.randao_reveal =
+        sign_with_domain(&mut epoch, &secret_key(proposer_index), domain).unwrap();
+    block.body.eth1_data = post_state.eth1_data.clone();
+    for proposer_slashing in proposer_slashings {
+        block.body.proposer_slashings.push(proposer_slashing);
+    }
+    process_block(&mut post_state, &mut block, context).unwrap();
+    block.state_root = post_state.hash_tree_root().unwrap();
+    sign_block(block, &post_state, context)
+}
+
+// An attestation to `slot` from every member of committee `index`, voting for the current
+// justified checkpoint of `state` as source.
+pub(crate) fn attestation(
+    state: &BeaconState,
+    slot: Slot,
+    index: CommitteeIndex,
+    context: &Context,
+) -> Attestation {
+    let epoch = compute_epoch_at_slot(slot, context);
+    let target_root = if compute_start_slot_at_epoch(epoch, context) < state.slot {
+        *get_block_root(state, epoch, context).unwrap()
+    } else {
+        Root::default()
+    };
+    let mut data = AttestationData {
+        slot,
+        index,
+        source: state.current_justified_checkpoint.clone(),
+        target: Checkpoint { epoch, root: target_root },
+        ..Default::default()
+    };
+    let domain = get_domain(state, DomainType::BeaconAttester, Some(epoch), context).unwrap();
+    let committee = get_beacon_committee(state, slot, index, context).unwrap();
+    let mut attestation = Attestation { data: data.clone(), ..Default::default() };
+    let mut signatures = Vec::with_capacity(committee.len());
+    for validator_index in committee {
+        attestation.aggregation_bits.push(true);
+        let signature = sign_with_domain(&mut data, &secret_key(validator_index), domain).unwrap();
+        signatures.push(signature);
+    }
+    attestation.signature = aggregate(&signatures).unwrap();
+    attestation
+}
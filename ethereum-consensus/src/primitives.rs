@@ -1,9 +1,18 @@
+mod execution_address;
+mod graffiti;
+mod withdrawal_credentials;
+
 use crate::ssz::prelude::*;
 pub use crate::{
     crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature},
     domains::DomainType,
     ssz::prelude::U256,
 };
+pub use execution_address::{
+    Error as ExecutionAddressError, ExecutionAddress, EXECUTION_ADDRESS_LENGTH,
+};
+pub use graffiti::{Error as GraffitiError, Graffiti, GRAFFITI_LENGTH};
+pub use withdrawal_credentials::{WithdrawalCredentials, WithdrawalCredentialsKind};
 
 pub type Root = Node;
 pub type Slot = u64;
@@ -20,8 +29,6 @@ pub type Version = [u8; 4];
 pub type ForkDigest = [u8; 4];
 pub type Domain = [u8; 32];
 
-pub type ExecutionAddress = ByteVector<20>;
-
 pub type ChainId = usize;
 pub type NetworkId = usize;
 
@@ -44,6 +51,7 @@ pub const FAR_FUTURE_EPOCH: Epoch = Epoch::MAX;
 
 pub const BLS_WITHDRAWAL_PREFIX: u8 = 0x00;
 pub const ETH1_ADDRESS_WITHDRAWAL_PREFIX: u8 = 0x01;
+pub const COMPOUNDING_WITHDRAWAL_PREFIX: u8 = 0x02;
 
 #[cfg(test)]
 #[cfg(feature = "serde")]
@@ -60,4 +68,13 @@ mod tests {
         let bytes_roundtrip: Bytes32 = serde_json::from_str(&json).unwrap();
         assert_eq!(bytes, bytes_roundtrip);
     }
+
+    #[test]
+    fn test_root_serde() {
+        let root = Root::try_from([0xab; 32].as_ref()).unwrap();
+        let json = serde_json::to_string(&root).unwrap();
+        assert_eq!(json, format!("\"0x{}\"", "ab".repeat(32)));
+        let root_roundtrip: Root = serde_json::from_str(&json).unwrap();
+        assert_eq!(root, root_roundtrip);
+    }
 }
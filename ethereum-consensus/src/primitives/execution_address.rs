@@ -0,0 +1,164 @@
+use crate::{
+    serde::{try_bytes_from_hex_str, HexError},
+    ssz::prelude::*,
+};
+use sha3::{Digest, Keccak256};
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
+use thiserror::Error;
+
+pub const EXECUTION_ADDRESS_LENGTH: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Hex(#[from] HexError),
+    #[error("execution address must be {EXECUTION_ADDRESS_LENGTH} bytes but got {0} bytes")]
+    InvalidLength(usize),
+    #[error("mixed-case execution address does not match its EIP-55 checksum")]
+    InvalidChecksum,
+}
+
+// An address on the execution layer, displayed with the EIP-55 checksum.
+//
+// Parsing is case-insensitive for all-lowercase or all-uppercase input; mixed-case
+// input must carry a valid checksum. Serializes as lowercase hex.
+#[derive(Default, Clone, PartialEq, Eq, Hash, SimpleSerialize)]
+pub struct ExecutionAddress(ByteVector<EXECUTION_ADDRESS_LENGTH>);
+
+impl ExecutionAddress {
+    pub fn to_checksum_string(&self) -> String {
+        let lower = self.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        let hash = Keccak256::digest(lower.as_bytes());
+        let checksummed = lower
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0x0f;
+                if nibble >= 8 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect::<String>();
+        format!("0x{checksummed}")
+    }
+}
+
+impl TryFrom<&[u8]> for ExecutionAddress {
+    type Error = DeserializeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        ByteVector::try_from(bytes).map(Self)
+    }
+}
+
+impl From<[u8; EXECUTION_ADDRESS_LENGTH]> for ExecutionAddress {
+    fn from(bytes: [u8; EXECUTION_ADDRESS_LENGTH]) -> Self {
+        Self::try_from(bytes.as_ref()).expect("is correct length")
+    }
+}
+
+impl FromStr for ExecutionAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = try_bytes_from_hex_str(s)?;
+        let address =
+            Self::try_from(bytes.as_ref()).map_err(|_| Error::InvalidLength(bytes.len()))?;
+        let digits = &s[2..];
+        let is_mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) &&
+            digits.chars().any(|c| c.is_ascii_uppercase());
+        if is_mixed_case && address.to_checksum_string()[2..] != *digits {
+            return Err(Error::InvalidChecksum)
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Debug for ExecutionAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_checksum_string())
+    }
+}
+
+impl fmt::Display for ExecutionAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_checksum_string())
+    }
+}
+
+impl Deref for ExecutionAddress {
+    type Target = ByteVector<EXECUTION_ADDRESS_LENGTH>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ExecutionAddress {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl serde::Serialize for ExecutionAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ExecutionAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde")]
+mod tests {
+    use super::*;
+
+    // from the EIP-55 test vectors
+    const CHECKSUMMED: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn test_checksum_roundtrip() {
+        for expected in CHECKSUMMED {
+            let address = ExecutionAddress::from_str(expected).unwrap();
+            assert_eq!(address.to_string(), expected);
+
+            let lower = expected.to_ascii_lowercase();
+            assert_eq!(ExecutionAddress::from_str(&lower).unwrap(), address);
+            let upper = format!("0x{}", expected[2..].to_ascii_uppercase());
+            assert_eq!(ExecutionAddress::from_str(&upper).unwrap(), address);
+        }
+    }
+
+    #[test]
+    fn test_invalid_addresses() {
+        let bad_checksum = CHECKSUMMED[0].replace('a', "A");
+        assert!(matches!(ExecutionAddress::from_str(&bad_checksum), Err(Error::InvalidChecksum)));
+        assert!(matches!(ExecutionAddress::from_str("0x1234"), Err(Error::InvalidLength(2))));
+        assert!(ExecutionAddress::from_str(&CHECKSUMMED[0][2..]).is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let address = ExecutionAddress::from_str(CHECKSUMMED[1]).unwrap();
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{}\"", CHECKSUMMED[1].to_ascii_lowercase()));
+        let recovered: ExecutionAddress = serde_json::from_str(&json).unwrap();
+        assert_eq!(recovered, address);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    primitives::Bytes32,
+    serde::{try_bytes_from_hex_str, HexError},
+};
+use ssz_rs::utils::write_bytes_to_lower_hex_display;
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+pub const GRAFFITI_LENGTH: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Hex(#[from] HexError),
+    #[error("graffiti can be at most {GRAFFITI_LENGTH} bytes but hex input has {0} bytes")]
+    TooLong(usize),
+}
+
+// The free-form `graffiti` of a block body. Parses from `0x`-prefixed hex or from
+// UTF-8 text, which is truncated (on a character boundary) or zero-padded to fit.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Graffiti(pub [u8; GRAFFITI_LENGTH]);
+
+impl Graffiti {
+    pub fn from_utf8_lossy(text: &str) -> Self {
+        let mut end = text.len().min(GRAFFITI_LENGTH);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut inner = [0u8; GRAFFITI_LENGTH];
+        inner[..end].copy_from_slice(&text.as_bytes()[..end]);
+        Self(inner)
+    }
+
+    // Text content with trailing zero padding removed, if it is valid UTF-8
+    pub fn as_utf8(&self) -> Option<&str> {
+        let end = self.0.iter().rposition(|&b| b != 0).map(|i| i + 1).unwrap_or(0);
+        std::str::from_utf8(&self.0[..end]).ok()
+    }
+}
+
+impl FromStr for Graffiti {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.starts_with("0x") {
+            return Ok(Self::from_utf8_lossy(s))
+        }
+        let bytes = try_bytes_from_hex_str(s)?;
+        if bytes.len() > GRAFFITI_LENGTH {
+            return Err(Error::TooLong(bytes.len()))
+        }
+        let mut inner = [0u8; GRAFFITI_LENGTH];
+        inner[..bytes.len()].copy_from_slice(&bytes);
+        Ok(Self(inner))
+    }
+}
+
+// Shows the text if possible, falling back to hex. Text that itself looks like hex
+// or contains control characters is shown as hex so that the output parses back.
+impl fmt::Display for Graffiti {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_utf8() {
+            Some(text) if !text.starts_with("0x") && !text.contains(char::is_control) => {
+                write!(f, "{text}")
+            }
+            _ => write_bytes_to_lower_hex_display(f, self.0.iter()),
+        }
+    }
+}
+
+impl AsRef<[u8]> for Graffiti {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<&Bytes32> for Graffiti {
+    fn from(bytes: &Bytes32) -> Self {
+        let mut inner = [0u8; GRAFFITI_LENGTH];
+        inner.copy_from_slice(bytes.as_ref());
+        Self(inner)
+    }
+}
+
+impl From<Graffiti> for Bytes32 {
+    fn from(graffiti: Graffiti) -> Self {
+        Bytes32::try_from(graffiti.0.as_ref()).expect("is correct length")
+    }
+}
+
+impl serde::Serialize for Graffiti {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::serde::as_hex::serialize(self.0, serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Graffiti {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "serde")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graffiti_from_text() {
+        let graffiti = Graffiti::from_str("hello").unwrap();
+        assert_eq!(&graffiti.0[..5], b"hello");
+        assert!(graffiti.0[5..].iter().all(|&b| b == 0));
+        assert_eq!(graffiti.to_string(), "hello");
+
+        // a multi-byte character straddling the limit is dropped
+        let text = format!("{}é", "a".repeat(31));
+        let graffiti = Graffiti::from_str(&text).unwrap();
+        assert_eq!(graffiti.as_utf8().unwrap(), "a".repeat(31));
+    }
+
+    #[test]
+    fn test_graffiti_from_hex() {
+        let graffiti = Graffiti::from_str("0xff00").unwrap();
+        assert_eq!(graffiti.0[0], 0xff);
+        let display = graffiti.to_string();
+        assert_eq!(display, format!("0xff{}", "00".repeat(31)));
+        assert_eq!(Graffiti::from_str(&display).unwrap(), graffiti);
+
+        let hex_like = Graffiti::from_utf8_lossy("0xabc");
+        assert_eq!(Graffiti::from_str(&hex_like.to_string()).unwrap(), hex_like);
+
+        assert!(Graffiti::from_str(&format!("0x{}", "00".repeat(33))).is_err());
+        assert!(Graffiti::from_str("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_graffiti_bytes_roundtrip() {
+        let graffiti = Graffiti::from_utf8_lossy("lighthouse/v4");
+        let bytes = Bytes32::from(graffiti);
+        assert_eq!(Graffiti::from(&bytes), graffiti);
+
+        let json = serde_json::to_string(&graffiti).unwrap();
+        assert_eq!(json, serde_json::to_string(&bytes).unwrap());
+        let recovered: Graffiti = serde_json::from_str(&json).unwrap();
+        assert_eq!(recovered, graffiti);
+    }
+}
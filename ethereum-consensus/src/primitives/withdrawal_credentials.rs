@@ -0,0 +1,127 @@
+use crate::{
+    crypto::hash,
+    primitives::{
+        BlsPublicKey, Bytes32, ExecutionAddress, BLS_WITHDRAWAL_PREFIX,
+        COMPOUNDING_WITHDRAWAL_PREFIX, ETH1_ADDRESS_WITHDRAWAL_PREFIX,
+    },
+    ssz::prelude::*,
+};
+use std::ops::{Deref, DerefMut};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WithdrawalCredentialsKind {
+    Bls,
+    Eth1,
+    Compounding,
+    Unknown(u8),
+}
+
+// The `withdrawal_credentials` of a `Validator`: a one-byte prefix followed by
+// either a hash of a BLS withdrawal key or a (zero-padded) execution address.
+#[derive(
+    Default, Clone, Hash, PartialEq, Eq, SimpleSerialize, serde::Serialize, serde::Deserialize,
+)]
+pub struct WithdrawalCredentials(Bytes32);
+
+impl WithdrawalCredentials {
+    pub fn from_bls_public_key(public_key: &BlsPublicKey) -> Self {
+        let mut inner = hash(public_key.as_ref());
+        inner[0] = BLS_WITHDRAWAL_PREFIX;
+        Self(inner)
+    }
+
+    pub fn from_execution_address(address: &ExecutionAddress) -> Self {
+        Self::with_address(ETH1_ADDRESS_WITHDRAWAL_PREFIX, address)
+    }
+
+    pub fn compounding(address: &ExecutionAddress) -> Self {
+        Self::with_address(COMPOUNDING_WITHDRAWAL_PREFIX, address)
+    }
+
+    fn with_address(prefix: u8, address: &ExecutionAddress) -> Self {
+        let mut inner = Bytes32::default();
+        inner[0] = prefix;
+        inner[12..].copy_from_slice(address.as_ref());
+        Self(inner)
+    }
+
+    pub fn kind(&self) -> WithdrawalCredentialsKind {
+        match self.0[0] {
+            BLS_WITHDRAWAL_PREFIX => WithdrawalCredentialsKind::Bls,
+            ETH1_ADDRESS_WITHDRAWAL_PREFIX => WithdrawalCredentialsKind::Eth1,
+            COMPOUNDING_WITHDRAWAL_PREFIX => WithdrawalCredentialsKind::Compounding,
+            prefix => WithdrawalCredentialsKind::Unknown(prefix),
+        }
+    }
+
+    // The address withdrawals are sent to, if these credentials have one
+    pub fn to_execution_address(&self) -> Option<ExecutionAddress> {
+        match self.kind() {
+            WithdrawalCredentialsKind::Eth1 | WithdrawalCredentialsKind::Compounding => {
+                Some(ExecutionAddress::try_from(&self.0[12..]).expect("is correct length"))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<Bytes32> for WithdrawalCredentials {
+    fn from(bytes: Bytes32) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<WithdrawalCredentials> for Bytes32 {
+    fn from(credentials: WithdrawalCredentials) -> Self {
+        credentials.0
+    }
+}
+
+impl std::fmt::Debug for WithdrawalCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl Deref for WithdrawalCredentials {
+    type Target = Bytes32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for WithdrawalCredentials {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_withdrawal_credentials() {
+        let public_key = BlsPublicKey::try_from([1u8; 48].as_ref()).unwrap();
+        let credentials = WithdrawalCredentials::from_bls_public_key(&public_key);
+        assert_eq!(credentials.kind(), WithdrawalCredentialsKind::Bls);
+        assert_eq!(credentials[1..], hash(public_key.as_ref())[1..]);
+        assert!(credentials.to_execution_address().is_none());
+
+        let address = ExecutionAddress::from([7u8; 20]);
+        let credentials = WithdrawalCredentials::from_execution_address(&address);
+        assert_eq!(credentials.kind(), WithdrawalCredentialsKind::Eth1);
+        assert!(credentials[1..12].iter().all(|&b| b == 0));
+        assert_eq!(credentials.to_execution_address(), Some(address.clone()));
+
+        let credentials = WithdrawalCredentials::compounding(&address);
+        assert_eq!(credentials.kind(), WithdrawalCredentialsKind::Compounding);
+        assert_eq!(credentials.to_execution_address(), Some(address));
+
+        let mut bytes = Bytes32::default();
+        bytes[0] = 0xff;
+        let credentials = WithdrawalCredentials::from(bytes);
+        assert_eq!(credentials.kind(), WithdrawalCredentialsKind::Unknown(0xff));
+    }
+}
@@ -0,0 +1,634 @@
+//! Merkle proofs that a block root is part of the canonical history committed
+//! to by a (recent) `BeaconState`, spanning `block_roots`, the pre-Capella
+//! `historical_roots` and the Capella `historical_summaries`.
+//!
+//! Also provides helpers to rebuild and check the `HistoricalBatch` of a completed
+//! `SLOTS_PER_HISTORICAL_ROOT` window, e.g. when validating era files.
+use crate::{
+    crypto::hash,
+    phase0::{HistoricalBatch, HistoricalSummary},
+    primitives::{Root, Slot},
+    ssz::prelude::*,
+    types::BeaconState,
+};
+use thiserror::Error;
+
+// Field indices in `BeaconState`, shared across all forks defined so far.
+pub const BLOCK_ROOTS_INDEX: usize = 5;
+pub const HISTORICAL_ROOTS_INDEX: usize = 7;
+pub const HISTORICAL_SUMMARIES_INDEX: usize = 27;
+// All `BeaconState` containers up to `deneb` fit in 32 leaves.
+pub const BEACON_STATE_DEPTH: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Merkleization(#[from] MerkleizationError),
+    #[error("slot {requested} is not in the history of a state at slot {state_slot}")]
+    SlotNotInHistory { requested: Slot, state_slot: Slot },
+    #[error("historical batch for slot {0} is required to build this proof")]
+    MissingHistoricalBatch(Slot),
+    #[error("historical batch does not match the root committed to by the state")]
+    HistoricalBatchMismatch,
+    #[error("state at slot {0} does not close a historical root period")]
+    IncompleteHistoricalPeriod(Slot),
+    #[error("historical period {period} is not summarized by a state at slot {state_slot}")]
+    PeriodNotInHistory { period: usize, state_slot: Slot },
+    #[error("block root at slot {slot} does not match the historical batch")]
+    BlockRootMismatch { slot: Slot },
+    #[error("proof does not verify against the provided root")]
+    InvalidProof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MerkleBranch {
+    // index of the leaf at the bottom layer of this (sub)tree
+    pub index: usize,
+    pub branch: Vec<Node>,
+}
+
+impl MerkleBranch {
+    pub fn new(leaves: &[Node], depth: usize, index: usize) -> Self {
+        let branch = compute_merkle_branch(leaves, depth, index);
+        Self { index, branch }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.branch.len()
+    }
+
+    pub fn compute_root(&self, leaf: &Node) -> Node {
+        let mut node = *leaf;
+        for (height, sibling) in self.branch.iter().enumerate() {
+            node = if (self.index >> height) & 1 == 1 {
+                hash_pair(sibling, &node)
+            } else {
+                hash_pair(&node, sibling)
+            };
+        }
+        node
+    }
+}
+
+// A multi-hop proof: each segment takes the root computed by the previous
+// segment as its leaf, starting from `block_root` and ending at the state root.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HistoricalBlockRootProof {
+    #[serde(with = "crate::serde::as_str")]
+    pub slot: Slot,
+    pub block_root: Root,
+    pub segments: Vec<MerkleBranch>,
+}
+
+impl HistoricalBlockRootProof {
+    pub fn compute_root(&self) -> Node {
+        self.segments.iter().fold(self.block_root, |node, segment| segment.compute_root(&node))
+    }
+
+    pub fn verify(&self, state_root: &Root) -> Result<(), Error> {
+        if self.compute_root() == *state_root {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+}
+
+pub(crate) fn hash_pair(a: &Node, b: &Node) -> Node {
+    let mut input = [0u8; 64];
+    input[..32].copy_from_slice(a.as_ref());
+    input[32..].copy_from_slice(b.as_ref());
+    Node::try_from(hash(input).as_ref()).expect("is valid instance")
+}
+
+pub(crate) fn zero_hashes(depth: usize) -> Vec<Node> {
+    let mut zeroes = vec![Node::default()];
+    for i in 0..depth {
+        let next = hash_pair(&zeroes[i], &zeroes[i]);
+        zeroes.push(next);
+    }
+    zeroes
+}
+
+// Compute the branch for the leaf at `index` in a tree of the given `depth`,
+// padding `leaves` with zero nodes as needed.
+pub fn compute_merkle_branch(leaves: &[Node], depth: usize, index: usize) -> Vec<Node> {
+    let zeroes = zero_hashes(depth);
+    let mut layer = leaves.to_vec();
+    let mut branch = Vec::with_capacity(depth);
+    for (height, zero) in zeroes.iter().enumerate().take(depth) {
+        let sibling_index = (index >> height) ^ 1;
+        branch.push(layer.get(sibling_index).copied().unwrap_or(*zero));
+
+        let next_layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(zero)))
+            .collect::<Vec<_>>();
+        layer = next_layer;
+    }
+    branch
+}
+
+pub(crate) fn depth_for(limit: usize) -> usize {
+    limit.next_power_of_two().trailing_zeros() as usize
+}
+
+fn length_mix_in(length: usize) -> MerkleBranch {
+    let mut length_node = Node::default();
+    length_node.as_mut()[..8].copy_from_slice(&(length as u64).to_le_bytes());
+    MerkleBranch { index: 0, branch: vec![length_node] }
+}
+
+macro_rules! field_roots {
+    ($state:expr, $($field:ident),* $(,)?) => {
+        vec![$($state.$field.hash_tree_root()?),*]
+    };
+}
+
+pub fn beacon_state_field_roots<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+) -> Result<Vec<Node>, Error> {
+    let roots = match state {
+        BeaconState::Phase0(state) => field_roots!(
+            state,
+            genesis_time,
+            genesis_validators_root,
+            slot,
+            fork,
+            latest_block_header,
+            block_roots,
+            state_roots,
+            historical_roots,
+            eth1_data,
+            eth1_data_votes,
+            eth1_deposit_index,
+            validators,
+            balances,
+            randao_mixes,
+            slashings,
+            previous_epoch_attestations,
+            current_epoch_attestations,
+            justification_bits,
+            previous_justified_checkpoint,
+            current_justified_checkpoint,
+            finalized_checkpoint,
+        ),
+        BeaconState::Altair(state) => field_roots!(
+            state,
+            genesis_time,
+            genesis_validators_root,
+            slot,
+            fork,
+            latest_block_header,
+            block_roots,
+            state_roots,
+            historical_roots,
+            eth1_data,
+            eth1_data_votes,
+            eth1_deposit_index,
+            validators,
+            balances,
+            randao_mixes,
+            slashings,
+            previous_epoch_participation,
+            current_epoch_participation,
+            justification_bits,
+            previous_justified_checkpoint,
+            current_justified_checkpoint,
+            finalized_checkpoint,
+            inactivity_scores,
+            current_sync_committee,
+            next_sync_committee,
+        ),
+        BeaconState::Bellatrix(state) => field_roots!(
+            state,
+            genesis_time,
+            genesis_validators_root,
+            slot,
+            fork,
+            latest_block_header,
+            block_roots,
+            state_roots,
+            historical_roots,
+            eth1_data,
+            eth1_data_votes,
+            eth1_deposit_index,
+            validators,
+            balances,
+            randao_mixes,
+            slashings,
+            previous_epoch_participation,
+            current_epoch_participation,
+            justification_bits,
+            previous_justified_checkpoint,
+            current_justified_checkpoint,
+            finalized_checkpoint,
+            inactivity_scores,
+            current_sync_committee,
+            next_sync_committee,
+            latest_execution_payload_header,
+        ),
+        BeaconState::Capella(state) => field_roots!(
+            state,
+            genesis_time,
+            genesis_validators_root,
+            slot,
+            fork,
+            latest_block_header,
+            block_roots,
+            state_roots,
+            historical_roots,
+            eth1_data,
+            eth1_data_votes,
+            eth1_deposit_index,
+            validators,
+            balances,
+            randao_mixes,
+            slashings,
+            previous_epoch_participation,
+            current_epoch_participation,
+            justification_bits,
+            previous_justified_checkpoint,
+            current_justified_checkpoint,
+            finalized_checkpoint,
+            inactivity_scores,
+            current_sync_committee,
+            next_sync_committee,
+            latest_execution_payload_header,
+            next_withdrawal_index,
+            next_withdrawal_validator_index,
+            historical_summaries,
+        ),
+        BeaconState::Deneb(state) => field_roots!(
+            state,
+            genesis_time,
+            genesis_validators_root,
+            slot,
+            fork,
+            latest_block_header,
+            block_roots,
+            state_roots,
+            historical_roots,
+            eth1_data,
+            eth1_data_votes,
+            eth1_deposit_index,
+            validators,
+            balances,
+            randao_mixes,
+            slashings,
+            previous_epoch_participation,
+            current_epoch_participation,
+            justification_bits,
+            previous_justified_checkpoint,
+            current_justified_checkpoint,
+            finalized_checkpoint,
+            inactivity_scores,
+            current_sync_committee,
+            next_sync_committee,
+            latest_execution_payload_header,
+            next_withdrawal_index,
+            next_withdrawal_validator_index,
+            historical_summaries,
+        ),
+    };
+    Ok(roots)
+}
+
+// Take the `HistoricalBatch` of the period that ends right before `state.slot()`.
+//
+// The state must sit exactly on a period boundary, i.e. after the epoch processing
+// that appended this batch to `historical_roots` (or `historical_summaries`).
+pub fn historical_batch_from_state<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+) -> Result<HistoricalBatch<SLOTS_PER_HISTORICAL_ROOT>, Error> {
+    let slot = state.slot();
+    if slot == 0 || slot as usize % SLOTS_PER_HISTORICAL_ROOT != 0 {
+        return Err(Error::IncompleteHistoricalPeriod(slot))
+    }
+    Ok(HistoricalBatch {
+        block_roots: state.block_roots().clone(),
+        state_roots: state.state_roots().clone(),
+    })
+}
+
+pub fn historical_summary_from_batch<const SLOTS_PER_HISTORICAL_ROOT: usize>(
+    batch: &mut HistoricalBatch<SLOTS_PER_HISTORICAL_ROOT>,
+) -> Result<HistoricalSummary, Error> {
+    Ok(HistoricalSummary {
+        block_summary_root: batch.block_roots.hash_tree_root()?,
+        state_summary_root: batch.state_roots.hash_tree_root()?,
+    })
+}
+
+// Check that `batch` is the one `state` committed to for the given `period`,
+// against `historical_roots` before Capella and `historical_summaries` after.
+pub fn verify_historical_batch<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    period: usize,
+    batch: &mut HistoricalBatch<SLOTS_PER_HISTORICAL_ROOT>,
+) -> Result<(), Error> {
+    let state_slot = state.slot();
+    let historical_roots = state.historical_roots();
+    let matches = if let Some(root) = historical_roots.get(period) {
+        batch.hash_tree_root()? == *root
+    } else {
+        let index = period - historical_roots.len();
+        let summary = state
+            .historical_summaries()
+            .and_then(|summaries| summaries.get(index))
+            .ok_or(Error::PeriodNotInHistory { period, state_slot })?;
+        historical_summary_from_batch(batch)? == *summary
+    };
+    if matches {
+        Ok(())
+    } else {
+        Err(Error::HistoricalBatchMismatch)
+    }
+}
+
+// Check that each `(slot, block_root)` pair is recorded in `batch`, e.g. for the
+// blocks of an era file against the batch committed to by its state.
+pub fn verify_block_roots_in_batch<'a, const SLOTS_PER_HISTORICAL_ROOT: usize>(
+    batch: &HistoricalBatch<SLOTS_PER_HISTORICAL_ROOT>,
+    block_roots: impl IntoIterator<Item = (Slot, &'a Root)>,
+) -> Result<(), Error> {
+    for (slot, block_root) in block_roots {
+        if batch.block_roots[slot as usize % SLOTS_PER_HISTORICAL_ROOT] != *block_root {
+            return Err(Error::BlockRootMismatch { slot })
+        }
+    }
+    Ok(())
+}
+
+// Build a proof that the block root at `slot` is committed to by `state`.
+//
+// Roots older than `SLOTS_PER_HISTORICAL_ROOT` slots are only summarized in the
+// state so the caller must provide the `HistoricalBatch` of the period containing
+// `slot` (e.g. from an era file or a state at the end of that period).
+pub fn prove_historical_block_root<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state: &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    slot: Slot,
+    historical_batch: Option<&mut HistoricalBatch<SLOTS_PER_HISTORICAL_ROOT>>,
+) -> Result<HistoricalBlockRootProof, Error> {
+    let state_slot = state.slot();
+    if slot >= state_slot {
+        return Err(Error::SlotNotInHistory { requested: slot, state_slot })
+    }
+
+    let vector_depth = depth_for(SLOTS_PER_HISTORICAL_ROOT);
+    let index_in_vector = slot as usize % SLOTS_PER_HISTORICAL_ROOT;
+    let field_roots = beacon_state_field_roots(state)?;
+
+    if state_slot <= slot + SLOTS_PER_HISTORICAL_ROOT as Slot {
+        let block_roots = state.block_roots();
+        let block_root = block_roots[index_in_vector];
+        let segments = vec![
+            MerkleBranch::new(block_roots.as_ref(), vector_depth, index_in_vector),
+            MerkleBranch::new(&field_roots, BEACON_STATE_DEPTH, BLOCK_ROOTS_INDEX),
+        ];
+        return Ok(HistoricalBlockRootProof { slot, block_root, segments })
+    }
+
+    let batch = historical_batch.ok_or(Error::MissingHistoricalBatch(slot))?;
+    let period = slot as usize / SLOTS_PER_HISTORICAL_ROOT;
+    verify_historical_batch(state, period, batch)?;
+
+    let block_root = batch.block_roots[index_in_vector];
+    let block_roots_segment =
+        MerkleBranch::new(batch.block_roots.as_ref(), vector_depth, index_in_vector);
+    let state_summary_root = batch.state_roots.hash_tree_root()?;
+
+    let list_depth = depth_for(HISTORICAL_ROOTS_LIMIT);
+    let historical_roots_count = state.historical_roots().len();
+    let segments = if period < historical_roots_count {
+        let historical_roots = state.historical_roots();
+        vec![
+            block_roots_segment,
+            // `HistoricalBatch { block_roots, state_roots }`
+            MerkleBranch { index: 0, branch: vec![state_summary_root] },
+            MerkleBranch::new(historical_roots.as_ref(), list_depth, period),
+            length_mix_in(historical_roots.len()),
+            MerkleBranch::new(&field_roots, BEACON_STATE_DEPTH, HISTORICAL_ROOTS_INDEX),
+        ]
+    } else {
+        let index = period - historical_roots_count;
+        let summaries = state.historical_summaries_mut().expect("checked by batch verification");
+        let count = summaries.len();
+        let mut summary_roots = Vec::with_capacity(count);
+        for i in 0..count {
+            summary_roots.push(summaries[i].hash_tree_root()?);
+        }
+        vec![
+            block_roots_segment,
+            // `HistoricalSummary { block_summary_root, state_summary_root }`
+            MerkleBranch { index: 0, branch: vec![state_summary_root] },
+            MerkleBranch::new(&summary_roots, list_depth, index),
+            length_mix_in(count),
+            MerkleBranch::new(&field_roots, BEACON_STATE_DEPTH, HISTORICAL_SUMMARIES_INDEX),
+        ]
+    };
+    Ok(HistoricalBlockRootProof { slot, block_root, segments })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        phase0::mainnet::{BeaconState as Phase0BeaconState, SLOTS_PER_HISTORICAL_ROOT},
+        types::mainnet::BeaconState,
+    };
+
+    #[test]
+    fn test_merkle_branch_roundtrip() {
+        let leaves =
+            (0..5u8).map(|i| Node::try_from([i; 32].as_ref()).unwrap()).collect::<Vec<_>>();
+        let depth = 3;
+        let mut tree = leaves.clone();
+        tree.resize(8, Node::default());
+        let root = MerkleBranch::new(&tree, depth, 0).compute_root(&tree[0]);
+        for (index, leaf) in leaves.iter().enumerate() {
+            let branch = MerkleBranch::new(&leaves, depth, index);
+            assert_eq!(branch.depth(), depth);
+            assert_eq!(branch.compute_root(leaf), root);
+            assert!(is_valid_merkle_branch(leaf, branch.branch.iter(), depth, index, &root));
+        }
+    }
+
+    #[test]
+    fn test_recent_block_root_proof() {
+        let mut inner = Phase0BeaconState { slot: 100, ..Default::default() };
+        inner.block_roots[42] = Node::try_from([7u8; 32].as_ref()).unwrap();
+        let mut state = BeaconState::Phase0(inner);
+        let state_root = state.hash_tree_root().unwrap();
+
+        let proof = prove_historical_block_root(&mut state, 42, None).unwrap();
+        assert_eq!(proof.block_root, state.block_roots()[42]);
+        proof.verify(&state_root).unwrap();
+
+        let period = SLOTS_PER_HISTORICAL_ROOT as Slot;
+        assert!(prove_historical_block_root(&mut state, 100 + period, None).is_err());
+    }
+
+    #[test]
+    fn test_historical_roots_proof() {
+        let mut batch = crate::phase0::mainnet::HistoricalBatch::default();
+        batch.block_roots[3] = Node::try_from([9u8; 32].as_ref()).unwrap();
+        let batch_root = batch.hash_tree_root().unwrap();
+
+        let mut inner =
+            Phase0BeaconState { slot: 3 * SLOTS_PER_HISTORICAL_ROOT as Slot, ..Default::default() };
+        inner.historical_roots.push(batch_root);
+        let mut state = BeaconState::Phase0(inner);
+        let state_root = state.hash_tree_root().unwrap();
+
+        assert!(prove_historical_block_root(&mut state, 3, None).is_err());
+        let proof = prove_historical_block_root(&mut state, 3, Some(&mut batch)).unwrap();
+        assert_eq!(proof.block_root, batch.block_roots[3]);
+        proof.verify(&state_root).unwrap();
+    }
+
+    #[test]
+    fn test_historical_summaries_proof() {
+        let mut batch = crate::phase0::mainnet::HistoricalBatch::default();
+        batch.block_roots[5] = Node::try_from([3u8; 32].as_ref()).unwrap();
+        let summary = crate::capella::HistoricalSummary {
+            block_summary_root: batch.block_roots.hash_tree_root().unwrap(),
+            state_summary_root: batch.state_roots.hash_tree_root().unwrap(),
+        };
+
+        let period = SLOTS_PER_HISTORICAL_ROOT as Slot;
+        let mut inner =
+            crate::capella::mainnet::BeaconState { slot: 4 * period, ..Default::default() };
+        inner.historical_roots.push(Node::default());
+        inner.historical_summaries.push(Default::default());
+        inner.historical_summaries.push(summary);
+        let mut state = BeaconState::Capella(inner);
+        let state_root = state.hash_tree_root().unwrap();
+
+        let slot = 2 * period + 5;
+        let proof = prove_historical_block_root(&mut state, slot, Some(&mut batch)).unwrap();
+        assert_eq!(proof.block_root, batch.block_roots[5]);
+        proof.verify(&state_root).unwrap();
+    }
+
+    #[test]
+    fn test_historical_batch_from_state() {
+        let period = SLOTS_PER_HISTORICAL_ROOT as Slot;
+        let state =
+            BeaconState::Phase0(Phase0BeaconState { slot: period - 1, ..Default::default() });
+        assert!(historical_batch_from_state(&state).is_err());
+
+        let mut inner = Phase0BeaconState { slot: period, ..Default::default() };
+        inner.block_roots[0] = Node::try_from([1u8; 32].as_ref()).unwrap();
+        inner.state_roots[0] = Node::try_from([2u8; 32].as_ref()).unwrap();
+        let mut state = BeaconState::Phase0(inner);
+        let mut batch = historical_batch_from_state(&state).unwrap();
+        let mut summary = historical_summary_from_batch(&mut batch).unwrap();
+        state.historical_roots_mut().push(summary.hash_tree_root().unwrap());
+        verify_historical_batch(&state, 0, &mut batch).unwrap();
+        assert!(verify_historical_batch(&state, 1, &mut batch).is_err());
+
+        let block_root = batch.block_roots[0];
+        verify_block_roots_in_batch(&batch, [(period * 2, &block_root)]).unwrap();
+        assert!(verify_block_roots_in_batch(&batch, [(1, &block_root)]).is_err());
+
+        let mut other = crate::phase0::mainnet::HistoricalBatch::default();
+        assert!(verify_historical_batch(&state, 0, &mut other).is_err());
+    }
+}
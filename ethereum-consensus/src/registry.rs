@@ -0,0 +1,165 @@
+//! Compare the validator registries of two states, e.g. at consecutive epochs.
+use crate::{
+    phase0::Validator,
+    primitives::{Bytes32, Epoch, Gwei, ValidatorIndex, FAR_FUTURE_EPOCH},
+    types::BeaconState,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialsChange {
+    pub index: ValidatorIndex,
+    pub previous: Bytes32,
+    pub current: Bytes32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveBalanceChange {
+    pub index: ValidatorIndex,
+    pub previous: Gwei,
+    pub current: Gwei,
+}
+
+// Changes to the registry going from the earlier state to the later one.
+// Epoch fields are only reported once they move off `FAR_FUTURE_EPOCH`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryDiff {
+    // validators appended to the registry
+    pub deposits: Vec<ValidatorIndex>,
+    pub activations: Vec<(ValidatorIndex, Epoch)>,
+    pub exits: Vec<(ValidatorIndex, Epoch)>,
+    pub slashings: Vec<ValidatorIndex>,
+    pub credential_changes: Vec<CredentialsChange>,
+    pub effective_balance_changes: Vec<EffectiveBalanceChange>,
+}
+
+impl RegistryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.deposits.is_empty() &&
+            self.activations.is_empty() &&
+            self.exits.is_empty() &&
+            self.slashings.is_empty() &&
+            self.credential_changes.is_empty() &&
+            self.effective_balance_changes.is_empty()
+    }
+}
+
+pub fn diff_validators(previous: &[Validator], current: &[Validator]) -> RegistryDiff {
+    let mut diff = RegistryDiff::default();
+    for (index, validator) in current.iter().enumerate() {
+        let Some(prior) = previous.get(index) else {
+            diff.deposits.push(index);
+            continue
+        };
+        if prior.activation_epoch == FAR_FUTURE_EPOCH &&
+            validator.activation_epoch != FAR_FUTURE_EPOCH
+        {
+            diff.activations.push((index, validator.activation_epoch));
+        }
+        if prior.exit_epoch == FAR_FUTURE_EPOCH && validator.exit_epoch != FAR_FUTURE_EPOCH {
+            diff.exits.push((index, validator.exit_epoch));
+        }
+        if !prior.slashed && validator.slashed {
+            diff.slashings.push(index);
+        }
+        if prior.withdrawal_credentials != validator.withdrawal_credentials {
+            diff.credential_changes.push(CredentialsChange {
+                index,
+                previous: prior.withdrawal_credentials.clone(),
+                current: validator.withdrawal_credentials.clone(),
+            });
+        }
+        if prior.effective_balance != validator.effective_balance {
+            diff.effective_balance_changes.push(EffectiveBalanceChange {
+                index,
+                previous: prior.effective_balance,
+                current: validator.effective_balance,
+            });
+        }
+    }
+    diff
+}
+
+pub fn diff_registries<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+>(
+    state_a: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    state_b: &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+) -> RegistryDiff {
+    diff_validators(state_a.validators(), state_b.validators())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator(effective_balance: Gwei) -> Validator {
+        Validator {
+            effective_balance,
+            activation_eligibility_epoch: FAR_FUTURE_EPOCH,
+            activation_epoch: FAR_FUTURE_EPOCH,
+            exit_epoch: FAR_FUTURE_EPOCH,
+            withdrawable_epoch: FAR_FUTURE_EPOCH,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_validators() {
+        let previous = vec![validator(32), validator(32), validator(32)];
+        assert!(diff_validators(&previous, &previous).is_empty());
+
+        let mut current = previous.clone();
+        current[0].activation_epoch = 10;
+        current[1].exit_epoch = 12;
+        current[1].slashed = true;
+        current[2].effective_balance = 31;
+        current[2].withdrawal_credentials[0] = 0x01;
+        current.push(validator(32));
+
+        let diff = diff_validators(&previous, &current);
+        assert_eq!(diff.deposits, vec![3]);
+        assert_eq!(diff.activations, vec![(0, 10)]);
+        assert_eq!(diff.exits, vec![(1, 12)]);
+        assert_eq!(diff.slashings, vec![1]);
+        assert_eq!(diff.credential_changes.len(), 1);
+        assert_eq!(diff.credential_changes[0].index, 2);
+        assert_eq!(
+            diff.effective_balance_changes,
+            vec![EffectiveBalanceChange { index: 2, previous: 32, current: 31 }]
+        );
+    }
+}
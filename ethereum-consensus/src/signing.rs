@@ -1,7 +1,9 @@
 use crate::{
-    crypto::{self, SecretKey},
-    primitives::{BlsPublicKey, BlsSignature, Domain, Root},
+    crypto::{self, Signer},
+    phase0::{compute_domain, compute_fork_data_root, DepositData, DepositMessage, Fork},
+    primitives::{BlsPublicKey, BlsSignature, Domain, DomainType, Epoch, Root, Version},
     ssz::prelude::*,
+    state_transition::{Context, VerifiedSignatureCache},
     Error,
 };
 
@@ -21,15 +23,61 @@ pub fn compute_signing_root<T: Merkleized>(
     s.hash_tree_root().map_err(Error::Merkleization)
 }
 
-pub fn sign_with_domain<T: Merkleized>(
+// Compute the root to sign for `object` at `epoch` on the chain with the given `fork` and
+// `genesis_validators_root`, deriving the domain as `get_domain` does for a state.
+pub fn signing_root<T: Merkleized>(
+    object: &mut T,
+    domain_type: DomainType,
+    epoch: Epoch,
+    fork: &Fork,
+    genesis_validators_root: Root,
+    context: &Context,
+) -> Result<Root, Error> {
+    let fork_version =
+        if epoch < fork.epoch { fork.previous_version } else { fork.current_version };
+    let domain =
+        compute_domain(domain_type, Some(fork_version), Some(genesis_validators_root), context)?;
+    compute_signing_root(object, domain)
+}
+
+pub fn sign_with_domain<T: Merkleized, S: Signer>(
     data: &mut T,
-    signing_key: &SecretKey,
+    signing_key: &S,
     domain: Domain,
 ) -> Result<BlsSignature, Error> {
     let signing_root = compute_signing_root(data, domain)?;
     Ok(signing_key.sign(signing_root.as_ref()))
 }
 
+// Compute the domain for deposits, which is always bound to the zero genesis validators root so
+// that deposits can be made before genesis and remain valid across forks.
+pub fn compute_deposit_domain(genesis_fork_version: Version) -> Result<Domain, Error> {
+    let fork_data_root = compute_fork_data_root(genesis_fork_version, Root::default())?;
+
+    let mut domain = Domain::default();
+    domain[..4].copy_from_slice(&DomainType::Deposit.as_bytes());
+    domain[4..].copy_from_slice(&fork_data_root[..28]);
+    Ok(domain)
+}
+
+// Sign `deposit_message` under the deposit domain of the chain with the given
+// `genesis_fork_version`, returning the `DepositData` to submit to the deposit contract.
+pub fn sign_deposit_data<S: Signer>(
+    secret_key: &S,
+    deposit_message: &DepositMessage,
+    genesis_fork_version: Version,
+) -> Result<DepositData, Error> {
+    let mut deposit_message = deposit_message.clone();
+    let domain = compute_deposit_domain(genesis_fork_version)?;
+    let signature = sign_with_domain(&mut deposit_message, secret_key, domain)?;
+    Ok(DepositData {
+        public_key: deposit_message.public_key,
+        withdrawal_credentials: deposit_message.withdrawal_credentials,
+        amount: deposit_message.amount,
+        signature,
+    })
+}
+
 pub fn verify_signed_data<T: Merkleized>(
     data: &mut T,
     signature: &BlsSignature,
@@ -52,3 +100,161 @@ pub fn verify_signature(
 ) -> Result<(), Error> {
     crypto::verify_signature(public_key, signing_root, signature).map_err(Into::into)
 }
+
+// Signatures collected for verification in a single batch. Each entry carries the error to
+// report if its signature turns out to be invalid.
+#[derive(Debug, Default)]
+pub struct SignatureBatch<'a> {
+    entries: Vec<(Vec<&'a BlsPublicKey>, Root, &'a BlsSignature, Error)>,
+}
+
+impl<'a> SignatureBatch<'a> {
+    pub fn push(
+        &mut self,
+        public_keys: Vec<&'a BlsPublicKey>,
+        signing_root: Root,
+        signature: &'a BlsSignature,
+        error: Error,
+    ) {
+        self.entries.push((public_keys, signing_root, signature, error));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Verify every signature not already in `cache` with one multi-pairing and record them in
+    // `cache`. If the batch fails, fall back to verifying each signature in turn so that the
+    // first invalid one is reported with its error.
+    pub fn verify(self, cache: &VerifiedSignatureCache) -> Result<(), Error> {
+        let entries = self
+            .entries
+            .into_iter()
+            .filter(|(public_keys, signing_root, signature, _)| {
+                !cache.contains(public_keys, signing_root, signature)
+            })
+            .collect::<Vec<_>>();
+        let sets = entries
+            .iter()
+            .map(|(public_keys, signing_root, signature, _)| crypto::SignatureSet {
+                public_keys: public_keys.clone(),
+                message: signing_root.as_ref(),
+                signature,
+            })
+            .collect::<Vec<_>>();
+        let all_valid = crypto::verify_signature_sets(&sets).is_ok();
+        drop(sets);
+
+        for (public_keys, signing_root, signature, error) in entries {
+            if !all_valid &&
+                crypto::fast_aggregate_verify(&public_keys, signing_root.as_ref(), signature)
+                    .is_err()
+            {
+                return Err(error)
+            }
+            cache.insert(&public_keys, &signing_root, signature);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::SecretKey;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_signature_batch_reports_invalid_signature() {
+        let mut rng = thread_rng();
+        let secret_keys = (0..3).map(|_| SecretKey::random(&mut rng).unwrap()).collect::<Vec<_>>();
+        let public_keys = secret_keys.iter().map(|sk| sk.public_key()).collect::<Vec<_>>();
+        let roots = (0..3u8).map(|i| Root::try_from([i; 32].as_ref()).unwrap()).collect::<Vec<_>>();
+        let signatures = secret_keys
+            .iter()
+            .zip(roots.iter())
+            .map(|(sk, root)| sk.sign(root.as_ref()))
+            .collect::<Vec<_>>();
+
+        let mut batch = SignatureBatch::default();
+        for i in 0..3 {
+            batch.push(vec![&public_keys[i]], roots[i], &signatures[i], Error::Overflow);
+        }
+        assert_eq!(batch.len(), 3);
+        let cache = VerifiedSignatureCache::default();
+        assert!(batch.verify(&cache).is_ok());
+        assert_eq!(cache.len(), 3);
+
+        let mut batch = SignatureBatch::default();
+        batch.push(vec![&public_keys[0]], roots[0], &signatures[0], Error::Overflow);
+        batch.push(vec![&public_keys[1]], roots[2], &signatures[1], Error::InvalidStateRoot);
+        batch.push(vec![&public_keys[2]], roots[2], &signatures[2], Error::Underflow);
+        let cache = VerifiedSignatureCache::default();
+        assert!(matches!(batch.verify(&cache), Err(Error::InvalidStateRoot)));
+        assert!(cache.contains(&[&public_keys[0]], &roots[0], &signatures[0]));
+        assert!(!cache.contains(&[&public_keys[1]], &roots[2], &signatures[1]));
+    }
+
+    #[test]
+    fn test_signing_root_uses_fork_version_at_epoch() {
+        let context = Context::for_mainnet();
+        let fork =
+            Fork { previous_version: [1, 0, 0, 0], current_version: [2, 0, 0, 0], epoch: 10 };
+        let genesis_validators_root = Root::try_from([7u8; 32].as_ref()).unwrap();
+        let domain_type = DomainType::BeaconAttester;
+        let mut object = Root::try_from([3u8; 32].as_ref()).unwrap();
+
+        for (epoch, fork_version) in [(9, fork.previous_version), (10, fork.current_version)] {
+            let domain = compute_domain(
+                domain_type,
+                Some(fork_version),
+                Some(genesis_validators_root),
+                &context,
+            )
+            .unwrap();
+            let expected = compute_signing_root(&mut object, domain).unwrap();
+            let root = signing_root(
+                &mut object,
+                domain_type,
+                epoch,
+                &fork,
+                genesis_validators_root,
+                &context,
+            )
+            .unwrap();
+            assert_eq!(root, expected);
+        }
+    }
+
+    #[test]
+    fn test_sign_deposit_data() {
+        let context = Context::for_mainnet();
+        let domain = compute_deposit_domain(context.genesis_fork_version).unwrap();
+        assert_eq!(domain, compute_domain(DomainType::Deposit, None, None, &context).unwrap());
+
+        let mut rng = thread_rng();
+        let secret_key = SecretKey::random(&mut rng).unwrap();
+        let mut deposit_message = DepositMessage {
+            public_key: secret_key.public_key(),
+            amount: context.max_effective_balance,
+            ..Default::default()
+        };
+        let deposit_data =
+            sign_deposit_data(&secret_key, &deposit_message, context.genesis_fork_version).unwrap();
+        assert_eq!(deposit_data.public_key, deposit_message.public_key);
+        assert_eq!(deposit_data.amount, deposit_message.amount);
+
+        let public_key = deposit_message.public_key.clone();
+        assert!(verify_signed_data(
+            &mut deposit_message,
+            &deposit_data.signature,
+            &public_key,
+            domain
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,83 @@
+use ssz_rs::prelude::Bitlist;
+
+// Set operations over bitlists, e.g. the `aggregation_bits` of attestations for the
+// same committee. Bitlists of different lengths never overlap and cannot be merged.
+pub trait BitlistExt: Sized {
+    fn set_indices(&self) -> Vec<usize>;
+
+    fn count_ones(&self) -> usize;
+
+    fn intersects(&self, other: &Self) -> bool;
+
+    fn is_subset(&self, other: &Self) -> bool;
+
+    fn union(&self, other: &Self) -> Option<Self>;
+}
+
+impl<const N: usize> BitlistExt for Bitlist<N> {
+    fn set_indices(&self) -> Vec<usize> {
+        (0..self.len()).filter(|&i| self[i]).collect()
+    }
+
+    fn count_ones(&self) -> usize {
+        (0..self.len()).filter(|&i| self[i]).count()
+    }
+
+    fn intersects(&self, other: &Self) -> bool {
+        self.len() == other.len() && (0..self.len()).any(|i| self[i] && other[i])
+    }
+
+    // `true` if every bit set in `self` is also set in `other`
+    fn is_subset(&self, other: &Self) -> bool {
+        self.len() == other.len() && (0..self.len()).all(|i| !self[i] || other[i])
+    }
+
+    fn union(&self, other: &Self) -> Option<Self> {
+        if self.len() != other.len() {
+            return None
+        }
+        let mut result = self.clone();
+        for i in other.set_indices() {
+            result.set(i, true);
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitlist(bits: &[bool]) -> Bitlist<16> {
+        let mut result = Bitlist::default();
+        for &bit in bits {
+            result.push(bit);
+        }
+        result
+    }
+
+    #[test]
+    fn test_bitlist_set_operations() {
+        let a = bitlist(&[true, false, false, true]);
+        let b = bitlist(&[false, true, false, true]);
+        let c = bitlist(&[false, true, false, false]);
+        let short = bitlist(&[true, true]);
+
+        assert_eq!(a.set_indices(), vec![0, 3]);
+        assert_eq!(BitlistExt::count_ones(&a), 2);
+
+        assert!(a.intersects(&b));
+        assert!(!a.intersects(&c));
+        assert!(!a.intersects(&short));
+
+        assert!(c.is_subset(&b));
+        assert!(!b.is_subset(&c));
+        assert!(!c.is_subset(&short));
+
+        let union = a.union(&b).unwrap();
+        assert_eq!(union.set_indices(), vec![0, 1, 3]);
+        assert_eq!(union.len(), 4);
+        assert!(a.is_subset(&union) && b.is_subset(&union));
+        assert!(a.union(&short).is_none());
+    }
+}
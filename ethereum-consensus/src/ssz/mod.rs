@@ -1,7 +1,9 @@
+mod bitlist;
 mod byte_list;
 mod byte_vector;
+pub mod stable_container;
 
 pub mod prelude {
-    pub use super::{byte_list::ByteList, byte_vector::ByteVector};
+    pub use super::{bitlist::BitlistExt, byte_list::ByteList, byte_vector::ByteVector};
     pub use ssz_rs::prelude::*;
 }
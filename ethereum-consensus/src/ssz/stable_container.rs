@@ -0,0 +1,748 @@
+//! SSZ `StableContainer` and `Profile` types, following EIP-7495.
+//!
+//! A `StableContainer[N]` has only optional fields and always merkleizes as if it had
+//! `N` of them, so the generalized index of a field does not move as fields are added
+//! or removed across forks. A `Profile` of a `StableContainer` restricts which fields
+//! are present, which shortens the encoding, but keeps the `hash_tree_root` of the base.
+//!
+//! Types are declared with the `stable_container!` and `profile!` macros; the items
+//! in this module are the support code they expand to. Existing containers can be
+//! mapped onto a `StableContainer` with `impl_profile!`, which keeps their encoding
+//! and exposes the root they would have as a `Profile` through `AsProfile`.
+use crate::{crypto::hash, ssz::prelude::*};
+use std::{ops::Range, vec};
+use thiserror::Error;
+
+const BYTES_PER_CHUNK: usize = 32;
+const BITS_PER_CHUNK: usize = 8 * BYTES_PER_CHUNK;
+const BYTES_PER_LENGTH_OFFSET: usize = 4;
+
+type Chunk = [u8; BYTES_PER_CHUNK];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("field `{0}` is required by the profile but is not active")]
+    MissingField(&'static str),
+    #[error("container has active fields that are not part of the profile")]
+    ExtraFields,
+}
+
+fn div_round_up(n: usize, d: usize) -> usize {
+    let quotient = n / d;
+    if quotient * d < n {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+fn byte_length(bits: usize) -> usize {
+    div_round_up(bits, 8)
+}
+
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; byte_length(bits.len())];
+    for (i, _) in bits.iter().enumerate().filter(|(_, &bit)| bit) {
+        bytes[i / 8] |= 1 << (i % 8);
+    }
+    bytes
+}
+
+fn hash_pair(left: &Chunk, right: &Chunk) -> Chunk {
+    let mut data = [0u8; 2 * BYTES_PER_CHUNK];
+    data[..BYTES_PER_CHUNK].copy_from_slice(left);
+    data[BYTES_PER_CHUNK..].copy_from_slice(right);
+    let mut result = Chunk::default();
+    result.copy_from_slice(hash(data).as_ref());
+    result
+}
+
+fn merkleize_chunks(chunks: Vec<Chunk>, limit: usize) -> Chunk {
+    let depth = limit.next_power_of_two().trailing_zeros();
+    let mut layer = chunks;
+    let mut zero_hash = Chunk::default();
+    for _ in 0..depth {
+        if layer.len() % 2 == 1 {
+            layer.push(zero_hash);
+        }
+        layer = layer.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+        zero_hash = hash_pair(&zero_hash, &zero_hash);
+    }
+    layer.first().copied().unwrap_or(zero_hash)
+}
+
+// Return the ``hash_tree_root`` of a ``StableContainer[capacity]`` given the roots of its
+// fields, where inactive fields are ``None``.
+pub fn merkleize_stable_container(field_roots: &[Option<Node>], capacity: usize) -> Node {
+    let chunks = field_roots
+        .iter()
+        .map(|root| {
+            let mut chunk = Chunk::default();
+            if let Some(root) = root {
+                chunk.copy_from_slice(root.as_ref());
+            }
+            chunk
+        })
+        .collect();
+    let root = merkleize_chunks(chunks, capacity);
+
+    let active_fields = field_roots.iter().map(Option::is_some).collect::<Vec<_>>();
+    let bits_chunks = pack_bits(&active_fields)
+        .chunks(BYTES_PER_CHUNK)
+        .map(|bytes| {
+            let mut chunk = Chunk::default();
+            chunk[..bytes.len()].copy_from_slice(bytes);
+            chunk
+        })
+        .collect();
+    let bits_limit = div_round_up(capacity, BITS_PER_CHUNK);
+    let active_fields_root = merkleize_chunks(bits_chunks, bits_limit);
+
+    let mut node = Node::default();
+    node.as_mut().copy_from_slice(&hash_pair(&root, &active_fields_root));
+    node
+}
+
+// A container that is already laid out as a `Profile` of `Base` with every field required.
+//
+// A `Profile` without optional fields serializes exactly like a plain container with the same
+// fields, so only the `hash_tree_root` changes when such a container moves onto `Base`.
+pub trait AsProfile: Clone + Into<Self::Base> + TryFrom<Self::Base, Error = Error> {
+    type Base: Merkleized;
+
+    // The `hash_tree_root` of this value as a `Profile` of `Base`
+    fn profile_hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        let mut base: Self::Base = self.clone().into();
+        base.hash_tree_root()
+    }
+}
+
+// Writes the fields that are present, in order, as an SSZ container, optionally
+// preceded by a bitvector of which optional fields are active.
+#[derive(Default)]
+pub struct Encoder {
+    active_fields_width: Option<usize>,
+    active_fields: Vec<bool>,
+    // encoding of each field and whether it is variable-size
+    parts: Vec<(Vec<u8>, bool)>,
+}
+
+impl Encoder {
+    pub fn with_active_fields(width: usize) -> Self {
+        Self { active_fields_width: Some(width), ..Default::default() }
+    }
+
+    pub fn push<T: SimpleSerialize>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let mut encoding = Vec::with_capacity(T::size_hint());
+        value.serialize(&mut encoding)?;
+        self.parts.push((encoding, T::is_variable_size()));
+        Ok(())
+    }
+
+    pub fn push_optional<T: SimpleSerialize>(
+        &mut self,
+        value: &Option<T>,
+    ) -> Result<(), SerializeError> {
+        self.active_fields.push(value.is_some());
+        match value {
+            Some(value) => self.push(value),
+            None => Ok(()),
+        }
+    }
+
+    pub fn finish(mut self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        let start = buffer.len();
+        if let Some(width) = self.active_fields_width {
+            self.active_fields.resize(width, false);
+            buffer.extend(pack_bits(&self.active_fields));
+        }
+
+        let fixed_length = self
+            .parts
+            .iter()
+            .map(
+                |(encoding, is_variable)| {
+                    if *is_variable {
+                        BYTES_PER_LENGTH_OFFSET
+                    } else {
+                        encoding.len()
+                    }
+                },
+            )
+            .sum::<usize>();
+        let mut offset = fixed_length;
+        for (encoding, is_variable) in &self.parts {
+            if *is_variable {
+                let offset_bytes = u32::try_from(offset)
+                    .map_err(|_| SerializeError::MaximumEncodedLengthReached(offset))?;
+                buffer.extend(offset_bytes.to_le_bytes());
+                offset += encoding.len();
+            } else {
+                buffer.extend(encoding);
+            }
+        }
+        if u32::try_from(offset).is_err() {
+            return Err(SerializeError::MaximumEncodedLengthReached(offset))
+        }
+        for (encoding, is_variable) in self.parts {
+            if is_variable {
+                buffer.extend(encoding);
+            }
+        }
+        Ok(buffer.len() - start)
+    }
+}
+
+// Reads back what an `Encoder` wrote, in two passes: first the caller declares the type of
+// each field that is present with `expect`, then decodes them in order from `Fields`.
+pub struct Decoder<'a> {
+    encoding: &'a [u8],
+    active_fields: Vec<bool>,
+    position: usize,
+    // `Some(size)` for fixed-size fields, `None` for variable-size fields
+    layout: Vec<Option<usize>>,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(encoding: &'a [u8]) -> Self {
+        Self { encoding, active_fields: vec![], position: 0, layout: vec![] }
+    }
+
+    // Read a bitvector of `width` bits off the front of `encoding`; bits past `field_count`
+    // must not be set.
+    pub fn with_active_fields(
+        encoding: &'a [u8],
+        width: usize,
+        field_count: usize,
+    ) -> Result<Self, DeserializeError> {
+        let length = byte_length(width);
+        if encoding.len() < length {
+            return Err(DeserializeError::ExpectedFurtherInput {
+                provided: encoding.len(),
+                expected: length,
+            })
+        }
+        let (bits, encoding) = encoding.split_at(length);
+        let is_set = |i: usize| (bits[i / 8] >> (i % 8)) & 1 == 1;
+        if let Some(i) = (field_count..8 * length).find(|&i| is_set(i)) {
+            return Err(DeserializeError::InvalidByte(bits[i / 8]))
+        }
+        let active_fields = (0..field_count).map(is_set).collect();
+        Ok(Self { encoding, active_fields, position: 0, layout: vec![] })
+    }
+
+    pub fn expect<T: SimpleSerialize>(&mut self) {
+        let size = if T::is_variable_size() { None } else { Some(T::size_hint()) };
+        self.layout.push(size);
+    }
+
+    pub fn expect_optional<T: SimpleSerialize>(&mut self) {
+        let is_active = self.active_fields.get(self.position).copied().unwrap_or_default();
+        self.position += 1;
+        if is_active {
+            self.expect::<T>();
+        }
+    }
+
+    pub fn into_fields(self) -> Result<Fields<'a>, DeserializeError> {
+        let encoding = self.encoding;
+        let mut spans = Vec::with_capacity(self.layout.len());
+        let mut offsets = vec![];
+        let mut cursor = 0;
+        for size in self.layout {
+            let width = size.unwrap_or(BYTES_PER_LENGTH_OFFSET);
+            if cursor + width > encoding.len() {
+                return Err(DeserializeError::ExpectedFurtherInput {
+                    provided: encoding.len(),
+                    expected: cursor + width,
+                })
+            }
+            if size.is_none() {
+                let mut offset = [0u8; BYTES_PER_LENGTH_OFFSET];
+                offset.copy_from_slice(&encoding[cursor..cursor + width]);
+                offsets.push((spans.len(), u32::from_le_bytes(offset) as usize));
+            }
+            spans.push(cursor..cursor + width);
+            cursor += width;
+        }
+
+        match offsets.first() {
+            Some(&(_, first_offset)) if first_offset != cursor => {
+                return Err(DeserializeError::OffsetNotIncreasing {
+                    start: cursor,
+                    end: first_offset,
+                })
+            }
+            None if cursor != encoding.len() => {
+                return Err(DeserializeError::AdditionalInput {
+                    provided: encoding.len(),
+                    expected: cursor,
+                })
+            }
+            _ => {}
+        }
+        for (i, &(span_index, start)) in offsets.iter().enumerate() {
+            let end = offsets.get(i + 1).map(|&(_, offset)| offset).unwrap_or(encoding.len());
+            if start > end {
+                return Err(DeserializeError::OffsetNotIncreasing { start, end })
+            }
+            if end > encoding.len() {
+                return Err(DeserializeError::ExpectedFurtherInput {
+                    provided: encoding.len(),
+                    expected: end,
+                })
+            }
+            spans[span_index] = start..end;
+        }
+
+        Ok(Fields {
+            encoding,
+            active_fields: self.active_fields.into_iter(),
+            spans: spans.into_iter(),
+        })
+    }
+}
+
+pub struct Fields<'a> {
+    encoding: &'a [u8],
+    active_fields: vec::IntoIter<bool>,
+    spans: vec::IntoIter<Range<usize>>,
+}
+
+impl<'a> Fields<'a> {
+    pub fn decode<T: SimpleSerialize>(&mut self) -> Result<T, DeserializeError> {
+        let span = self.spans.next().expect("field was expected by the decoder");
+        T::deserialize(&self.encoding[span])
+    }
+
+    pub fn decode_optional<T: SimpleSerialize>(&mut self) -> Result<Option<T>, DeserializeError> {
+        if self.active_fields.next().unwrap_or_default() {
+            self.decode().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// Define a `StableContainer[N]`. Each declared field is stored as an `Option` of its type.
+//
+// The struct must also derive (or implement) `Default` to be `SimpleSerialize`.
+#[macro_export]
+macro_rules! stable_container {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident[$capacity:expr] {
+            $($field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: Option<$ty>,)*
+        }
+
+        const _: () = assert!(
+            [$(stringify!($field)),*].len() <= $capacity,
+            "stable container has more fields than its capacity"
+        );
+
+        impl $name {
+            // Whether each field is present, in declaration order
+            pub fn active_fields(&self) -> Vec<bool> {
+                vec![$(self.$field.is_some()),*]
+            }
+        }
+
+        impl $crate::ssz::prelude::Serialize for $name {
+            fn serialize(
+                &self,
+                buffer: &mut Vec<u8>,
+            ) -> Result<usize, $crate::ssz::prelude::SerializeError> {
+                let mut encoder =
+                    $crate::ssz::stable_container::Encoder::with_active_fields($capacity);
+                $(encoder.push_optional(&self.$field)?;)*
+                encoder.finish(buffer)
+            }
+        }
+
+        impl $crate::ssz::prelude::Deserialize for $name {
+            fn deserialize(
+                encoding: &[u8],
+            ) -> Result<Self, $crate::ssz::prelude::DeserializeError> {
+                let field_count = [$(stringify!($field)),*].len();
+                let mut decoder = $crate::ssz::stable_container::Decoder::with_active_fields(
+                    encoding,
+                    $capacity,
+                    field_count,
+                )?;
+                $(decoder.expect_optional::<$ty>();)*
+                let mut fields = decoder.into_fields()?;
+                Ok(Self { $($field: fields.decode_optional::<$ty>()?,)* })
+            }
+        }
+
+        impl $crate::ssz::prelude::Sized for $name {
+            fn is_variable_size() -> bool {
+                true
+            }
+
+            fn size_hint() -> usize {
+                0
+            }
+        }
+
+        impl $crate::ssz::prelude::Merkleized for $name {
+            fn hash_tree_root(
+                &mut self,
+            ) -> Result<$crate::ssz::prelude::Node, $crate::ssz::prelude::MerkleizationError> {
+                let field_roots = vec![$(
+                    self.$field
+                        .as_mut()
+                        .map($crate::ssz::prelude::Merkleized::hash_tree_root)
+                        .transpose()?,
+                )*];
+                Ok($crate::ssz::stable_container::merkleize_stable_container(
+                    &field_roots,
+                    $capacity,
+                ))
+            }
+        }
+
+        impl $crate::ssz::prelude::SimpleSerialize for $name {}
+    };
+}
+
+// Define a `Profile` of a type declared with `stable_container!`. Each field is marked
+// `required` or `optional`, must have the same name and type as in the base, and fields
+// must be listed in the same order as in the base.
+//
+// The struct must also derive (or implement) `Default` and `Clone`.
+#[macro_export]
+macro_rules! profile {
+    (@field_type required $ty:ty) => { $ty };
+    (@field_type optional $ty:ty) => { Option<$ty> };
+    (@optional_count required) => { 0 };
+    (@optional_count optional) => { 1 };
+    (@encode required $encoder:ident $value:expr) => { $encoder.push(&$value)? };
+    (@encode optional $encoder:ident $value:expr) => { $encoder.push_optional(&$value)? };
+    (@expect required $decoder:ident $ty:ty) => { $decoder.expect::<$ty>() };
+    (@expect optional $decoder:ident $ty:ty) => { $decoder.expect_optional::<$ty>() };
+    (@decode required $fields:ident $ty:ty) => { $fields.decode::<$ty>()? };
+    (@decode optional $fields:ident $ty:ty) => { $fields.decode_optional::<$ty>()? };
+    (@to_base required $value:expr) => { Some($value) };
+    (@to_base optional $value:expr) => { $value };
+    (@from_base required $field:ident $value:expr) => {
+        $value.ok_or($crate::ssz::stable_container::Error::MissingField(stringify!($field)))?
+    };
+    (@from_base optional $field:ident $value:expr) => { $value };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident: $base:ty {
+            $($field_vis:vis $field:ident: $kind:ident $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $crate::profile!(@field_type $kind $ty),)*
+        }
+
+        impl $name {
+            const OPTIONAL_FIELD_COUNT: usize = 0 $(+ $crate::profile!(@optional_count $kind))*;
+        }
+
+        impl From<$name> for $base {
+            #[allow(clippy::needless_update)]
+            fn from(value: $name) -> Self {
+                Self {
+                    $($field: $crate::profile!(@to_base $kind value.$field),)*
+                    ..Default::default()
+                }
+            }
+        }
+
+        impl TryFrom<$base> for $name {
+            type Error = $crate::ssz::stable_container::Error;
+
+            fn try_from(value: $base) -> Result<Self, Self::Error> {
+                let active_fields = value.active_fields();
+                let profile = Self {
+                    $($field: $crate::profile!(@from_base $kind $field value.$field),)*
+                };
+                if <$base>::from(profile.clone()).active_fields() != active_fields {
+                    return Err($crate::ssz::stable_container::Error::ExtraFields)
+                }
+                Ok(profile)
+            }
+        }
+
+        impl $crate::ssz::prelude::Serialize for $name {
+            fn serialize(
+                &self,
+                buffer: &mut Vec<u8>,
+            ) -> Result<usize, $crate::ssz::prelude::SerializeError> {
+                let mut encoder = if Self::OPTIONAL_FIELD_COUNT > 0 {
+                    $crate::ssz::stable_container::Encoder::with_active_fields(
+                        Self::OPTIONAL_FIELD_COUNT,
+                    )
+                } else {
+                    $crate::ssz::stable_container::Encoder::default()
+                };
+                $($crate::profile!(@encode $kind encoder self.$field);)*
+                encoder.finish(buffer)
+            }
+        }
+
+        impl $crate::ssz::prelude::Deserialize for $name {
+            fn deserialize(
+                encoding: &[u8],
+            ) -> Result<Self, $crate::ssz::prelude::DeserializeError> {
+                let mut decoder = if Self::OPTIONAL_FIELD_COUNT > 0 {
+                    $crate::ssz::stable_container::Decoder::with_active_fields(
+                        encoding,
+                        Self::OPTIONAL_FIELD_COUNT,
+                        Self::OPTIONAL_FIELD_COUNT,
+                    )?
+                } else {
+                    $crate::ssz::stable_container::Decoder::new(encoding)
+                };
+                $($crate::profile!(@expect $kind decoder $ty);)*
+                let mut fields = decoder.into_fields()?;
+                Ok(Self { $($field: $crate::profile!(@decode $kind fields $ty),)* })
+            }
+        }
+
+        impl $crate::ssz::prelude::Sized for $name {
+            fn is_variable_size() -> bool {
+                Self::OPTIONAL_FIELD_COUNT > 0
+                    $(|| <$ty as $crate::ssz::prelude::Sized>::is_variable_size())*
+            }
+
+            fn size_hint() -> usize {
+                if Self::is_variable_size() {
+                    0
+                } else {
+                    0 $(+ <$ty as $crate::ssz::prelude::Sized>::size_hint())*
+                }
+            }
+        }
+
+        impl $crate::ssz::prelude::Merkleized for $name {
+            fn hash_tree_root(
+                &mut self,
+            ) -> Result<$crate::ssz::prelude::Node, $crate::ssz::prelude::MerkleizationError> {
+                <$base>::from(self.clone()).hash_tree_root()
+            }
+        }
+
+        impl $crate::ssz::prelude::SimpleSerialize for $name {}
+    };
+}
+
+// Map an existing container onto a type declared with `stable_container!`, as a `Profile`
+// where every listed field is required. Fields must have the same name and type as in the
+// base and be listed in the same order as in the base.
+//
+// The container must implement `Clone`.
+#[macro_export]
+macro_rules! impl_profile {
+    ($name:ty: $base:ty { $($field:ident),* $(,)? }) => {
+        impl From<$name> for $base {
+            #[allow(clippy::needless_update)]
+            fn from(value: $name) -> Self {
+                Self { $($field: Some(value.$field),)* ..Default::default() }
+            }
+        }
+
+        impl TryFrom<$base> for $name {
+            type Error = $crate::ssz::stable_container::Error;
+
+            fn try_from(value: $base) -> Result<Self, Self::Error> {
+                let active_fields = value.active_fields();
+                let container = Self {
+                    $($field: value.$field.ok_or(
+                        $crate::ssz::stable_container::Error::MissingField(stringify!($field)),
+                    )?,)*
+                };
+                if <$base>::from(container.clone()).active_fields() != active_fields {
+                    return Err($crate::ssz::stable_container::Error::ExtraFields)
+                }
+                Ok(container)
+            }
+        }
+
+        impl $crate::ssz::stable_container::AsProfile for $name {
+            type Base = $base;
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        phase0::Checkpoint,
+        primitives::{Epoch, Root},
+    };
+
+    // from the examples in EIP-7495
+    crate::stable_container! {
+        #[derive(Default, Debug, Clone, PartialEq, Eq)]
+        pub struct Shape[4] {
+            pub side: u16,
+            pub color: u8,
+            pub radius: u16,
+        }
+    }
+
+    crate::profile! {
+        #[derive(Default, Debug, Clone, PartialEq, Eq)]
+        pub struct Square: Shape {
+            pub side: required u16,
+            pub color: required u8,
+        }
+    }
+
+    crate::profile! {
+        #[derive(Default, Debug, Clone, PartialEq, Eq)]
+        pub struct Circle: Shape {
+            pub color: required u8,
+            pub radius: required u16,
+        }
+    }
+
+    crate::stable_container! {
+        #[derive(Default, Debug, Clone, PartialEq, Eq)]
+        pub struct Record[8] {
+            pub id: u64,
+            pub name: List<u8, 32>,
+            pub tags: List<u8, 4>,
+        }
+    }
+
+    crate::profile! {
+        #[derive(Default, Debug, Clone, PartialEq, Eq)]
+        pub struct NamedRecord: Record {
+            pub id: required u64,
+            pub name: optional List<u8, 32>,
+        }
+    }
+
+    crate::stable_container! {
+        #[derive(Default, Debug, Clone, PartialEq, Eq)]
+        pub struct StableCheckpoint[4] {
+            pub epoch: Epoch,
+            pub root: Root,
+            pub slot: u64,
+        }
+    }
+
+    crate::impl_profile!(Checkpoint: StableCheckpoint { epoch, root });
+
+    // The same Merkle tree as a `Shape`, without the `active_fields` mix-in
+    #[derive(Default, Debug, SimpleSerialize)]
+    struct ShapeFields {
+        side: u16,
+        color: u8,
+        radius: u16,
+        padding: u8,
+    }
+
+    #[test]
+    fn test_stable_container() {
+        let mut shape = Shape { side: Some(0x42), color: Some(1), radius: None };
+        let encoding = serialize(&shape).unwrap();
+        assert_eq!(encoding, [0x03, 0x42, 0x00, 0x01]);
+        assert_eq!(Shape::deserialize(&encoding).unwrap(), shape);
+
+        let mut fields = ShapeFields { side: 0x42, color: 1, ..Default::default() };
+        let mut active_fields = Bitvector::<4>::default();
+        active_fields.set(0, true);
+        active_fields.set(1, true);
+        let mut data = fields.hash_tree_root().unwrap().as_ref().to_vec();
+        data.extend_from_slice(active_fields.hash_tree_root().unwrap().as_ref());
+        assert_eq!(shape.hash_tree_root().unwrap(), &hash(data)[..]);
+
+        // only 3 of the 4 bits refer to fields
+        assert!(Shape::deserialize(&[0x08]).is_err());
+        assert!(Shape::deserialize(&[0x03, 0x42]).is_err());
+        assert!(Shape::deserialize(&[0x03, 0x42, 0x00, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let mut shape = Shape { side: Some(0x42), color: Some(1), radius: None };
+        let mut square = Square::try_from(shape.clone()).unwrap();
+        assert_eq!(square, Square { side: 0x42, color: 1 });
+        assert_eq!(serialize(&square).unwrap(), [0x42, 0x00, 0x01]);
+        assert_eq!(square.hash_tree_root().unwrap(), shape.hash_tree_root().unwrap());
+        assert!(!Square::is_variable_size());
+        assert_eq!(Square::size_hint(), 3);
+
+        assert!(matches!(Circle::try_from(shape.clone()), Err(Error::MissingField("radius"))));
+        shape.radius = Some(7);
+        assert!(matches!(Square::try_from(shape), Err(Error::ExtraFields)));
+    }
+
+    #[test]
+    fn test_variable_size_fields() {
+        let name = List::<u8, 32>::try_from(b"validator".to_vec()).unwrap();
+        let tags = List::<u8, 4>::try_from(vec![1, 2]).unwrap();
+        let mut record = Record { id: Some(3), name: Some(name.clone()), tags: Some(tags) };
+        let encoding = serialize(&record).unwrap();
+        assert_eq!(Record::deserialize(&encoding).unwrap(), record);
+        let mut truncated = encoding.clone();
+        truncated[9] = 0xff;
+        assert!(Record::deserialize(&truncated).is_err());
+
+        record.tags = None;
+        let mut named = NamedRecord::try_from(record.clone()).unwrap();
+        let encoding = serialize(&named).unwrap();
+        assert_eq!(encoding[0], 0x01);
+        assert_eq!(NamedRecord::deserialize(&encoding).unwrap(), named);
+        assert_eq!(named.hash_tree_root().unwrap(), record.hash_tree_root().unwrap());
+
+        let mut unnamed = NamedRecord { id: 3, name: None };
+        let encoding = serialize(&unnamed).unwrap();
+        assert_eq!(encoding, [0x00, 0x03, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(NamedRecord::deserialize(&encoding).unwrap(), unnamed);
+        record.name = None;
+        assert_eq!(unnamed.hash_tree_root().unwrap(), record.hash_tree_root().unwrap());
+    }
+
+    #[test]
+    fn test_container_as_profile() {
+        let mut checkpoint =
+            Checkpoint { epoch: 11, root: Root::try_from([7u8; 32].as_ref()).unwrap() };
+        let mut stable_checkpoint = StableCheckpoint::from(checkpoint.clone());
+        assert_eq!(
+            stable_checkpoint,
+            StableCheckpoint { epoch: Some(11), root: Some(checkpoint.root), slot: None }
+        );
+        assert_eq!(Checkpoint::try_from(stable_checkpoint.clone()).unwrap(), checkpoint);
+
+        // the encoding of the container is that of the base without `active_fields`
+        let encoding = serialize(&checkpoint).unwrap();
+        let mut stable_encoding = vec![0x03];
+        stable_encoding.extend_from_slice(&encoding);
+        assert_eq!(serialize(&stable_checkpoint).unwrap(), stable_encoding);
+        assert_eq!(Checkpoint::deserialize(&encoding).unwrap(), checkpoint);
+
+        // only the root moves onto the base
+        let field_roots = [
+            Some(checkpoint.epoch.hash_tree_root().unwrap()),
+            Some(checkpoint.root.hash_tree_root().unwrap()),
+            None,
+        ];
+        let root = merkleize_stable_container(&field_roots, 4);
+        assert_eq!(checkpoint.profile_hash_tree_root().unwrap(), root);
+        assert_eq!(stable_checkpoint.hash_tree_root().unwrap(), root);
+        assert_ne!(checkpoint.hash_tree_root().unwrap(), root);
+
+        stable_checkpoint.slot = Some(3);
+        assert!(matches!(Checkpoint::try_from(stable_checkpoint.clone()), Err(Error::ExtraFields)));
+        stable_checkpoint.root = None;
+        assert!(matches!(
+            Checkpoint::try_from(stable_checkpoint),
+            Err(Error::MissingField("root"))
+        ));
+    }
+}
@@ -0,0 +1,199 @@
+//! Persist the caches of a `Context` alongside a state so a restarted process starts warm.
+//!
+//! The snapshot is plain data and can be written with any `serde` format. It records the root of
+//! the validator registry it was taken with and a checksum of its contents. Restoring checks only
+//! these two, then loads the entries without computing shufflings or decompressing public keys
+//! again, so a warm start costs little more than reading the snapshot. The checksum detects a
+//! corrupted snapshot but not a forged one, so only restore snapshots from a trusted source.
+use crate::{
+    primitives::{BlsPublicKey, Epoch, Gwei, Root},
+    state_transition::{Context, DecompressedPublicKeySnapshot, Result, ShufflingSnapshot},
+    Error,
+};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CacheSnapshot {
+    pub registry_root: Root,
+    pub shufflings: Vec<ShufflingSnapshot>,
+    pub public_keys: Vec<BlsPublicKey>,
+    pub decompressed_public_keys: Vec<DecompressedPublicKeySnapshot>,
+    pub total_active_balances: Vec<(Epoch, Root, Gwei)>,
+    pub checksum: Root,
+}
+
+impl CacheSnapshot {
+    // Digest of every other field, with the length of each list so entries cannot shift between
+    // lists.
+    fn compute_checksum(&self) -> Root {
+        let mut hasher = Sha256::new();
+        hasher.update(self.registry_root.as_ref());
+        hasher.update((self.shufflings.len() as u64).to_le_bytes());
+        for shuffling in &self.shufflings {
+            hasher.update(shuffling.epoch.to_le_bytes());
+            hasher.update(shuffling.seed.as_ref());
+            for indices in [&shuffling.active_indices, &shuffling.shuffled_indices] {
+                hasher.update((indices.len() as u64).to_le_bytes());
+                for &index in indices {
+                    hasher.update((index as u64).to_le_bytes());
+                }
+            }
+        }
+        hasher.update((self.public_keys.len() as u64).to_le_bytes());
+        for public_key in &self.public_keys {
+            hasher.update(public_key.as_ref());
+        }
+        hasher.update((self.decompressed_public_keys.len() as u64).to_le_bytes());
+        for entry in &self.decompressed_public_keys {
+            hasher.update((entry.index as u64).to_le_bytes());
+            hasher.update(entry.public_key.as_ref());
+            hasher.update(entry.uncompressed.as_ref());
+        }
+        hasher.update((self.total_active_balances.len() as u64).to_le_bytes());
+        for (epoch, root, total) in &self.total_active_balances {
+            hasher.update(epoch.to_le_bytes());
+            hasher.update(root.as_ref());
+            hasher.update(total.to_le_bytes());
+        }
+        Root::try_from(hasher.finalize().as_slice()).expect("correct length")
+    }
+}
+
+impl Context {
+    // Take a snapshot of the caches of this context. `registry_root` is the hash tree root of the
+    // validator registry of the state the caches were used with, e.g. of `state.validators`.
+    pub fn snapshot_caches(&self, registry_root: Root) -> CacheSnapshot {
+        let mut snapshot = CacheSnapshot {
+            registry_root,
+            shufflings: self.committee_cache.snapshot(),
+            public_keys: self.pubkey_cache.snapshot(),
+            decompressed_public_keys: self.decompressed_pubkey_cache.snapshot(),
+            total_active_balances: self.total_active_balance_cache.snapshot(),
+            checksum: Root::default(),
+        };
+        snapshot.checksum = snapshot.compute_checksum();
+        snapshot
+    }
+
+    // Load `snapshot` into the caches of this context, which are shared with its clones.
+    // `registry_root` is the hash tree root of the validator registry of the state the caches
+    // will be used with. A snapshot taken with another registry or failing its checksum is
+    // rejected and nothing is loaded.
+    pub fn restore_caches(&self, snapshot: CacheSnapshot, registry_root: &Root) -> Result<()> {
+        if &snapshot.registry_root != registry_root {
+            return Err(Error::InvalidCacheSnapshot("taken with another registry".to_string()))
+        }
+        if snapshot.checksum != snapshot.compute_checksum() {
+            return Err(Error::InvalidCacheSnapshot("checksum".to_string()))
+        }
+        let CacheSnapshot {
+            shufflings,
+            public_keys,
+            decompressed_public_keys,
+            total_active_balances,
+            ..
+        } = snapshot;
+        self.decompressed_pubkey_cache.restore(decompressed_public_keys)?;
+        self.committee_cache.restore(shufflings);
+        self.pubkey_cache.restore(public_keys);
+        self.total_active_balance_cache.restore(total_active_balances);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::{DecompressedPublicKey, SecretKey},
+        phase0::Validator,
+        primitives::Bytes32,
+    };
+
+    fn validators(count: u8) -> Vec<Validator> {
+        (1..=count)
+            .map(|seed| {
+                let public_key = SecretKey::key_gen(&[seed; 32]).unwrap().public_key();
+                Validator { public_key, ..Default::default() }
+            })
+            .collect()
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_restore_cache_snapshot() {
+        let context = Context::for_minimal();
+        let validators = validators(4);
+        let registry_root = Root::try_from([9u8; 32].as_ref()).unwrap();
+        let seed = Bytes32::try_from([7u8; 32].as_ref()).unwrap();
+        let active_indices = vec![0, 1, 2, 3];
+        let shuffling = context
+            .committee_cache
+            .get_or_compute(3, &seed, active_indices.clone(), &context)
+            .unwrap();
+        context.pubkey_cache.get_validator_index(&validators, &validators[3].public_key);
+        context.decompressed_pubkey_cache.get(&validators, 2).unwrap().unwrap();
+        context.total_active_balance_cache.insert(3, Root::default(), 128);
+
+        let json = serde_json::to_string(&context.snapshot_caches(registry_root)).unwrap();
+        let snapshot: CacheSnapshot = serde_json::from_str(&json).unwrap();
+        let restored = Context::for_minimal();
+        restored.restore_caches(snapshot, &registry_root).unwrap();
+
+        assert_eq!(restored.committee_cache.len(), 1);
+        assert_eq!(restored.pubkey_cache.len(), 4);
+        assert_eq!(restored.decompressed_pubkey_cache.len(), 1);
+        assert_eq!(restored.total_active_balance_cache.get(3, &Root::default()), Some(128));
+        assert_eq!(
+            restored.decompressed_pubkey_cache.get(&validators, 2).unwrap().unwrap(),
+            DecompressedPublicKey::try_from(&validators[2].public_key).unwrap()
+        );
+        assert_eq!(
+            restored.pubkey_cache.get_validator_index(&validators, &validators[1].public_key),
+            Some(1)
+        );
+        let restored_shuffling =
+            restored.committee_cache.get_or_compute(3, &seed, active_indices, &restored).unwrap();
+        assert_eq!(restored_shuffling, shuffling);
+    }
+
+    #[test]
+    fn test_restore_rejects_invalid_snapshot() {
+        let context = Context::for_minimal();
+        let validators = validators(2);
+        let registry_root = Root::try_from([9u8; 32].as_ref()).unwrap();
+        context.decompressed_pubkey_cache.get(&validators, 0).unwrap().unwrap();
+        context.decompressed_pubkey_cache.get(&validators, 1).unwrap().unwrap();
+        let seed = Bytes32::try_from([7u8; 32].as_ref()).unwrap();
+        context.committee_cache.get_or_compute(3, &seed, vec![0, 1, 2, 3], &context).unwrap();
+        let snapshot = context.snapshot_caches(registry_root);
+
+        let is_rejected = |snapshot: CacheSnapshot, registry_root: &Root| {
+            let restored = Context::for_minimal();
+            let result = restored.restore_caches(snapshot, registry_root);
+            matches!(result, Err(Error::InvalidCacheSnapshot(_))) &&
+                restored.committee_cache.is_empty() &&
+                restored.decompressed_pubkey_cache.is_empty()
+        };
+
+        // taken with another registry
+        assert!(is_rejected(snapshot.clone(), &Root::default()));
+
+        // the point of one validator stored under the key of another
+        let mut tampered = snapshot.clone();
+        let uncompressed = tampered.decompressed_public_keys[1].uncompressed.clone();
+        tampered.decompressed_public_keys[0].uncompressed = uncompressed;
+        assert!(is_rejected(tampered, &registry_root));
+
+        let mut tampered = snapshot.clone();
+        tampered.shufflings[0].shuffled_indices.reverse();
+        assert!(is_rejected(tampered, &registry_root));
+
+        // a point that is not on the curve, even with a matching checksum
+        let mut tampered = snapshot;
+        tampered.decompressed_public_keys[0].uncompressed = Default::default();
+        tampered.decompressed_public_keys[0].uncompressed[1] = 1;
+        tampered.checksum = tampered.compute_checksum();
+        assert!(is_rejected(tampered, &registry_root));
+    }
+}
@@ -0,0 +1,139 @@
+//! A cache of committee shufflings shared by clones of a `Context`.
+use crate::{
+    phase0::compute_shuffled_indices,
+    primitives::{Bytes32, Epoch, ValidatorIndex},
+    state_transition::{Context, Result},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+#[derive(Debug)]
+struct Shuffling {
+    active_indices: Vec<ValidatorIndex>,
+    shuffled_indices: Arc<Vec<ValidatorIndex>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ShufflingSnapshot {
+    pub epoch: Epoch,
+    pub seed: Bytes32,
+    pub active_indices: Vec<ValidatorIndex>,
+    pub shuffled_indices: Vec<ValidatorIndex>,
+}
+
+// Shuffled active validator indices keyed by `(epoch, seed)`. The active indices are kept
+// alongside each entry so that a state with a different validator set never reuses a shuffling.
+#[derive(Debug, Default, Clone)]
+pub struct CommitteeCache {
+    shufflings: Arc<Mutex<HashMap<(Epoch, Bytes32), Shuffling>>>,
+}
+
+impl CommitteeCache {
+    fn lock(&self) -> MutexGuard<'_, HashMap<(Epoch, Bytes32), Shuffling>> {
+        self.shufflings.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    // Return the full shuffling of `active_indices` for `epoch` and `seed`, computing it on a miss.
+    // The committee with position `index` out of `count` is the slice
+    // `[len * index / count, len * (index + 1) / count)` of the result.
+    pub fn get_or_compute(
+        &self,
+        epoch: Epoch,
+        seed: &Bytes32,
+        active_indices: Vec<ValidatorIndex>,
+        context: &Context,
+    ) -> Result<Arc<Vec<ValidatorIndex>>> {
+        let key = (epoch, seed.clone());
+        if let Some(shuffling) = self.lock().get(&key) {
+            if shuffling.active_indices == active_indices {
+                return Ok(shuffling.shuffled_indices.clone())
+            }
+        }
+
+        let shuffled_indices = Arc::new(compute_shuffled_indices(&active_indices, seed, context));
+        self.lock()
+            .insert(key, Shuffling { active_indices, shuffled_indices: shuffled_indices.clone() });
+        Ok(shuffled_indices)
+    }
+
+    pub fn snapshot(&self) -> Vec<ShufflingSnapshot> {
+        self.lock()
+            .iter()
+            .map(|((epoch, seed), shuffling)| ShufflingSnapshot {
+                epoch: *epoch,
+                seed: seed.clone(),
+                active_indices: shuffling.active_indices.clone(),
+                shuffled_indices: shuffling.shuffled_indices.to_vec(),
+            })
+            .collect()
+    }
+
+    // Add the shufflings in `snapshot`, replacing any cached for the same `(epoch, seed)`. The
+    // shufflings are not computed again, so only restore a snapshot that has been checked.
+    pub fn restore(&self, snapshot: Vec<ShufflingSnapshot>) {
+        self.lock().extend(snapshot.into_iter().map(
+            |ShufflingSnapshot { epoch, seed, active_indices, shuffled_indices }| {
+                let shuffling =
+                    Shuffling { active_indices, shuffled_indices: Arc::new(shuffled_indices) };
+                ((epoch, seed), shuffling)
+            },
+        ));
+    }
+
+    // Drop shufflings for epochs before the one preceding `current_epoch`.
+    pub fn prune(&self, current_epoch: Epoch) {
+        self.lock().retain(|(epoch, _), _| epoch + 1 >= current_epoch);
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phase0::compute_committee;
+
+    #[test]
+    fn test_cached_shuffling_matches_compute_committee() {
+        let context = Context::for_minimal();
+        let cache = CommitteeCache::default();
+        let seed = Bytes32::try_from([7u8; 32].as_ref()).unwrap();
+        let active_indices = (0..64).map(|i| i * 2).collect::<Vec<_>>();
+        let count = 8;
+
+        let shuffling = cache.get_or_compute(3, &seed, active_indices.clone(), &context).unwrap();
+        for index in 0..count {
+            let start = active_indices.len() * index / count;
+            let end = active_indices.len() * (index + 1) / count;
+            let expected =
+                compute_committee(&active_indices, &seed, index, count, &context).unwrap();
+            assert_eq!(&shuffling[start..end], expected.as_slice());
+        }
+
+        let cached = cache.get_or_compute(3, &seed, active_indices.clone(), &context).unwrap();
+        assert!(Arc::ptr_eq(&shuffling, &cached));
+
+        // a different validator set under the same key is recomputed
+        let other = cache.get_or_compute(3, &seed, active_indices[1..].to_vec(), &context).unwrap();
+        assert_eq!(other.len(), active_indices.len() - 1);
+
+        cache.get_or_compute(4, &seed, active_indices, &context).unwrap();
+        assert_eq!(cache.len(), 2);
+        cache.prune(5);
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -5,7 +5,14 @@ use crate::{
     deneb,
     networks::Network,
     phase0,
-    primitives::{Epoch, ExecutionAddress, Gwei, Hash32, Slot, Version, U256},
+    primitives::{
+        Epoch, ExecutionAddress, ForkDigest, Gwei, Hash32, Root, Slot, Version, FAR_FUTURE_EPOCH,
+        U256,
+    },
+    state_transition::{
+        CommitteeCache, DecompressedPubkeyCache, PubkeyCache, TotalActiveBalanceCache,
+        VerifiedSignatureCache,
+    },
     Error, Fork,
 };
 
@@ -112,6 +119,17 @@ pub struct Context {
     pub deposit_chain_id: usize,
     pub deposit_network_id: usize,
     pub deposit_contract_address: ExecutionAddress,
+
+    #[serde(skip)]
+    pub committee_cache: CommitteeCache,
+    #[serde(skip)]
+    pub pubkey_cache: PubkeyCache,
+    #[serde(skip)]
+    pub decompressed_pubkey_cache: DecompressedPubkeyCache,
+    #[serde(skip)]
+    pub total_active_balance_cache: TotalActiveBalanceCache,
+    #[serde(skip)]
+    pub verified_signature_cache: VerifiedSignatureCache,
 }
 
 impl Context {
@@ -135,6 +153,21 @@ impl Context {
                     &config,
                 )
             }
+            "gnosis" => {
+                let phase0_preset = &phase0::gnosis::PRESET;
+                let altair_preset = &altair::gnosis::PRESET;
+                let bellatrix_preset = &bellatrix::gnosis::PRESET;
+                let capella_preset = &capella::gnosis::PRESET;
+                let deneb_preset = &deneb::gnosis::PRESET;
+                Self::from(
+                    phase0_preset,
+                    altair_preset,
+                    bellatrix_preset,
+                    capella_preset,
+                    deneb_preset,
+                    &config,
+                )
+            }
             "minimal" => {
                 let phase0_preset = &phase0::minimal::PRESET;
                 let altair_preset = &altair::minimal::PRESET;
@@ -259,6 +292,11 @@ impl Context {
             deposit_chain_id: config.deposit_chain_id,
             deposit_network_id: config.deposit_network_id,
             deposit_contract_address: config.deposit_contract_address.clone(),
+            committee_cache: CommitteeCache::default(),
+            pubkey_cache: PubkeyCache::default(),
+            decompressed_pubkey_cache: DecompressedPubkeyCache::default(),
+            total_active_balance_cache: TotalActiveBalanceCache::default(),
+            verified_signature_cache: VerifiedSignatureCache::default(),
         }
     }
 
@@ -347,6 +385,23 @@ impl Context {
         )
     }
 
+    pub fn for_gnosis() -> Self {
+        let config = &configs::gnosis::config();
+        let phase0_preset = &phase0::gnosis::PRESET;
+        let altair_preset = &altair::gnosis::PRESET;
+        let bellatrix_preset = &bellatrix::gnosis::PRESET;
+        let capella_preset = &capella::gnosis::PRESET;
+        let deneb_preset = &deneb::gnosis::PRESET;
+        Self::from(
+            phase0_preset,
+            altair_preset,
+            bellatrix_preset,
+            capella_preset,
+            deneb_preset,
+            config,
+        )
+    }
+
     pub fn fork_for(&self, slot: Slot) -> Fork {
         let epoch = slot / self.slots_per_epoch;
         if epoch >= self.deneb_fork_epoch {
@@ -362,6 +417,37 @@ impl Context {
         }
     }
 
+    // Find the fork whose digest under `genesis_validators_root` matches `fork_digest`
+    pub fn fork_for_digest(
+        &self,
+        fork_digest: ForkDigest,
+        genesis_validators_root: Root,
+    ) -> Result<Option<Fork>, Error> {
+        for fork in [Fork::Deneb, Fork::Capella, Fork::Bellatrix, Fork::Altair, Fork::Phase0] {
+            let version = self.fork_version_for(fork);
+            if phase0::compute_fork_digest(version, genesis_validators_root)? == fork_digest {
+                return Ok(Some(fork))
+            }
+        }
+        Ok(None)
+    }
+
+    // The forks scheduled for this network, in activation order
+    pub fn fork_schedule(&self) -> ForkSchedule {
+        let forks = [
+            (Fork::Phase0, self.genesis_fork_version, 0),
+            (Fork::Altair, self.altair_fork_version, self.altair_fork_epoch),
+            (Fork::Bellatrix, self.bellatrix_fork_version, self.bellatrix_fork_epoch),
+            (Fork::Capella, self.capella_fork_version, self.capella_fork_epoch),
+            (Fork::Deneb, self.deneb_fork_version, self.deneb_fork_epoch),
+        ]
+        .into_iter()
+        .filter(|(_, _, epoch)| *epoch != FAR_FUTURE_EPOCH)
+        .map(|(fork, version, epoch)| ScheduledFork { fork, version, epoch })
+        .collect();
+        ForkSchedule { forks }
+    }
+
     pub fn fork_version_for(&self, fork: Fork) -> Version {
         match fork {
             Fork::Phase0 => self.genesis_fork_version,
@@ -378,6 +464,7 @@ impl Context {
             Network::Sepolia => Ok(crate::clock::SEPOLIA_GENESIS_TIME),
             Network::Goerli => Ok(crate::clock::GOERLI_GENESIS_TIME),
             Network::Holesky => Ok(crate::clock::HOLESKY_GENESIS_TIME),
+            Network::Gnosis => Ok(crate::clock::GNOSIS_GENESIS_TIME),
             name => Err(Error::UnknownGenesisTime(name.to_string())),
         }
     }
@@ -388,6 +475,7 @@ impl Context {
             Network::Sepolia => Some(clock::for_sepolia()),
             Network::Goerli => Some(clock::for_goerli()),
             Network::Holesky => Some(clock::for_holesky()),
+            Network::Gnosis => Some(clock::for_gnosis()),
             _ => None,
         }
     }
@@ -400,3 +488,106 @@ impl Context {
         &self.name
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledFork {
+    pub fork: Fork,
+    pub version: Version,
+    pub epoch: Epoch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForkSchedule {
+    forks: Vec<ScheduledFork>,
+}
+
+impl ForkSchedule {
+    pub fn iter(&self) -> impl Iterator<Item = &ScheduledFork> {
+        self.forks.iter()
+    }
+
+    pub fn active_at(&self, epoch: Epoch) -> &ScheduledFork {
+        self.forks
+            .iter()
+            .rev()
+            .find(|scheduled| scheduled.epoch <= epoch)
+            .expect("genesis fork is always scheduled")
+    }
+
+    pub fn next_after(&self, epoch: Epoch) -> Option<&ScheduledFork> {
+        self.forks.iter().find(|scheduled| scheduled.epoch > epoch)
+    }
+
+    // The `Fork` recorded in a `BeaconState` at `epoch`
+    pub fn fork_at(&self, epoch: Epoch) -> phase0::Fork {
+        let index = self.forks.iter().rposition(|scheduled| scheduled.epoch <= epoch).unwrap_or(0);
+        let current = &self.forks[index];
+        let previous = &self.forks[index.saturating_sub(1)];
+        phase0::Fork {
+            previous_version: previous.version,
+            current_version: current.version,
+            epoch: current.epoch,
+        }
+    }
+
+    // The digest used in gossip topics and ENRs at `epoch`
+    pub fn fork_digest_at(
+        &self,
+        epoch: Epoch,
+        genesis_validators_root: Root,
+    ) -> Result<ForkDigest, Error> {
+        phase0::compute_fork_digest(self.active_at(epoch).version, genesis_validators_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fork_schedule() {
+        let mut context = Context::for_mainnet();
+        context.deneb_fork_epoch = FAR_FUTURE_EPOCH;
+        assert_eq!(context.fork_schedule().iter().count(), 4);
+
+        context.deneb_fork_epoch = context.capella_fork_epoch + 100;
+        let schedule = context.fork_schedule();
+        assert_eq!(schedule.iter().count(), 5);
+        assert_eq!(schedule.active_at(0).fork, Fork::Phase0);
+        assert_eq!(schedule.active_at(context.capella_fork_epoch).fork, Fork::Capella);
+        assert_eq!(schedule.active_at(context.capella_fork_epoch - 1).fork, Fork::Bellatrix);
+        assert_eq!(schedule.next_after(context.capella_fork_epoch).unwrap().fork, Fork::Deneb);
+        assert!(schedule.next_after(context.deneb_fork_epoch).is_none());
+
+        let fork = schedule.fork_at(context.capella_fork_epoch + 1);
+        assert_eq!(fork.previous_version, context.bellatrix_fork_version);
+        assert_eq!(fork.current_version, context.capella_fork_version);
+        assert_eq!(fork.epoch, context.capella_fork_epoch);
+        let fork = schedule.fork_at(0);
+        assert_eq!(fork.previous_version, context.genesis_fork_version);
+        assert_eq!(fork.current_version, context.genesis_fork_version);
+
+        let genesis_validators_root = Root::default();
+        let digest =
+            schedule.fork_digest_at(context.deneb_fork_epoch, genesis_validators_root).unwrap();
+        assert_eq!(
+            context.fork_for_digest(digest, genesis_validators_root).unwrap(),
+            Some(Fork::Deneb)
+        );
+    }
+
+    #[test]
+    fn test_gnosis_context() {
+        let context = Context::for_gnosis();
+        assert_eq!(context.slots_per_epoch, 16);
+        assert_eq!(context.seconds_per_slot, 5);
+        assert_eq!(context.max_withdrawals_per_payload, 8);
+        assert_eq!(context.fork_for(context.capella_fork_epoch * 16), Fork::Capella);
+        assert_eq!(context.genesis_time().unwrap(), crate::clock::GNOSIS_GENESIS_TIME);
+        let context = Context::try_from(Network::Gnosis).unwrap();
+        assert_eq!(context.network(), &Network::Gnosis);
+
+        let state = crate::types::gnosis::BeaconState::Capella(Default::default());
+        assert_eq!(state.slot(), 0);
+    }
+}
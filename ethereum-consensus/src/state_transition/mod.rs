@@ -1,24 +1,100 @@
 mod beacon_block;
 mod beacon_state;
+mod cache_snapshot;
+mod committee_cache;
 mod context;
 mod execution_engine;
 mod executor;
 mod presets;
+mod state_cache;
 
 pub use beacon_block::*;
 pub use beacon_state::*;
+pub use cache_snapshot::*;
+pub use committee_cache::*;
 pub use context::*;
 pub use execution_engine::*;
 pub use executor::*;
+pub use state_cache::*;
+
+use crate::{
+    primitives::{Gwei, ValidatorIndex},
+    ssz::prelude::List,
+};
 
 pub type Result<T> = std::result::Result<T, crate::Error>;
 
+// Apply `f` to each of `indices`, across threads when the `parallel` feature is enabled.
+#[cfg(feature = "parallel")]
+pub(crate) fn map_validator_indices<T, F>(indices: &[ValidatorIndex], f: F) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(ValidatorIndex) -> Result<T> + Send + Sync,
+{
+    use rayon::prelude::*;
+    indices.par_iter().map(|&index| f(index)).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map_validator_indices<T, F>(indices: &[ValidatorIndex], f: F) -> Result<Vec<T>>
+where
+    F: Fn(ValidatorIndex) -> Result<T>,
+{
+    indices.iter().map(|&index| f(index)).collect()
+}
+
+// Apply `f` to each of `items`, across threads when the `parallel` feature is enabled.
+#[cfg(feature = "parallel")]
+pub(crate) fn for_each_mut<T, F>(items: &mut [T], f: F)
+where
+    T: Send,
+    F: Fn(&mut T) + Send + Sync,
+{
+    use rayon::prelude::*;
+    items.par_iter_mut().for_each(f)
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn for_each_mut<T, F>(items: &mut [T], f: F)
+where
+    F: Fn(&mut T),
+{
+    items.iter_mut().for_each(f)
+}
+
+// Replace each balance with `f(index, balance)`, across threads when the `parallel` feature is
+// enabled. The balances are copied out of the `List` to be split between threads.
+#[cfg(feature = "parallel")]
+pub(crate) fn update_balances<const N: usize, F>(balances: &mut List<Gwei, N>, f: F)
+where
+    F: Fn(ValidatorIndex, Gwei) -> Gwei + Send + Sync,
+{
+    use rayon::prelude::*;
+    let mut values = balances.to_vec();
+    values.par_iter_mut().enumerate().for_each(|(index, balance)| *balance = f(index, *balance));
+    *balances = values.try_into().expect("length is unchanged");
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn update_balances<const N: usize, F>(balances: &mut List<Gwei, N>, f: F)
+where
+    F: Fn(ValidatorIndex, Gwei) -> Gwei,
+{
+    for index in 0..balances.len() {
+        balances[index] = f(index, balances[index]);
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum Validation {
     Enabled,
     Disabled,
 }
 
+pub mod gnosis {
+    pub use super::presets::gnosis::*;
+}
+
 pub mod mainnet {
     pub use super::presets::mainnet::*;
 }
@@ -0,0 +1,495 @@
+//! Caches derived from the validator registry, shared by clones of a `Context`.
+use crate::{
+    crypto::{
+        hash, verify_signature, DecompressedPublicKey, Error as CryptoError,
+        BLS_UNCOMPRESSED_PUBLIC_KEY_BYTES_LEN,
+    },
+    phase0::Validator,
+    primitives::{BlsPublicKey, BlsSignature, Epoch, Gwei, Root, ValidatorIndex},
+    ssz::prelude::ByteVector,
+    Error,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+// Map from validator public key to index in the registry.
+//
+// Validators are only ever appended to the registry so the cache records the length of the
+// registry it was built from and is extended lazily with the validators appended since. Earlier
+// entries are not compared again; call `clear` before using a `Context` with a registry that
+// does not extend the cached one, e.g. when a new registry is built at genesis.
+#[derive(Debug, Default, Clone)]
+pub struct PubkeyCache {
+    inner: Arc<Mutex<PubkeyIndices>>,
+}
+
+#[derive(Debug, Default)]
+struct PubkeyIndices {
+    indices: HashMap<BlsPublicKey, ValidatorIndex>,
+    // the cached keys in registry order; its length is the length of the registry cached
+    public_keys: Vec<BlsPublicKey>,
+}
+
+impl PubkeyIndices {
+    fn extend(&mut self, validators: &[Validator]) {
+        for validator in validators.iter().skip(self.public_keys.len()) {
+            self.push(validator.public_key.clone());
+        }
+    }
+
+    fn push(&mut self, public_key: BlsPublicKey) {
+        self.indices.insert(public_key.clone(), self.public_keys.len());
+        self.public_keys.push(public_key);
+    }
+
+    fn clear(&mut self) {
+        self.indices.clear();
+        self.public_keys.clear();
+    }
+}
+
+impl PubkeyCache {
+    fn lock(&self) -> MutexGuard<'_, PubkeyIndices> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    // Return the index of the validator with `public_key` in `validators`, if any.
+    pub fn get_validator_index(
+        &self,
+        validators: &[Validator],
+        public_key: &BlsPublicKey,
+    ) -> Option<ValidatorIndex> {
+        let mut inner = self.lock();
+        inner.extend(validators);
+
+        // the cache may extend past a shorter registry of the same chain
+        let index =
+            inner.indices.get(public_key).copied().filter(|&index| index < validators.len())?;
+        if &validators[index].public_key == public_key {
+            return Some(index)
+        }
+        // a registry that was replaced without a `clear`; rebuild rather than return a stale index
+        inner.clear();
+        inner.extend(validators);
+        inner.indices.get(public_key).copied()
+    }
+
+    // Return the cached public keys in registry order.
+    pub fn snapshot(&self) -> Vec<BlsPublicKey> {
+        self.lock().public_keys.clone()
+    }
+
+    // Replace the cache with the keys of the first `public_keys.len()` validators of a registry.
+    pub fn restore(&self, public_keys: Vec<BlsPublicKey>) {
+        let mut inner = self.lock();
+        inner.clear();
+        for public_key in public_keys {
+            inner.push(public_key);
+        }
+    }
+
+    // Record a validator appended to the registry at `index`.
+    pub fn insert(&self, public_key: BlsPublicKey, index: ValidatorIndex) {
+        let mut inner = self.lock();
+        if inner.public_keys.len() == index {
+            inner.push(public_key);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().public_keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Map from validator index to the decompressed public key of that validator.
+//
+// Entries are filled in as keys are needed and keep the compressed key they were made from, so
+// an entry from another registry is detected and replaced rather than returned.
+#[derive(Debug, Default, Clone)]
+pub struct DecompressedPubkeyCache {
+    keys: Arc<Mutex<Vec<Option<CachedPublicKey>>>>,
+}
+
+type CachedPublicKey = (BlsPublicKey, DecompressedPublicKey);
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DecompressedPublicKeySnapshot {
+    pub index: ValidatorIndex,
+    pub public_key: BlsPublicKey,
+    pub uncompressed: ByteVector<BLS_UNCOMPRESSED_PUBLIC_KEY_BYTES_LEN>,
+}
+
+impl DecompressedPubkeyCache {
+    fn lock(&self) -> MutexGuard<'_, Vec<Option<CachedPublicKey>>> {
+        self.keys.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    // Return the decompressed public key of `validators[index]`, or `None` if there is no
+    // such validator.
+    pub fn get(
+        &self,
+        validators: &[Validator],
+        index: ValidatorIndex,
+    ) -> Option<Result<DecompressedPublicKey, CryptoError>> {
+        let public_key = &validators.get(index)?.public_key;
+        let mut keys = self.lock();
+        if let Some(Some((cached, key))) = keys.get(index) {
+            if cached == public_key {
+                return Some(Ok(*key))
+            }
+        }
+        let key = match DecompressedPublicKey::try_from(public_key) {
+            Ok(key) => key,
+            Err(err) => return Some(Err(err)),
+        };
+        if keys.len() <= index {
+            keys.resize(index + 1, None);
+        }
+        keys[index] = Some((public_key.clone(), key));
+        Some(Ok(key))
+    }
+
+    pub fn snapshot(&self) -> Vec<DecompressedPublicKeySnapshot> {
+        self.lock()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let (public_key, key) = entry.as_ref()?;
+                let uncompressed = key.to_uncompressed_bytes();
+                Some(DecompressedPublicKeySnapshot {
+                    index,
+                    public_key: public_key.clone(),
+                    uncompressed: uncompressed.as_ref().try_into().expect("correct length"),
+                })
+            })
+            .collect()
+    }
+
+    // Add the keys in `snapshot`, decoded from their uncompressed points without decompressing
+    // or validating them again, so only restore a snapshot that has been checked. Each entry
+    // keeps its compressed key, so it is only returned for a validator with that key.
+    pub fn restore(&self, snapshot: Vec<DecompressedPublicKeySnapshot>) -> Result<(), Error> {
+        let mut restored = Vec::with_capacity(snapshot.len());
+        for DecompressedPublicKeySnapshot { index, public_key, uncompressed } in snapshot {
+            let key = DecompressedPublicKey::from_uncompressed_bytes(uncompressed.as_ref())
+                .map_err(|_| {
+                    Error::InvalidCacheSnapshot(format!(
+                        "decompressed public key of validator {index}"
+                    ))
+                })?;
+            restored.push((index, public_key, key));
+        }
+
+        let mut keys = self.lock();
+        for (index, public_key, key) in restored {
+            if keys.len() <= index {
+                keys.resize(index + 1, None);
+            }
+            keys[index] = Some((public_key, key));
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().iter().filter(|entry| entry.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Total active balance keyed by `(epoch, root)`, where `root` is the state root of the last slot
+// of the previous epoch. Blocks cannot change the total of the epoch they are in: deposits, exits
+// and slashings only change the active set from a later epoch, and effective balances are only
+// updated by `process_effective_balance_updates` in the epoch transition.
+//
+// That update runs while the state is still in `epoch` with the same `root`, so it must call
+// `invalidate` for the key. Later lookups of an invalidated key miss and their totals are not
+// inserted, so the updated total is never stored under the key of the states before the update.
+#[derive(Debug, Default, Clone)]
+pub struct TotalActiveBalanceCache {
+    totals: Arc<Mutex<TotalActiveBalances>>,
+}
+
+#[derive(Debug, Default)]
+struct TotalActiveBalances {
+    totals: HashMap<(Epoch, Root), Gwei>,
+    invalidated: HashSet<(Epoch, Root)>,
+}
+
+impl TotalActiveBalanceCache {
+    fn lock(&self) -> MutexGuard<'_, TotalActiveBalances> {
+        self.totals.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub fn get(&self, epoch: Epoch, root: &Root) -> Option<Gwei> {
+        self.lock().totals.get(&(epoch, *root)).copied()
+    }
+
+    pub fn insert(&self, epoch: Epoch, root: Root, total: Gwei) {
+        let mut balances = self.lock();
+        if !balances.invalidated.contains(&(epoch, root)) {
+            balances.totals.insert((epoch, root), total);
+        }
+    }
+
+    // Drop the total for `(epoch, root)` and ignore any later insert for it.
+    pub fn invalidate(&self, epoch: Epoch, root: Root) {
+        let mut balances = self.lock();
+        balances.totals.remove(&(epoch, root));
+        balances.invalidated.insert((epoch, root));
+    }
+
+    pub fn snapshot(&self) -> Vec<(Epoch, Root, Gwei)> {
+        self.lock().totals.iter().map(|(&(epoch, root), &total)| (epoch, root, total)).collect()
+    }
+
+    pub fn restore(&self, snapshot: Vec<(Epoch, Root, Gwei)>) {
+        let mut balances = self.lock();
+        for (epoch, root, total) in snapshot {
+            if !balances.invalidated.contains(&(epoch, root)) {
+                balances.totals.insert((epoch, root), total);
+            }
+        }
+    }
+
+    // Drop totals for epochs before the one preceding `current_epoch`.
+    pub fn prune(&self, current_epoch: Epoch) {
+        let mut balances = self.lock();
+        balances.totals.retain(|(epoch, _), _| epoch + 1 >= current_epoch);
+        balances.invalidated.retain(|(epoch, _)| epoch + 1 >= current_epoch);
+    }
+
+    pub fn clear(&self) {
+        *self.lock() = TotalActiveBalances::default();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().totals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Signatures already verified against a signing root and a set of public keys, e.g. as part of a
+// batch, so that block processing does not verify them again one by one.
+//
+// Verification is a pure function of its inputs so an entry stays valid for any state. The cache
+// is bounded and simply cleared once full. Only this crate records entries, after verifying them.
+#[derive(Debug, Default, Clone)]
+pub struct VerifiedSignatureCache {
+    digests: Arc<Mutex<HashSet<Root>>>,
+}
+
+impl VerifiedSignatureCache {
+    const CAPACITY: usize = 1 << 16;
+
+    fn lock(&self) -> MutexGuard<'_, HashSet<Root>> {
+        self.digests.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    // Every field has a fixed size and the keys come first, prefixed by their count, so distinct
+    // inputs never share an encoding.
+    fn digest(
+        public_keys: &[&BlsPublicKey],
+        signing_root: &Root,
+        signature: &BlsSignature,
+    ) -> Root {
+        let mut data = Vec::with_capacity(8 + 48 * public_keys.len() + 32 + 96);
+        data.extend_from_slice(&(public_keys.len() as u64).to_le_bytes());
+        for public_key in public_keys {
+            data.extend_from_slice(public_key.as_ref());
+        }
+        data.extend_from_slice(signing_root.as_ref());
+        data.extend_from_slice(signature.as_ref());
+        Root::try_from(hash(data).as_ref()).expect("is valid instance")
+    }
+
+    pub fn contains(
+        &self,
+        public_keys: &[&BlsPublicKey],
+        signing_root: &Root,
+        signature: &BlsSignature,
+    ) -> bool {
+        self.lock().contains(&Self::digest(public_keys, signing_root, signature))
+    }
+
+    // Verify `signature` over `signing_root` by `public_key` unless it is already known to be
+    // valid.
+    pub fn verify_signature(
+        &self,
+        public_key: &BlsPublicKey,
+        signing_root: &Root,
+        signature: &BlsSignature,
+    ) -> Result<(), CryptoError> {
+        if self.contains(&[public_key], signing_root, signature) {
+            Ok(())
+        } else {
+            verify_signature(public_key, signing_root.as_ref(), signature)
+        }
+    }
+
+    // Record that `signature` over `signing_root` by the aggregate of `public_keys` is valid. A
+    // hit skips verification, so only call this once the signature has been verified.
+    pub(crate) fn insert(
+        &self,
+        public_keys: &[&BlsPublicKey],
+        signing_root: &Root,
+        signature: &BlsSignature,
+    ) {
+        let digest = Self::digest(public_keys, signing_root, signature);
+        let mut digests = self.lock();
+        if digests.len() >= Self::CAPACITY {
+            digests.clear();
+        }
+        digests.insert(digest);
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::SecretKey;
+
+    fn validator(seed: u8) -> Validator {
+        let mut public_key = BlsPublicKey::default();
+        public_key[0] = seed;
+        Validator { public_key, ..Default::default() }
+    }
+
+    #[test]
+    fn test_pubkey_cache() {
+        let cache = PubkeyCache::default();
+        let mut validators = (1..=4).map(validator).collect::<Vec<_>>();
+        assert_eq!(cache.get_validator_index(&validators, &validators[2].public_key), Some(2));
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.get_validator_index(&validators, &validator(9).public_key), None);
+
+        validators.push(validator(9));
+        cache.insert(validators[4].public_key.clone(), 4);
+        assert_eq!(cache.len(), 5);
+        assert_eq!(cache.get_validator_index(&validators, &validator(9).public_key), Some(4));
+
+        // a shorter registry of the same chain
+        assert_eq!(cache.get_validator_index(&validators[..3], &validator(9).public_key), None);
+
+        // a different registry, after an explicit `clear`
+        let other = (5..=9).map(validator).collect::<Vec<_>>();
+        cache.clear();
+        assert_eq!(cache.get_validator_index(&other, &validator(1).public_key), None);
+        assert_eq!(cache.get_validator_index(&other, &validator(6).public_key), Some(1));
+        assert_eq!(cache.get_validator_index(&other, &validator(9).public_key), Some(4));
+
+        // only the appended validators are added to the cache
+        let mut extended = other.clone();
+        extended.push(validator(10));
+        assert_eq!(cache.get_validator_index(&extended, &validator(10).public_key), Some(5));
+        assert_eq!(cache.len(), 6);
+
+        // a stale index is never returned for a registry replaced without a `clear`
+        let mut forked = extended.clone();
+        forked.swap(1, 2);
+        assert_eq!(cache.get_validator_index(&forked, &validator(6).public_key), Some(2));
+        assert_eq!(
+            cache.snapshot(),
+            forked.iter().map(|v| v.public_key.clone()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_decompressed_pubkey_cache() {
+        let keyed_validator = |seed: u8| {
+            let public_key = SecretKey::key_gen(&[seed; 32]).unwrap().public_key();
+            Validator { public_key, ..Default::default() }
+        };
+        let cache = DecompressedPubkeyCache::default();
+        let validators = (1..=3).map(keyed_validator).collect::<Vec<_>>();
+        let key = cache.get(&validators, 2).unwrap().unwrap();
+        assert_eq!(key, DecompressedPublicKey::try_from(&validators[2].public_key).unwrap());
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&validators, 3).is_none());
+
+        // a different registry
+        let other = (4..=6).map(keyed_validator).collect::<Vec<_>>();
+        let key = cache.get(&other, 2).unwrap().unwrap();
+        assert_eq!(key, DecompressedPublicKey::try_from(&other[2].public_key).unwrap());
+        assert_eq!(cache.len(), 1);
+
+        // not a valid key
+        assert!(cache.get(&[validator(1)], 0).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_total_active_balance_cache() {
+        let cache = TotalActiveBalanceCache::default();
+        let root = Root::try_from([1u8; 32].as_ref()).unwrap();
+        assert_eq!(cache.get(3, &root), None);
+        cache.insert(3, root, 32);
+        cache.insert(4, root, 64);
+        assert_eq!(cache.get(3, &root), Some(32));
+        assert_eq!(cache.get(3, &Root::default()), None);
+        cache.prune(5);
+        assert_eq!(cache.get(3, &root), None);
+        assert_eq!(cache.len(), 1);
+
+        // an invalidated key is never filled again
+        cache.invalidate(4, root);
+        assert_eq!(cache.get(4, &root), None);
+        cache.insert(4, root, 96);
+        cache.restore(vec![(4, root, 96)]);
+        assert_eq!(cache.get(4, &root), None);
+        cache.insert(5, root, 96);
+        assert_eq!(cache.get(5, &root), Some(96));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        cache.insert(4, root, 96);
+        assert_eq!(cache.get(4, &root), Some(96));
+    }
+
+    #[test]
+    fn test_verified_signature_cache() {
+        let cache = VerifiedSignatureCache::default();
+        let public_keys = (1..=2).map(|seed| validator(seed).public_key).collect::<Vec<_>>();
+        let public_keys = public_keys.iter().collect::<Vec<_>>();
+        let signing_root = Root::try_from([1u8; 32].as_ref()).unwrap();
+        let other_root = Root::try_from([2u8; 32].as_ref()).unwrap();
+        let signature = BlsSignature::default();
+        assert!(!cache.contains(&public_keys, &signing_root, &signature));
+        cache.insert(&public_keys, &signing_root, &signature);
+        assert!(cache.contains(&public_keys, &signing_root, &signature));
+        assert!(!cache.contains(&public_keys, &other_root, &signature));
+        assert!(!cache.contains(&public_keys[..1], &signing_root, &signature));
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
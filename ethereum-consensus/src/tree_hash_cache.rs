@@ -0,0 +1,1048 @@
+//! Incremental `hash_tree_root` for `BeaconState`.
+//!
+//! `CachedBeaconState` keeps the merkle trees of the large fields of a state
+//! (the registry, balances, participation, inactivity scores and the root
+//! vectors) between calls to `hash_tree_root`. Each call compares the current
+//! leaves against the cached ones and only rehashes the paths above leaves that
+//! changed, so the state can be mutated freely through `state_mut` in between.
+//! The registry is the exception: only the roots of the validators are kept, so
+//! `state_mut` rehashes every validator while `validator_mut` and
+//! `process_slots` track the validators they change.
+//!
+//! It also keeps running totals of the balance attesting to the previous and
+//! current epoch targets, updated as attestations are processed through
+//! `process_attestation`, so `process_justification_and_finalization` does not
+//! rescan the registry. These totals are discarded by `state_mut`.
+use crate::{
+    altair::{self, constants::TIMELY_TARGET_FLAG_INDEX, helpers::has_flag},
+    bellatrix, capella,
+    crypto::hash_64b_blocks,
+    deneb,
+    phase0::{self, compute_epoch_at_slot, is_active_validator, Attestation, Validator},
+    primitives::{Epoch, Gwei, Root, Slot, ValidatorIndex, GENESIS_EPOCH},
+    proofs::{depth_for, hash_pair, zero_hashes, BEACON_STATE_DEPTH},
+    ssz::prelude::*,
+    state_transition::Context,
+    types::BeaconState,
+    Error,
+};
+use std::collections::BTreeSet;
+
+// A merkle tree of fixed `depth` where all leaves past the end of `layers[0]` are zero.
+#[derive(Debug, Clone, Default)]
+pub struct MerkleCache {
+    depth: usize,
+    zeroes: Vec<Node>,
+    // `layers[0]` holds the leaves and `layers[height]` the nodes at `height` that
+    // have at least one leaf below them.
+    layers: Vec<Vec<Node>>,
+}
+
+impl MerkleCache {
+    pub fn new(depth: usize) -> Self {
+        Self { depth, zeroes: zero_hashes(depth), layers: vec![vec![]; depth + 1] }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn leaves(&self) -> &[Node] {
+        &self.layers[0]
+    }
+
+    pub fn root(&self) -> Node {
+        self.layers[self.depth].first().copied().unwrap_or(self.zeroes[self.depth])
+    }
+
+    // Replace the leaves of the tree with `leaves`, only rehashing the paths above leaves
+    // that differ from the cached ones, and return the new root.
+    pub fn update(&mut self, leaves: Vec<Node>) -> Node {
+        debug_assert!(leaves.len() <= 1 << self.depth);
+        let mut dirty = leaves
+            .iter()
+            .enumerate()
+            .filter(|&(index, leaf)| self.layers[0].get(index) != Some(leaf))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let shrunk = leaves.len() < self.layers[0].len();
+        self.layers[0] = leaves;
+        self.rehash(dirty, shrunk)
+    }
+
+    // Resize the leaves of the tree to `len` and set the leaves in `changes`, which must be
+    // in increasing order of index and include every leaf past the previous length. Only the
+    // paths above these leaves are rehashed, without comparing the other leaves.
+    pub fn update_leaves(&mut self, len: usize, changes: Vec<(usize, Node)>) -> Node {
+        debug_assert!(len <= 1 << self.depth);
+        debug_assert!(changes.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        let shrunk = len < self.layers[0].len();
+        self.layers[0].resize(len, Node::default());
+        let leaves = &mut self.layers[0];
+        let dirty = changes
+            .into_iter()
+            .map(|(index, leaf)| {
+                leaves[index] = leaf;
+                index
+            })
+            .collect();
+        self.rehash(dirty, shrunk)
+    }
+
+    // Recompute the parents of the leaves in `dirty`, in increasing order, up to the root.
+    fn rehash(&mut self, mut dirty: Vec<usize>, mut shrunk: bool) -> Node {
+        for height in 0..self.depth {
+            let (lower, upper) = self.layers.split_at_mut(height + 1);
+            let (layer, parent_layer) = (&lower[height], &mut upper[0]);
+            // the last node lost its right sibling
+            if shrunk && !layer.is_empty() && dirty.last() != Some(&(layer.len() - 1)) {
+                dirty.push(layer.len() - 1);
+            }
+            let parent_len = layer.len() / 2 + (layer.len() & 1);
+            shrunk = parent_len < parent_layer.len();
+            parent_layer.resize(parent_len, Node::default());
+
+            let mut parents = dirty.iter().map(|index| index / 2).collect::<Vec<_>>();
+            parents.dedup();
+            let mut input = Vec::with_capacity(64 * parents.len());
+            for &parent in &parents {
+                input.extend_from_slice(layer[2 * parent].as_ref());
+                let right = layer.get(2 * parent + 1).unwrap_or(&self.zeroes[height]);
+                input.extend_from_slice(right.as_ref());
+            }
+            let mut output = vec![0u8; 32 * parents.len()];
+            hash_64b_blocks(&input, &mut output).expect("one digest per pair of nodes");
+            for (&parent, node) in parents.iter().zip(output.chunks_exact(32)) {
+                parent_layer[parent] = Node::try_from(node).expect("is valid instance");
+            }
+            dirty = parents;
+        }
+        self.root()
+    }
+}
+
+// Depth of the tree for a list of up to `limit` basic values of `item_size` bytes, packed
+// into 32 byte leaves.
+fn packed_depth(limit: usize, item_size: usize) -> usize {
+    depth_for(limit * item_size).saturating_sub(5)
+}
+
+fn mix_in_length(root: &Node, length: usize) -> Node {
+    let mut length_node = Node::default();
+    length_node.as_mut()[..8].copy_from_slice(&(length as u64).to_le_bytes());
+    hash_pair(root, &length_node)
+}
+
+fn pack_u64s(values: &[u64]) -> Vec<Node> {
+    values
+        .chunks(4)
+        .map(|chunk| {
+            let mut node = Node::default();
+            for (i, value) in chunk.iter().enumerate() {
+                node.as_mut()[8 * i..8 * (i + 1)].copy_from_slice(&value.to_le_bytes());
+            }
+            node
+        })
+        .collect()
+}
+
+fn pack_bytes(values: &[u8]) -> Vec<Node> {
+    values
+        .chunks(32)
+        .map(|chunk| {
+            let mut node = Node::default();
+            node.as_mut()[..chunk.len()].copy_from_slice(chunk);
+            node
+        })
+        .collect()
+}
+
+// The registry tree, with the root of each validator as a leaf, and the indices of the
+// validators changed since it was last computed. Validators past the end of the leaves are new.
+#[derive(Debug, Clone, Default)]
+struct ValidatorsCache {
+    tree: MerkleCache,
+    dirty: BTreeSet<usize>,
+    // any validator may have changed, e.g. through `state_mut`
+    all_dirty: bool,
+}
+
+impl ValidatorsCache {
+    fn mark_dirty(&mut self, index: usize) {
+        if !self.all_dirty {
+            self.dirty.insert(index);
+        }
+    }
+
+    fn mark_all_dirty(&mut self) {
+        self.all_dirty = true;
+        self.dirty.clear();
+    }
+
+    fn update(&mut self, validators: &[Validator]) -> Result<Node, MerkleizationError> {
+        let leaf = |index: usize| validators[index].clone().hash_tree_root();
+        let root = if self.all_dirty {
+            let leaves = (0..validators.len()).map(leaf).collect::<Result<Vec<_>, _>>()?;
+            self.tree.update(leaves)
+        } else {
+            let cached_len = self.tree.leaves().len().min(validators.len());
+            let changes = self
+                .dirty
+                .range(..cached_len)
+                .copied()
+                .chain(cached_len..validators.len())
+                .map(|index| Ok((index, leaf(index)?)))
+                .collect::<Result<Vec<_>, MerkleizationError>>()?;
+            self.tree.update_leaves(validators.len(), changes)
+        };
+        self.dirty.clear();
+        self.all_dirty = false;
+        Ok(mix_in_length(&root, validators.len()))
+    }
+}
+
+// The fields of a validator the epoch transition may change.
+fn epoch_transition_fields(validator: &Validator) -> [u64; 5] {
+    [
+        validator.effective_balance,
+        validator.activation_eligibility_epoch,
+        validator.activation_epoch,
+        validator.exit_epoch,
+        validator.withdrawable_epoch,
+    ]
+}
+
+#[derive(Debug, Clone, Default)]
+struct BeaconStateTreeCache {
+    block_roots: MerkleCache,
+    state_roots: MerkleCache,
+    validators: ValidatorsCache,
+    balances: MerkleCache,
+    randao_mixes: MerkleCache,
+    previous_epoch_participation: MerkleCache,
+    current_epoch_participation: MerkleCache,
+    inactivity_scores: MerkleCache,
+    fields: MerkleCache,
+}
+
+macro_rules! field_root {
+    ($cache:ident, $state:ident, block_roots) => {
+        $cache.block_roots.update($state.block_roots.to_vec())
+    };
+    ($cache:ident, $state:ident, state_roots) => {
+        $cache.state_roots.update($state.state_roots.to_vec())
+    };
+    ($cache:ident, $state:ident, validators) => {
+        $cache.validators.update(&$state.validators)?
+    };
+    ($cache:ident, $state:ident, balances) => {
+        mix_in_length(&$cache.balances.update(pack_u64s(&$state.balances)), $state.balances.len())
+    };
+    ($cache:ident, $state:ident, randao_mixes) => {
+        $cache.randao_mixes.update(
+            $state
+                .randao_mixes
+                .iter()
+                .map(|mix| Node::try_from(mix.as_ref()).expect("is valid instance"))
+                .collect(),
+        )
+    };
+    ($cache:ident, $state:ident, previous_epoch_participation) => {
+        mix_in_length(
+            &$cache
+                .previous_epoch_participation
+                .update(pack_bytes(&$state.previous_epoch_participation)),
+            $state.previous_epoch_participation.len(),
+        )
+    };
+    ($cache:ident, $state:ident, current_epoch_participation) => {
+        mix_in_length(
+            &$cache
+                .current_epoch_participation
+                .update(pack_bytes(&$state.current_epoch_participation)),
+            $state.current_epoch_participation.len(),
+        )
+    };
+    ($cache:ident, $state:ident, inactivity_scores) => {
+        mix_in_length(
+            &$cache.inactivity_scores.update(pack_u64s(&$state.inactivity_scores)),
+            $state.inactivity_scores.len(),
+        )
+    };
+    ($cache:ident, $state:ident, $field:ident) => {
+        $state.$field.hash_tree_root()?
+    };
+}
+
+macro_rules! field_roots {
+    ($cache:ident, $state:ident, $($field:ident),* $(,)?) => {
+        vec![$(field_root!($cache, $state, $field)),*]
+    };
+}
+
+// The balances `process_justification_and_finalization` reads for a state in `epoch`: the
+// effective balance of active validators, and of unslashed validators with the timely target flag
+// (or a matching target attestation before altair) in the previous and current epochs. Unlike
+// `get_total_balance`, the totals are not rounded up to one `EFFECTIVE_BALANCE_INCREMENT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressiveBalances {
+    pub epoch: Epoch,
+    pub total_active_balance: Gwei,
+    pub previous_epoch_target_balance: Gwei,
+    pub current_epoch_target_balance: Gwei,
+}
+
+// Process `attestation` on a `state` with participation flags and return the effective balance of
+// the unslashed attesters that gained the timely target flag for the target epoch.
+macro_rules! process_attestation_with_target_balance {
+    ($fork:ident, $state:ident, $attestation:ident, $is_current:ident, $context:ident) => {{
+        let attesting_indices = $fork::get_attesting_indices(
+            $state,
+            &$attestation.data,
+            &$attestation.aggregation_bits,
+            $context,
+        )?;
+        let had_target = if $is_current {
+            &$state.current_epoch_participation
+        } else {
+            &$state.previous_epoch_participation
+        };
+        let attesting_indices = attesting_indices
+            .into_iter()
+            .filter(|&index| !has_flag(had_target[index], TIMELY_TARGET_FLAG_INDEX))
+            .collect::<Vec<_>>();
+        $fork::process_attestation($state, $attestation, $context)?;
+        let has_target = if $is_current {
+            &$state.current_epoch_participation
+        } else {
+            &$state.previous_epoch_participation
+        };
+        attesting_indices
+            .into_iter()
+            .filter(|&index| has_flag(has_target[index], TIMELY_TARGET_FLAG_INDEX))
+            .map(|index| &$state.validators[index])
+            .filter(|validator| !validator.slashed)
+            .map(|validator| validator.effective_balance)
+            .sum::<Gwei>()
+    }};
+}
+
+// A `BeaconState` that caches the merkle trees of its large fields across calls to
+// `hash_tree_root`, and the `ProgressiveBalances` of its current epoch.
+#[derive(Debug, Clone)]
+pub struct CachedBeaconState<
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const HISTORICAL_ROOTS_LIMIT: usize,
+    const ETH1_DATA_VOTES_BOUND: usize,
+    const VALIDATOR_REGISTRY_LIMIT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    const MAX_VALIDATORS_PER_COMMITTEE: usize,
+    const PENDING_ATTESTATIONS_BOUND: usize,
+    const SYNC_COMMITTEE_SIZE: usize,
+    const BYTES_PER_LOGS_BLOOM: usize,
+    const MAX_EXTRA_DATA_BYTES: usize,
+> {
+    state: BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >,
+    cache: BeaconStateTreeCache,
+    progressive_balances: Option<ProgressiveBalances>,
+}
+
+impl<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+    >
+    CachedBeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    >
+{
+    pub fn new(
+        state: BeaconState<
+            SLOTS_PER_HISTORICAL_ROOT,
+            HISTORICAL_ROOTS_LIMIT,
+            ETH1_DATA_VOTES_BOUND,
+            VALIDATOR_REGISTRY_LIMIT,
+            EPOCHS_PER_HISTORICAL_VECTOR,
+            EPOCHS_PER_SLASHINGS_VECTOR,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            PENDING_ATTESTATIONS_BOUND,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+        >,
+    ) -> Self {
+        let registry_depth = depth_for(VALIDATOR_REGISTRY_LIMIT);
+        let cache = BeaconStateTreeCache {
+            block_roots: MerkleCache::new(depth_for(SLOTS_PER_HISTORICAL_ROOT)),
+            state_roots: MerkleCache::new(depth_for(SLOTS_PER_HISTORICAL_ROOT)),
+            validators: ValidatorsCache {
+                tree: MerkleCache::new(registry_depth),
+                ..Default::default()
+            },
+            balances: MerkleCache::new(packed_depth(VALIDATOR_REGISTRY_LIMIT, 8)),
+            randao_mixes: MerkleCache::new(depth_for(EPOCHS_PER_HISTORICAL_VECTOR)),
+            previous_epoch_participation: MerkleCache::new(packed_depth(
+                VALIDATOR_REGISTRY_LIMIT,
+                1,
+            )),
+            current_epoch_participation: MerkleCache::new(packed_depth(
+                VALIDATOR_REGISTRY_LIMIT,
+                1,
+            )),
+            inactivity_scores: MerkleCache::new(packed_depth(VALIDATOR_REGISTRY_LIMIT, 8)),
+            fields: MerkleCache::new(BEACON_STATE_DEPTH),
+        };
+        Self { state, cache, progressive_balances: None }
+    }
+
+    pub fn state(
+        &self,
+    ) -> &BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    > {
+        &self.state
+    }
+
+    pub fn state_mut(
+        &mut self,
+    ) -> &mut BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    > {
+        // the caller may change anything the totals depend on, including any validator
+        self.progressive_balances = None;
+        self.cache.validators.mark_all_dirty();
+        &mut self.state
+    }
+
+    // Mutable access to the validator at `index`, only rehashing that validator on the next call
+    // to `hash_tree_root` where `state_mut` would rehash the whole registry.
+    pub fn validator_mut(&mut self, index: ValidatorIndex) -> Option<&mut Validator> {
+        let validator = self.state.validators_mut().get_mut(index)?;
+        self.progressive_balances = None;
+        self.cache.validators.mark_dirty(index);
+        Some(validator)
+    }
+
+    pub fn into_inner(
+        self,
+    ) -> BeaconState<
+        SLOTS_PER_HISTORICAL_ROOT,
+        HISTORICAL_ROOTS_LIMIT,
+        ETH1_DATA_VOTES_BOUND,
+        VALIDATOR_REGISTRY_LIMIT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        PENDING_ATTESTATIONS_BOUND,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+    > {
+        self.state
+    }
+
+    // Equivalent to `self.state_mut().hash_tree_root()`.
+    pub fn hash_tree_root(&mut self) -> Result<Root, MerkleizationError> {
+        let cache = &mut self.cache;
+        let field_roots = match &mut self.state {
+            BeaconState::Phase0(state) => field_roots!(
+                cache,
+                state,
+                genesis_time,
+                genesis_validators_root,
+                slot,
+                fork,
+                latest_block_header,
+                block_roots,
+                state_roots,
+                historical_roots,
+                eth1_data,
+                eth1_data_votes,
+                eth1_deposit_index,
+                validators,
+                balances,
+                randao_mixes,
+                slashings,
+                previous_epoch_attestations,
+                current_epoch_attestations,
+                justification_bits,
+                previous_justified_checkpoint,
+                current_justified_checkpoint,
+                finalized_checkpoint,
+            ),
+            BeaconState::Altair(state) => field_roots!(
+                cache,
+                state,
+                genesis_time,
+                genesis_validators_root,
+                slot,
+                fork,
+                latest_block_header,
+                block_roots,
+                state_roots,
+                historical_roots,
+                eth1_data,
+                eth1_data_votes,
+                eth1_deposit_index,
+                validators,
+                balances,
+                randao_mixes,
+                slashings,
+                previous_epoch_participation,
+                current_epoch_participation,
+                justification_bits,
+                previous_justified_checkpoint,
+                current_justified_checkpoint,
+                finalized_checkpoint,
+                inactivity_scores,
+                current_sync_committee,
+                next_sync_committee,
+            ),
+            BeaconState::Bellatrix(state) => field_roots!(
+                cache,
+                state,
+                genesis_time,
+                genesis_validators_root,
+                slot,
+                fork,
+                latest_block_header,
+                block_roots,
+                state_roots,
+                historical_roots,
+                eth1_data,
+                eth1_data_votes,
+                eth1_deposit_index,
+                validators,
+                balances,
+                randao_mixes,
+                slashings,
+                previous_epoch_participation,
+                current_epoch_participation,
+                justification_bits,
+                previous_justified_checkpoint,
+                current_justified_checkpoint,
+                finalized_checkpoint,
+                inactivity_scores,
+                current_sync_committee,
+                next_sync_committee,
+                latest_execution_payload_header,
+            ),
+            BeaconState::Capella(state) => field_roots!(
+                cache,
+                state,
+                genesis_time,
+                genesis_validators_root,
+                slot,
+                fork,
+                latest_block_header,
+                block_roots,
+                state_roots,
+                historical_roots,
+                eth1_data,
+                eth1_data_votes,
+                eth1_deposit_index,
+                validators,
+                balances,
+                randao_mixes,
+                slashings,
+                previous_epoch_participation,
+                current_epoch_participation,
+                justification_bits,
+                previous_justified_checkpoint,
+                current_justified_checkpoint,
+                finalized_checkpoint,
+                inactivity_scores,
+                current_sync_committee,
+                next_sync_committee,
+                latest_execution_payload_header,
+                next_withdrawal_index,
+                next_withdrawal_validator_index,
+                historical_summaries,
+            ),
+            BeaconState::Deneb(state) => field_roots!(
+                cache,
+                state,
+                genesis_time,
+                genesis_validators_root,
+                slot,
+                fork,
+                latest_block_header,
+                block_roots,
+                state_roots,
+                historical_roots,
+                eth1_data,
+                eth1_data_votes,
+                eth1_deposit_index,
+                validators,
+                balances,
+                randao_mixes,
+                slashings,
+                previous_epoch_participation,
+                current_epoch_participation,
+                justification_bits,
+                previous_justified_checkpoint,
+                current_justified_checkpoint,
+                finalized_checkpoint,
+                inactivity_scores,
+                current_sync_committee,
+                next_sync_committee,
+                latest_execution_payload_header,
+                next_withdrawal_index,
+                next_withdrawal_validator_index,
+                historical_summaries,
+            ),
+        };
+        Ok(cache.fields.update(field_roots))
+    }
+
+    // The `ProgressiveBalances` of the wrapped state, only read from the whole registry if they
+    // were discarded by `state_mut` or the state has moved to another epoch.
+    pub fn progressive_balances(
+        &mut self,
+        context: &Context,
+    ) -> Result<ProgressiveBalances, Error> {
+        let epoch = compute_epoch_at_slot(self.state.slot(), context);
+        if let Some(balances) = self.progressive_balances.filter(|balances| balances.epoch == epoch)
+        {
+            return Ok(balances)
+        }
+        let balances = self.compute_progressive_balances(epoch, context)?;
+        self.progressive_balances = Some(balances);
+        Ok(balances)
+    }
+
+    fn compute_progressive_balances(
+        &self,
+        current_epoch: Epoch,
+        context: &Context,
+    ) -> Result<ProgressiveBalances, Error> {
+        let previous_epoch =
+            if current_epoch == GENESIS_EPOCH { GENESIS_EPOCH } else { current_epoch - 1 };
+        let mut balances = ProgressiveBalances { epoch: current_epoch, ..Default::default() };
+        let participation =
+            self.state.previous_epoch_participation().zip(self.state.current_epoch_participation());
+        for (index, validator) in self.state.validators().iter().enumerate() {
+            let effective_balance = validator.effective_balance;
+            if is_active_validator(validator, current_epoch) {
+                balances.total_active_balance += effective_balance;
+            }
+            if validator.slashed {
+                continue
+            }
+            if let Some((previous, current)) = participation {
+                if is_active_validator(validator, previous_epoch) &&
+                    has_flag(previous[index], TIMELY_TARGET_FLAG_INDEX)
+                {
+                    balances.previous_epoch_target_balance += effective_balance;
+                }
+                if is_active_validator(validator, current_epoch) &&
+                    has_flag(current[index], TIMELY_TARGET_FLAG_INDEX)
+                {
+                    balances.current_epoch_target_balance += effective_balance;
+                }
+            }
+        }
+        if let BeaconState::Phase0(state) = &self.state {
+            let target_balance = |epoch| -> Result<Gwei, Error> {
+                let attestations = phase0::get_matching_target_attestations(state, epoch, context)?;
+                let indices =
+                    phase0::get_unslashed_attesting_indices(state, attestations, context)?;
+                Ok(indices.into_iter().map(|index| state.validators[index].effective_balance).sum())
+            };
+            balances.previous_epoch_target_balance = target_balance(previous_epoch)?;
+            balances.current_epoch_target_balance = target_balance(current_epoch)?;
+        }
+        Ok(balances)
+    }
+
+    // `process_slots` for the wrapped state. Processing a slot leaves the registry and the
+    // participation alone, so the `ProgressiveBalances` are kept until the state changes epoch.
+    pub fn process_slots(&mut self, slot: Slot, context: &Context) -> Result<(), Error> {
+        let balances = self.progressive_balances.take();
+        // Only the epoch transition changes validators, and only some of their fields, so these
+        // are compared to find the validators to rehash.
+        let crosses_epoch = compute_epoch_at_slot(slot, context) >
+            compute_epoch_at_slot(self.state.slot(), context);
+        let previous_fields = crosses_epoch.then(|| {
+            self.state.validators().iter().map(epoch_transition_fields).collect::<Vec<_>>()
+        });
+        let result = match &mut self.state {
+            BeaconState::Phase0(state) => phase0::process_slots(state, slot, context),
+            BeaconState::Altair(state) => altair::process_slots(state, slot, context),
+            BeaconState::Bellatrix(state) => bellatrix::process_slots(state, slot, context),
+            BeaconState::Capella(state) => capella::process_slots(state, slot, context),
+            BeaconState::Deneb(state) => deneb::process_slots(state, slot, context),
+        };
+        for (index, fields) in previous_fields.into_iter().flatten().enumerate() {
+            if epoch_transition_fields(&self.state.validators()[index]) != fields {
+                self.cache.validators.mark_dirty(index);
+            }
+        }
+        result?;
+        self.progressive_balances = balances;
+        Ok(())
+    }
+
+    // `process_attestation` for the wrapped state, adding the attesters that gain the timely target
+    // flag to the `ProgressiveBalances`.
+    pub fn process_attestation(
+        &mut self,
+        attestation: &Attestation<MAX_VALIDATORS_PER_COMMITTEE>,
+        context: &Context,
+    ) -> Result<(), Error> {
+        // Pending attestations are only matched against the target in the epoch transition, so
+        // the totals of a phase0 state are recomputed on the next read instead.
+        if let BeaconState::Phase0(state) = &mut self.state {
+            self.progressive_balances = None;
+            return phase0::process_attestation(state, attestation, context)
+        }
+
+        let mut balances = self.progressive_balances(context)?;
+        // discarded unless the attestation is processed in full
+        self.progressive_balances = None;
+        let is_current = attestation.data.target.epoch == balances.epoch;
+        let target_balance = match &mut self.state {
+            BeaconState::Phase0(_) => unreachable!("handled above"),
+            BeaconState::Altair(state) => process_attestation_with_target_balance!(
+                altair,
+                state,
+                attestation,
+                is_current,
+                context
+            ),
+            BeaconState::Bellatrix(state) => process_attestation_with_target_balance!(
+                bellatrix,
+                state,
+                attestation,
+                is_current,
+                context
+            ),
+            BeaconState::Capella(state) => process_attestation_with_target_balance!(
+                capella,
+                state,
+                attestation,
+                is_current,
+                context
+            ),
+            BeaconState::Deneb(state) => process_attestation_with_target_balance!(
+                deneb,
+                state,
+                attestation,
+                is_current,
+                context
+            ),
+        };
+        if is_current {
+            balances.current_epoch_target_balance += target_balance;
+        } else {
+            balances.previous_epoch_target_balance += target_balance;
+        }
+        self.progressive_balances = Some(balances);
+        Ok(())
+    }
+
+    // `process_justification_and_finalization` for the wrapped state, reading the
+    // `ProgressiveBalances` instead of the whole registry.
+    pub fn process_justification_and_finalization(
+        &mut self,
+        context: &Context,
+    ) -> Result<(), Error> {
+        let balances = self.progressive_balances(context)?;
+        // Skip FFG updates in the first two epochs, as the spec does
+        if balances.epoch <= GENESIS_EPOCH + 1 {
+            return Ok(())
+        }
+
+        // Match `get_total_balance`, which returns at least one increment
+        let total_balance = |balance| u64::max(context.effective_balance_increment, balance);
+        let total_active_balance = total_balance(balances.total_active_balance);
+        let previous_target_balance = total_balance(balances.previous_epoch_target_balance);
+        let current_target_balance = total_balance(balances.current_epoch_target_balance);
+        match &mut self.state {
+            BeaconState::Phase0(state) => phase0::weigh_justification_and_finalization(
+                state,
+                total_active_balance,
+                previous_target_balance,
+                current_target_balance,
+                context,
+            ),
+            BeaconState::Altair(state) => altair::weigh_justification_and_finalization(
+                state,
+                total_active_balance,
+                previous_target_balance,
+                current_target_balance,
+                context,
+            ),
+            BeaconState::Bellatrix(state) => bellatrix::weigh_justification_and_finalization(
+                state,
+                total_active_balance,
+                previous_target_balance,
+                current_target_balance,
+                context,
+            ),
+            BeaconState::Capella(state) => capella::weigh_justification_and_finalization(
+                state,
+                total_active_balance,
+                previous_target_balance,
+                current_target_balance,
+                context,
+            ),
+            BeaconState::Deneb(state) => deneb::weigh_justification_and_finalization(
+                state,
+                total_active_balance,
+                previous_target_balance,
+                current_target_balance,
+                context,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        altair::mainnet::BeaconState as AltairBeaconState,
+        deneb::{
+            minimal as deneb_minimal,
+            test_utils::{attestation, state_at},
+        },
+        phase0::mainnet::BeaconState as Phase0BeaconState,
+        types::{mainnet::BeaconState, minimal::BeaconState as MinimalBeaconState},
+    };
+
+    fn validator(seed: u8) -> Validator {
+        let mut validator = Validator { effective_balance: seed as u64, ..Default::default() };
+        validator.public_key[0] = seed;
+        validator
+    }
+
+    #[test]
+    fn test_merkle_cache_matches_full_merkleization() {
+        let depth = 4;
+        let mut cache = MerkleCache::new(depth);
+        let leaf = |i: usize| Node::try_from([i as u8 + 1; 32].as_ref()).unwrap();
+        for leaves in [
+            (0..5).map(leaf).collect::<Vec<_>>(),
+            (0..8).map(leaf).collect(),
+            vec![leaf(0), leaf(9), leaf(2), leaf(3), leaf(4), leaf(5), leaf(6), leaf(7)],
+            (0..3).map(leaf).collect(),
+            (0..4).map(leaf).collect(),
+            vec![],
+            (0..16).map(leaf).collect(),
+        ] {
+            let root = cache.update(leaves.clone());
+            let mut padded = leaves.clone();
+            padded.resize(1 << depth, Node::default());
+            let branch = crate::proofs::MerkleBranch::new(&padded, depth, 0);
+            assert_eq!(root, branch.compute_root(&padded[0]));
+        }
+
+        // the same trees, given only the leaves that changed
+        let mut partial = MerkleCache::new(depth);
+        for (len, changes) in [
+            (5, (0..5).map(|i| (i, leaf(i))).collect::<Vec<_>>()),
+            (8, (5..8).map(|i| (i, leaf(i))).collect()),
+            (8, vec![(1, leaf(9))]),
+            (3, vec![(1, leaf(1))]),
+            (4, vec![(3, leaf(3))]),
+            (0, vec![]),
+            (16, (0..16).map(|i| (i, leaf(i))).collect()),
+        ] {
+            let root = partial.update_leaves(len, changes);
+            let mut padded = partial.leaves().to_vec();
+            padded.resize(1 << depth, Node::default());
+            let branch = crate::proofs::MerkleBranch::new(&padded, depth, 0);
+            assert_eq!(root, branch.compute_root(&padded[0]));
+        }
+        assert_eq!(partial.root(), cache.root());
+    }
+
+    #[test]
+    fn test_cached_phase0_state_root() {
+        let mut inner = Phase0BeaconState { slot: 10, ..Default::default() };
+        for i in 0..10 {
+            inner.validators.push(validator(i));
+            inner.balances.push(i as u64);
+        }
+        let mut state = CachedBeaconState::new(BeaconState::Phase0(inner));
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+
+        let inner = state.state_mut().phase0_mut().unwrap();
+        inner.validators[3].slashed = true;
+        inner.balances[7] = 100;
+        inner.block_roots[2] = Node::try_from([1u8; 32].as_ref()).unwrap();
+        inner.validators.push(validator(42));
+        inner.balances.push(42);
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+
+        let inner = state.state_mut().phase0_mut().unwrap();
+        inner.validators.pop();
+        inner.balances.pop();
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cached_altair_state_root() {
+        let mut inner = AltairBeaconState::default();
+        for i in 0..40 {
+            inner.validators.push(validator(i));
+            inner.balances.push(i as u64);
+            inner.previous_epoch_participation.push(i & 7);
+            inner.current_epoch_participation.push(0);
+            inner.inactivity_scores.push(0);
+        }
+        let mut state = CachedBeaconState::new(BeaconState::Altair(inner));
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+
+        let inner = state.state_mut().altair_mut().unwrap();
+        inner.current_epoch_participation[33] = 7;
+        inner.inactivity_scores[5] = 4;
+        inner.randao_mixes[1] = Default::default();
+        inner.randao_mixes[2] = crate::primitives::Bytes32::try_from([3u8; 32].as_ref()).unwrap();
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cached_registry_tracks_changed_validators() {
+        let context = Context::for_minimal();
+        let mut state = CachedBeaconState::new(MinimalBeaconState::Deneb(state_at(29, &context)));
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+
+        state.validator_mut(3).unwrap().slashed = true;
+        assert!(state.validator_mut(64).is_none());
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+
+        state.state_mut().deneb_mut().unwrap().balances[5] = 0;
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+
+        // the epoch transition lowers the effective balance of the validator and ejects it
+        state.process_slots(32, &context).unwrap();
+        let validator = &state.state().validators()[5];
+        assert_eq!(validator.effective_balance, 0);
+        assert_ne!(validator.exit_epoch, crate::primitives::FAR_FUTURE_EPOCH);
+        assert_eq!(
+            state.hash_tree_root().unwrap(),
+            state.state().clone().hash_tree_root().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_progressive_balances() {
+        let context = Context::for_minimal();
+        let max_effective_balance = context.max_effective_balance;
+        // the state is in epoch 3, late enough for justification to run
+        let mut expected = state_at(29, &context);
+        let mut state = CachedBeaconState::new(MinimalBeaconState::Deneb(expected.clone()));
+        let balances = state.progressive_balances(&context).unwrap();
+        assert_eq!(balances.epoch, 3);
+        assert_eq!(balances.total_active_balance, 64 * max_effective_balance);
+        assert_eq!(balances.previous_epoch_target_balance, 0);
+        assert_eq!(balances.current_epoch_target_balance, 0);
+
+        // every validator attests in the previous epoch and the first 5 slots, with 2 committees
+        // of 4 each, in the current one; the repeated attestation must not be counted twice
+        for (slot, index) in (16..29).flat_map(|slot| [(slot, 0), (slot, 1)]).chain([(24, 0)]) {
+            let attestation = attestation(&expected, slot, index, &context);
+            deneb_minimal::process_attestation(&mut expected, &attestation, &context).unwrap();
+            state.process_attestation(&attestation, &context).unwrap();
+        }
+        let balances = state.progressive_balances(&context).unwrap();
+        assert_eq!(balances.previous_epoch_target_balance, 64 * max_effective_balance);
+        assert_eq!(balances.current_epoch_target_balance, 40 * max_effective_balance);
+        let recomputed = CachedBeaconState::new(MinimalBeaconState::Deneb(expected.clone()))
+            .progressive_balances(&context)
+            .unwrap();
+        assert_eq!(balances, recomputed);
+
+        // the totals are kept within the epoch
+        deneb_minimal::process_slots(&mut expected, 30, &context).unwrap();
+        state.process_slots(30, &context).unwrap();
+        assert_eq!(state.progressive_balances(&context).unwrap(), balances);
+
+        deneb_minimal::process_justification_and_finalization(&mut expected, &context).unwrap();
+        state.process_justification_and_finalization(&context).unwrap();
+        assert_eq!(state.state().deneb().unwrap(), &expected);
+        assert_eq!(expected.current_justified_checkpoint.epoch, 2);
+
+        // direct mutations discard the totals
+        state.state_mut().deneb_mut().unwrap().validators[0].slashed = true;
+        let balances = state.progressive_balances(&context).unwrap();
+        assert_eq!(balances.previous_epoch_target_balance, 63 * max_effective_balance);
+    }
+}
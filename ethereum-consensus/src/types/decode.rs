@@ -0,0 +1,154 @@
+use crate::{
+    altair::beacon_block as altair,
+    bellatrix::beacon_block as bellatrix,
+    capella::beacon_block as capella,
+    deneb::beacon_block as deneb,
+    phase0::beacon_block as phase0,
+    primitives::{ForkDigest, Root, Slot},
+    ssz::prelude::*,
+    state_transition::Context,
+    types::SignedBeaconBlock,
+    Fork,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Deserialize(#[from] DeserializeError),
+    #[error("{0}")]
+    Consensus(#[from] crate::Error),
+    #[error("fork digest {0:?} does not match any known fork")]
+    UnknownForkDigest(ForkDigest),
+}
+
+// Determines which fork an encoded object belongs to, e.g. from the context bytes
+// of a req/resp chunk, from the slot a caller expects the object to be at, or directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkHint {
+    Digest { fork_digest: ForkDigest, genesis_validators_root: Root },
+    Slot(Slot),
+    Fork(Fork),
+}
+
+impl ForkHint {
+    pub fn fork(&self, context: &Context) -> Result<Fork, Error> {
+        match *self {
+            Self::Digest { fork_digest, genesis_validators_root } => context
+                .fork_for_digest(fork_digest, genesis_validators_root)?
+                .ok_or(Error::UnknownForkDigest(fork_digest)),
+            Self::Slot(slot) => Ok(context.fork_for(slot)),
+            Self::Fork(fork) => Ok(fork),
+        }
+    }
+}
+
+impl From<Slot> for ForkHint {
+    fn from(slot: Slot) -> Self {
+        Self::Slot(slot)
+    }
+}
+
+impl From<Fork> for ForkHint {
+    fn from(fork: Fork) -> Self {
+        Self::Fork(fork)
+    }
+}
+
+impl<
+        const MAX_PROPOSER_SLASHINGS: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const MAX_ATTESTER_SLASHINGS: usize,
+        const MAX_ATTESTATIONS: usize,
+        const MAX_DEPOSITS: usize,
+        const MAX_VOLUNTARY_EXITS: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+        const MAX_BYTES_PER_TRANSACTION: usize,
+        const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+        const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+        const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+        const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+    >
+    SignedBeaconBlock<
+        MAX_PROPOSER_SLASHINGS,
+        MAX_VALIDATORS_PER_COMMITTEE,
+        MAX_ATTESTER_SLASHINGS,
+        MAX_ATTESTATIONS,
+        MAX_DEPOSITS,
+        MAX_VOLUNTARY_EXITS,
+        SYNC_COMMITTEE_SIZE,
+        BYTES_PER_LOGS_BLOOM,
+        MAX_EXTRA_DATA_BYTES,
+        MAX_BYTES_PER_TRANSACTION,
+        MAX_TRANSACTIONS_PER_PAYLOAD,
+        MAX_WITHDRAWALS_PER_PAYLOAD,
+        MAX_BLS_TO_EXECUTION_CHANGES,
+        MAX_BLOB_COMMITMENTS_PER_BLOCK,
+    >
+{
+    // Decode the SSZ `bytes` as the variant for `fork`.
+    pub fn from_ssz_bytes_for_fork(fork: Fork, bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let block = match fork {
+            Fork::Phase0 => Self::Phase0(phase0::SignedBeaconBlock::deserialize(bytes)?),
+            Fork::Altair => Self::Altair(altair::SignedBeaconBlock::deserialize(bytes)?),
+            Fork::Bellatrix => Self::Bellatrix(bellatrix::SignedBeaconBlock::deserialize(bytes)?),
+            Fork::Capella => Self::Capella(capella::SignedBeaconBlock::deserialize(bytes)?),
+            Fork::Deneb => Self::Deneb(deneb::SignedBeaconBlock::deserialize(bytes)?),
+        };
+        Ok(block)
+    }
+
+    // Decode the SSZ `bytes` as the variant for the fork selected by `hint`.
+    pub fn deserialize_with_fork(
+        bytes: &[u8],
+        hint: impl Into<ForkHint>,
+        context: &Context,
+    ) -> Result<Self, Error> {
+        let fork = hint.into().fork(context)?;
+        Ok(Self::from_ssz_bytes_for_fork(fork, bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{phase0::compute_fork_digest, types::mainnet::SignedBeaconBlock};
+
+    #[test]
+    fn test_deserialize_with_fork() {
+        let context = Context::for_mainnet();
+        let mut inner = crate::capella::mainnet::SignedBeaconBlock::default();
+        inner.message.slot = context.capella_fork_epoch * context.slots_per_epoch;
+        let bytes = serialize(&inner).unwrap();
+
+        let block =
+            SignedBeaconBlock::deserialize_with_fork(&bytes, inner.message.slot, &context).unwrap();
+        assert_eq!(block, SignedBeaconBlock::Capella(inner.clone()));
+
+        let genesis_validators_root = Root::default();
+        let fork_digest =
+            compute_fork_digest(context.capella_fork_version, genesis_validators_root).unwrap();
+        let hint = ForkHint::Digest { fork_digest, genesis_validators_root };
+        let block = SignedBeaconBlock::deserialize_with_fork(&bytes, hint, &context).unwrap();
+        assert_eq!(block.version(), Fork::Capella);
+
+        let hint = ForkHint::Digest { fork_digest: [0xff; 4], genesis_validators_root };
+        assert!(matches!(
+            SignedBeaconBlock::deserialize_with_fork(&bytes, hint, &context),
+            Err(Error::UnknownForkDigest(_))
+        ));
+        assert!(SignedBeaconBlock::deserialize_with_fork(&bytes, 0, &context).is_err());
+
+        let block =
+            SignedBeaconBlock::deserialize_with_fork(&bytes, Fork::Capella, &context).unwrap();
+        assert_eq!(block.message().slot(), inner.message.slot);
+        assert_eq!(block.message().parent_root(), inner.message.parent_root);
+        assert!(SignedBeaconBlock::deserialize_with_fork(&bytes, Fork::Deneb, &context).is_err());
+
+        let block = SignedBeaconBlock::from_ssz_bytes_for_fork(Fork::Capella, &bytes).unwrap();
+        assert_eq!(block, SignedBeaconBlock::Capella(inner));
+        assert!(SignedBeaconBlock::from_ssz_bytes_for_fork(Fork::Phase0, &bytes).is_err());
+    }
+}
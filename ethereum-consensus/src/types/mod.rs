@@ -9,20 +9,24 @@ mod beacon_block_body;
 mod beacon_state;
 mod blinded_beacon_block;
 mod blinded_beacon_block_body;
+mod decode;
 mod execution_payload;
 mod execution_payload_header;
 mod presets;
 mod signed_beacon_block;
 mod signed_blinded_beacon_block;
+mod state_view;
 
 pub use beacon_block::*;
 pub use beacon_block_body::*;
 pub use beacon_state::*;
 pub use blinded_beacon_block::*;
 pub use blinded_beacon_block_body::*;
+pub use decode::{Error as DecodeError, ForkHint};
 pub use execution_payload::*;
 pub use execution_payload_header::*;
 pub use signed_beacon_block::*;
 pub use signed_blinded_beacon_block::*;
+pub use state_view::*;
 
-pub use presets::{mainnet, minimal};
+pub use presets::{gnosis, mainnet, minimal};
@@ -135,3 +135,10 @@ pub type BeaconState = types::BeaconState<
     BYTES_PER_LOGS_BLOOM,
     MAX_EXTRA_DATA_BYTES,
 >;
+
+pub type BeaconStateView<'a> = types::BeaconStateView<
+    'a,
+    SLOTS_PER_HISTORICAL_ROOT,
+    EPOCHS_PER_HISTORICAL_VECTOR,
+    EPOCHS_PER_SLASHINGS_VECTOR,
+>;
@@ -0,0 +1,222 @@
+//! Read the registry of an SSZ-encoded `BeaconState` without decoding the rest of the state.
+//!
+//! The encoding of every fork so far agrees on the fields up to and including `slashings`, so
+//! the view only needs the preset lengths of the vectors before them. It borrows the encoding,
+//! e.g. from a memory-mapped file, and decodes a validator only when it is asked for.
+use crate::{
+    phase0::Validator,
+    primitives::{Gwei, Slot},
+    ssz::prelude::*,
+};
+use std::ops::Range;
+
+const BYTES_PER_LENGTH_OFFSET: usize = 4;
+const ROOT_LEN: usize = 32;
+const FORK_LEN: usize = 16;
+const BEACON_BLOCK_HEADER_LEN: usize = 112;
+const ETH1_DATA_LEN: usize = 72;
+const VALIDATOR_LEN: usize = 121;
+const GWEI_LEN: usize = 8;
+
+const SLOT_OFFSET: usize = 40;
+const BLOCK_ROOTS_OFFSET: usize = SLOT_OFFSET + 8 + FORK_LEN + BEACON_BLOCK_HEADER_LEN;
+
+#[derive(Debug, Clone)]
+pub struct BeaconStateView<
+    'a,
+    const SLOTS_PER_HISTORICAL_ROOT: usize,
+    const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+    const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+> {
+    bytes: &'a [u8],
+    validators: Range<usize>,
+    balances: Range<usize>,
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().expect("slice has 8 bytes"))
+}
+
+fn read_offset(bytes: &[u8], offset: usize) -> usize {
+    let offset_bytes = &bytes[offset..offset + BYTES_PER_LENGTH_OFFSET];
+    u32::from_le_bytes(offset_bytes.try_into().expect("slice has 4 bytes")) as usize
+}
+
+impl<
+        'a,
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+    >
+    BeaconStateView<
+        'a,
+        SLOTS_PER_HISTORICAL_ROOT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+    >
+{
+    // Locate the `validators` and `balances` of the state encoded in `bytes`, which may be of any
+    // fork using these preset values.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DeserializeError> {
+        let historical_roots_offset = BLOCK_ROOTS_OFFSET + 2 * ROOT_LEN * SLOTS_PER_HISTORICAL_ROOT;
+        let eth1_data_votes_offset =
+            historical_roots_offset + BYTES_PER_LENGTH_OFFSET + ETH1_DATA_LEN;
+        let validators_offset = eth1_data_votes_offset + BYTES_PER_LENGTH_OFFSET + 8;
+        let balances_offset = validators_offset + BYTES_PER_LENGTH_OFFSET;
+        // the field after `slashings` is variable-size in every fork and ends `balances`
+        let next_offset = balances_offset +
+            BYTES_PER_LENGTH_OFFSET +
+            ROOT_LEN * EPOCHS_PER_HISTORICAL_VECTOR +
+            GWEI_LEN * EPOCHS_PER_SLASHINGS_VECTOR;
+        let fixed_len = next_offset + BYTES_PER_LENGTH_OFFSET;
+        if bytes.len() < fixed_len {
+            return Err(DeserializeError::ExpectedFurtherInput {
+                provided: bytes.len(),
+                expected: fixed_len,
+            })
+        }
+
+        let validators_start = read_offset(bytes, validators_offset);
+        let balances_start = read_offset(bytes, balances_offset);
+        let balances_end = read_offset(bytes, next_offset);
+        if validators_start > balances_start {
+            return Err(DeserializeError::OffsetNotIncreasing {
+                start: validators_start,
+                end: balances_start,
+            })
+        }
+        if balances_start > balances_end {
+            return Err(DeserializeError::OffsetNotIncreasing {
+                start: balances_start,
+                end: balances_end,
+            })
+        }
+        if balances_end > bytes.len() {
+            return Err(DeserializeError::ExpectedFurtherInput {
+                provided: bytes.len(),
+                expected: balances_end,
+            })
+        }
+        let validators = validators_start..balances_start;
+        let balances = balances_start..balances_end;
+        if validators.len() % VALIDATOR_LEN != 0 {
+            return Err(DeserializeError::AdditionalInput {
+                provided: validators.len(),
+                expected: validators.len() / VALIDATOR_LEN * VALIDATOR_LEN,
+            })
+        }
+        if balances.len() % GWEI_LEN != 0 {
+            return Err(DeserializeError::AdditionalInput {
+                provided: balances.len(),
+                expected: balances.len() / GWEI_LEN * GWEI_LEN,
+            })
+        }
+        Ok(Self { bytes, validators, balances })
+    }
+
+    pub fn slot(&self) -> Slot {
+        read_u64(self.bytes, SLOT_OFFSET)
+    }
+
+    pub fn validator_count(&self) -> usize {
+        self.validators.len() / VALIDATOR_LEN
+    }
+
+    pub fn balance_count(&self) -> usize {
+        self.balances.len() / GWEI_LEN
+    }
+
+    pub fn validator(&self, index: usize) -> Option<Result<Validator, DeserializeError>> {
+        if index >= self.validator_count() {
+            return None
+        }
+        let start = self.validators.start + index * VALIDATOR_LEN;
+        Some(Validator::deserialize(&self.bytes[start..start + VALIDATOR_LEN]))
+    }
+
+    pub fn balance(&self, index: usize) -> Option<Gwei> {
+        if index >= self.balance_count() {
+            return None
+        }
+        Some(read_u64(self.bytes, self.balances.start + index * GWEI_LEN))
+    }
+
+    pub fn validators(
+        &self,
+    ) -> impl ExactSizeIterator<Item = Result<Validator, DeserializeError>> + 'a {
+        self.bytes[self.validators.clone()].chunks_exact(VALIDATOR_LEN).map(Validator::deserialize)
+    }
+
+    pub fn balances(&self) -> impl ExactSizeIterator<Item = Gwei> + 'a {
+        self.bytes[self.balances.clone()]
+            .chunks_exact(GWEI_LEN)
+            .map(|balance| u64::from_le_bytes(balance.try_into().expect("chunk has 8 bytes")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        altair::minimal as altair,
+        phase0::minimal::{
+            self as phase0, EPOCHS_PER_HISTORICAL_VECTOR, EPOCHS_PER_SLASHINGS_VECTOR,
+            SLOTS_PER_HISTORICAL_ROOT,
+        },
+    };
+
+    type View<'a> = BeaconStateView<
+        'a,
+        SLOTS_PER_HISTORICAL_ROOT,
+        EPOCHS_PER_HISTORICAL_VECTOR,
+        EPOCHS_PER_SLASHINGS_VECTOR,
+    >;
+
+    fn validators() -> Vec<Validator> {
+        (0..5u64)
+            .map(|i| Validator {
+                effective_balance: i * 1_000,
+                slashed: i % 2 == 0,
+                exit_epoch: i + 10,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_view_matches_decoded_state() {
+        let mut state = phase0::BeaconState { slot: 17, ..Default::default() };
+        state.eth1_data_votes.push(Default::default());
+        for (i, validator) in validators().into_iter().enumerate() {
+            state.validators.push(validator);
+            state.balances.push(i as u64 * 7);
+        }
+        let bytes = serialize(&state).unwrap();
+        let view = View::new(&bytes).unwrap();
+        assert_eq!(view.slot(), 17);
+        assert_eq!(view.validator_count(), state.validators.len());
+        assert_eq!(view.validator(3).unwrap().unwrap(), state.validators[3]);
+        assert!(view.validator(5).is_none());
+        assert_eq!(view.balance(4), Some(28));
+        assert_eq!(view.balances().collect::<Vec<_>>(), state.balances.to_vec());
+        let decoded = view.validators().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(decoded, state.validators.to_vec());
+
+        assert!(View::new(&bytes[..100]).is_err());
+    }
+
+    #[test]
+    fn test_view_of_later_fork() {
+        let mut state = altair::BeaconState::default();
+        for validator in validators() {
+            state.validators.push(validator);
+            state.balances.push(32);
+            state.inactivity_scores.push(1);
+        }
+        let bytes = serialize(&state).unwrap();
+        let view = View::new(&bytes).unwrap();
+        assert_eq!(view.validators().len(), 5);
+        assert_eq!(view.validator(0).unwrap().unwrap(), state.validators[0]);
+        assert!(view.balances().all(|balance| balance == 32));
+    }
+}
@@ -0,0 +1,617 @@
+//! Monitor a set of validators from the blocks and states of a chain, reporting
+//! per-epoch summaries and notable events to registered listeners.
+use crate::{
+    altair, bellatrix, capella, deneb, phase0,
+    primitives::{BlsPublicKey, Epoch, Gwei, Slot, ValidatorIndex},
+    state_transition::Context,
+    types::{BeaconState, SignedBeaconBlock},
+    Error,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorEvent {
+    MissedAttestation { index: ValidatorIndex, epoch: Epoch },
+    MissedProposal { index: ValidatorIndex, slot: Slot },
+    // `slot` is the slot the sync committee signed, i.e. the slot before the block
+    SyncParticipation { index: ValidatorIndex, slot: Slot, participated: bool },
+    BalanceDelta { index: ValidatorIndex, epoch: Epoch, delta: i64 },
+    Slashed { index: ValidatorIndex, epoch: Epoch },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpochSummary {
+    pub epoch: Epoch,
+    pub index: ValidatorIndex,
+    // balance in the latest state seen for the epoch, if any
+    pub balance: Option<Gwei>,
+    // change since the balance in the previous summary
+    pub balance_delta: Option<i64>,
+    // `None` before `altair` or while the validator is not active
+    pub attested: Option<bool>,
+    pub proposals: u64,
+    pub missed_proposals: u64,
+    pub sync_participations: u64,
+    pub sync_misses: u64,
+    pub slashed: bool,
+}
+
+pub trait MonitorListener {
+    fn on_event(&mut self, _event: &MonitorEvent) {}
+
+    fn on_epoch_summary(&mut self, _summary: &EpochSummary) {}
+}
+
+// Feed the monitor each imported block and the (post-)state of the head as the chain
+// advances. Validators registered by public key are resolved on the next state.
+//
+// The summary for an epoch is emitted once a state two epochs later is seen, so that
+// attestations included late are still counted.
+#[derive(Default)]
+pub struct ValidatorMonitor {
+    indices: HashSet<ValidatorIndex>,
+    pending_public_keys: HashSet<BlsPublicKey>,
+    listeners: Vec<Box<dyn MonitorListener>>,
+    current_epoch: Option<Epoch>,
+    // positions of monitored validators in the sync committee of each known period
+    sync_committee_positions: BTreeMap<u64, HashMap<ValidatorIndex, Vec<usize>>>,
+    proposer_duties: BTreeMap<Slot, ValidatorIndex>,
+    proposed_slots: HashSet<Slot>,
+    summaries: BTreeMap<Epoch, HashMap<ValidatorIndex, EpochSummary>>,
+    balances: HashMap<ValidatorIndex, Gwei>,
+    slashed: HashSet<ValidatorIndex>,
+}
+
+impl ValidatorMonitor {
+    pub fn register_index(&mut self, index: ValidatorIndex) {
+        self.indices.insert(index);
+    }
+
+    pub fn register_public_key(&mut self, public_key: BlsPublicKey) {
+        self.pending_public_keys.insert(public_key);
+    }
+
+    pub fn add_listener(&mut self, listener: Box<dyn MonitorListener>) {
+        self.listeners.push(listener);
+    }
+
+    pub fn indices(&self) -> impl Iterator<Item = &ValidatorIndex> {
+        self.indices.iter()
+    }
+
+    pub fn process_block<
+        const MAX_PROPOSER_SLASHINGS: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const MAX_ATTESTER_SLASHINGS: usize,
+        const MAX_ATTESTATIONS: usize,
+        const MAX_DEPOSITS: usize,
+        const MAX_VOLUNTARY_EXITS: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+        const MAX_BYTES_PER_TRANSACTION: usize,
+        const MAX_TRANSACTIONS_PER_PAYLOAD: usize,
+        const MAX_WITHDRAWALS_PER_PAYLOAD: usize,
+        const MAX_BLS_TO_EXECUTION_CHANGES: usize,
+        const MAX_BLOB_COMMITMENTS_PER_BLOCK: usize,
+    >(
+        &mut self,
+        signed_block: &SignedBeaconBlock<
+            MAX_PROPOSER_SLASHINGS,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            MAX_ATTESTER_SLASHINGS,
+            MAX_ATTESTATIONS,
+            MAX_DEPOSITS,
+            MAX_VOLUNTARY_EXITS,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+            MAX_BYTES_PER_TRANSACTION,
+            MAX_TRANSACTIONS_PER_PAYLOAD,
+            MAX_WITHDRAWALS_PER_PAYLOAD,
+            MAX_BLS_TO_EXECUTION_CHANGES,
+            MAX_BLOB_COMMITMENTS_PER_BLOCK,
+        >,
+        context: &Context,
+    ) {
+        let block = signed_block.message();
+        let slot = block.slot();
+        let epoch = slot / context.slots_per_epoch;
+        self.proposed_slots.insert(slot);
+        let proposer_index = block.proposer_index();
+        if self.indices.contains(&proposer_index) {
+            self.summary(epoch, proposer_index).proposals += 1;
+        }
+
+        let mut events = vec![];
+        let body = block.body();
+        if let Some(sync_aggregate) = body.sync_aggregate() {
+            let signed_slot = slot.saturating_sub(1);
+            let signed_epoch = signed_slot / context.slots_per_epoch;
+            // the aggregate is signed by the committee of `signed_slot`, which differs from the
+            // committee of `slot` for the first block of a period
+            let period = altair::compute_sync_committee_period_at_slot(signed_slot, context);
+            let mut participation = self
+                .sync_committee_positions
+                .get(&period)
+                .into_iter()
+                .flatten()
+                .map(|(&index, positions)| {
+                    let participated =
+                        positions.iter().any(|&i| sync_aggregate.sync_committee_bits[i]);
+                    (index, participated)
+                })
+                .collect::<Vec<_>>();
+            participation.sort_unstable();
+            for (index, participated) in participation {
+                let summary = self.summary(signed_epoch, index);
+                if participated {
+                    summary.sync_participations += 1;
+                } else {
+                    summary.sync_misses += 1;
+                }
+                events.push(MonitorEvent::SyncParticipation {
+                    index,
+                    slot: signed_slot,
+                    participated,
+                });
+            }
+        }
+
+        let mut slashed = body
+            .proposer_slashings()
+            .iter()
+            .map(|slashing| slashing.signed_header_1.message.proposer_index)
+            .collect::<Vec<_>>();
+        for slashing in body.attester_slashings().iter() {
+            let indices_2 = slashing.attestation_2.attesting_indices.iter().collect::<HashSet<_>>();
+            slashed.extend(
+                slashing
+                    .attestation_1
+                    .attesting_indices
+                    .iter()
+                    .filter(|index| indices_2.contains(index)),
+            );
+        }
+        for index in slashed {
+            if let Some(event) = self.record_slashing(index, epoch) {
+                events.push(event);
+            }
+        }
+        self.emit(&events);
+    }
+
+    pub fn process_state<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+    >(
+        &mut self,
+        state: &BeaconState<
+            SLOTS_PER_HISTORICAL_ROOT,
+            HISTORICAL_ROOTS_LIMIT,
+            ETH1_DATA_VOTES_BOUND,
+            VALIDATOR_REGISTRY_LIMIT,
+            EPOCHS_PER_HISTORICAL_VECTOR,
+            EPOCHS_PER_SLASHINGS_VECTOR,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            PENDING_ATTESTATIONS_BOUND,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+        >,
+        context: &Context,
+    ) -> Result<(), Error> {
+        let validators = state.validators();
+        let mut resolved = false;
+        if !self.pending_public_keys.is_empty() {
+            for (index, validator) in validators.iter().enumerate() {
+                if self.pending_public_keys.remove(&validator.public_key) {
+                    self.indices.insert(index);
+                    resolved = true;
+                }
+            }
+        }
+
+        let epoch = state.slot() / context.slots_per_epoch;
+        if self.current_epoch != Some(epoch) || resolved {
+            self.current_epoch = Some(epoch);
+            self.update_sync_committee_positions(state, epoch, context);
+            self.update_proposer_duties(state, epoch, context)?;
+        }
+
+        let mut events = vec![];
+        let balances = state.balances();
+        let mut indices = self.indices.iter().copied().collect::<Vec<_>>();
+        indices.sort_unstable();
+        for index in indices {
+            let Some(validator) = validators.get(index) else { continue };
+            self.summary(epoch, index).balance = balances.get(index).copied();
+            if validator.slashed {
+                if let Some(event) = self.record_slashing(index, epoch) {
+                    events.push(event);
+                }
+            }
+            if let (Some(previous_epoch), Some(participation)) =
+                (epoch.checked_sub(1), state.previous_epoch_participation())
+            {
+                let is_active = validator.activation_epoch <= previous_epoch &&
+                    previous_epoch < validator.exit_epoch;
+                let attested = participation.get(index).map(|&flags| flags != 0);
+                self.summary(previous_epoch, index).attested = attested.filter(|_| is_active);
+            }
+        }
+        self.emit(&events);
+
+        self.finalize_epochs_before(epoch.saturating_sub(1), context);
+        Ok(())
+    }
+
+    fn summary(&mut self, epoch: Epoch, index: ValidatorIndex) -> &mut EpochSummary {
+        self.summaries.entry(epoch).or_default().entry(index).or_insert_with(|| EpochSummary {
+            epoch,
+            index,
+            ..Default::default()
+        })
+    }
+
+    fn record_slashing(&mut self, index: ValidatorIndex, epoch: Epoch) -> Option<MonitorEvent> {
+        if !self.indices.contains(&index) {
+            return None
+        }
+        self.summary(epoch, index).slashed = true;
+        self.slashed.insert(index).then_some(MonitorEvent::Slashed { index, epoch })
+    }
+
+    fn update_sync_committee_positions<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+    >(
+        &mut self,
+        state: &BeaconState<
+            SLOTS_PER_HISTORICAL_ROOT,
+            HISTORICAL_ROOTS_LIMIT,
+            ETH1_DATA_VOTES_BOUND,
+            VALIDATOR_REGISTRY_LIMIT,
+            EPOCHS_PER_HISTORICAL_VECTOR,
+            EPOCHS_PER_SLASHINGS_VECTOR,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            PENDING_ATTESTATIONS_BOUND,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+        >,
+        epoch: Epoch,
+        context: &Context,
+    ) {
+        // keep the previous period for blocks at its boundary that are seen after the new state
+        let period = altair::compute_sync_committee_period(epoch, context);
+        self.sync_committee_positions.retain(|&known, _| known + 1 >= period);
+        let committees =
+            [(period, state.current_sync_committee()), (period + 1, state.next_sync_committee())];
+        let validators = state.validators();
+        for (period, sync_committee) in committees {
+            let Some(sync_committee) = sync_committee else { continue };
+            let mut positions = HashMap::<_, Vec<_>>::new();
+            for (position, public_key) in sync_committee.public_keys.iter().enumerate() {
+                positions.entry(public_key).or_default().push(position);
+            }
+            let monitored = self
+                .indices
+                .iter()
+                .filter_map(|&index| {
+                    let validator = validators.get(index)?;
+                    Some((index, positions.get(&validator.public_key)?.clone()))
+                })
+                .collect();
+            self.sync_committee_positions.insert(period, monitored);
+        }
+    }
+
+    // Proposers are only computed if a monitored validator is active in `epoch`.
+    fn update_proposer_duties<
+        const SLOTS_PER_HISTORICAL_ROOT: usize,
+        const HISTORICAL_ROOTS_LIMIT: usize,
+        const ETH1_DATA_VOTES_BOUND: usize,
+        const VALIDATOR_REGISTRY_LIMIT: usize,
+        const EPOCHS_PER_HISTORICAL_VECTOR: usize,
+        const EPOCHS_PER_SLASHINGS_VECTOR: usize,
+        const MAX_VALIDATORS_PER_COMMITTEE: usize,
+        const PENDING_ATTESTATIONS_BOUND: usize,
+        const SYNC_COMMITTEE_SIZE: usize,
+        const BYTES_PER_LOGS_BLOOM: usize,
+        const MAX_EXTRA_DATA_BYTES: usize,
+    >(
+        &mut self,
+        state: &BeaconState<
+            SLOTS_PER_HISTORICAL_ROOT,
+            HISTORICAL_ROOTS_LIMIT,
+            ETH1_DATA_VOTES_BOUND,
+            VALIDATOR_REGISTRY_LIMIT,
+            EPOCHS_PER_HISTORICAL_VECTOR,
+            EPOCHS_PER_SLASHINGS_VECTOR,
+            MAX_VALIDATORS_PER_COMMITTEE,
+            PENDING_ATTESTATIONS_BOUND,
+            SYNC_COMMITTEE_SIZE,
+            BYTES_PER_LOGS_BLOOM,
+            MAX_EXTRA_DATA_BYTES,
+        >,
+        epoch: Epoch,
+        context: &Context,
+    ) -> Result<(), Error> {
+        let validators = state.validators();
+        let any_active = self.indices.iter().any(|&index| {
+            validators.get(index).is_some_and(|validator| {
+                validator.activation_epoch <= epoch && epoch < validator.exit_epoch
+            })
+        });
+        if !any_active {
+            return Ok(())
+        }
+
+        let proposers = match state {
+            BeaconState::Phase0(state) => {
+                phase0::get_beacon_proposer_indices(state, epoch, context)
+            }
+            BeaconState::Altair(state) => {
+                altair::get_beacon_proposer_indices(state, epoch, context)
+            }
+            BeaconState::Bellatrix(state) => {
+                bellatrix::get_beacon_proposer_indices(state, epoch, context)
+            }
+            BeaconState::Capella(state) => {
+                capella::get_beacon_proposer_indices(state, epoch, context)
+            }
+            BeaconState::Deneb(state) => deneb::get_beacon_proposer_indices(state, epoch, context),
+        }?;
+        let start_slot = epoch * context.slots_per_epoch;
+        for (slot, index) in (start_slot..).zip(proposers) {
+            if self.indices.contains(&index) {
+                self.proposer_duties.insert(slot, index);
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize_epochs_before(&mut self, epoch: Epoch, context: &Context) {
+        let mut events = vec![];
+        let end_slot = epoch * context.slots_per_epoch;
+        let remaining_duties = self.proposer_duties.split_off(&end_slot);
+        let duties = std::mem::replace(&mut self.proposer_duties, remaining_duties);
+        for (slot, index) in duties {
+            if !self.proposed_slots.contains(&slot) {
+                self.summary(slot / context.slots_per_epoch, index).missed_proposals += 1;
+                events.push(MonitorEvent::MissedProposal { index, slot });
+            }
+        }
+        self.proposed_slots.retain(|&slot| slot >= end_slot);
+
+        let remaining = self.summaries.split_off(&epoch);
+        let finalized = std::mem::replace(&mut self.summaries, remaining);
+        let mut indices = self.indices.iter().copied().collect::<Vec<_>>();
+        indices.sort_unstable();
+        let mut summaries = vec![];
+        for (epoch, mut epoch_summaries) in finalized {
+            for &index in &indices {
+                let mut summary = epoch_summaries.remove(&index).unwrap_or(EpochSummary {
+                    epoch,
+                    index,
+                    ..Default::default()
+                });
+                if summary.attested == Some(false) {
+                    events.push(MonitorEvent::MissedAttestation { index, epoch });
+                }
+                if let Some(balance) = summary.balance {
+                    if let Some(previous) = self.balances.insert(index, balance) {
+                        let delta = balance as i64 - previous as i64;
+                        summary.balance_delta = Some(delta);
+                        events.push(MonitorEvent::BalanceDelta { index, epoch, delta });
+                    }
+                }
+                summaries.push(summary);
+            }
+        }
+
+        self.emit(&events);
+        for listener in self.listeners.iter_mut() {
+            for summary in &summaries {
+                listener.on_epoch_summary(summary);
+            }
+        }
+    }
+
+    fn emit(&mut self, events: &[MonitorEvent]) {
+        for listener in self.listeners.iter_mut() {
+            for event in events {
+                listener.on_event(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        altair::mainnet as spec,
+        crypto::SecretKey,
+        primitives::FAR_FUTURE_EPOCH,
+        types::mainnet::{BeaconState, SignedBeaconBlock},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<MonitorEvent>,
+        summaries: Vec<EpochSummary>,
+    }
+
+    impl MonitorListener for Rc<RefCell<Recorder>> {
+        fn on_event(&mut self, event: &MonitorEvent) {
+            self.borrow_mut().events.push(event.clone());
+        }
+
+        fn on_epoch_summary(&mut self, summary: &EpochSummary) {
+            self.borrow_mut().summaries.push(summary.clone());
+        }
+    }
+
+    fn push_validator(state: &mut spec::BeaconState, public_key: &BlsPublicKey, context: &Context) {
+        state.validators.push(crate::phase0::Validator {
+            public_key: public_key.clone(),
+            effective_balance: context.max_effective_balance,
+            exit_epoch: FAR_FUTURE_EPOCH,
+            withdrawable_epoch: FAR_FUTURE_EPOCH,
+            ..Default::default()
+        });
+        state.balances.push(context.max_effective_balance);
+        state.previous_epoch_participation.push(0);
+        state.current_epoch_participation.push(0);
+        state.inactivity_scores.push(0);
+    }
+
+    #[test]
+    fn test_validator_monitor() {
+        let context = Context::for_mainnet();
+        let slots_per_epoch = context.slots_per_epoch;
+        let mut rng = rand::thread_rng();
+        let public_key = SecretKey::random(&mut rng).unwrap().public_key();
+
+        let mut inner = spec::BeaconState::default();
+        push_validator(&mut inner, &public_key, &context);
+        inner.current_sync_committee.public_keys[0] = public_key.clone();
+
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+        let mut monitor = ValidatorMonitor::default();
+        monitor.register_public_key(public_key);
+        monitor.add_listener(Box::new(recorder.clone()));
+
+        inner.slot = 2 * slots_per_epoch;
+        monitor.process_state(&BeaconState::Altair(inner.clone()), &context).unwrap();
+        assert_eq!(monitor.indices().collect::<Vec<_>>(), vec![&0]);
+
+        let mut block = spec::SignedBeaconBlock::default();
+        block.message.slot = 2 * slots_per_epoch + 1;
+        block.message.body.sync_aggregate.sync_committee_bits.set(0, true);
+        monitor.process_block(&SignedBeaconBlock::Altair(block), &context);
+        assert_eq!(
+            recorder.borrow().events,
+            vec![MonitorEvent::SyncParticipation {
+                index: 0,
+                slot: 2 * slots_per_epoch,
+                participated: true
+            }]
+        );
+
+        inner.slot = 3 * slots_per_epoch;
+        inner.balances[0] += 100;
+        monitor.process_state(&BeaconState::Altair(inner.clone()), &context).unwrap();
+        inner.slot = 4 * slots_per_epoch;
+        inner.previous_epoch_participation[0] = 1;
+        monitor.process_state(&BeaconState::Altair(inner.clone()), &context).unwrap();
+
+        {
+            let recorder = recorder.borrow();
+            let summaries = &recorder.summaries;
+            assert_eq!(summaries.len(), 2);
+            assert_eq!(summaries[0].epoch, 1);
+            assert_eq!(summaries[0].attested, Some(false));
+            let summary = &summaries[1];
+            assert_eq!(summary.epoch, 2);
+            assert_eq!(summary.proposals, 1);
+            assert_eq!(summary.missed_proposals, slots_per_epoch - 1);
+            assert_eq!(summary.sync_participations, 1);
+            assert_eq!(summary.balance_delta, None);
+            assert!(recorder
+                .events
+                .contains(&MonitorEvent::MissedAttestation { index: 0, epoch: 1 }));
+        }
+
+        inner.slot = 5 * slots_per_epoch;
+        inner.validators[0].slashed = true;
+        monitor.process_state(&BeaconState::Altair(inner), &context).unwrap();
+        let recorder = recorder.borrow();
+        let summary = recorder.summaries.last().unwrap();
+        assert_eq!(summary.epoch, 3);
+        assert_eq!(summary.attested, Some(true));
+        assert_eq!(summary.balance_delta, Some(100));
+        assert!(recorder.events.contains(&MonitorEvent::Slashed { index: 0, epoch: 5 }));
+    }
+
+    #[test]
+    fn test_sync_participation_at_period_boundary() {
+        let context = Context::for_mainnet();
+        let period_slots = context.epochs_per_sync_committee_period * context.slots_per_epoch;
+        let mut rng = rand::thread_rng();
+        let outgoing = SecretKey::random(&mut rng).unwrap().public_key();
+        let incoming = SecretKey::random(&mut rng).unwrap().public_key();
+
+        let mut inner = spec::BeaconState::default();
+        push_validator(&mut inner, &outgoing, &context);
+        push_validator(&mut inner, &incoming, &context);
+        inner.current_sync_committee.public_keys[0] = outgoing.clone();
+        inner.next_sync_committee.public_keys[0] = incoming.clone();
+
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+        let mut monitor = ValidatorMonitor::default();
+        monitor.register_index(0);
+        monitor.register_index(1);
+        monitor.add_listener(Box::new(recorder.clone()));
+
+        inner.slot = period_slots - 1;
+        monitor.process_state(&BeaconState::Altair(inner.clone()), &context).unwrap();
+
+        // the state of the new period is seen before the block signed by the outgoing committee
+        inner.slot = period_slots;
+        inner.current_sync_committee = inner.next_sync_committee.clone();
+        inner.next_sync_committee = Default::default();
+        monitor.process_state(&BeaconState::Altair(inner), &context).unwrap();
+
+        for slot in [period_slots, period_slots + 1] {
+            let mut block = spec::SignedBeaconBlock::default();
+            block.message.slot = slot;
+            block.message.body.sync_aggregate.sync_committee_bits.set(0, true);
+            monitor.process_block(&SignedBeaconBlock::Altair(block), &context);
+        }
+
+        let events = recorder
+            .borrow()
+            .events
+            .iter()
+            .filter(|event| matches!(event, MonitorEvent::SyncParticipation { .. }))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                MonitorEvent::SyncParticipation {
+                    index: 0,
+                    slot: period_slots - 1,
+                    participated: true
+                },
+                MonitorEvent::SyncParticipation {
+                    index: 1,
+                    slot: period_slots,
+                    participated: true
+                },
+            ]
+        );
+    }
+}
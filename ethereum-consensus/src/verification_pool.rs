@@ -0,0 +1,172 @@
+//! Signature verification fanned out across a dedicated thread pool.
+use crate::{
+    crypto::{self, SignatureSet},
+    primitives::{BlsPublicKey, BlsSignature, Root},
+};
+use rayon::prelude::*;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid signatures for jobs at indices {0:?}")]
+    InvalidSignatures(Vec<usize>),
+    #[error("verification ended before reporting a result")]
+    Canceled,
+    #[error("could not build thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+}
+
+// A `signing_root` signed by the aggregate of `public_keys`. Jobs own their data so they can be
+// moved onto the pool's threads.
+#[derive(Debug, Clone)]
+pub struct VerificationJob {
+    pub public_keys: Vec<BlsPublicKey>,
+    pub signing_root: Root,
+    pub signature: BlsSignature,
+}
+
+impl VerificationJob {
+    fn verify(&self) -> bool {
+        let public_keys = self.public_keys.iter().collect::<Vec<_>>();
+        crypto::fast_aggregate_verify(&public_keys, self.signing_root.as_ref(), &self.signature)
+            .is_ok()
+    }
+}
+
+pub struct VerificationPool {
+    pool: rayon::ThreadPool,
+}
+
+impl VerificationPool {
+    // Build a pool with `num_threads` threads, or one per CPU if `num_threads` is 0.
+    pub fn new(num_threads: usize) -> Result<Self, Error> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("signature-verification-{i}"))
+            .build()?;
+        Ok(Self { pool })
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    // Queue `jobs` for verification and return a handle to the result, which can be awaited or
+    // waited on. The jobs are split into one batch per thread; each batch is checked with a
+    // single multi-pairing and only falls back to verifying jobs one by one if it fails, so that
+    // the invalid jobs can be reported.
+    pub fn verify(&self, jobs: Vec<VerificationJob>) -> VerificationHandle {
+        let (sender, receiver) = oneshot::channel();
+        let batch_size = (jobs.len() / self.num_threads()).max(1);
+        self.pool.spawn(move || {
+            let invalid = jobs
+                .par_chunks(batch_size)
+                .enumerate()
+                .flat_map_iter(|(i, batch)| {
+                    invalid_jobs(batch).into_iter().map(move |index| i * batch_size + index)
+                })
+                .collect::<Vec<_>>();
+            let result =
+                if invalid.is_empty() { Ok(()) } else { Err(Error::InvalidSignatures(invalid)) };
+            // the handle may have been dropped if the caller is no longer interested
+            let _ = sender.send(result);
+        });
+        VerificationHandle { receiver }
+    }
+
+    pub fn verify_blocking(&self, jobs: Vec<VerificationJob>) -> Result<(), Error> {
+        self.verify(jobs).wait()
+    }
+}
+
+// Indices into `batch` of the jobs with an invalid signature
+fn invalid_jobs(batch: &[VerificationJob]) -> Vec<usize> {
+    let sets = batch
+        .iter()
+        .map(|job| SignatureSet {
+            public_keys: job.public_keys.iter().collect(),
+            message: job.signing_root.as_ref(),
+            signature: &job.signature,
+        })
+        .collect::<Vec<_>>();
+    if crypto::verify_signature_sets(&sets).is_ok() {
+        return vec![]
+    }
+    batch.iter().enumerate().filter(|(_, job)| !job.verify()).map(|(i, _)| i).collect()
+}
+
+// Result of a call to `VerificationPool::verify`. Await it from async code or `wait` on it from
+// a thread outside of any async runtime.
+#[derive(Debug)]
+pub struct VerificationHandle {
+    receiver: oneshot::Receiver<Result<(), Error>>,
+}
+
+impl VerificationHandle {
+    // NOTE: panics if called from within an async runtime
+    pub fn wait(self) -> Result<(), Error> {
+        self.receiver.blocking_recv().unwrap_or(Err(Error::Canceled))
+    }
+}
+
+impl Future for VerificationHandle {
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(|result| result.unwrap_or(Err(Error::Canceled)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::SecretKey;
+    use rand::prelude::*;
+
+    fn jobs(count: usize) -> Vec<VerificationJob> {
+        let mut rng = thread_rng();
+        (0..count)
+            .map(|i| {
+                let secret_key = SecretKey::random(&mut rng).unwrap();
+                let signing_root = Root::try_from([i as u8; 32].as_ref()).unwrap();
+                VerificationJob {
+                    public_keys: vec![secret_key.public_key()],
+                    signature: secret_key.sign(signing_root.as_ref()),
+                    signing_root,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_blocking() {
+        let pool = VerificationPool::new(3).unwrap();
+        assert_eq!(pool.num_threads(), 3);
+
+        let mut jobs = jobs(10);
+        assert!(pool.verify_blocking(jobs.clone()).is_ok());
+        assert!(pool.verify_blocking(vec![]).is_ok());
+
+        jobs[2].signing_root = Root::default();
+        jobs[7].signature = jobs[6].signature.clone();
+        let result = pool.verify_blocking(jobs);
+        assert!(matches!(result, Err(Error::InvalidSignatures(indices)) if indices == vec![2, 7]));
+    }
+
+    #[tokio::test]
+    async fn test_verify_async() {
+        let pool = VerificationPool::new(2).unwrap();
+        let mut jobs = jobs(4);
+        assert!(pool.verify(jobs.clone()).await.is_ok());
+
+        jobs[0].public_keys = jobs[1].public_keys.clone();
+        let result = pool.verify(jobs).await;
+        assert!(matches!(result, Err(Error::InvalidSignatures(indices)) if indices == vec![0]));
+    }
+}
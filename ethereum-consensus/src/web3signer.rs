@@ -0,0 +1,248 @@
+//! Request and response bodies of the Web3Signer remote signing API for Ethereum consensus,
+//! i.e. `POST /api/v1/eth2/sign/{identifier}` where the identifier is the hex-encoded public key
+//! of the signing validator.
+use crate::{
+    altair::SyncAggregatorSelectionData,
+    builder::ValidatorRegistration,
+    phase0::{AttestationData, BeaconBlockHeader, Fork as ForkData, VoluntaryExit},
+    primitives::{BlsPublicKey, BlsSignature, Bytes32, Epoch, Gwei, Root, Slot, Version},
+    Fork,
+};
+
+// Identifies the chain (and fork of that chain) a message is signed for so that the remote
+// signer can compute the signing domain.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ForkInfo {
+    pub fork: ForkData,
+    pub genesis_validators_root: Root,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AggregationSlot {
+    #[serde(with = "crate::serde::as_str")]
+    pub slot: Slot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RandaoReveal {
+    #[serde(with = "crate::serde::as_str")]
+    pub epoch: Epoch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SyncCommitteeMessage {
+    pub beacon_block_root: Root,
+    #[serde(with = "crate::serde::as_str")]
+    pub slot: Slot,
+}
+
+// A deposit is signed over `genesis_fork_version` rather than the current fork of a chain.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DepositData {
+    #[serde(rename = "pubkey")]
+    pub public_key: BlsPublicKey,
+    pub withdrawal_credentials: Bytes32,
+    #[serde(with = "crate::serde::as_str")]
+    pub amount: Gwei,
+    #[serde(with = "crate::serde::as_hex")]
+    pub genesis_fork_version: Version,
+}
+
+fn serialize_block_version<S: serde::Serializer>(
+    version: &Fork,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let version = match version {
+        Fork::Phase0 => "PHASE0",
+        Fork::Altair => "ALTAIR",
+        Fork::Bellatrix => "BELLATRIX",
+        Fork::Capella => "CAPELLA",
+        Fork::Deneb => "DENEB",
+    };
+    serializer.serialize_str(version)
+}
+
+// Web3Signer expects the full block for phase0 and altair blocks and only the header of blocks
+// from bellatrix onwards.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BlockRequest<'a, B> {
+    #[serde(serialize_with = "serialize_block_version")]
+    pub version: Fork,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<&'a B>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_header: Option<BeaconBlockHeader>,
+}
+
+impl<'a, B> BlockRequest<'a, B> {
+    pub fn from_block(version: Fork, block: &'a B) -> Self {
+        Self { version, block: Some(block), block_header: None }
+    }
+
+    pub fn from_header(version: Fork, block_header: BeaconBlockHeader) -> Self {
+        Self { version, block: None, block_header: Some(block_header) }
+    }
+}
+
+// The message to sign, tagged with its Web3Signer `type`. Containers that depend on the preset
+// are left generic; see the `mainnet` and `minimal` modules for concrete instances.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SignableMessage<'a, BeaconBlock, AggregateAndProof, ContributionAndProof> {
+    AggregationSlot {
+        aggregation_slot: AggregationSlot,
+    },
+    AggregateAndProof {
+        aggregate_and_proof: &'a AggregateAndProof,
+    },
+    Attestation {
+        attestation: &'a AttestationData,
+    },
+    #[serde(rename = "BLOCK_V2")]
+    BlockV2 {
+        beacon_block: BlockRequest<'a, BeaconBlock>,
+    },
+    Deposit {
+        deposit: DepositData,
+    },
+    RandaoReveal {
+        randao_reveal: RandaoReveal,
+    },
+    VoluntaryExit {
+        voluntary_exit: &'a VoluntaryExit,
+    },
+    SyncCommitteeMessage {
+        sync_committee_message: SyncCommitteeMessage,
+    },
+    SyncCommitteeSelectionProof {
+        sync_aggregator_selection_data: &'a SyncAggregatorSelectionData,
+    },
+    SyncCommitteeContributionAndProof {
+        contribution_and_proof: &'a ContributionAndProof,
+    },
+    ValidatorRegistration {
+        validator_registration: &'a ValidatorRegistration,
+    },
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SigningRequest<'a, BeaconBlock, AggregateAndProof, ContributionAndProof> {
+    #[serde(flatten)]
+    pub message: SignableMessage<'a, BeaconBlock, AggregateAndProof, ContributionAndProof>,
+    // deposits and validator registrations are not tied to a fork of a chain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_info: Option<ForkInfo>,
+    // if present, the signer checks it against the root it computes from `message`
+    #[serde(rename = "signingRoot", skip_serializing_if = "Option::is_none")]
+    pub signing_root: Option<Root>,
+}
+
+impl<'a, B, A, C> SigningRequest<'a, B, A, C> {
+    pub fn new(message: SignableMessage<'a, B, A, C>, fork_info: Option<ForkInfo>) -> Self {
+        Self { message, fork_info, signing_root: None }
+    }
+
+    pub fn with_signing_root(mut self, signing_root: Root) -> Self {
+        self.signing_root = Some(signing_root);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SigningResponse {
+    pub signature: BlsSignature,
+}
+
+pub mod mainnet {
+    use crate::{altair::mainnet as altair, phase0::mainnet as phase0, types::mainnet as types};
+
+    pub type SignableMessage<'a> = super::SignableMessage<
+        'a,
+        types::BeaconBlock,
+        phase0::AggregateAndProof,
+        altair::ContributionAndProof,
+    >;
+    pub type SigningRequest<'a> = super::SigningRequest<
+        'a,
+        types::BeaconBlock,
+        phase0::AggregateAndProof,
+        altair::ContributionAndProof,
+    >;
+}
+
+pub mod minimal {
+    use crate::{altair::minimal as altair, phase0::minimal as phase0, types::minimal as types};
+
+    pub type SignableMessage<'a> = super::SignableMessage<
+        'a,
+        types::BeaconBlock,
+        phase0::AggregateAndProof,
+        altair::ContributionAndProof,
+    >;
+    pub type SigningRequest<'a> = super::SigningRequest<
+        'a,
+        types::BeaconBlock,
+        phase0::AggregateAndProof,
+        altair::ContributionAndProof,
+    >;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fork_info() -> ForkInfo {
+        ForkInfo {
+            fork: ForkData {
+                previous_version: [0, 0, 0, 1],
+                current_version: [0, 0, 0, 2],
+                epoch: 10,
+            },
+            genesis_validators_root: Root::try_from([1u8; 32].as_ref()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_serialize_signing_requests() {
+        let message =
+            mainnet::SignableMessage::RandaoReveal { randao_reveal: RandaoReveal { epoch: 3 } };
+        let request = mainnet::SigningRequest::new(message, Some(fork_info()));
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "type": "RANDAO_REVEAL",
+                "randao_reveal": { "epoch": "3" },
+                "fork_info": {
+                    "fork": {
+                        "previous_version": "0x00000001",
+                        "current_version": "0x00000002",
+                        "epoch": "10",
+                    },
+                    "genesis_validators_root": format!("0x{}", "01".repeat(32)),
+                },
+            })
+        );
+
+        let header = BeaconBlockHeader { slot: 7, proposer_index: 2, ..Default::default() };
+        let message = mainnet::SignableMessage::BlockV2 {
+            beacon_block: BlockRequest::from_header(Fork::Capella, header),
+        };
+        let request = mainnet::SigningRequest::new(message, Some(fork_info()))
+            .with_signing_root(Root::default());
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["type"], "BLOCK_V2");
+        assert_eq!(value["beacon_block"]["version"], "CAPELLA");
+        assert_eq!(value["beacon_block"]["block_header"]["slot"], "7");
+        assert!(value["beacon_block"].get("block").is_none());
+        assert_eq!(value["signingRoot"], format!("0x{}", "00".repeat(32)));
+
+        let registration = ValidatorRegistration::default();
+        let message = mainnet::SignableMessage::ValidatorRegistration {
+            validator_registration: &registration,
+        };
+        let value = serde_json::to_value(mainnet::SigningRequest::new(message, None)).unwrap();
+        assert_eq!(value["type"], "VALIDATOR_REGISTRATION");
+        assert!(value.get("fork_info").is_none());
+    }
+}
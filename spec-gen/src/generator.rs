@@ -126,7 +126,7 @@ impl Fork {
             Fork::Altair => {
                 let fragment: syn::File = parse_quote! {
                     use std::cmp;
-                    use std::collections::HashSet;
+                    use std::collections::{hash_map::Entry, HashMap, HashSet};
                     use crate::ssz::prelude::*;
                     use crate::crypto::{hash, fast_aggregate_verify};
 
@@ -138,11 +138,11 @@ impl Fork {
                 let fragment: syn::File = parse_quote! {
                     use std::cmp;
                     use std::mem;
-                    use std::collections::{HashSet, HashMap};
+                    use std::collections::{hash_map::Entry, HashMap, HashSet};
                     use std::iter::zip;
                     use crate::ssz::prelude::*;
                     use integer_sqrt::IntegerSquareRoot;
-                    use crate::crypto::{hash, fast_aggregate_verify, eth_aggregate_public_keys, eth_fast_aggregate_verify};
+                    use crate::crypto::{hash, fast_aggregate_verify, eth_aggregate_decompressed_public_keys, eth_fast_aggregate_verify};
 
                     pub use crate::bellatrix::fork::upgrade_to_bellatrix;
                 };
@@ -152,11 +152,11 @@ impl Fork {
                 let fragment: syn::File = parse_quote! {
                     use std::cmp;
                     use std::mem;
-                    use std::collections::{HashSet, HashMap};
+                    use std::collections::{hash_map::Entry, HashMap, HashSet};
                     use std::iter::zip;
                     use crate::ssz::prelude::*;
                     use integer_sqrt::IntegerSquareRoot;
-                    use crate::crypto::{hash, fast_aggregate_verify, eth_aggregate_public_keys, eth_fast_aggregate_verify};
+                    use crate::crypto::{hash, fast_aggregate_verify, eth_aggregate_decompressed_public_keys, eth_fast_aggregate_verify};
 
                     pub use crate::capella::fork::upgrade_to_capella;
                 };
@@ -166,11 +166,11 @@ impl Fork {
                 let fragment: syn::File = parse_quote! {
                     use std::cmp;
                     use std::mem;
-                    use std::collections::{HashSet, HashMap};
+                    use std::collections::{hash_map::Entry, HashMap, HashSet};
                     use std::iter::zip;
                     use crate::ssz::prelude::*;
                     use integer_sqrt::IntegerSquareRoot;
-                    use crate::crypto::{hash, fast_aggregate_verify, eth_aggregate_public_keys, eth_fast_aggregate_verify};
+                    use crate::crypto::{hash, fast_aggregate_verify, eth_aggregate_decompressed_public_keys, eth_fast_aggregate_verify};
 
                     pub use crate::deneb::fork::upgrade_to_deneb;
                 };
@@ -405,10 +405,13 @@ impl Spec {
                 let mut all_arguments = vec![];
                 for name in type_names {
                     if let Some(target_module) = index.get(&name) {
+                        // containers carried over from `previous_module` are only merged into
+                        // `self.diff` after its fns, so look in `module` as well
                         let target_module = self.diff.modules.get(target_module).unwrap();
-                        let container = target_module
+                        let container = module
                             .containers
                             .iter()
+                            .chain(target_module.containers.iter())
                             .find(|&c| c.name == name)
                             .expect("internal state integrity");
 
@@ -594,6 +597,9 @@ fn parse_fork_diff_with_symbol_index(fork: &Fork) -> (ForkDiff, HashMap<String,
                 Item::Impl(_) => {
                     println!("skipping item: `impl` block in {source_path}");
                 }
+                Item::Mod(item) => {
+                    println!("skipping item: `mod {}` in {source_path}", item.ident);
+                }
                 Item::Trait(item) => {
                     let item = TraitDef::new(item, *fork);
                     if item.is_pub() {
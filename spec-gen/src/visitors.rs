@@ -114,14 +114,14 @@ pub fn collate_generics_from(
 
     if !lifetimes.is_empty() {
         let lifetimes: syn::punctuated::Punctuated<syn::GenericParam, syn::Token![,]> = parse_quote! {
-            #(#lifetimes)*,
+            #(#lifetimes,)*
         };
         generics.params.extend(lifetimes);
     }
 
     if !type_params.is_empty() {
         let type_params: syn::punctuated::Punctuated<syn::GenericParam, syn::Token![,]> = parse_quote! {
-            #(#type_params)*,
+            #(#type_params,)*
         };
         generics.params.extend(type_params);
     }